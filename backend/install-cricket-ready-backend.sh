@@ -103,28 +103,44 @@ if [ ! -f "cricket-ready.crt" ] || [ ! -f "cricket-ready.key" ]; then
     chmod 600 cricket-ready.crt cricket-ready.key
 fi
 
-# Set up systemd service
+# Set up systemd socket and service
+# systemd holds the port and starts the backend when the first request arrives, restarting it if it stops responding
+SOCKET_FILE="/etc/systemd/system/cricket-ready-backend.socket"
 SERVICE_FILE="/etc/systemd/system/cricket-ready-backend.service"
-echo "Configuring systemd service..."
+echo "Configuring systemd socket and service..."
+sudo bash -c "cat > $SOCKET_FILE" <<EOL
+[Unit]
+Description=Cricket-Ready Ball Classifier Backend Socket
+
+[Socket]
+ListenStream=49161
+
+[Install]
+WantedBy=sockets.target
+EOL
+
 sudo bash -c "cat > $SERVICE_FILE" <<EOL
 [Unit]
 Description=Cricket-Ready Ball Classifier Backend
 After=network.target
+Requires=cricket-ready-backend.socket
 
 [Service]
+Type=notify
 ExecStart=$INSTALL_DIR/Cricket-Ready-Backend
 WorkingDirectory=$INSTALL_DIR
 Restart=always
+WatchdogSec=60
 User=$(whoami)
 
 [Install]
 WantedBy=multi-user.target
 EOL
 
-# Reload and enable service
+# Reload and enable socket and service
 sudo systemctl daemon-reload
-sudo systemctl enable cricket-ready-backend.service
-sudo systemctl start cricket-ready-backend.service
+sudo systemctl enable cricket-ready-backend.socket cricket-ready-backend.service
+sudo systemctl start cricket-ready-backend.socket cricket-ready-backend.service
 
 # Verify it's running
 if sudo systemctl is-active cricket-ready-backend.service >/dev/null; then
@@ -0,0 +1,11 @@
+-- Every prediction served by /predict, kept for reporting and exports.
+CREATE TABLE IF NOT EXISTS predictions (
+    id BIGSERIAL PRIMARY KEY,
+    request_id BIGINT NOT NULL,
+    created_at TEXT NOT NULL,
+    prediction TEXT NOT NULL,
+    confidence DOUBLE PRECISION NOT NULL,
+    image_size_bytes BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_predictions_created_at ON predictions (created_at);
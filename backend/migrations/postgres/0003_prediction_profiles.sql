@@ -0,0 +1,3 @@
+-- Strictness profile applied to each prediction, and the model's raw output before it.
+ALTER TABLE predictions ADD COLUMN profile TEXT NOT NULL DEFAULT 'social';
+ALTER TABLE predictions ADD COLUMN model_prediction TEXT;
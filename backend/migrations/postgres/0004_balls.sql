@@ -0,0 +1,11 @@
+-- Registered balls and the token printed in their QR code or written to their NFC tag.
+CREATE TABLE IF NOT EXISTS balls (
+    id TEXT PRIMARY KEY,
+    description TEXT,
+    tag TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL
+);
+
+ALTER TABLE predictions ADD COLUMN ball_id TEXT REFERENCES balls (id);
+
+CREATE INDEX IF NOT EXISTS idx_predictions_ball_id ON predictions (ball_id);
@@ -0,0 +1,13 @@
+-- Long-running background jobs, such as retraining the models.
+CREATE TABLE IF NOT EXISTS jobs (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    -- One of 'queued', 'running', 'succeeded', 'failed' or 'cancelled'
+    status TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    started_at TEXT,
+    finished_at TEXT,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs (status);
@@ -0,0 +1,16 @@
+-- API keys issued to clubs and apps. Only a hash of each key is kept.
+CREATE TABLE IF NOT EXISTS api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    -- First characters of the key, so admins can tell keys apart
+    key_prefix TEXT NOT NULL,
+    -- Strictness profile for requests made with the key; the default profile when null
+    profile TEXT,
+    -- Comma-separated, e.g. 'predict,training'
+    scopes TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    rotated_at TEXT,
+    last_used_at TEXT,
+    revoked_at TEXT
+);
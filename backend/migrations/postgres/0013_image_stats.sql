@@ -0,0 +1,29 @@
+-- Statistics of each training image, by content hash, describing the distribution the models were trained on.
+CREATE TABLE IF NOT EXISTS training_image_stats (
+    content_hash TEXT PRIMARY KEY,
+    measured_at TEXT NOT NULL,
+    brightness DOUBLE PRECISION NOT NULL,
+    contrast DOUBLE PRECISION NOT NULL,
+    red DOUBLE PRECISION NOT NULL,
+    green DOUBLE PRECISION NOT NULL,
+    blue DOUBLE PRECISION NOT NULL,
+    saturation DOUBLE PRECISION NOT NULL,
+    width BIGINT NOT NULL,
+    height BIGINT NOT NULL
+);
+
+-- Statistics of each image sent for prediction, compared against the training images to detect drift.
+CREATE TABLE IF NOT EXISTS prediction_image_stats (
+    prediction_id BIGINT PRIMARY KEY REFERENCES predictions (id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    brightness DOUBLE PRECISION NOT NULL,
+    contrast DOUBLE PRECISION NOT NULL,
+    red DOUBLE PRECISION NOT NULL,
+    green DOUBLE PRECISION NOT NULL,
+    blue DOUBLE PRECISION NOT NULL,
+    saturation DOUBLE PRECISION NOT NULL,
+    width BIGINT NOT NULL,
+    height BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_prediction_image_stats_created_at ON prediction_image_stats (created_at);
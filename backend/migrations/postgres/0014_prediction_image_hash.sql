@@ -0,0 +1,2 @@
+-- Content hash of the image each prediction was made on, kept in the prediction images area for replays
+ALTER TABLE predictions ADD COLUMN image_hash TEXT;
@@ -0,0 +1,14 @@
+-- How and when each training photo was taken, from the EXIF metadata stripped from the stored image.
+CREATE TABLE IF NOT EXISTS sample_capture (
+    sample_id BIGINT PRIMARY KEY REFERENCES samples (id) ON DELETE CASCADE,
+    captured_at TEXT,
+    camera_make TEXT,
+    camera_model TEXT,
+    focal_length_mm DOUBLE PRECISION,
+    focal_length_35mm BIGINT,
+    f_number DOUBLE PRECISION,
+    exposure_time DOUBLE PRECISION,
+    iso BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS idx_sample_capture_camera_model ON sample_capture (camera_model);
@@ -0,0 +1,5 @@
+-- The prediction a sample was made from, when a user corrected its label through /predictions/{id}/correct.
+-- Each prediction can only be corrected once.
+ALTER TABLE samples ADD COLUMN prediction_id BIGINT REFERENCES predictions (id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_samples_prediction_id ON samples (prediction_id);
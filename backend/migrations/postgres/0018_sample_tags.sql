@@ -0,0 +1,9 @@
+-- Tags given to training samples by /admin/samples/bulk, e.g. to mark a batch for a closer look.
+CREATE TABLE IF NOT EXISTS sample_tags (
+    sample_id BIGINT NOT NULL REFERENCES samples (id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (sample_id, tag)
+);
+
+CREATE INDEX IF NOT EXISTS idx_sample_tags_tag ON sample_tags (tag);
@@ -0,0 +1,8 @@
+-- The replica running each job and when it last renewed its lease on it, so replicas sharing the database only
+-- fail their own interrupted jobs, or those whose replica stopped renewing them.
+ALTER TABLE jobs ADD COLUMN instance_id TEXT;
+ALTER TABLE jobs ADD COLUMN heartbeat_at TEXT;
+
+-- A job being cancelled keeps its slot until the replica running it has stopped its process
+DROP INDEX IF EXISTS idx_jobs_active_kind;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_jobs_active_kind ON jobs (kind) WHERE status IN ('queued', 'running', 'cancelling');
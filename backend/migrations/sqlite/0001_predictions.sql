@@ -0,0 +1,11 @@
+-- Every prediction served by /predict, kept for reporting and exports.
+CREATE TABLE IF NOT EXISTS predictions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    prediction TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    image_size_bytes INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_predictions_created_at ON predictions (created_at);
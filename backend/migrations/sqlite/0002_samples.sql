@@ -0,0 +1,17 @@
+-- Training images submitted through /training, with their review outcome.
+CREATE TABLE IF NOT EXISTS samples (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    request_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL,
+    contributor TEXT,
+    label TEXT NOT NULL,
+    filename TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    image_size_bytes INTEGER NOT NULL,
+    -- One of 'pending', 'approved' or 'rejected'
+    review_status TEXT NOT NULL DEFAULT 'pending',
+    reviewed_label TEXT,
+    reviewed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_samples_contributor ON samples (contributor);
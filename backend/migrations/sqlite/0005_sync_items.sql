@@ -0,0 +1,8 @@
+-- Items received through /sync, keyed by the client's ID so retried batches aren't processed twice.
+CREATE TABLE IF NOT EXISTS sync_items (
+    client_id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    -- Row ID in `predictions` or `samples`, depending on `kind`
+    result_id INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Model version that produced each prediction.
+ALTER TABLE predictions ADD COLUMN model_version TEXT;
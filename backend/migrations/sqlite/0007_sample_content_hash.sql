@@ -0,0 +1,4 @@
+-- SHA-256 of each training image, which is also its name in storage.
+ALTER TABLE samples ADD COLUMN content_hash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_samples_content_hash ON samples (content_hash);
@@ -0,0 +1,4 @@
+-- Archive bundle holding the sample's image once it has been moved to cold storage.
+ALTER TABLE samples ADD COLUMN archive_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_samples_archive_id ON samples (archive_id);
@@ -0,0 +1,17 @@
+-- Settings changed at runtime through /admin/config, overriding the environment. Values are JSON.
+CREATE TABLE IF NOT EXISTS runtime_settings (
+    name TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Every change made through /admin/config, oldest first.
+CREATE TABLE IF NOT EXISTS settings_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    changed_at TEXT NOT NULL,
+    -- Address of the admin client that made the change
+    client TEXT NOT NULL,
+    name TEXT NOT NULL,
+    old_value TEXT NOT NULL,
+    new_value TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Second-opinion model and its verdict (after the profile threshold), when one was requested.
+ALTER TABLE predictions ADD COLUMN second_opinion_model TEXT;
+ALTER TABLE predictions ADD COLUMN second_opinion_prediction TEXT;
@@ -0,0 +1,29 @@
+-- Statistics of each training image, by content hash, describing the distribution the models were trained on.
+CREATE TABLE IF NOT EXISTS training_image_stats (
+    content_hash TEXT PRIMARY KEY,
+    measured_at TEXT NOT NULL,
+    brightness REAL NOT NULL,
+    contrast REAL NOT NULL,
+    red REAL NOT NULL,
+    green REAL NOT NULL,
+    blue REAL NOT NULL,
+    saturation REAL NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL
+);
+
+-- Statistics of each image sent for prediction, compared against the training images to detect drift.
+CREATE TABLE IF NOT EXISTS prediction_image_stats (
+    prediction_id INTEGER PRIMARY KEY REFERENCES predictions (id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    brightness REAL NOT NULL,
+    contrast REAL NOT NULL,
+    red REAL NOT NULL,
+    green REAL NOT NULL,
+    blue REAL NOT NULL,
+    saturation REAL NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_prediction_image_stats_created_at ON prediction_image_stats (created_at);
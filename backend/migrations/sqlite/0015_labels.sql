@@ -0,0 +1,11 @@
+-- Labels training images can be given, in the order they are shown to contributors.
+-- Each is also a class folder in the dataset and a class the models predict.
+CREATE TABLE IF NOT EXISTS labels (
+    name TEXT PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT '',
+    display_order INTEGER NOT NULL DEFAULT 0
+);
+
+INSERT INTO labels (name, description, display_order) VALUES
+    ('match_ready', 'Fit for use in a match.', 1),
+    ('not_match_ready', 'Too worn or damaged for a match.', 2);
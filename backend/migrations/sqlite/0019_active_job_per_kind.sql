@@ -0,0 +1,6 @@
+-- At most one job of each kind can be queued or running. Older duplicates left by concurrent starts are failed first.
+UPDATE jobs SET status = 'failed', error = 'Superseded by a job started at the same time', finished_at = COALESCE(started_at, created_at)
+WHERE status IN ('queued', 'running')
+  AND id < (SELECT MAX(newer.id) FROM jobs AS newer WHERE newer.kind = jobs.kind AND newer.status IN ('queued', 'running'));
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_jobs_active_kind ON jobs (kind) WHERE status IN ('queued', 'running');
@@ -0,0 +1,181 @@
+"""
+Cricket Ball Classifier - Calibration Script
+
+Fits a temperature that rescales the ensemble's outputs so its confidence matches how often it is right:
+of the balls called match ready with 80% confidence, about 80% should really be match ready. Raw softmax
+outputs tend to be overconfident.
+
+Each model is scored on the cross-validation fold it was validated on during training, and a single
+temperature is chosen to minimise the negative log-likelihood of those predictions. The result is saved
+as calibration.json in the models directory, which predict.py applies automatically. Each class's precision
+and recall on the folds, and a reliability curve, are saved with it for the model report. train.py runs this
+after training; run it by hand for models trained before calibration was added. The folds are only the
+same as in training if the dataset hasn't changed since.
+
+Usage:
+    python calibrate.py
+    python calibrate.py --models-dir models_v2 --dataset-dir dataset
+"""
+
+# Check for required dependencies
+try:
+    import os
+    import sys
+    import json
+    import torch
+    import torch.nn as nn
+    import torch.nn.functional as F
+    from torch.utils.data import DataLoader, Subset
+    from torchvision import models, transforms
+    from sklearn.model_selection import KFold
+    from taxonomy import ClassFolder, load_classes
+except ImportError as e:
+    print(f"❌ Missing required package: {e}")
+    print("💡 Install required packages with:")
+    print("   pip install torch torchvision pillow scikit-learn")
+    print("   Or use: pip install -r requirements.txt")
+    exit(1)
+
+k_folds = 3         # Must match train.py so each model is scored on its own validation fold
+ece_bins = 10       # Confidence bins for the expected calibration error
+
+# Same preprocessing as predict.py
+transform = transforms.Compose([
+    transforms.Resize((224, 224)),
+    transforms.ToTensor(),
+    transforms.Normalize(mean=[0.485, 0.456, 0.406], std=[0.229, 0.224, 0.225])
+])
+
+def validation_logits(models_dir, dataset_dir, device):
+    """Runs each fold's model over the images it was validated on, returning their logits and labels."""
+    classes = load_classes(models_dir)
+    dataset = ClassFolder(dataset_dir, classes, transform=transform)
+    kfold = KFold(n_splits=k_folds, shuffle=True, random_state=42)
+    logits, labels = [], []
+    for fold_number, (_, test_indices) in enumerate(kfold.split(dataset)):
+        model = models.resnet18(weights=None)
+        model.fc = nn.Sequential(
+            nn.Dropout(0.5),
+            nn.Linear(model.fc.in_features, len(classes))
+        )
+        model.load_state_dict(torch.load(os.path.join(models_dir, f"model_{fold_number+1}.pth"), map_location=device))
+        model.to(device)
+        model.eval()
+
+        loader = DataLoader(Subset(dataset, test_indices), batch_size=16, shuffle=False)
+        with torch.no_grad():
+            for batch_inputs, batch_labels in loader:
+                logits.append(model(batch_inputs.to(device)).float().cpu())
+                labels.append(batch_labels)
+    return torch.cat(logits), torch.cat(labels)
+
+def fit_temperature(logits, labels):
+    """Finds the temperature minimising the negative log-likelihood of the scaled logits."""
+    log_temperature = torch.zeros(1, requires_grad=True)  # Optimised in log space so it stays positive
+    optimizer = torch.optim.LBFGS([log_temperature], lr=0.1, max_iter=200)
+
+    def closure():
+        optimizer.zero_grad()
+        loss = F.cross_entropy(logits / log_temperature.exp(), labels)
+        loss.backward()
+        return loss
+
+    optimizer.step(closure)
+    return log_temperature.exp().item()
+
+def expected_calibration_error(logits, labels, temperature=1.0):
+    """Average gap between confidence and accuracy across confidence bins, weighted by how many predictions fall in each."""
+    probs = F.softmax(logits / temperature, dim=1)
+    confidences, predictions = probs.max(dim=1)
+    correct = (predictions == labels).float()
+    error = 0.0
+    for i in range(ece_bins):
+        in_bin = (confidences > i / ece_bins) & (confidences <= (i + 1) / ece_bins)
+        if in_bin.any():
+            error += in_bin.float().mean().item() * abs(confidences[in_bin].mean().item() - correct[in_bin].mean().item())
+    return error
+
+def reliability_curve(logits, labels, temperature):
+    """Accuracy against average confidence in each confidence bin with predictions in it, for plotting calibration."""
+    probs = F.softmax(logits / temperature, dim=1)
+    confidences, predictions = probs.max(dim=1)
+    correct = (predictions == labels).float()
+    curve = []
+    for i in range(ece_bins):
+        in_bin = (confidences > i / ece_bins) & (confidences <= (i + 1) / ece_bins)
+        if in_bin.any():
+            curve.append({
+                "confidence": confidences[in_bin].mean().item(),
+                "accuracy": correct[in_bin].mean().item(),
+                "count": int(in_bin.sum().item()),
+            })
+    return curve
+
+def per_class_metrics(logits, labels, classes):
+    """Precision, recall and number of validation images for each class."""
+    predictions = logits.argmax(dim=1)
+    metrics = {}
+    for index, name in enumerate(classes):
+        true_positives = ((predictions == index) & (labels == index)).sum().item()
+        predicted = (predictions == index).sum().item()
+        support = (labels == index).sum().item()
+        metrics[name] = {
+            "precision": true_positives / predicted if predicted else None,
+            "recall": true_positives / support if support else None,
+            "support": support,
+        }
+    return metrics
+
+def calibrate(models_dir, dataset_dir, device):
+    """Fits the temperature for the models in models_dir and saves it with them."""
+    logits, labels = validation_logits(models_dir, dataset_dir, device)
+    temperature = fit_temperature(logits, labels)
+    calibration = {
+        "method": "temperature",
+        "temperature": temperature,
+        "validation_images": len(labels),
+        "nll_before": F.cross_entropy(logits, labels).item(),
+        "nll_after": F.cross_entropy(logits / temperature, labels).item(),
+        "ece_before": expected_calibration_error(logits, labels),
+        "ece_after": expected_calibration_error(logits, labels, temperature),
+        "reliability": reliability_curve(logits, labels, temperature),
+        "per_class": per_class_metrics(logits, labels, load_classes(models_dir)),
+    }
+    calibration_filename = os.path.join(models_dir, "calibration.json")
+    with open(calibration_filename, "w") as f:
+        json.dump(calibration, f, indent=2)
+
+    print(f"🌡️  Temperature {temperature:.4f} fitted on {len(labels)} validation images")
+    print(f"   📊 Expected calibration error: {calibration['ece_before']:.4f} → {calibration['ece_after']:.4f}")
+    print(f"💾 Calibration saved: {calibration_filename}")
+    return calibration
+
+def option(args, name, default):
+    """Reads `--name value` from the command line."""
+    if name not in args:
+        return default
+    index = args.index(name)
+    if index + 1 >= len(args):
+        print(f"❌ Error: {name} requires a value.")
+        sys.exit(1)
+    return args[index + 1]
+
+def main():
+    args = sys.argv[1:]
+    models_dir = option(args, '--models-dir', 'models')
+    dataset_dir = option(args, '--dataset-dir', 'dataset')
+    if not os.path.exists(dataset_dir):
+        print(f"❌ Error: Dataset directory '{dataset_dir}' does not exist.")
+        sys.exit(1)
+    for i in range(1, k_folds + 1):
+        path = os.path.join(models_dir, f"model_{i}.pth")
+        if not os.path.exists(path):
+            print(f"❌ Error: Missing model file {path}")
+            print("💡 Please train the model first by running: python train.py")
+            sys.exit(1)
+
+    device = torch.device('mps' if torch.backends.mps.is_available() else 'cuda' if torch.cuda.is_available() else 'cpu')
+    calibrate(models_dir, dataset_dir, device)
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,75 @@
+"""
+Cricket Ball Classifier - HEIC/HEIF Conversion
+
+Converts a HEIC/HEIF photo, the format iPhones save by default, to a JPEG written to standard output, so the
+backend and predict.py can read it like any other upload. The backend runs this on every HEIC/HEIF upload.
+
+libheif turns the photo upright as it decodes it. Its colours are converted from the profile embedded in the photo
+(Display P3 on recent iPhones) to sRGB, which the training photos are in: reading P3 values as sRGB would make
+every photo look duller than it is. Of an image sequence, such as a burst, only the primary image is converted.
+The photo's EXIF metadata is carried over, with its orientation reset, so the backend can record how it was taken.
+
+Errors go to standard error. The script exits with 2 if the photo can't be read, and 1 for anything else.
+
+Usage:
+    python convert_heif.py photo.heic > photo.jpg
+    python convert_heif.py photo.heic --quality 90 > photo.jpg
+"""
+
+# Check for required dependencies
+try:
+    import io
+    import sys
+    from PIL import Image, ImageCms
+    from pillow_heif import register_heif_opener
+except ImportError as e:
+    print(f"❌ Missing required package: {e}", file=sys.stderr)
+    print("💡 Install required packages with:", file=sys.stderr)
+    print("   pip install pillow pillow-heif", file=sys.stderr)
+    print("   Or use: pip install -r requirements.txt", file=sys.stderr)
+    exit(1)
+
+register_heif_opener()
+
+def to_srgb(image):
+    """Converts an image to 8-bit sRGB, using its embedded colour profile if it has one."""
+    icc_profile = image.info.get('icc_profile')
+    image = image.convert('RGB')
+    if icc_profile:
+        source = ImageCms.ImageCmsProfile(io.BytesIO(icc_profile))
+        image = ImageCms.profileToProfile(image, source, ImageCms.createProfile('sRGB'), outputMode='RGB')
+    return image
+
+def main():
+    args = sys.argv[1:]
+    quality = 95
+    if '--quality' in args:
+        index = args.index('--quality')
+        try:
+            quality = int(args[index + 1])
+        except (IndexError, ValueError):
+            print("❌ Error: --quality requires a number from 1 to 100.", file=sys.stderr)
+            sys.exit(1)
+        del args[index:index + 2]
+    if len(args) != 1:
+        print(f"💡 Usage: python {sys.argv[0]} <path_to_photo> [--quality 95]", file=sys.stderr)
+        sys.exit(1)
+
+    try:
+        source = Image.open(args[0])
+        exif = source.getexif()
+        # Loaded so the Exif IFD, with the capture time and focal length, is written out with the rest
+        exif.get_ifd(0x8769)
+        image = to_srgb(source)
+    except Exception as e:
+        print(f"❌ Error: Could not read '{args[0]}' as a HEIC/HEIF photo: {e}", file=sys.stderr)
+        sys.exit(2)
+
+    output = io.BytesIO()
+    # The photo was turned upright as it was decoded
+    exif[0x0112] = 1
+    image.save(output, 'JPEG', quality=quality, exif=exif.tobytes())
+    sys.stdout.buffer.write(output.getvalue())
+
+if __name__ == "__main__":
+    main()
@@ -5,10 +5,17 @@ Classifies cricket balls as match-ready or not match-ready using an ensemble of
 
 Usage:
     python predict.py path/to/your/image.jpg # Replace with your image path
+    python predict.py path/to/your/image.jpg --models-dir path/to/models # Use a specific model version
+    python predict.py path/to/your/image.jpg --tta # Also classify the mirrored image and average
+    python predict.py path/to/your/image.jpg --mc-dropout 20 # Estimate uncertainty from 20 passes with dropout on
+    python predict.py path/to/your/image.jpg --precision int8 # Use the variants made by quantize.py (fp16 or int8)
+    python predict.py path/to/your/image.jpg --device cuda:1 # Run on a specific device: cpu, cuda, cuda:<index> or mps
+    python predict.py path/to/your/image.jpg --uncalibrated # Report raw softmax confidence, ignoring calibration.json
 
 Examples:
     python predict.py test_images/ball1.jpg
     python predict.py /Users/username/Desktop/cricket_ball.jpg
+    python predict.py test_images/ball1.jpg --models-dir nn-classifier/models_v2
 """
 
 # Check for required dependencies
@@ -19,7 +26,10 @@ try:
     from PIL import Image
     import os
     import sys
+    import json
+    import platform
     import torch.nn.functional as F
+    from taxonomy import load_classes
 except ImportError as e:
     print(f"❌ Missing required package: {e}")
     print("💡 Install required packages with:")
@@ -29,14 +39,64 @@ except ImportError as e:
 
 def main():
     # Parameters
-    models_dir = 'nn-classifier/models'   # Directory containing trained models
-    model_paths = [os.path.join(models_dir, f"model_{i}.pth") for i in range(1,4)]
-    class_names = ['match_ready', 'not_match_ready']
+    models_dir = 'nn-classifier/models'   # Directory containing trained models (overridden by --models-dir)
+    args = sys.argv[1:]
+    if '--models-dir' in args:
+        index = args.index('--models-dir')
+        if index + 1 >= len(args):
+            print(f"❌ Error: --models-dir requires a directory.")
+            sys.exit(1)
+        models_dir = args[index + 1]
+        del args[index:index + 2]
+    tta = '--tta' in args   # Test-time augmentation
+    if tta:
+        args.remove('--tta')
+    mc_passes = 0   # Monte Carlo dropout passes for an uncertainty estimate; 0 disables it
+    if '--mc-dropout' in args:
+        index = args.index('--mc-dropout')
+        try:
+            mc_passes = int(args[index + 1])
+        except (IndexError, ValueError):
+            print(f"❌ Error: --mc-dropout requires a number of passes.")
+            sys.exit(1)
+        del args[index:index + 2]
+    precision = 'fp32'  # Weights made by quantize.py can be chosen instead: fp16 or int8
+    if '--precision' in args:
+        index = args.index('--precision')
+        if index + 1 >= len(args) or args[index + 1] not in ('fp32', 'fp16', 'int8'):
+            print(f"❌ Error: --precision must be fp32, fp16 or int8.")
+            sys.exit(1)
+        precision = args[index + 1]
+        del args[index:index + 2]
+    calibrated = '--uncalibrated' not in args  # Apply the temperature calibrate.py fitted, if there is one
+    if not calibrated:
+        args.remove('--uncalibrated')
+    weight_files = {'fp32': 'model_{}.pth', 'fp16': 'model_{}.fp16.pth', 'int8': 'model_{}.int8.pt'}
+    model_paths = [os.path.join(models_dir, weight_files[precision].format(i)) for i in range(1,4)]
+    class_names = load_classes(models_dir)  # The labels the models were trained on, in the order of their outputs
     device = torch.device('mps' if torch.backends.mps.is_available() else 'cuda' if torch.cuda.is_available() else 'cpu')
+    if '--device' in args:
+        index = args.index('--device')
+        try:
+            device = torch.device(args[index + 1])
+        except (IndexError, RuntimeError):
+            print(f"❌ Error: --device must be cpu, cuda, cuda:<index> or mps.")
+            sys.exit(1)
+        del args[index:index + 2]
+        if device.type == 'cuda' and (not torch.cuda.is_available() or (device.index or 0) >= torch.cuda.device_count()):
+            print(f"❌ Error: CUDA device {device} is not available.")
+            sys.exit(1)
+        if device.type == 'mps' and not torch.backends.mps.is_available():
+            print(f"❌ Error: Metal (mps) is not available.")
+            sys.exit(1)
+    if precision == 'int8':
+        # Quantized models only run on the CPU, with the engine they were quantized for (see quantize.py)
+        device = torch.device('cpu')
+        torch.backends.quantized.engine = 'qnnpack' if platform.machine() in ('aarch64', 'arm64', 'armv7l') else 'fbgemm'
 
     # Parse command line arguments
-    if len(sys.argv) > 1:
-        image_path = sys.argv[1]
+    if len(args) > 0:
+        image_path = args[0]
     else:
         print(f"❌ Error: No image path provided.")
         sys.exit(1)
@@ -76,6 +136,8 @@ def main():
     try:
         image = Image.open(image_path).convert('RGB')
         input_tensor = transform(image).unsqueeze(0).to(device)  # shape: [1, 3, 224, 224]
+        if precision == 'fp16':
+            input_tensor = input_tensor.half()
     except Exception as e:
         print(f"❌ Error loading image: {e}")
         sys.exit(1)
@@ -84,6 +146,13 @@ def main():
     models_list = []
     for i, path in enumerate(model_paths):
         try:
+            if precision == 'int8':
+                # Saved whole as TorchScript, since quantized layers differ from the training architecture
+                model = torch.jit.load(path, map_location=device)
+                model.eval()
+                models_list.append(model)
+                continue
+
             model = models.resnet18(weights=None)
             
             # Match the exact architecture from training script
@@ -91,6 +160,8 @@ def main():
                 nn.Dropout(0.5),  # Add dropout before final layer
                 nn.Linear(model.fc.in_features, len(class_names))
             )
+            if precision == 'fp16':
+                model.half()
             
             model.load_state_dict(torch.load(path, map_location=device))
             model.to(device)
@@ -100,25 +171,56 @@ def main():
             print(f"❌ Error loading model {path}: {e}")
             sys.exit(1)
 
+    # Scale logits by the temperature fitted on the validation folds so confidence matches accuracy
+    temperature = 1.0
+    calibration_path = os.path.join(models_dir, 'calibration.json')
+    if calibrated and os.path.exists(calibration_path):
+        try:
+            with open(calibration_path) as f:
+                temperature = float(json.load(f)['temperature'])
+        except Exception as e:
+            print(f"❌ Error loading calibration {calibration_path}: {e}")
+            sys.exit(1)
+
+    inputs = [input_tensor]
+    if tta:
+        inputs.append(torch.flip(input_tensor, dims=[3]))  # Mirrored left to right
+
+    def ensemble_probabilities():
+        # Average the softmax probabilities of every model on every input
+        probs = []
+        for model in models_list:
+            for batch in inputs:
+                outputs = model(batch)
+                probs.append(F.softmax(outputs.float() / temperature, dim=1).cpu())
+        return torch.mean(torch.stack(probs), dim=0)
+
     # Predict with voting
     with torch.no_grad():
-        # Collect all softmax probabilities
-        probs = []
-        for i, model in enumerate(models_list):
-            outputs = model(input_tensor)
-            prob = F.softmax(outputs, dim=1)
-            probs.append(prob.cpu())
-            individual_prediction = class_names[prob.argmax().item()]
-            individual_confidence = prob.max().item()
-
-        # Average probabilities
-        avg_prob = torch.mean(torch.stack(probs), dim=0)
+        if mc_passes > 0:
+            # Keep dropout active so each pass samples a slightly different network;
+            # how much the passes disagree shows how unsure the models are about this photo
+            for model in models_list:
+                for module in model.modules():
+                    if isinstance(module, nn.Dropout):
+                        module.train()
+            samples = torch.stack([ensemble_probabilities() for _ in range(mc_passes)])
+            avg_prob = samples.mean(dim=0)
+            match_ready = class_names.index('match_ready') if 'match_ready' in class_names else 0
+            uncertainty = samples[:, 0, match_ready].std().item() if mc_passes > 1 else 0.0
+        else:
+            avg_prob = ensemble_probabilities()
+
         predicted_class = torch.argmax(avg_prob, dim=1).item()
         confidence = avg_prob[0][predicted_class].item()
         label = class_names[predicted_class]
 
     # Display results
-    print(f"Prediction: {label}; Confidence: {confidence:.4f}")
+    device_name = f"cuda:{device.index or 0}" if device.type == 'cuda' else device.type
+    if mc_passes > 0:
+        print(f"Prediction: {label}; Confidence: {confidence:.4f}; Uncertainty: {uncertainty:.4f}; Device: {device_name}")
+    else:
+        print(f"Prediction: {label}; Confidence: {confidence:.4f}; Device: {device_name}")
 
 if __name__ == "__main__":
     main()
\ No newline at end of file
@@ -0,0 +1,114 @@
+"""
+Cricket Ball Classifier - Quantization Script
+
+Makes lower-precision variants of a trained ensemble, which the backend runs instead of the full
+weights when MODEL_PRECISION asks for them. They trade a little accuracy for lower latency and memory:
+
+    model_{1,2,3}.fp16.pth  Half-precision weights. Best suited to GPUs.
+    model_{1,2,3}.int8.pt   Statically quantized TorchScript models for the CPU, e.g. on a Raspberry Pi.
+
+The int8 models are calibrated on images from the dataset, and only run with the quantization engine
+they were made for: qnnpack on ARM, fbgemm on x86. Quantize on the kind of machine that will serve them,
+or pass --engine.
+
+Usage:
+    python quantize.py
+    python quantize.py --models-dir models_v2 --dataset-dir dataset --engine qnnpack
+"""
+
+# Check for required dependencies
+try:
+    import os
+    import sys
+    import platform
+    import torch
+    import torch.nn as nn
+    from torch.utils.data import DataLoader
+    from torchvision import models, transforms
+    from torchvision.models import quantization
+    from taxonomy import ClassFolder, load_classes
+except ImportError as e:
+    print(f"❌ Missing required package: {e}")
+    print("💡 Install required packages with:")
+    print("   pip install torch torchvision pillow")
+    print("   Or use: pip install -r requirements.txt")
+    exit(1)
+
+calibration_images = 100    # How many dataset images to calibrate the int8 models on
+
+# Same preprocessing as predict.py
+transform = transforms.Compose([
+    transforms.Resize((224, 224)),
+    transforms.ToTensor(),
+    transforms.Normalize(mean=[0.485, 0.456, 0.406], std=[0.229, 0.224, 0.225])
+])
+
+def option(args, name, default):
+    """Reads `--name value` from the command line."""
+    if name not in args:
+        return default
+    index = args.index(name)
+    if index + 1 >= len(args):
+        print(f"❌ Error: {name} requires a value.")
+        sys.exit(1)
+    return args[index + 1]
+
+def with_classifier_head(model, num_classes):
+    """Replaces the final layer with the one used in train.py."""
+    model.fc = nn.Sequential(
+        nn.Dropout(0.5),
+        nn.Linear(model.fc.in_features, num_classes)
+    )
+    return model
+
+def main():
+    args = sys.argv[1:]
+    models_dir = option(args, '--models-dir', 'models')
+    dataset_dir = option(args, '--dataset-dir', 'dataset')
+    default_engine = 'qnnpack' if platform.machine() in ('aarch64', 'arm64', 'armv7l') else 'fbgemm'
+    engine = option(args, '--engine', default_engine)
+    if engine not in torch.backends.quantized.supported_engines:
+        print(f"❌ Error: Quantization engine '{engine}' is not supported here.")
+        sys.exit(1)
+    torch.backends.quantized.engine = engine
+
+    if not os.path.exists(dataset_dir):
+        print(f"❌ Error: Dataset directory '{dataset_dir}' does not exist.")
+        sys.exit(1)
+    classes = load_classes(models_dir)
+    calibration = DataLoader(ClassFolder(dataset_dir, classes, transform=transform), batch_size=16, shuffle=True)
+
+    for i in range(1, 4):
+        path = os.path.join(models_dir, f"model_{i}.pth")
+        if not os.path.exists(path):
+            print(f"❌ Error: Missing model file {path}")
+            print("💡 Please train the model first by running: python train.py")
+            sys.exit(1)
+        state = torch.load(path, map_location='cpu')
+
+        # Half precision: the same architecture with 16-bit weights
+        model = with_classifier_head(models.resnet18(weights=None), len(classes))
+        model.load_state_dict(state)
+        torch.save(model.half().state_dict(), os.path.join(models_dir, f"model_{i}.fp16.pth"))
+
+        # Int8: fuse layers, observe activations on real images, then convert
+        model = with_classifier_head(quantization.resnet18(weights=None, quantize=False), len(classes))
+        model.load_state_dict(state)
+        model.eval()
+        model.fuse_model()
+        model.qconfig = torch.ao.quantization.get_default_qconfig(engine)
+        torch.ao.quantization.prepare(model, inplace=True)
+        seen = 0
+        with torch.no_grad():
+            for images, _ in calibration:
+                model(images)
+                seen += len(images)
+                if seen >= calibration_images:
+                    break
+        torch.ao.quantization.convert(model, inplace=True)
+        torch.jit.save(torch.jit.script(model), os.path.join(models_dir, f"model_{i}.int8.pt"))
+
+        print(f"✅ Quantized model {i} (fp16, int8 with {engine}, calibrated on {seen} images)")
+
+if __name__ == "__main__":
+    main()
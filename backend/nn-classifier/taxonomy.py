@@ -0,0 +1,50 @@
+"""
+Cricket Ball Classifier - Label Taxonomy
+
+The classes the models learn come from the backend's label taxonomy, which its training job writes to
+labels.json next to this script before running train.py. Each label is a folder of images in the dataset
+directory. Without labels.json, every folder in the dataset is a class.
+
+The classes a set of models was trained on are saved with them as classes.json, so predict.py, calibrate.py
+and quantize.py read the models' outputs in the same order, even after the taxonomy has changed.
+"""
+
+import os
+import json
+from torchvision import datasets
+
+labels_path = os.path.join(os.path.dirname(os.path.abspath(__file__)), 'labels.json')
+default_classes = ['match_ready', 'not_match_ready']  # Classes of models trained before classes.json was saved
+
+class ClassFolder(datasets.ImageFolder):
+    """An image folder dataset over just the given class folders, in the given order."""
+
+    def __init__(self, root, classes, transform=None):
+        self.chosen_classes = classes  # Set first: ImageFolder calls find_classes while initialising
+        super().__init__(root, transform=transform)
+
+    def find_classes(self, directory):
+        missing = [name for name in self.chosen_classes if not os.path.isdir(os.path.join(directory, name))]
+        if missing:
+            raise FileNotFoundError(f"Dataset directory '{directory}' has no folder for: {', '.join(missing)}")
+        return self.chosen_classes, {name: index for index, name in enumerate(self.chosen_classes)}
+
+def training_classes(dataset_dir):
+    """Classes to train on: the labels in labels.json, or every folder in the dataset, sorted by name."""
+    if os.path.exists(labels_path):
+        with open(labels_path) as f:
+            return sorted(json.load(f))
+    return sorted(entry.name for entry in os.scandir(dataset_dir) if entry.is_dir())
+
+def save_classes(models_dir, classes):
+    """Saves the classes models were trained on alongside them."""
+    with open(os.path.join(models_dir, 'classes.json'), 'w') as f:
+        json.dump(classes, f, indent=2)
+
+def load_classes(models_dir):
+    """Reads the classes models were trained on, in the order of their outputs."""
+    path = os.path.join(models_dir, 'classes.json')
+    if not os.path.exists(path):
+        return default_classes
+    with open(path) as f:
+        return json.load(f)
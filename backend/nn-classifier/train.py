@@ -5,19 +5,24 @@ Trains an ensemble of models to classify cricket balls as match-ready or not mat
 
 Usage:
     python train.py
+    python train.py --models-dir models_v2 --dataset-dir dataset
 """
 
 # Check for required dependencies
 try:
     import os
+    import sys
+    import json
     import torch
     import torch.nn as nn
     import numpy as np
     import random
     from PIL import Image
     from torch.utils.data import DataLoader, Dataset
-    from torchvision import datasets, models, transforms
+    from torchvision import models, transforms
     from sklearn.model_selection import KFold
+    from calibrate import calibrate, option
+    from taxonomy import ClassFolder, training_classes, save_classes
 except ImportError as e:
     print(f"❌ Missing required package: {e}")
     print("💡 Install required packages with:")
@@ -30,8 +35,8 @@ print(f"✅ All dependencies loaded successfully!")
 # ----------------------
 # Configuration Parameters
 # ----------------------
-dataset_dir = 'dataset' # Directory containing the dataset of cricket ball images
-models_dir = 'models'   # Directory to save trained models
+dataset_dir = option(sys.argv[1:], '--dataset-dir', 'dataset') # Directory containing the dataset of cricket ball images
+models_dir = option(sys.argv[1:], '--models-dir', 'models')    # Directory to save trained models
 
 # Create models directory if it doesn't exist
 if not os.path.exists(models_dir):
@@ -44,7 +49,6 @@ if not os.path.exists(dataset_dir):
 batch_size = 16         # Number of images processed together
 num_epochs = 15         # How many times to go through the training data
 learning_rate = 0.001   # How fast the model learns (step size)
-num_classes = 2         # Match-ready vs non-match-ready (2 classes)
 k_folds = 3             # Split data into 5 parts for cross-validation
 
 # Use Apple Silicon GPU if available, otherwise CUDA GPU, otherwise CPU
@@ -210,8 +214,9 @@ def validate_epoch(model, dataloader, criterion):
 # Load and Explore Dataset
 # ----------------------
 print("📁 Loading cricket ball dataset...")
-full_dataset = datasets.ImageFolder(dataset_dir)  # Load images from folder structure
-class_names = full_dataset.classes  # Get class names from folder names
+class_names = training_classes(dataset_dir)  # The backend's label taxonomy, one folder per label
+full_dataset = ClassFolder(dataset_dir, class_names)  # Load images from folder structure
+num_classes = len(class_names)
 print(f"✅ Found {len(full_dataset)} total images")
 print(f"📊 Classes: {class_names}")
 print(f"📊 Images per class: {[full_dataset.targets.count(i) for i in range(len(class_names))]}")
@@ -332,4 +337,23 @@ print(f"   📊 Average Accuracy: {average_accuracy:.4f} ({average_accuracy*100:
 print(f"   📊 Standard Deviation: {std_accuracy:.4f}")
 print(f"   📊 Best Fold: {max(fold_results):.4f}")
 print(f"   📊 Worst Fold: {min(fold_results):.4f}")
-print("="*50)
\ No newline at end of file
+print("="*50)
+
+# Save the evaluation results alongside the models, for the backend's dashboard
+metrics = {
+    "fold_accuracies": [float(accuracy) for accuracy in fold_results],
+    "average_accuracy": float(average_accuracy),
+    "std_accuracy": float(std_accuracy),
+    "num_epochs": num_epochs,
+    "k_folds": k_folds,
+    "images_per_class": {name: full_dataset.targets.count(i) for i, name in enumerate(class_names)},
+}
+metrics_filename = os.path.join(models_dir, "metrics.json")
+with open(metrics_filename, "w") as f:
+    json.dump(metrics, f, indent=2)
+print(f"💾 Evaluation metrics saved: {metrics_filename}")
+save_classes(models_dir, class_names)
+
+# Fit a temperature on the validation folds so reported confidences match how often the models are right
+print("\n🌡️  Calibrating confidences...")
+calibrate(models_dir, dataset_dir, device)
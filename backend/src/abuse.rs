@@ -0,0 +1,115 @@
+use chrono::Utc;
+use std::time::Duration;
+
+use crate::cache;
+use crate::rate_limit::client_id;
+use crate::request_logger::RequestLogger;
+
+/// Rejected uploads in a row after which a client is locked out.
+const STREAK_LIMIT: i64 = 10;
+/// How long a streak of rejections lasts from its first rejection.
+const STREAK_TTL: Duration = Duration::from_secs(10 * 60);
+/// Length of a client's first lockout; each further lockout within `OFFENCE_TTL` doubles it, up to `MAX_LOCKOUT_SECS`.
+const BASE_LOCKOUT_SECS: u64 = 60;
+const MAX_LOCKOUT_SECS: u64 = 60 * 60;
+/// How long a lockout counts towards the length of the next.
+const OFFENCE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn streak_key(client: &str) -> String {
+    format!("abuse:streak:{}", client)
+}
+
+fn offences_key(client: &str) -> String {
+    format!("abuse:offences:{}", client)
+}
+
+fn lockout_key(client: &str) -> String {
+    format!("abuse:lockout:{}", client)
+}
+
+/// Length of a client's `offence`th lockout in a day, in seconds.
+fn lockout_secs(offence: i64) -> u64 {
+    let doublings = offence.clamp(1, 32) as u32 - 1;
+    BASE_LOCKOUT_SECS.saturating_mul(2u64.saturating_pow(doublings)).min(MAX_LOCKOUT_SECS)
+}
+
+/// Turns away clients locked out for sending too many invalid uploads, with `429` and a `Retry-After` header.
+/// Requests are let through if the shared store is unavailable.
+pub async fn check(req: &rusty_api::HttpRequest, logger: &RequestLogger) -> Result<(), rusty_api::HttpResponse> {
+    let Some(store) = cache::store_for_request(logger).await else { return Ok(()) };
+
+    let client = client_id(req);
+    let locked_until = match store.get(&lockout_key(&client)).await {
+        Ok(value) => value.and_then(|v| v.parse::<i64>().ok()),
+        Err(message) => {
+            logger.error(format!("Failed to check upload lockout: {}", message));
+            return Ok(());
+        }
+    };
+
+    match locked_until {
+        Some(until) => {
+            let retry_after = (until - Utc::now().timestamp()).max(1);
+            logger.error(format!("Rejected upload from locked out client {}", client));
+            Err(rusty_api::HttpResponse::TooManyRequests()
+                .insert_header(("Retry-After", retry_after.to_string()))
+                .body(format!("Too many invalid uploads; try again in {} seconds", retry_after)))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Counts a rejected upload, e.g. a payload with unexpected fields, that isn't an image or is too large,
+/// towards the client's streak, locking the client out once the streak reaches `STREAK_LIMIT`.
+pub async fn record_rejection(req: &rusty_api::HttpRequest, logger: &RequestLogger) {
+    let Some(store) = cache::store_for_request(logger).await else { return };
+
+    let client = client_id(req);
+    let result: Result<(), String> = async {
+        if store.increment(&streak_key(&client), STREAK_TTL).await? < STREAK_LIMIT {
+            return Ok(());
+        }
+
+        let offence = store.increment(&offences_key(&client), OFFENCE_TTL).await?;
+        let secs = lockout_secs(offence);
+        let until = Utc::now().timestamp() + secs as i64;
+        store.set(&lockout_key(&client), &until.to_string(), Duration::from_secs(secs)).await?;
+        store.set(&streak_key(&client), "0", STREAK_TTL).await?;
+        logger.error(format!("Locked out {} for {} seconds after {} invalid uploads", client, secs, STREAK_LIMIT));
+        Ok(())
+    }
+    .await;
+
+    if let Err(message) = result {
+        logger.error(format!("Failed to record rejected upload: {}", message));
+    }
+}
+
+/// Ends the client's streak of rejected uploads after a valid one.
+pub async fn record_success(req: &rusty_api::HttpRequest, logger: &RequestLogger) {
+    let Some(store) = cache::store_for_request(logger).await else { return };
+
+    let key = streak_key(&client_id(req));
+    let result = match store.get(&key).await {
+        Ok(Some(streak)) if streak != "0" => store.set(&key, "0", STREAK_TTL).await,
+        Ok(_) => Ok(()),
+        Err(message) => Err(message),
+    };
+    if let Err(message) = result {
+        logger.error(format!("Failed to reset rejected upload streak: {}", message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lockouts_double_up_to_an_hour() {
+        assert_eq!(lockout_secs(1), 60);
+        assert_eq!(lockout_secs(2), 120);
+        assert_eq!(lockout_secs(6), 1920);
+        assert_eq!(lockout_secs(7), 3600);
+        assert_eq!(lockout_secs(1000), 3600);
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{Days, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+pub use crate::api_types::{Bin, ConfidenceDistribution};
+use crate::api_types::ConfidenceResponse;
+use crate::db;
+use crate::predictions::format_timestamp;
+use crate::request_logger::RequestLogger;
+
+/// How many days of predictions are included unless `days` is given, and the most that can be asked for.
+const DEFAULT_DAYS: u64 = 7;
+const MAX_DAYS: u64 = 365;
+
+/// How many equal-width confidence bins are used unless `bins` is given, and the most that can be asked for.
+const DEFAULT_BINS: usize = 10;
+const MAX_BINS: usize = 50;
+
+/// Splits confidences into `bins` equal-width bins between 0 and 1.
+pub fn histogram(confidences: &[f64], bins: usize) -> ConfidenceDistribution {
+    let mut counts = vec![0i64; bins];
+    for confidence in confidences {
+        let bin = (confidence.clamp(0.0, 1.0) * bins as f64) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+
+    ConfidenceDistribution {
+        predictions: confidences.len() as i64,
+        mean_confidence: (!confidences.is_empty()).then(|| confidences.iter().sum::<f64>() / confidences.len() as f64),
+        histogram: counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| Bin { from: i as f64 / bins as f64, to: (i + 1) as f64 / bins as f64, count })
+            .collect(),
+    }
+}
+
+/// Verdict and confidence of each prediction recorded at or after `since`, optionally only from one model version.
+pub async fn confidences(pool: &db::Pool, since: &str, model_version: Option<&str>) -> Result<Vec<(String, f64)>, sqlx::Error> {
+    sqlx::query_as::<_, (String, f64)>(
+        "SELECT prediction, confidence FROM predictions
+         WHERE created_at >= $1 AND ($2 IS NULL OR model_version = $2)"
+    )
+    .bind(since)
+    .bind(model_version)
+    .fetch_all(pool)
+    .await
+}
+
+/// Query parameters accepted by `/analytics/confidence`.
+#[derive(Debug, Deserialize)]
+pub struct ConfidenceQuery {
+    /// How many days of predictions to include.
+    pub days: Option<u64>,
+    /// How many bins to split confidences into.
+    pub bins: Option<usize>,
+    /// Only include predictions from this model version.
+    pub model_version: Option<String>,
+}
+
+/// Confidence route handler returning a histogram of recent prediction confidences for each verdict,
+/// so a model that has started hedging can be spotted before its accuracy visibly drops.
+pub async fn confidence_route(query: rusty_api::web::Query<ConfidenceQuery>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /analytics/confidence");
+
+    let days = query.days.unwrap_or(DEFAULT_DAYS);
+    if days == 0 || days > MAX_DAYS {
+        return rusty_api::HttpResponse::BadRequest().body(format!("days must be between 1 and {}", MAX_DAYS));
+    }
+    let bins = query.bins.unwrap_or(DEFAULT_BINS);
+    if !(2..=MAX_BINS).contains(&bins) {
+        return rusty_api::HttpResponse::BadRequest().body(format!("bins must be between 2 and {}", MAX_BINS));
+    }
+    let since = Utc::now().date_naive() - Days::new(days - 1);
+    let since = format_timestamp(since.and_hms_opt(0, 0, 0).unwrap().and_utc());
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let rows = match confidences(pool, &since, query.model_version.as_deref()).await {
+        Ok(rows) => rows,
+        Err(e) => {
+            logger.error(format!("Failed to load prediction confidences: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    let mut by_verdict: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for (verdict, confidence) in &rows {
+        by_verdict.entry(verdict.clone()).or_default().push(*confidence);
+    }
+    let all: Vec<f64> = rows.iter().map(|(_, confidence)| *confidence).collect();
+
+    rusty_api::HttpResponse::Ok().json(ConfidenceResponse {
+        since,
+        model_version: query.model_version.clone(),
+        all: histogram(&all, bins),
+        verdicts: by_verdict
+            .into_iter()
+            .map(|(verdict, confidences)| (verdict, histogram(&confidences, bins)))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confidences_are_binned_including_the_upper_bound() {
+        let distribution = histogram(&[0.05, 0.55, 0.58, 0.9, 1.0], 4);
+        let counts: Vec<i64> = distribution.histogram.iter().map(|bin| bin.count).collect();
+        assert_eq!(counts, vec![1, 0, 2, 2]);
+        assert_eq!((distribution.histogram[3].from, distribution.histogram[3].to), (0.75, 1.0));
+        assert_eq!(distribution.predictions, 5);
+        assert!((distribution.mean_confidence.unwrap() - 0.616).abs() < 1e-9);
+
+        assert_eq!(histogram(&[], 4).mean_confidence, None);
+    }
+}
@@ -0,0 +1,498 @@
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::api_types::{ApiKeyRecord, ApiKeyResponse, ApiKeysResponse, InvalidResponse, IssuedApiKeyResponse};
+use crate::auth;
+use crate::config;
+use crate::db;
+use crate::predictions::format_timestamp;
+use crate::profiles;
+use crate::request_logger::RequestLogger;
+
+/// What a key can be used for: `predict` covers `/predict` and prediction items in `/sync`,
+/// `training` covers `/training` and training items in `/sync`.
+pub const SCOPES: [&str; 2] = ["predict", "training"];
+
+/// Prefix of every issued key, so leaked keys are easy to spot in logs and code.
+const KEY_PREFIX: &str = "crk_";
+
+/// Number of random characters after the prefix.
+const KEY_LENGTH: usize = 32;
+
+/// Number of leading characters of a key kept in the clear to tell keys apart.
+const VISIBLE_LENGTH: usize = 8;
+
+/// An issued API key, as stored in the metadata database. The key itself is never stored.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: i64,
+    /// Who the key was issued to, e.g. the club's name.
+    pub name: String,
+    /// The key's first characters.
+    pub key_prefix: String,
+    /// Strictness profile applied to the key's requests unless they ask for another.
+    pub profile: Option<String>,
+    /// The scopes the key allows, comma-separated.
+    pub scopes: String,
+    pub created_at: String,
+    pub rotated_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl ApiKey {
+    /// Stands in for a key configured in `API_KEY_PROFILES` rather than issued, so it passes `require_key`.
+    /// Such keys allow every scope, have ID 0 and are never stored.
+    fn configured(key: &str, profile: &str) -> ApiKey {
+        ApiKey {
+            id: 0,
+            name: "API_KEY_PROFILES".to_string(),
+            key_prefix: key.chars().take(VISIBLE_LENGTH).collect(),
+            profile: Some(profile.to_string()),
+            scopes: SCOPES.join(","),
+            created_at: String::new(),
+            rotated_at: None,
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    /// Whether the key may be used for `scope`.
+    pub fn allows(&self, scope: &str) -> bool {
+        self.scopes.split(',').any(|s| s == scope)
+    }
+}
+
+impl From<ApiKey> for ApiKeyRecord {
+    fn from(key: ApiKey) -> Self {
+        ApiKeyRecord {
+            id: key.id,
+            name: key.name,
+            key_prefix: key.key_prefix,
+            profile: key.profile,
+            scopes: key.scopes.split(',').filter(|s| !s.is_empty()).map(str::to_string).collect(),
+            created_at: key.created_at,
+            rotated_at: key.rotated_at,
+            last_used_at: key.last_used_at,
+            revoked_at: key.revoked_at,
+        }
+    }
+}
+
+const COLUMNS: &str = "id, name, key_prefix, profile, scopes, created_at, rotated_at, last_used_at, revoked_at";
+
+/// Generates a new random key.
+fn new_key() -> String {
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(KEY_LENGTH)
+        .map(char::from)
+        .collect();
+    format!("{}{}", KEY_PREFIX, random)
+}
+
+/// Hashes a key for storage and lookup. Keys are long and random, so a plain SHA-256 is enough.
+fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Checks a key's profile and scopes, returning the scopes in storage form.
+fn validate(profile: Option<&str>, scopes: &[String]) -> Result<String, Vec<String>> {
+    let mut errors = Vec::new();
+    if let Some(name) = profile {
+        if profiles::find(name).is_none() {
+            errors.push(format!("Unknown profile '{}'", name));
+        }
+    }
+    if scopes.is_empty() {
+        errors.push(format!("At least one scope is required: {}", SCOPES.join(", ")));
+    }
+    for scope in scopes {
+        if !SCOPES.contains(&scope.as_str()) {
+            errors.push(format!("Unknown scope '{}'. Available scopes: {}", scope, SCOPES.join(", ")));
+        }
+    }
+    if errors.is_empty() {
+        // Stored in a fixed order without duplicates
+        Ok(SCOPES.iter().filter(|s| scopes.iter().any(|scope| scope == *s)).copied().collect::<Vec<_>>().join(","))
+    } else {
+        Err(errors)
+    }
+}
+
+/// Issues a new key, returning it with the key itself, which can't be recovered later.
+pub async fn create(pool: &db::Pool, name: &str, profile: Option<&str>, scopes: &str) -> Result<(ApiKey, String), sqlx::Error> {
+    let key = new_key();
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        "INSERT INTO api_keys (name, key_hash, key_prefix, profile, scopes, created_at) VALUES ($1, $2, $3, $4, $5, $6) RETURNING {}",
+        COLUMNS
+    ))
+    .bind(name)
+    .bind(hash_key(&key))
+    .bind(&key[..VISIBLE_LENGTH])
+    .bind(profile)
+    .bind(scopes)
+    .bind(format_timestamp(Utc::now()))
+    .fetch_one(pool)
+    .await?;
+    Ok((api_key, key))
+}
+
+/// Returns every key, including revoked ones, oldest first.
+pub async fn list(pool: &db::Pool) -> Result<Vec<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(&format!("SELECT {} FROM api_keys ORDER BY id", COLUMNS))
+        .fetch_all(pool)
+        .await
+}
+
+/// Looks up the unrevoked key matching `key`.
+pub async fn find_active(pool: &db::Pool, key: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(&format!("SELECT {} FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL", COLUMNS))
+        .bind(hash_key(key))
+        .fetch_optional(pool)
+        .await
+}
+
+/// Replaces an unrevoked key's profile and scopes. Returns `None` if there is no such key.
+pub async fn update(pool: &db::Pool, id: i64, profile: Option<&str>, scopes: &str) -> Result<Option<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(&format!(
+        "UPDATE api_keys SET profile = $1, scopes = $2 WHERE id = $3 AND revoked_at IS NULL RETURNING {}",
+        COLUMNS
+    ))
+    .bind(profile)
+    .bind(scopes)
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Replaces an unrevoked key with a new one, keeping its name, profile and scopes.
+/// The old key stops working immediately. Returns `None` if there is no such key.
+pub async fn rotate(pool: &db::Pool, id: i64) -> Result<Option<(ApiKey, String)>, sqlx::Error> {
+    let key = new_key();
+    let api_key = sqlx::query_as::<_, ApiKey>(&format!(
+        "UPDATE api_keys SET key_hash = $1, key_prefix = $2, rotated_at = $3 WHERE id = $4 AND revoked_at IS NULL RETURNING {}",
+        COLUMNS
+    ))
+    .bind(hash_key(&key))
+    .bind(&key[..VISIBLE_LENGTH])
+    .bind(format_timestamp(Utc::now()))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(api_key.map(|api_key| (api_key, key)))
+}
+
+/// Revokes a key for good. Returns `None` if there is no such key or it was already revoked.
+pub async fn revoke(pool: &db::Pool, id: i64) -> Result<Option<ApiKey>, sqlx::Error> {
+    sqlx::query_as::<_, ApiKey>(&format!(
+        "UPDATE api_keys SET revoked_at = $1 WHERE id = $2 AND revoked_at IS NULL RETURNING {}",
+        COLUMNS
+    ))
+    .bind(format_timestamp(Utc::now()))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Records that a key was just used.
+async fn touch(pool: &db::Pool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE api_keys SET last_used_at = $1 WHERE id = $2")
+        .bind(format_timestamp(Utc::now()))
+        .bind(id)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+/// Identifies the key a request was made with from its `X-Api-Key` header.
+/// Requests without a key give `None`, and a key still configured in `API_KEY_PROFILES` is let through with
+/// every scope; any other key must have been issued and not revoked, or the request is refused with `401`.
+pub async fn authenticate(req: &rusty_api::HttpRequest, logger: &RequestLogger) -> Result<Option<ApiKey>, rusty_api::HttpResponse> {
+    let key = match req.headers().get("X-Api-Key").and_then(|h| h.to_str().ok()) {
+        Some(key) => key,
+        None => return Ok(None),
+    };
+    if let Some(profile) = config::get().api_key_profiles.get(key) {
+        return Ok(Some(ApiKey::configured(key, profile)));
+    }
+
+    let pool = db::pool_for_request(logger).await?;
+    match find_active(pool, key).await {
+        Ok(Some(api_key)) => {
+            if let Err(e) = touch(pool, api_key.id).await {
+                logger.error(format!("Failed to record use of API key {}: {}", api_key.id, e));
+            }
+            Ok(Some(api_key))
+        }
+        Ok(None) => {
+            logger.error("Rejected unknown or revoked API key");
+            Err(rusty_api::HttpResponse::Unauthorized().body("Invalid or revoked API key"))
+        }
+        Err(e) => {
+            logger.error(format!("Failed to look up API key: {}", e));
+            Err(rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e)))
+        }
+    }
+}
+
+/// Refuses a request with `403` if its key doesn't allow `scope`. Requests without an issued key are allowed.
+pub fn require_scope(api_key: Option<&ApiKey>, scope: &str, logger: &RequestLogger) -> Result<(), rusty_api::HttpResponse> {
+    match api_key {
+        Some(api_key) if !api_key.allows(scope) => {
+            logger.error(format!("API key {} lacks the '{}' scope", api_key.id, scope));
+            Err(rusty_api::HttpResponse::Forbidden().body(format!("API key is not allowed to use '{}'", scope)))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuses a request with `401` unless it carries an issued or configured key, and with `403` if that key doesn't allow `scope`.
+/// For routes that mustn't be open to anyone, unlike those `require_scope` guards.
+pub fn require_key(api_key: Option<&ApiKey>, scope: &str, logger: &RequestLogger) -> Result<(), rusty_api::HttpResponse> {
+    if api_key.is_none() {
+        logger.error("Rejected request without an API key");
+        return Err(rusty_api::HttpResponse::Unauthorized().body("An API key issued with /admin/api-keys/new is required"));
+    }
+    require_scope(api_key, scope, logger)
+}
+
+/// Body accepted by `/admin/api-keys/new`.
+#[derive(Debug, Deserialize)]
+pub struct CreateInput {
+    pub name: String,
+    pub profile: Option<String>,
+    /// Defaults to every scope.
+    pub scopes: Option<Vec<String>>,
+}
+
+/// Body accepted by `PUT /admin/api-keys/{id}`.
+#[derive(Debug, Deserialize)]
+pub struct UpdateInput {
+    pub profile: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+/// Create route handler issuing a new API key. The key is only ever shown in this response.
+/// Requires the admin token.
+pub async fn create_route(req: rusty_api::HttpRequest, body: rusty_api::web::Json<CreateInput>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/api-keys/new");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized API key creation");
+        return resp;
+    }
+
+    let scopes = body.scopes.clone().unwrap_or_else(|| SCOPES.iter().map(|s| s.to_string()).collect());
+    let mut errors = Vec::new();
+    if body.name.trim().is_empty() {
+        errors.push("name is required".to_string());
+    }
+    let scopes = validate(body.profile.as_deref(), &scopes).unwrap_or_else(|mut invalid| {
+        errors.append(&mut invalid);
+        String::new()
+    });
+    if !errors.is_empty() {
+        logger.error(format!("Rejected API key: {}", errors.join("; ")));
+        return rusty_api::HttpResponse::BadRequest().json(InvalidResponse { status: "invalid", errors });
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match create(pool, body.name.trim(), body.profile.as_deref(), &scopes).await {
+        Ok((api_key, key)) => {
+            logger.info(format!("Issued API key {} to {}", api_key.id, api_key.name));
+            rusty_api::HttpResponse::Ok().json(IssuedApiKeyResponse { status: "success", key, api_key: api_key.into() })
+        }
+        Err(e) => {
+            logger.error(format!("Failed to create API key: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// List route handler returning every API key, without the keys themselves. Requires the admin token.
+pub async fn list_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/api-keys");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized API key listing");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match list(pool).await {
+        Ok(api_keys) => rusty_api::HttpResponse::Ok().json(ApiKeysResponse { api_keys: api_keys.into_iter().map(ApiKeyRecord::from).collect() }),
+        Err(e) => {
+            logger.error(format!("Failed to list API keys: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Update route handler replacing an API key's profile and scopes. Requires the admin token.
+pub async fn update_route(
+    req: rusty_api::HttpRequest,
+    path: rusty_api::web::Path<i64>,
+    body: rusty_api::web::Json<UpdateInput>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let id = path.into_inner();
+
+    logger.info(format!("Received request to update API key {}", id));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized API key update");
+        return resp;
+    }
+
+    let scopes = match validate(body.profile.as_deref(), &body.scopes) {
+        Ok(scopes) => scopes,
+        Err(errors) => {
+            logger.error(format!("Rejected API key update: {}", errors.join("; ")));
+            return rusty_api::HttpResponse::BadRequest().json(InvalidResponse { status: "invalid", errors });
+        }
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match update(pool, id, body.profile.as_deref(), &scopes).await {
+        Ok(Some(api_key)) => {
+            logger.info(format!("Updated API key {}: profile {:?}, scopes {}", id, api_key.profile, api_key.scopes));
+            rusty_api::HttpResponse::Ok().json(ApiKeyResponse { status: "success", api_key: api_key.into() })
+        }
+        Ok(None) => rusty_api::HttpResponse::NotFound().body(format!("API key {} not found or revoked", id)),
+        Err(e) => {
+            logger.error(format!("Failed to update API key {}: {}", id, e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Rotate route handler issuing a replacement for an API key. The old key stops working at once,
+/// and the new one is only ever shown in this response. Requires the admin token.
+pub async fn rotate_route(req: rusty_api::HttpRequest, path: rusty_api::web::Path<i64>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let id = path.into_inner();
+
+    logger.info(format!("Received request to rotate API key {}", id));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized API key rotation");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match rotate(pool, id).await {
+        Ok(Some((api_key, key))) => {
+            logger.info(format!("Rotated API key {}", id));
+            rusty_api::HttpResponse::Ok().json(IssuedApiKeyResponse { status: "success", key, api_key: api_key.into() })
+        }
+        Ok(None) => rusty_api::HttpResponse::NotFound().body(format!("API key {} not found or revoked", id)),
+        Err(e) => {
+            logger.error(format!("Failed to rotate API key {}: {}", id, e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Revoke route handler permanently disabling an API key. Requires the admin token.
+pub async fn revoke_route(req: rusty_api::HttpRequest, path: rusty_api::web::Path<i64>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let id = path.into_inner();
+
+    logger.info(format!("Received request to revoke API key {}", id));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized API key revocation");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match revoke(pool, id).await {
+        Ok(Some(api_key)) => {
+            logger.info(format!("Revoked API key {}", id));
+            rusty_api::HttpResponse::Ok().json(ApiKeyResponse { status: "success", api_key: api_key.into() })
+        }
+        Ok(None) => rusty_api::HttpResponse::NotFound().body(format!("API key {} not found or already revoked", id)),
+        Err(e) => {
+            logger.error(format!("Failed to revoke API key {}: {}", id, e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rotated_and_revoked_keys_stop_working() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let scopes = validate(Some("club"), &["training".to_string(), "predict".to_string()]).unwrap();
+        assert_eq!(scopes, "predict,training");
+
+        let (api_key, key) = create(&pool, "Hawks CC", Some("club"), &scopes).await.unwrap();
+        assert!(key.starts_with(&api_key.key_prefix));
+        assert_eq!(find_active(&pool, &key).await.unwrap().unwrap().id, api_key.id);
+
+        let (_, rotated) = rotate(&pool, api_key.id).await.unwrap().unwrap();
+        assert!(find_active(&pool, &key).await.unwrap().is_none());
+        assert!(find_active(&pool, &rotated).await.unwrap().is_some());
+
+        let updated = update(&pool, api_key.id, None, "predict").await.unwrap().unwrap();
+        assert!(updated.allows("predict") && !updated.allows("training"));
+
+        assert!(revoke(&pool, api_key.id).await.unwrap().is_some());
+        assert!(find_active(&pool, &rotated).await.unwrap().is_none());
+        assert!(revoke(&pool, api_key.id).await.unwrap().is_none());
+        assert!(rotate(&pool, api_key.id).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn configured_keys_pass_every_key_check() {
+        let logger = RequestLogger::new(1);
+        let api_key = ApiKey::configured("club-secret", "club");
+        assert_eq!(api_key.key_prefix, "club-sec");
+        assert_eq!(api_key.profile.as_deref(), Some("club"));
+        for scope in SCOPES {
+            assert!(require_key(Some(&api_key), scope, &logger).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_scopes_and_profiles_are_rejected() {
+        assert_eq!(validate(Some("test"), &["admin".to_string()]).unwrap_err().len(), 2);
+        assert!(validate(None, &[]).is_err());
+    }
+}
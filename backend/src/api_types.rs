@@ -0,0 +1,1698 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Defines a struct shared with the generated clients. Fields must be `pub` and may carry doc comments and `serde`
+/// attributes; `rename`, `default`, `skip` and `skip_serializing_if` are reflected in the generated types.
+macro_rules! client_struct {
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident {
+            $( $(#[$field_meta:meta])* pub $field:ident: $ty:ty ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        pub struct $name {
+            $( $(#[$field_meta])* pub $field: $ty ),*
+        }
+
+        #[cfg(test)]
+        impl codegen::ClientType for $name {
+            fn definition() -> codegen::TypeDef {
+                codegen::TypeDef {
+                    name: stringify!($name),
+                    docs: codegen::docs(&[$(stringify!($meta)),*]),
+                    shape: codegen::Shape::Struct(
+                        vec![$( codegen::Field::new(stringify!($field), stringify!($ty), &[$(stringify!($field_meta)),*]) ),*]
+                            .into_iter()
+                            .flatten()
+                            .collect(),
+                    ),
+                }
+            }
+        }
+    };
+}
+
+/// Defines an enum of unit variants shared with the generated clients. It must derive `Serialize`,
+/// which gives the string each variant is sent as.
+macro_rules! client_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $( $(#[$variant_meta:meta])* $variant:ident ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        pub enum $name {
+            $( $(#[$variant_meta])* $variant ),*
+        }
+
+        #[cfg(test)]
+        impl codegen::ClientType for $name {
+            fn definition() -> codegen::TypeDef {
+                codegen::TypeDef {
+                    name: stringify!($name),
+                    docs: codegen::docs(&[$(stringify!($meta)),*]),
+                    shape: codegen::Shape::Enum(vec![
+                        $( codegen::Variant {
+                            value: serde_json::to_value($name::$variant).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default(),
+                            docs: codegen::docs(&[$(stringify!($variant_meta)),*]),
+                        } ),*
+                    ]),
+                }
+            }
+        }
+    };
+}
+
+client_enum! {
+    /// Number type a model version's weights are stored in. Lower precisions trade a little accuracy
+    /// for lower latency and memory, e.g. on a Raspberry Pi.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Precision {
+        /// The weights as trained, in `model_{1,2,3}.pth`.
+        #[default]
+        Fp32,
+        /// Half-precision copies made by `quantize.py`, in `model_{1,2,3}.fp16.pth`. Best suited to GPUs.
+        Fp16,
+        /// Quantized TorchScript models made by `quantize.py`, in `model_{1,2,3}.int8.pt`. Run on the CPU.
+        Int8,
+    }
+}
+
+client_enum! {
+    /// How the photo quality pre-check affects a request.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum QualityMode {
+        /// Skip the check entirely.
+        Off,
+        /// Attach any issues to the response as warnings.
+        Warn,
+        /// Reject photos with any issue before running the classifier.
+        Reject,
+    }
+}
+
+client_struct! {
+    /// A problem found with a photo, with guidance on how to fix it.
+    #[derive(Debug, Clone, Serialize)]
+    pub struct QualityIssue {
+        /// Stable code: `too_blurry`, `too_dark`, `too_bright` or `ball_too_small`.
+        pub code: &'static str,
+        /// How to retake the photo, in the client's language.
+        pub message: &'static str,
+    }
+}
+
+client_struct! {
+    /// Body of the `422` response to a photo rejected by the quality pre-check.
+    #[derive(Debug, Serialize)]
+    pub struct QualityRejection {
+        pub status: &'static str,
+        pub code: &'static str,
+        pub message: &'static str,
+        pub issues: Vec<QualityIssue>,
+    }
+}
+
+client_struct! {
+    /// Body of the `400` response to a request that failed validation, with one message per problem.
+    #[derive(Debug, Serialize)]
+    pub struct InvalidResponse {
+        pub status: &'static str,
+        pub errors: Vec<String>,
+    }
+}
+
+client_struct! {
+    /// The second-opinion model's verdict on a `/predict` photo, under the same profile.
+    #[derive(Debug, Serialize)]
+    pub struct SecondOpinion {
+        pub prediction: String,
+        pub confidence: f64,
+        pub model_prediction: String,
+        pub model_version: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub uncertainty: Option<f64>,
+    }
+}
+
+client_struct! {
+    /// Response from `/predict`.
+    #[derive(Debug, Serialize)]
+    pub struct PredictResponse {
+        /// ID of the stored prediction, for `/predictions/{id}`; `null` if it couldn't be recorded.
+        pub prediction_id: Option<i64>,
+        /// The verdict under the profile: a label from the taxonomy, or `unknown`.
+        pub prediction: String,
+        pub confidence: f64,
+        /// The model's verdict before the profile's threshold was applied.
+        pub model_prediction: String,
+        pub model_version: String,
+        pub model_precision: Precision,
+        pub profile: &'static str,
+        pub ball_id: Option<String>,
+        /// Spread of the match-ready probability across dropout passes, when asked for with `uncertainty=true` and the `uncertainty` flag is on.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub uncertainty: Option<f64>,
+        /// Whether the photo was enhanced, when asked for with `enhance=true` and the `enhance` flag is on.
+        pub enhanced: bool,
+        /// The enhanced photo as a JPEG data URL, when asked for with `return_enhanced=true`.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub enhanced_image: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub second_opinion: Option<SecondOpinion>,
+        /// Whether the second opinion reached the same verdict.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub agreement: Option<bool>,
+        pub verdict: String,
+        pub recommendation: &'static str,
+        pub quality_warnings: Vec<QualityIssue>,
+    }
+}
+
+client_struct! {
+    /// Where a ball is in the original photo, in pixels.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+    pub struct BoundingBox {
+        pub x: u32,
+        pub y: u32,
+        pub width: u32,
+        pub height: u32,
+    }
+}
+
+client_struct! {
+    /// The verdict on one ball found by `/predict/multi`.
+    #[derive(Debug, Serialize)]
+    pub struct BallPrediction {
+        #[serde(rename = "box")]
+        pub bounding_box: BoundingBox,
+        /// ID of the stored prediction, for `/predictions/{id}`; `null` if it couldn't be recorded.
+        pub prediction_id: Option<i64>,
+        pub prediction: String,
+        pub confidence: f64,
+        pub model_prediction: String,
+        pub model_version: String,
+        pub model_precision: Precision,
+        pub verdict: String,
+        pub recommendation: &'static str,
+    }
+}
+
+client_struct! {
+    /// Response from `/predict/multi`.
+    #[derive(Debug, Serialize)]
+    pub struct PredictMultiResponse {
+        pub count: usize,
+        pub balls: Vec<BallPrediction>,
+        pub profile: &'static str,
+        pub quality_warnings: Vec<QualityIssue>,
+    }
+}
+
+client_struct! {
+    /// One model version's verdict in `/predict/compare-models`.
+    #[derive(Debug, Serialize)]
+    pub struct ModelComparison {
+        pub model_version: String,
+        pub prediction: String,
+        pub confidence: f64,
+        pub model_prediction: String,
+        pub model_precision: Precision,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub uncertainty: Option<f64>,
+    }
+}
+
+client_struct! {
+    /// Response from `/predict/compare-models`.
+    #[derive(Debug, Serialize)]
+    pub struct CompareModelsResponse {
+        pub profile: &'static str,
+        pub models: Vec<ModelComparison>,
+        /// Whether every model reached the same verdict under the profile.
+        pub agreement: bool,
+        /// Whether every model's own verdict was the same.
+        pub model_agreement: bool,
+    }
+}
+
+client_struct! {
+    /// Response from `/training`.
+    #[derive(Debug, Serialize)]
+    pub struct TrainingResponse {
+        pub status: &'static str,
+        pub message: &'static str,
+        pub filename: String,
+        pub label: String,
+        pub request_id: i64,
+        /// ID of the sample recorded for review; `null` if it couldn't be recorded.
+        pub sample_id: Option<i64>,
+        pub quality_warnings: Vec<QualityIssue>,
+    }
+}
+
+client_struct! {
+    /// Body accepted by `/predictions/{id}/correct`.
+    #[derive(Debug, Deserialize)]
+    pub struct CorrectionInput {
+        /// What the ball really was: a label from the taxonomy.
+        pub label: String,
+        pub contributor: Option<String>,
+    }
+}
+
+client_struct! {
+    /// Response from `/predictions/{id}/correct`.
+    #[derive(Debug, Serialize)]
+    pub struct CorrectionResponse {
+        pub status: &'static str,
+        pub prediction_id: i64,
+        /// ID of the sample queued for review with the corrected label.
+        pub sample_id: i64,
+        /// The label the prediction was corrected to, and the prediction it corrects.
+        pub label: String,
+        pub prediction: String,
+    }
+}
+
+client_enum! {
+    /// The kind of work queued on the phone.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum ItemKind {
+        Prediction,
+        Training,
+    }
+}
+
+client_struct! {
+    /// One queued request, referring to an image sent in the same multipart payload.
+    #[derive(Debug, Deserialize)]
+    pub struct SyncItem {
+        /// ID generated on the phone, used to make retries idempotent.
+        pub client_id: String,
+        #[serde(rename = "type")]
+        pub kind: ItemKind,
+        /// Name of the multipart field holding this item's image.
+        pub image: String,
+        #[serde(default)]
+        pub ball_id: Option<String>,
+        #[serde(default)]
+        pub label: Option<String>,
+        #[serde(default)]
+        pub contributor: Option<String>,
+    }
+}
+
+client_struct! {
+    /// The `manifest` field of a `/sync` upload, describing every item in the batch.
+    #[derive(Debug, Deserialize)]
+    pub struct SyncManifest {
+        pub items: Vec<SyncItem>,
+    }
+}
+
+client_enum! {
+    /// What became of a synced item.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum SyncStatus {
+        /// A training image was stored.
+        Saved,
+        Predicted,
+        /// The item was synced before; `id` is what it produced then.
+        Duplicate,
+        /// The photo failed the quality pre-check; see `issues`.
+        Rejected,
+        /// The item couldn't be processed; see `error`.
+        Error,
+    }
+}
+
+client_struct! {
+    /// The result for one item of a `/sync` batch. Prediction fields are only given for predicted items.
+    #[derive(Debug, Serialize)]
+    pub struct SyncResult {
+        pub client_id: String,
+        #[serde(rename = "type")]
+        pub kind: ItemKind,
+        pub status: SyncStatus,
+        /// ID of the new sample or prediction.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub id: Option<i64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub issues: Option<Vec<QualityIssue>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub quality_warnings: Option<Vec<QualityIssue>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub error: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub prediction: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub confidence: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub model_prediction: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub profile: Option<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub model_version: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub ball_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub verdict: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub recommendation: Option<&'static str>,
+    }
+}
+
+client_struct! {
+    /// Response from `/sync`, with one result per item in manifest order.
+    #[derive(Debug, Serialize)]
+    pub struct SyncResponse {
+        pub status: &'static str,
+        pub request_id: i64,
+        pub results: Vec<SyncResult>,
+    }
+}
+
+client_struct! {
+    /// A label training images can be given, which is also a class folder in the dataset and a class the models predict.
+    #[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+    pub struct Label {
+        pub name: String,
+        pub description: String,
+        /// Position of the label when shown to contributors, lowest first.
+        pub display_order: i64,
+    }
+}
+
+client_struct! {
+    /// Response from `/labels`.
+    #[derive(Debug, Serialize)]
+    pub struct LabelsResponse {
+        pub labels: Vec<Label>,
+    }
+}
+
+client_enum! {
+    /// Where an upload session's upload has got to.
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum UploadStatus {
+        /// No bytes have arrived yet.
+        Waiting,
+        Receiving,
+        /// Every byte has arrived; the upload is being processed or has been.
+        Received,
+        /// The connection broke before the upload finished.
+        Interrupted,
+    }
+}
+
+client_struct! {
+    /// Response from `/uploads`.
+    #[derive(Debug, Serialize)]
+    pub struct UploadSession {
+        pub status: &'static str,
+        pub id: String,
+        /// Header to send the session ID in with the upload.
+        pub header: &'static str,
+        pub expires_in_seconds: u64,
+    }
+}
+
+client_struct! {
+    /// Response from `/uploads/{id}`.
+    #[derive(Debug, Serialize)]
+    pub struct UploadProgress {
+        pub id: String,
+        pub status: UploadStatus,
+        pub received_bytes: u64,
+        pub total_bytes: Option<u64>,
+        /// Share of the upload received so far, if its size is known.
+        pub fraction: Option<f64>,
+    }
+}
+
+client_struct! {
+    /// Response from `/tags/{tag}`.
+    #[derive(Debug, Serialize)]
+    pub struct TagResponse {
+        pub ball_id: String,
+        pub description: Option<String>,
+    }
+}
+
+client_struct! {
+    /// A single prediction served by `/predict`, as stored in the metadata database.
+    #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+    pub struct PredictionRecord {
+        pub id: i64,
+        pub request_id: i64,
+        pub created_at: String,
+        pub prediction: String,
+        pub confidence: f64,
+        pub image_size_bytes: i64,
+        pub profile: String,
+        pub model_prediction: Option<String>,
+        pub ball_id: Option<String>,
+        pub model_version: Option<String>,
+        /// The second-opinion model and its verdict under the profile, if one was asked for.
+        pub second_opinion_model: Option<String>,
+        pub second_opinion_prediction: Option<String>,
+        /// Content hash of the photo, if it was kept for replaying the prediction.
+        pub image_hash: Option<String>,
+    }
+}
+
+client_struct! {
+    /// Response from `/balls/{id}/predictions`, newest first.
+    #[derive(Debug, Serialize)]
+    pub struct BallHistoryResponse {
+        pub ball_id: String,
+        pub predictions: Vec<PredictionRecord>,
+    }
+}
+
+client_struct! {
+    /// What was served for a prediction when it was made, in `/admin/predictions/{id}/replay`.
+    #[derive(Debug, Serialize)]
+    pub struct ReplayOriginal {
+        pub created_at: String,
+        pub prediction: String,
+        pub confidence: f64,
+        pub model_prediction: Option<String>,
+        pub model_version: Option<String>,
+    }
+}
+
+client_struct! {
+    /// The verdict on a replayed prediction's photo now, in `/admin/predictions/{id}/replay`.
+    #[derive(Debug, Serialize)]
+    pub struct ReplayVerdict {
+        pub prediction: String,
+        pub confidence: f64,
+        pub model_prediction: String,
+        pub model_version: String,
+        pub model_precision: Precision,
+    }
+}
+
+client_struct! {
+    /// How a replayed prediction differs from the original.
+    #[derive(Debug, Serialize)]
+    pub struct ReplayDiff {
+        pub prediction_changed: bool,
+        pub model_prediction_changed: bool,
+        pub model_version_changed: bool,
+        /// The replay's confidence less the original's.
+        pub confidence_change: f64,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/predictions/{id}/replay`.
+    #[derive(Debug, Serialize)]
+    pub struct ReplayResponse {
+        pub id: i64,
+        pub image_hash: String,
+        /// The profile the prediction was made with, applied at its current threshold.
+        pub profile: &'static str,
+        pub original: ReplayOriginal,
+        pub replay: ReplayVerdict,
+        pub diff: ReplayDiff,
+    }
+}
+
+client_struct! {
+    /// The result of one of the checks made by `/ready`.
+    #[derive(Debug, Serialize)]
+    pub struct ReadinessCheck {
+        /// The service checked: `database`, `store` or `storage`.
+        pub name: &'static str,
+        pub ok: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub error: Option<String>,
+    }
+}
+
+client_struct! {
+    /// Response from `/ready`: `ready` with `200`, or `unavailable` with `503`.
+    #[derive(Debug, Serialize)]
+    pub struct ReadinessResponse {
+        pub status: &'static str,
+        pub checks: Vec<ReadinessCheck>,
+    }
+}
+
+client_struct! {
+    /// The device the classifier is configured for, and the one the last prediction ran on.
+    #[derive(Debug, Serialize)]
+    pub struct InferenceDevice {
+        pub configured: String,
+        pub active: Option<String>,
+    }
+}
+
+client_struct! {
+    /// Response from `/version`.
+    #[derive(Debug, Serialize)]
+    pub struct VersionResponse {
+        pub version: &'static str,
+        pub active_model: String,
+        pub model_precision: Precision,
+        pub inference_device: InferenceDevice,
+    }
+}
+
+client_struct! {
+    /// How and when a photo was taken, from its EXIF metadata, for analysing the dataset by phone and conditions.
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+    pub struct Capture {
+        /// When the photo was taken, in the phone's local time (`2024-05-04T15:42:10`), with its UTC offset if recorded.
+        pub captured_at: Option<String>,
+        pub camera_make: Option<String>,
+        pub camera_model: Option<String>,
+        pub focal_length_mm: Option<f64>,
+        /// Focal length as it would be on a full-frame camera, comparable between phones.
+        pub focal_length_35mm: Option<i64>,
+        pub f_number: Option<f64>,
+        /// Exposure time in seconds.
+        pub exposure_time: Option<f64>,
+        pub iso: Option<i64>,
+    }
+}
+
+client_struct! {
+    /// A training submission, as recorded in the training log. Fields added to the log later are left out of older entries.
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct TrainingLogEntry {
+        /// When the image was received, as an RFC 3339 timestamp.
+        pub timestamp: String,
+        pub request_id: i64,
+        pub label: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub contributor: Option<String>,
+        pub filename: String,
+        pub file_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub content_hash: Option<String>,
+        pub image_size_bytes: u64,
+        /// Size of the image as stored, after any recompression.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub stored_size_bytes: Option<u64>,
+        /// The `/sync` item the image came in, for images queued on a phone.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub client_id: Option<String>,
+        /// The prediction the image corrects, for images sent to `/predictions/{id}/correct`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub prediction_id: Option<i64>,
+        /// The annotated task, for images added from Label Studio.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub label_studio_task: Option<i64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub capture: Option<Capture>,
+    }
+}
+
+client_struct! {
+    /// Response from `/training/log`, oldest first.
+    #[derive(Debug, Serialize)]
+    pub struct TrainingLogResponse {
+        pub count: usize,
+        /// Whether more entries matched than `limit` allowed.
+        pub truncated: bool,
+        pub entries: Vec<TrainingLogEntry>,
+    }
+}
+
+client_struct! {
+    /// Response from `/samples/{id}/review`.
+    #[derive(Debug, Serialize)]
+    pub struct ReviewResponse {
+        pub status: &'static str,
+        pub sample_id: i64,
+        /// `approve` or `reject`, as sent.
+        pub decision: String,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/samples/bulk`.
+    #[derive(Debug, Serialize)]
+    pub struct BulkResponse {
+        /// Whether nothing was changed, and the counts are what would have been.
+        pub dry_run: bool,
+        pub matched: usize,
+        pub sample_ids: Vec<i64>,
+        pub relabeled: u64,
+        pub tags_added: u64,
+    }
+}
+
+client_struct! {
+    /// A training image that couldn't be read or checked.
+    #[derive(Debug, Serialize)]
+    pub struct ImageFailure {
+        pub content_hash: String,
+        pub error: String,
+    }
+}
+
+client_struct! {
+    /// Response from `/samples/integrity`.
+    #[derive(Debug, Serialize)]
+    pub struct IntegrityResponse {
+        pub images_checked: usize,
+        pub failures: Vec<ImageFailure>,
+    }
+}
+
+client_struct! {
+    /// Response from adding or updating a label through `/admin/labels`.
+    #[derive(Debug, Serialize)]
+    pub struct LabelResponse {
+        pub status: &'static str,
+        pub label: Label,
+    }
+}
+
+client_struct! {
+    /// Response from `DELETE /admin/labels/{name}`.
+    #[derive(Debug, Serialize)]
+    pub struct LabelRemovedResponse {
+        pub status: &'static str,
+        pub name: String,
+    }
+}
+
+client_struct! {
+    /// Response from `/integrations/label-studio`: `ignored` for webhooks that don't label anything, otherwise
+    /// `success` with the samples the annotation was applied to.
+    #[derive(Debug, Serialize)]
+    pub struct LabelStudioResponse {
+        pub status: &'static str,
+        /// The webhook's action, e.g. `ANNOTATION_CREATED`.
+        pub action: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub label: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub sample_ids: Option<Vec<i64>>,
+        /// Whether a kept prediction image became a new sample.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub created: Option<bool>,
+    }
+}
+
+client_struct! {
+    /// A registered ball.
+    #[derive(Debug, Serialize, sqlx::FromRow)]
+    pub struct Ball {
+        pub id: String,
+        pub description: Option<String>,
+        pub tag: String,
+        pub created_at: String,
+    }
+}
+
+client_struct! {
+    /// Response from `/balls`.
+    #[derive(Debug, Serialize)]
+    pub struct RegisterBallResponse {
+        pub status: &'static str,
+        /// What to write to the ball's NFC tag or QR code.
+        pub tag_payload: String,
+        pub ball: Ball,
+    }
+}
+
+client_struct! {
+    /// Aggregated submission and review statistics for one contributor.
+    #[derive(Debug, Serialize, sqlx::FromRow)]
+    pub struct ContributorStats {
+        pub contributor: String,
+        pub samples_submitted: i64,
+        pub samples_approved: i64,
+        pub samples_rejected: i64,
+        /// Approved samples whose label the reviewer kept unchanged.
+        #[serde(skip)]
+        pub labels_confirmed: i64,
+        /// Share of approved samples the reviewer didn't have to relabel.
+        #[sqlx(skip)]
+        pub label_accuracy: Option<f64>,
+    }
+}
+
+client_struct! {
+    /// Response from `/contributors/stats`, as a leaderboard by approved samples.
+    #[derive(Debug, Serialize)]
+    pub struct ContributorsResponse {
+        pub contributors: Vec<ContributorStats>,
+    }
+}
+
+client_struct! {
+    /// Number of predictions whose confidence fell in one bin.
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    pub struct Bin {
+        /// Lower bound of the bin, inclusive.
+        pub from: f64,
+        /// Upper bound of the bin, exclusive except for the last bin.
+        pub to: f64,
+        pub count: i64,
+    }
+}
+
+client_struct! {
+    /// How confident the model was in one verdict's predictions.
+    #[derive(Debug, Clone, PartialEq, Serialize)]
+    pub struct ConfidenceDistribution {
+        pub predictions: i64,
+        /// `null` without any predictions.
+        pub mean_confidence: Option<f64>,
+        pub histogram: Vec<Bin>,
+    }
+}
+
+client_struct! {
+    /// Response from `/analytics/confidence`.
+    #[derive(Debug, Serialize)]
+    pub struct ConfidenceResponse {
+        pub since: String,
+        pub model_version: Option<String>,
+        pub all: ConfidenceDistribution,
+        /// The distribution for each verdict.
+        pub verdicts: BTreeMap<String, ConfidenceDistribution>,
+    }
+}
+
+client_struct! {
+    /// A trained ensemble the classifier can run, stored as `model_{1,2,3}.pth` in `dir`.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct ModelVersion {
+        pub name: String,
+        pub dir: String,
+    }
+}
+
+client_struct! {
+    /// How often the models were right among validation predictions in one confidence bin.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct ReliabilityBin {
+        pub confidence: f64,
+        pub accuracy: f64,
+        pub count: u64,
+    }
+}
+
+client_struct! {
+    /// Precision and recall of one class on the validation images; `null` when nothing was predicted as,
+    /// or labeled as, the class.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct ClassMetrics {
+        pub precision: Option<f64>,
+        pub recall: Option<f64>,
+        /// Validation images of the class.
+        pub support: u64,
+    }
+}
+
+client_struct! {
+    /// Cross-validation results `train.py` saves with a model version, in `metrics.json`.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct EvalMetrics {
+        pub fold_accuracies: Vec<f64>,
+        pub average_accuracy: f64,
+        pub std_accuracy: f64,
+        pub num_epochs: u32,
+        pub k_folds: u32,
+        /// Training images of each class; left out for versions trained before it was recorded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub images_per_class: Option<BTreeMap<String, u64>>,
+    }
+}
+
+client_struct! {
+    /// The temperature `calibrate.py` fitted for a model version and how much it improved calibration, in `calibration.json`.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Calibration {
+        /// Always `temperature`.
+        pub method: String,
+        pub temperature: f64,
+        pub validation_images: u64,
+        /// Negative log-likelihood and expected calibration error on the validation images, before and after scaling.
+        pub nll_before: f64,
+        pub nll_after: f64,
+        pub ece_before: f64,
+        pub ece_after: f64,
+        /// Left out for versions calibrated before they were recorded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub reliability: Option<Vec<ReliabilityBin>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub per_class: Option<BTreeMap<String, ClassMetrics>>,
+    }
+}
+
+client_enum! {
+    /// Length of the periods production statistics are grouped into.
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum Bucket {
+        Day,
+        /// Weeks start on Monday.
+        Week,
+    }
+}
+
+client_struct! {
+    /// How a model version performed in production over one period.
+    #[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+    pub struct PeriodStats {
+        #[serde(skip)]
+        pub model_version: String,
+        /// First day of the period, as `YYYY-MM-DD`.
+        pub period: String,
+        pub predictions: i64,
+        /// Predictions the model couldn't make, usually because the script failed on the image.
+        pub unknown: i64,
+        #[serde(skip)]
+        pub confidence_sum: f64,
+        /// Predictions that also asked the second-opinion model.
+        pub second_opinions: i64,
+        /// Second opinions that reached a different verdict.
+        pub disagreements: i64,
+        #[sqlx(skip)]
+        pub mean_confidence: Option<f64>,
+        /// Share of second opinions that disagreed; `null` without any second opinions.
+        #[sqlx(skip)]
+        pub disagreement_rate: Option<f64>,
+    }
+}
+
+client_struct! {
+    /// A model version's evaluation results from training and its production statistics, in `/models/metrics`.
+    /// Versions that served predictions but are no longer configured have no directory or evaluation results.
+    #[derive(Debug, Serialize)]
+    pub struct ModelMetrics {
+        pub name: String,
+        pub dir: Option<String>,
+        pub active: bool,
+        pub eval_metrics: Option<EvalMetrics>,
+        pub calibration: Option<Calibration>,
+        pub production: Vec<PeriodStats>,
+    }
+}
+
+client_struct! {
+    /// Response from `/models/metrics`.
+    #[derive(Debug, Serialize)]
+    pub struct MetricsResponse {
+        pub bucket: Bucket,
+        pub since: String,
+        pub inference_device: Option<String>,
+        /// Configured versions first, oldest first, then any that have since been removed.
+        pub models: Vec<ModelMetrics>,
+    }
+}
+
+client_struct! {
+    /// A way a model version has been wrong: what it called the balls reviewers confirmed were something else,
+    /// how often, and the tags reviewers gave those samples, e.g. `glare`.
+    #[derive(Debug, PartialEq, Serialize)]
+    pub struct FailureMode {
+        pub predicted: String,
+        pub actual: String,
+        pub count: i64,
+        pub tags: BTreeMap<String, i64>,
+    }
+}
+
+client_struct! {
+    /// The model version a model card describes.
+    #[derive(Debug, Serialize)]
+    pub struct ModelCardVersion {
+        pub name: String,
+        pub active: bool,
+    }
+}
+
+client_struct! {
+    /// The images a model version was trained on.
+    #[derive(Debug, Serialize)]
+    pub struct ModelCardTrainingData {
+        pub total_images: u64,
+        pub images_per_class: BTreeMap<String, u64>,
+    }
+}
+
+client_struct! {
+    /// A model version's cross-validation results, and its per-class precision and recall from calibration.
+    #[derive(Debug, Serialize)]
+    pub struct ModelCardEvaluation {
+        pub k_folds: u32,
+        pub fold_accuracies: Vec<f64>,
+        pub average_accuracy: f64,
+        pub std_accuracy: f64,
+        pub per_class: Option<BTreeMap<String, ClassMetrics>>,
+    }
+}
+
+client_struct! {
+    /// How well a model version's confidences are calibrated.
+    #[derive(Debug, Serialize)]
+    pub struct ModelCardCalibration {
+        pub method: String,
+        pub temperature: f64,
+        pub validation_images: u64,
+        pub ece_before: f64,
+        pub ece_after: f64,
+        pub reliability: Option<Vec<ReliabilityBin>>,
+    }
+}
+
+client_struct! {
+    /// Response from `/models/{version}/report`. Sections recorded by training and calibration are `null` for
+    /// versions trained before they were.
+    #[derive(Debug, Serialize)]
+    pub struct ModelCard {
+        pub model: ModelCardVersion,
+        pub training_data: Option<ModelCardTrainingData>,
+        pub evaluation: Option<ModelCardEvaluation>,
+        pub calibration: Option<ModelCardCalibration>,
+        /// Most frequent first.
+        pub failure_modes: Vec<FailureMode>,
+    }
+}
+
+client_struct! {
+    /// An issued API key, without the key itself.
+    #[derive(Debug, Serialize)]
+    pub struct ApiKeyRecord {
+        pub id: i64,
+        /// Who the key was issued to, e.g. the club's name.
+        pub name: String,
+        /// The key's first characters.
+        pub key_prefix: String,
+        /// Strictness profile applied to the key's requests unless they ask for another.
+        pub profile: Option<String>,
+        pub scopes: Vec<String>,
+        pub created_at: String,
+        pub rotated_at: Option<String>,
+        pub last_used_at: Option<String>,
+        pub revoked_at: Option<String>,
+    }
+}
+
+client_struct! {
+    /// Response from issuing or rotating an API key. The key is only ever shown here.
+    #[derive(Debug, Serialize)]
+    pub struct IssuedApiKeyResponse {
+        pub status: &'static str,
+        pub key: String,
+        pub api_key: ApiKeyRecord,
+    }
+}
+
+client_struct! {
+    /// Response from updating or revoking an API key.
+    #[derive(Debug, Serialize)]
+    pub struct ApiKeyResponse {
+        pub status: &'static str,
+        pub api_key: ApiKeyRecord,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/api-keys`.
+    #[derive(Debug, Serialize)]
+    pub struct ApiKeysResponse {
+        pub api_keys: Vec<ApiKeyRecord>,
+    }
+}
+
+client_struct! {
+    /// Who a feature flag is switched on for.
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Rollout {
+        /// On for every request.
+        #[serde(default)]
+        pub everyone: bool,
+        /// On for requests made with these issued API keys, by ID.
+        #[serde(default)]
+        pub api_keys: Vec<i64>,
+    }
+}
+
+client_struct! {
+    /// Settings that are safe to change while the server is running.
+    /// They start from the environment, and changes made through `/admin/config` are stored in the metadata database.
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct RuntimeSettings {
+        /// Strictness profile used when neither the request nor its API key selects one.
+        pub default_profile: String,
+        /// Minimum confidence for a match-ready verdict, by profile. Profiles left out use their built-in threshold.
+        pub profile_thresholds: BTreeMap<String, f64>,
+        /// Whether photo quality issues are ignored, reported as warnings, or rejected.
+        pub quality_mode: QualityMode,
+        /// Uploads each client may make per minute; `0` disables the limit.
+        pub upload_rate_limit: u32,
+        /// How long classifier results are cached for identical images, in seconds; `0` disables the cache.
+        pub prediction_cache_ttl: u64,
+        /// Age in months after which `/admin/archive` moves training images to cold storage.
+        pub archive_after_months: u32,
+        /// Who each experimental behaviour is switched on for, by flag name.
+        pub feature_flags: BTreeMap<String, Rollout>,
+    }
+}
+
+client_struct! {
+    /// A change recorded in the settings audit log. Values are JSON.
+    #[derive(Debug, Serialize, sqlx::FromRow)]
+    pub struct AuditEntry {
+        pub changed_at: String,
+        pub client: String,
+        pub name: String,
+        pub old_value: String,
+        pub new_value: String,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/config`.
+    #[derive(Debug, Serialize)]
+    pub struct SettingsResponse {
+        /// The settings in force.
+        pub settings: RuntimeSettings,
+        /// The settings as configured in the environment.
+        pub defaults: RuntimeSettings,
+        /// The most recent changes, newest first.
+        pub history: Vec<AuditEntry>,
+    }
+}
+
+client_struct! {
+    /// Response from `PUT /admin/config` and `PUT /admin/config/{name}`.
+    #[derive(Debug, Serialize)]
+    pub struct SettingsUpdateResponse {
+        pub status: &'static str,
+        /// Names of the settings whose values changed.
+        pub changed: Vec<String>,
+        pub settings: RuntimeSettings,
+    }
+}
+
+client_struct! {
+    /// A background job, as stored in the metadata database.
+    #[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+    pub struct Job {
+        pub id: i64,
+        /// What the job does; currently always `training`, since predictions are answered while the client waits.
+        pub kind: String,
+        /// One of `queued`, `running`, `cancelling`, `succeeded`, `failed` or `cancelled`.
+        pub status: String,
+        pub created_at: String,
+        pub started_at: Option<String>,
+        pub finished_at: Option<String>,
+        pub error: Option<String>,
+    }
+}
+
+client_struct! {
+    /// Response from `/jobs/training`.
+    #[derive(Debug, Serialize)]
+    pub struct JobStartedResponse {
+        pub job_id: i64,
+        pub status: &'static str,
+    }
+}
+
+client_struct! {
+    /// Response from `/jobs`, newest first.
+    #[derive(Debug, Serialize)]
+    pub struct JobsResponse {
+        pub jobs: Vec<Job>,
+    }
+}
+
+client_struct! {
+    /// Response from `/jobs/{id}/cancel`.
+    #[derive(Debug, Serialize)]
+    pub struct JobCancelledResponse {
+        pub job_id: i64,
+        /// `cancelled` for a job that hadn't started, or `cancelling` until a running job's process has stopped.
+        pub status: &'static str,
+        /// Whether this server was running the job's process and has started stopping it.
+        pub terminated: bool,
+    }
+}
+
+client_struct! {
+    /// How far one image feature has drifted.
+    #[derive(Debug, Serialize)]
+    pub struct FeatureDrift {
+        pub name: &'static str,
+        /// Population stability index of production images against training images.
+        pub psi: f64,
+        /// `ok`, `warning` or `drift`.
+        pub level: &'static str,
+        pub baseline_mean: f64,
+        pub production_mean: f64,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/drift`.
+    #[derive(Debug, Serialize)]
+    pub struct DriftReport {
+        /// `insufficient_data`, `ok`, `warning` or `drift`.
+        pub status: &'static str,
+        pub since: String,
+        pub baseline_images: usize,
+        pub production_images: usize,
+        /// Empty with `insufficient_data`.
+        pub features: Vec<FeatureDrift>,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/drift/baseline`.
+    #[derive(Debug, Serialize)]
+    pub struct DriftBaselineResponse {
+        pub images_measured: usize,
+        pub failures: Vec<ImageFailure>,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/archive`: `archived`, or `nothing_to_archive` without the archive's details.
+    #[derive(Debug, Serialize)]
+    pub struct ArchiveResponse {
+        pub status: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub archive_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub images: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub samples: Option<u64>,
+        /// Images older than this were archived.
+        pub cutoff: String,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/archives/{id}/rehydrate`.
+    #[derive(Debug, Serialize)]
+    pub struct RehydrateResponse {
+        pub status: &'static str,
+        pub archive_id: String,
+        pub images: usize,
+    }
+}
+
+client_struct! {
+    /// The settings a deployment runs with, minus secrets such as API keys and connection strings.
+    #[derive(Debug, Serialize, Deserialize)]
+    pub struct ConfigSnapshot {
+        pub default_profile: String,
+        pub quality_mode: QualityMode,
+        pub model_versions: Vec<ModelVersion>,
+        pub active_model: String,
+        pub second_opinion_model: Option<String>,
+        /// `local` or `s3`.
+        pub storage_backend: String,
+        pub upload_rate_limit: u32,
+        pub prediction_cache_ttl: u64,
+        /// Flags switched on for everyone; left out of backups made before feature flags.
+        #[serde(default)]
+        pub feature_flags: Vec<String>,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/restore`. Configuration comes from the environment, so the backup's is handed back
+    /// for the operator to apply; `null` if the bundle's couldn't be read.
+    #[derive(Debug, Serialize)]
+    pub struct RestoreResponse {
+        pub status: &'static str,
+        pub files_restored: usize,
+        pub balls: usize,
+        pub predictions: usize,
+        pub samples: usize,
+        pub config: Option<ConfigSnapshot>,
+    }
+}
+
+client_struct! {
+    /// Number of training samples carrying a label, excluding rejected ones.
+    #[derive(Debug, PartialEq, Serialize, sqlx::FromRow)]
+    pub struct LabelCount {
+        /// The reviewer's label if the sample has been reviewed, otherwise the contributor's.
+        pub label: String,
+        pub samples: i64,
+        pub approved: i64,
+    }
+}
+
+client_struct! {
+    /// Predictions served today, by verdict.
+    #[derive(Debug, Serialize)]
+    pub struct PredictionCounts {
+        pub total: i64,
+        pub by_prediction: BTreeMap<String, i64>,
+    }
+}
+
+client_struct! {
+    /// The model version serving predictions, with its evaluation results from training.
+    #[derive(Debug, Serialize)]
+    pub struct ActiveModel {
+        pub name: String,
+        pub dir: String,
+        pub eval_metrics: Option<EvalMetrics>,
+    }
+}
+
+client_struct! {
+    /// Bytes stored in each area that grows with use, plus their total.
+    /// An area whose size can't be read is `null` and left out of the total.
+    #[derive(Debug, Default, Serialize)]
+    pub struct DiskUsage {
+        pub training_data: Option<u64>,
+        pub exports: Option<u64>,
+        pub archive: Option<u64>,
+        pub prediction_images: Option<u64>,
+        pub total: u64,
+    }
+}
+
+client_struct! {
+    /// Response from `/admin/summary`. Counts for "today" cover the current UTC day.
+    #[derive(Debug, Serialize)]
+    pub struct SummaryResponse {
+        pub date: String,
+        pub predictions_today: PredictionCounts,
+        /// Classification attempts that failed today.
+        pub errors_today: i64,
+        pub error_rate: f64,
+        /// Classifications running right now.
+        pub queue_depth: usize,
+        pub dataset: Vec<LabelCount>,
+        pub pending_reviews: i64,
+        pub active_model: ActiveModel,
+        pub disk_usage_bytes: DiskUsage,
+    }
+}
+
+client_struct! {
+    /// Artificial failures to inject, for checking how client apps and monitoring cope with them.
+    /// Only injected when the server runs with `FAULT_INJECTION=true`, which is never meant for production.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    pub struct Faults {
+        /// Extra time each model takes to classify an image, in milliseconds.
+        #[serde(default)]
+        pub slow_inference_ms: u64,
+        /// The prediction script dies without giving a verdict.
+        #[serde(default)]
+        pub classifier_crash: bool,
+        /// The prediction script prints something that isn't a verdict.
+        #[serde(default)]
+        pub malformed_output: bool,
+        /// Writing images fails as if the disk were full.
+        #[serde(default)]
+        pub disk_full: bool,
+    }
+}
+
+/// Describes the shared types to the generated clients, and generates the clients' declarations of them.
+/// Generation runs as a test, which fails when the checked-in declarations are out of date.
+#[cfg(test)]
+mod codegen {
+    use super::*;
+    use std::path::Path;
+
+    /// Where the generated client types are written, relative to the backend directory.
+    const TYPESCRIPT_PATH: &str = "../frontend/src/api/types.ts";
+    const SWIFT_PATH: &str = "../clients/swift/ApiTypes.swift";
+
+    /// A request or response type, as described to the generated clients.
+    pub struct TypeDef {
+        pub name: &'static str,
+        pub docs: Vec<String>,
+        pub shape: Shape,
+    }
+
+    pub enum Shape {
+        Struct(Vec<Field>),
+        /// An enum of unit variants, serialized as strings.
+        Enum(Vec<Variant>),
+    }
+
+    pub struct Field {
+        /// Name of the field in JSON.
+        pub name: String,
+        pub rust_type: String,
+        pub docs: Vec<String>,
+        /// Whether the field is left out rather than sent as `null` when it has no value.
+        pub omitted_when_none: bool,
+    }
+
+    pub struct Variant {
+        /// The variant as serialized to JSON.
+        pub value: String,
+        pub docs: Vec<String>,
+    }
+
+    /// A type shared with the generated clients. Implemented by `client_struct!` and `client_enum!`.
+    pub trait ClientType {
+        fn definition() -> TypeDef;
+    }
+
+    /// Lines of the doc comments among an item's attributes, as given by `stringify!`.
+    pub fn docs(attributes: &[&str]) -> Vec<String> {
+        attributes
+            .iter()
+            .filter_map(|attribute| {
+                let value = attribute.strip_prefix("doc")?.trim_start().strip_prefix('=')?.trim();
+                Some(value.trim_start_matches('r').trim_matches('#').trim_matches('"').trim().to_string())
+            })
+            .collect()
+    }
+
+    impl Field {
+        /// Describes a field, or returns `None` if it is never serialized.
+        pub fn new(name: &str, rust_type: &str, attributes: &[&str]) -> Option<Self> {
+            let serde: Vec<&str> = attributes.iter().copied().filter(|attribute| attribute.starts_with("serde")).collect();
+            let skipped = serde.iter().any(|attribute| {
+                let options = attribute.trim_start_matches("serde").trim().trim_start_matches('(').trim_end_matches(')');
+                options.split(',').any(|option| matches!(option.trim(), "skip" | "skip_serializing"))
+            });
+            if skipped {
+                return None;
+            }
+            let renamed = serde.iter().find_map(|attribute| {
+                let rest = &attribute[attribute.find("rename = \"")? + "rename = \"".len()..];
+                Some(rest[..rest.find('"')?].to_string())
+            });
+            Some(Self {
+                name: renamed.unwrap_or_else(|| name.to_string()),
+                rust_type: rust_type.to_string(),
+                docs: docs(attributes),
+                omitted_when_none: serde.iter().any(|attribute| attribute.contains("skip_serializing_if") || attribute.contains("default")),
+            })
+        }
+    }
+
+    /// Every type shared with the clients, in the order they are generated.
+    pub fn definitions() -> Vec<TypeDef> {
+        vec![
+            Precision::definition(),
+            QualityMode::definition(),
+            QualityIssue::definition(),
+            QualityRejection::definition(),
+            InvalidResponse::definition(),
+            SecondOpinion::definition(),
+            PredictResponse::definition(),
+            BoundingBox::definition(),
+            BallPrediction::definition(),
+            PredictMultiResponse::definition(),
+            ModelComparison::definition(),
+            CompareModelsResponse::definition(),
+            TrainingResponse::definition(),
+            CorrectionInput::definition(),
+            CorrectionResponse::definition(),
+            ItemKind::definition(),
+            SyncItem::definition(),
+            SyncManifest::definition(),
+            SyncStatus::definition(),
+            SyncResult::definition(),
+            SyncResponse::definition(),
+            Label::definition(),
+            LabelsResponse::definition(),
+            UploadStatus::definition(),
+            UploadSession::definition(),
+            UploadProgress::definition(),
+            TagResponse::definition(),
+            PredictionRecord::definition(),
+            BallHistoryResponse::definition(),
+            ReplayOriginal::definition(),
+            ReplayVerdict::definition(),
+            ReplayDiff::definition(),
+            ReplayResponse::definition(),
+            ReadinessCheck::definition(),
+            ReadinessResponse::definition(),
+            InferenceDevice::definition(),
+            VersionResponse::definition(),
+            Capture::definition(),
+            TrainingLogEntry::definition(),
+            TrainingLogResponse::definition(),
+            ReviewResponse::definition(),
+            BulkResponse::definition(),
+            ImageFailure::definition(),
+            IntegrityResponse::definition(),
+            LabelResponse::definition(),
+            LabelRemovedResponse::definition(),
+            LabelStudioResponse::definition(),
+            Ball::definition(),
+            RegisterBallResponse::definition(),
+            ContributorStats::definition(),
+            ContributorsResponse::definition(),
+            Bin::definition(),
+            ConfidenceDistribution::definition(),
+            ConfidenceResponse::definition(),
+            ModelVersion::definition(),
+            ReliabilityBin::definition(),
+            ClassMetrics::definition(),
+            EvalMetrics::definition(),
+            Calibration::definition(),
+            Bucket::definition(),
+            PeriodStats::definition(),
+            ModelMetrics::definition(),
+            MetricsResponse::definition(),
+            FailureMode::definition(),
+            ModelCardVersion::definition(),
+            ModelCardTrainingData::definition(),
+            ModelCardEvaluation::definition(),
+            ModelCardCalibration::definition(),
+            ModelCard::definition(),
+            ApiKeyRecord::definition(),
+            IssuedApiKeyResponse::definition(),
+            ApiKeyResponse::definition(),
+            ApiKeysResponse::definition(),
+            Rollout::definition(),
+            RuntimeSettings::definition(),
+            AuditEntry::definition(),
+            SettingsResponse::definition(),
+            SettingsUpdateResponse::definition(),
+            Job::definition(),
+            JobStartedResponse::definition(),
+            JobsResponse::definition(),
+            JobCancelledResponse::definition(),
+            FeatureDrift::definition(),
+            DriftReport::definition(),
+            DriftBaselineResponse::definition(),
+            ArchiveResponse::definition(),
+            RehydrateResponse::definition(),
+            ConfigSnapshot::definition(),
+            RestoreResponse::definition(),
+            LabelCount::definition(),
+            PredictionCounts::definition(),
+            ActiveModel::definition(),
+            DiskUsage::definition(),
+            SummaryResponse::definition(),
+            Faults::definition(),
+        ]
+    }
+
+    /// The type inside `wrapper<...>`, if `rust_type` is one.
+    fn type_argument<'a>(rust_type: &'a str, wrapper: &str) -> Option<&'a str> {
+        rust_type.strip_prefix(wrapper)?.trim_start().strip_prefix('<')?.strip_suffix('>').map(str::trim)
+    }
+
+    /// The value type of a `BTreeMap` keyed by strings, if `rust_type` is one.
+    fn map_value(rust_type: &str) -> Option<&str> {
+        let (key, value) = type_argument(rust_type, "BTreeMap")?.split_once(',')?;
+        (key.trim() == "String").then(|| value.trim())
+    }
+
+    fn typescript_type(rust_type: &str) -> String {
+        if let Some(inner) = type_argument(rust_type, "Option") {
+            return format!("{} | null", typescript_type(inner));
+        }
+        if let Some(inner) = type_argument(rust_type, "Vec") {
+            return format!("{}[]", typescript_type(inner));
+        }
+        if let Some(value) = map_value(rust_type) {
+            return format!("Record<string, {}>", typescript_type(value));
+        }
+        match rust_type {
+            "String" | "&'static str" => "string".to_string(),
+            "bool" => "boolean".to_string(),
+            "f64" | "i64" | "u64" | "u32" | "usize" => "number".to_string(),
+            name => name.to_string(),
+        }
+    }
+
+    fn swift_type(rust_type: &str) -> String {
+        if let Some(inner) = type_argument(rust_type, "Option") {
+            return format!("{}?", swift_type(inner));
+        }
+        if let Some(inner) = type_argument(rust_type, "Vec") {
+            return format!("[{}]", swift_type(inner));
+        }
+        if let Some(value) = map_value(rust_type) {
+            return format!("[String: {}]", swift_type(value));
+        }
+        match rust_type {
+            "String" | "&'static str" => "String".to_string(),
+            "bool" => "Bool".to_string(),
+            "f64" => "Double".to_string(),
+            "i64" | "u64" | "u32" | "usize" => "Int".to_string(),
+            name => name.to_string(),
+        }
+    }
+
+    fn camel_case(name: &str) -> String {
+        let mut words = name.split('_');
+        let mut camel = words.next().unwrap_or_default().to_string();
+        for word in words {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                camel.extend(first.to_uppercase());
+                camel.push_str(chars.as_str());
+            }
+        }
+        camel
+    }
+
+    fn typescript_docs(docs: &[String], indent: &str) -> String {
+        match docs {
+            [] => String::new(),
+            [line] => format!("{}/** {} */\n", indent, line),
+            lines => {
+                let body: String = lines.iter().map(|line| format!("{} * {}\n", indent, line)).collect();
+                format!("{}/**\n{}{} */\n", indent, body, indent)
+            }
+        }
+    }
+
+    fn swift_docs(docs: &[String], indent: &str) -> String {
+        docs.iter().map(|line| format!("{}/// {}\n", indent, line)).collect()
+    }
+
+    /// TypeScript declarations of every shared type.
+    pub fn typescript() -> String {
+        let mut output = String::from("// Generated from backend/src/api_types.rs. Do not edit by hand: run\n// `UPDATE_CLIENT_TYPES=1 cargo test` in backend/ after changing the types there.\n");
+        for definition in definitions() {
+            output.push('\n');
+            output += &typescript_docs(&definition.docs, "");
+            match definition.shape {
+                Shape::Struct(fields) => {
+                    output += &format!("export interface {} {{\n", definition.name);
+                    for field in fields {
+                        output += &typescript_docs(&field.docs, "\t");
+                        let optional_inner = type_argument(&field.rust_type, "Option").filter(|_| field.omitted_when_none);
+                        output += &match optional_inner {
+                            Some(inner) => format!("\t{}?: {};\n", field.name, typescript_type(inner)),
+                            None => format!("\t{}: {};\n", field.name, typescript_type(&field.rust_type)),
+                        };
+                    }
+                    output += "}\n";
+                }
+                Shape::Enum(variants) => {
+                    let values: Vec<String> = variants.iter().map(|variant| format!("'{}'", variant.value)).collect();
+                    output += &format!("export type {} = {};\n", definition.name, values.join(" | "));
+                }
+            }
+        }
+        output
+    }
+
+    /// Swift declarations of every shared type, as `Codable` values with Swift-style property names.
+    pub fn swift() -> String {
+        let mut output = String::from("// Generated from backend/src/api_types.rs. Do not edit by hand: run\n// `UPDATE_CLIENT_TYPES=1 cargo test` in backend/ after changing the types there.\n\nimport Foundation\n");
+        for definition in definitions() {
+            output.push('\n');
+            output += &swift_docs(&definition.docs, "");
+            match definition.shape {
+                Shape::Struct(fields) => {
+                    output += &format!("public struct {}: Codable {{\n", definition.name);
+                    for field in &fields {
+                        output += &swift_docs(&field.docs, "    ");
+                        output += &format!("    public let {}: {}\n", camel_case(&field.name), swift_type(&field.rust_type));
+                    }
+                    output += "\n    enum CodingKeys: String, CodingKey {\n";
+                    for field in &fields {
+                        let property = camel_case(&field.name);
+                        output += &if property == field.name {
+                            format!("        case {}\n", property)
+                        } else {
+                            format!("        case {} = \"{}\"\n", property, field.name)
+                        };
+                    }
+                    output += "    }\n}\n";
+                }
+                Shape::Enum(variants) => {
+                    output += &format!("public enum {}: String, Codable {{\n", definition.name);
+                    for variant in variants {
+                        output += &swift_docs(&variant.docs, "    ");
+                        let case = camel_case(&variant.value);
+                        output += &if case == variant.value {
+                            format!("    case {}\n", case)
+                        } else {
+                            format!("    case {} = \"{}\"\n", case, variant.value)
+                        };
+                    }
+                    output += "}\n";
+                }
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn definitions_follow_serde_names_and_docs() {
+        let Shape::Struct(fields) = BallPrediction::definition().shape else { panic!("expected a struct") };
+        assert_eq!((fields[0].name.as_str(), fields[0].rust_type.as_str()), ("box", "BoundingBox"));
+
+        let Shape::Struct(fields) = PredictResponse::definition().shape else { panic!("expected a struct") };
+        let uncertainty = fields.iter().find(|field| field.name == "uncertainty").unwrap();
+        assert!(uncertainty.omitted_when_none);
+        assert_eq!(uncertainty.docs, vec!["Spread of the match-ready probability across dropout passes, when asked for with `uncertainty=true` and the `uncertainty` flag is on."]);
+        assert!(!fields.iter().find(|field| field.name == "ball_id").unwrap().omitted_when_none);
+
+        let Shape::Enum(variants) = Precision::definition().shape else { panic!("expected an enum") };
+        assert_eq!(variants.iter().map(|variant| variant.value.as_str()).collect::<Vec<_>>(), vec!["fp32", "fp16", "int8"]);
+
+        assert_eq!(typescript_type("Option<Vec<QualityIssue>>"), "QualityIssue[] | null");
+        assert_eq!(swift_type("Option<Vec<QualityIssue>>"), "[QualityIssue]?");
+        assert_eq!(typescript_type("BTreeMap<String, Vec<f64>>"), "Record<string, number[]>");
+        assert_eq!(swift_type("Option<BTreeMap<String, i64>>"), "[String: Int]?");
+
+        let Shape::Struct(fields) = PeriodStats::definition().shape else { panic!("expected a struct") };
+        assert!(fields.iter().all(|field| field.name != "model_version" && field.name != "confidence_sum"));
+        assert_eq!(camel_case("model_prediction"), "modelPrediction");
+    }
+
+    /// Fails when the checked-in client types no longer match these definitions.
+    /// Run with `UPDATE_CLIENT_TYPES=1` to regenerate them.
+    #[test]
+    fn generated_client_types_are_up_to_date() {
+        for (path, generated) in [(TYPESCRIPT_PATH, typescript()), (SWIFT_PATH, swift())] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(path);
+            if std::env::var("UPDATE_CLIENT_TYPES").is_ok() {
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(&path, &generated).unwrap();
+                continue;
+            }
+            let current = std::fs::read_to_string(&path).unwrap_or_default();
+            assert!(current == generated, "{} is out of date; run `UPDATE_CLIENT_TYPES=1 cargo test`", path.display());
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use chrono::{DateTime, Months, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::api_types::{ArchiveResponse, RehydrateResponse};
+use crate::auth;
+use crate::backup;
+use crate::db;
+use crate::predictions::format_timestamp;
+use crate::request_logger::RequestLogger;
+use crate::settings;
+use crate::storage::{self, Area};
+use crate::training;
+
+/// What an archive run moved to cold storage.
+#[derive(Debug, PartialEq)]
+pub struct ArchiveSummary {
+    pub archive_id: String,
+    pub images: usize,
+    pub samples: u64,
+}
+
+/// Storage key of an archive bundle within the archive area.
+fn archive_key(archive_id: &str) -> String {
+    format!("{}.tar.gz", archive_id)
+}
+
+/// Checks that an archive ID is one this module could have generated.
+fn is_valid_archive_id(archive_id: &str) -> bool {
+    archive_id.starts_with("archive_") && archive_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Content hashes of images whose samples were all submitted before `cutoff` and that are still in the training data area.
+/// An image shared with a newer sample stays put.
+async fn candidates(pool: &db::Pool, cutoff: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT content_hash FROM samples
+         WHERE content_hash IS NOT NULL
+         GROUP BY content_hash
+         HAVING MAX(created_at) < $1 AND SUM(CASE WHEN archive_id IS NULL THEN 1 ELSE 0 END) > 0
+         ORDER BY content_hash"
+    )
+    .bind(cutoff)
+    .fetch_all(pool)
+    .await
+}
+
+/// Moves images of samples submitted before `cutoff`, and their kept originals, into one compressed archive bundle.
+/// Their samples keep all their metadata and record which archive holds the image.
+/// Returns `None` if there was nothing to archive.
+pub async fn archive_before(pool: &db::Pool, cutoff: DateTime<Utc>) -> Result<Option<ArchiveSummary>, String> {
+    let hashes = candidates(pool, &format_timestamp(cutoff))
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+    if hashes.is_empty() {
+        return Ok(None);
+    }
+
+    let storage = storage::get();
+    let mut files = BTreeMap::new();
+    for hash in &hashes {
+        files.insert(training::image_key(hash), training::read_image(hash)?);
+        if let Some(original) = training::read_original(hash)? {
+            files.insert(training::original_key(hash), original);
+        }
+    }
+
+    // Store the archive before touching the originals, so a failure part way never loses an image
+    let archive_id = format!("archive_{}", Utc::now().format("%Y%m%d_%H%M%S_%3f"));
+    let bundle = backup::build_bundle(&files).map_err(|e| format!("Failed to build archive: {}", e))?;
+    storage
+        .put(Area::Archive, &archive_key(&archive_id), &bundle)
+        .map_err(|e| format!("Failed to store archive: {}", e))?;
+
+    let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+    let mut samples = 0;
+    for hash in &hashes {
+        let result = sqlx::query("UPDATE samples SET archive_id = $1 WHERE content_hash = $2 AND archive_id IS NULL")
+            .bind(&archive_id)
+            .bind(hash)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        samples += result.rows_affected();
+    }
+    tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+
+    for key in files.keys() {
+        training::remove_image(key).map_err(|e| format!("Failed to remove archived file {}: {}", key, e))?;
+    }
+
+    Ok(Some(ArchiveSummary { archive_id, images: hashes.len(), samples }))
+}
+
+/// Restores every image in an archive, and the originals archived with them, to the training data area and marks
+/// their samples as live again.
+/// Returns the number of images restored, or `None` if there is no such archive.
+pub async fn rehydrate(pool: &db::Pool, archive_id: &str) -> Result<Option<usize>, String> {
+    let storage = storage::get();
+    let key = archive_key(archive_id);
+    if !storage.exists(Area::Archive, &key).map_err(|e| format!("Failed to check archive: {}", e))? {
+        return Ok(None);
+    }
+
+    let bundle = storage.get(Area::Archive, &key).map_err(|e| format!("Failed to read archive: {}", e))?;
+    let files = backup::open_bundle(&bundle)?;
+    for (image_key, data) in &files {
+        storage
+            .put(Area::TrainingData, image_key, data)
+            .map_err(|e| format!("Failed to restore {}: {}", image_key, e))?;
+    }
+
+    sqlx::query("UPDATE samples SET archive_id = NULL WHERE archive_id = $1")
+        .bind(archive_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Database error: {}", e))?;
+
+    storage.delete(Area::Archive, &key).map_err(|e| format!("Failed to remove archive: {}", e))?;
+
+    Ok(Some(files.keys().filter(|key| key.starts_with("images/")).count()))
+}
+
+/// Query parameters accepted by `/admin/archive`.
+#[derive(Debug, Deserialize)]
+pub struct ArchiveQuery {
+    /// Archive images older than this many months; defaults to the `archive_after_months` setting.
+    pub older_than_months: Option<u32>,
+}
+
+/// Archive route handler applying the lifecycle policy: images older than the configured age are moved to cold storage.
+/// Requires the admin token. Meant to be called on a schedule, e.g. from cron.
+pub async fn archive_route(
+    req: rusty_api::HttpRequest,
+    query: rusty_api::web::Query<ArchiveQuery>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/archive");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized archive run");
+        return resp;
+    }
+
+    let months = match query.older_than_months {
+        Some(months) => months,
+        None => settings::current(&logger).await.archive_after_months,
+    };
+    let cutoff = match Utc::now().checked_sub_months(Months::new(months)) {
+        Some(cutoff) if months > 0 => cutoff,
+        _ => return rusty_api::HttpResponse::BadRequest().body("older_than_months must be a positive number of months"),
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match archive_before(pool, cutoff).await {
+        Ok(Some(summary)) => {
+            logger.info(format!("Archived {} image(s) into {}", summary.images, summary.archive_id));
+            rusty_api::HttpResponse::Ok().json(ArchiveResponse {
+                status: "archived",
+                archive_id: Some(summary.archive_id),
+                images: Some(summary.images),
+                samples: Some(summary.samples),
+                cutoff: format_timestamp(cutoff),
+            })
+        }
+        Ok(None) => rusty_api::HttpResponse::Ok().json(ArchiveResponse {
+            status: "nothing_to_archive",
+            archive_id: None,
+            images: None,
+            samples: None,
+            cutoff: format_timestamp(cutoff),
+        }),
+        Err(message) => {
+            logger.error(format!("Archive run failed: {}", message));
+            rusty_api::HttpResponse::InternalServerError().body(message)
+        }
+    }
+}
+
+/// Rehydrate route handler bringing an archive's images back into the training data area.
+/// Requires the admin token.
+pub async fn rehydrate_route(
+    req: rusty_api::HttpRequest,
+    path: rusty_api::web::Path<String>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let archive_id = path.into_inner();
+
+    logger.info(format!("Received request to /admin/archives/{}/rehydrate", archive_id));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized rehydration");
+        return resp;
+    }
+
+    if !is_valid_archive_id(&archive_id) {
+        return rusty_api::HttpResponse::NotFound().body(format!("Archive '{}' not found", archive_id));
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match rehydrate(pool, &archive_id).await {
+        Ok(Some(images)) => {
+            logger.info(format!("Rehydrated {} image(s) from {}", images, archive_id));
+            rusty_api::HttpResponse::Ok().json(RehydrateResponse { status: "rehydrated", archive_id, images })
+        }
+        Ok(None) => rusty_api::HttpResponse::NotFound().body(format!("Archive '{}' not found", archive_id)),
+        Err(message) => {
+            logger.error(format!("Rehydration failed: {}", message));
+            rusty_api::HttpResponse::InternalServerError().body(message)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::{self, NewSample};
+
+    #[tokio::test]
+    async fn only_images_with_no_recent_samples_are_candidates() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        for (hash, created_at) in [("old", "2023-01-01T00:00:00.000Z"), ("shared", "2023-01-01T00:00:00.000Z"), ("shared", "2025-06-01T00:00:00.000Z")] {
+            let sample = NewSample { content_hash: hash, ..NewSample::example() };
+            let id = samples::record(&pool, &sample).await.unwrap();
+            sqlx::query("UPDATE samples SET created_at = $1 WHERE id = $2").bind(created_at).bind(id).execute(&pool).await.unwrap();
+        }
+
+        assert_eq!(candidates(&pool, "2024-01-01T00:00:00.000Z").await.unwrap(), vec!["old"]);
+
+        sqlx::query("UPDATE samples SET archive_id = 'archive_1' WHERE content_hash = 'old'").execute(&pool).await.unwrap();
+        assert!(candidates(&pool, "2024-01-01T00:00:00.000Z").await.unwrap().is_empty());
+        assert!(is_valid_archive_id("archive_20240101_000000_000"));
+        assert!(!is_valid_archive_id("archive_../../etc"));
+    }
+}
@@ -0,0 +1,29 @@
+use rusty_api::HttpRequest;
+
+/// Extracts the bearer token from the `Authorization` header, if present.
+pub fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+}
+
+/// Checks that the request carries the admin token configured in `ADMIN_TOKEN`.
+/// Admin routes are disabled entirely when no token is configured.
+pub fn require_admin(req: &HttpRequest) -> Result<(), rusty_api::HttpResponse> {
+    let expected = match std::env::var("ADMIN_TOKEN") {
+        Ok(token) if !token.is_empty() => token,
+        _ => return Err(rusty_api::HttpResponse::Forbidden().body("Admin routes are disabled")),
+    };
+
+    match bearer_token(req) {
+        Some(token) if token == expected => Ok(()),
+        _ => Err(rusty_api::HttpResponse::Unauthorized().body("Missing or invalid admin token")),
+    }
+}
+
+/// Enables admin routes for tests, with the same token in every test so they can run in parallel.
+#[cfg(test)]
+pub fn enable_admin_for_tests() {
+    std::env::set_var("ADMIN_TOKEN", "test-admin-token");
+}
@@ -0,0 +1,737 @@
+use chrono::Utc;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures_util::StreamExt as _;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use crate::api_types::{ConfigSnapshot, RestoreResponse};
+use crate::auth;
+use crate::config;
+use crate::db;
+use crate::drift::{self, ImageStats};
+use crate::exif::{self, Capture};
+use crate::models;
+use crate::predictions::format_timestamp;
+use crate::request_logger::RequestLogger;
+use crate::storage::{self, Area, StorageBackend};
+use crate::training;
+
+/// Version of the bundle layout, bumped whenever a restore would need to read it differently.
+const FORMAT_VERSION: u32 = 1;
+
+/// Bundle entries holding the manifest, configuration and metadata; every other entry is a stored file.
+const MANIFEST_PATH: &str = "manifest.json";
+const CONFIG_PATH: &str = "config.json";
+const METADATA_PATH: &str = "metadata.json";
+
+/// Bundle path prefixes for files from each storage area.
+const TRAINING_DATA_PREFIX: &str = "training_data/";
+const MODELS_PREFIX: &str = "models/";
+const ARCHIVE_PREFIX: &str = "archive/";
+
+/// Checksum of one file in a bundle.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub sha256: String,
+    pub size: usize,
+}
+
+/// Describes a bundle's contents so a restore can verify it before changing anything.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub created_at: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct BallRow {
+    id: String,
+    description: Option<String>,
+    tag: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct PredictionRow {
+    id: i64,
+    request_id: i64,
+    created_at: String,
+    prediction: String,
+    confidence: f64,
+    image_size_bytes: i64,
+    profile: String,
+    model_prediction: Option<String>,
+    ball_id: Option<String>,
+    model_version: Option<String>,
+    second_opinion_model: Option<String>,
+    second_opinion_prediction: Option<String>,
+    /// Missing from bundles made before prediction images were kept.
+    #[serde(default)]
+    image_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct SampleRow {
+    id: i64,
+    request_id: i64,
+    created_at: String,
+    contributor: Option<String>,
+    label: String,
+    filename: String,
+    file_path: String,
+    content_hash: Option<String>,
+    image_size_bytes: i64,
+    review_status: String,
+    reviewed_label: Option<String>,
+    reviewed_at: Option<String>,
+    archive_id: Option<String>,
+    /// Missing from bundles made before predictions could be corrected.
+    #[serde(default)]
+    prediction_id: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct SyncItemRow {
+    client_id: String,
+    kind: String,
+    result_id: i64,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct SampleCaptureRow {
+    sample_id: i64,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    capture: Capture,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct SampleTagRow {
+    sample_id: i64,
+    tag: String,
+    created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct TrainingImageStatsRow {
+    content_hash: String,
+    measured_at: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    stats: ImageStats,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct PredictionImageStatsRow {
+    prediction_id: i64,
+    created_at: String,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    stats: ImageStats,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct RuntimeSettingRow {
+    name: String,
+    value: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct ApiKeyRow {
+    id: i64,
+    name: String,
+    key_hash: String,
+    key_prefix: String,
+    profile: Option<String>,
+    scopes: String,
+    created_at: String,
+    rotated_at: Option<String>,
+    last_used_at: Option<String>,
+    revoked_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+struct LabelRow {
+    name: String,
+    description: String,
+    display_order: i64,
+}
+
+/// Every row of the metadata database, independent of whether it is SQLite or PostgreSQL.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Metadata {
+    balls: Vec<BallRow>,
+    predictions: Vec<PredictionRow>,
+    samples: Vec<SampleRow>,
+    /// Missing from bundles made before capture metadata was read from uploads.
+    #[serde(default)]
+    sample_capture: Vec<SampleCaptureRow>,
+    /// Missing from bundles made before samples could be tagged.
+    #[serde(default)]
+    sample_tags: Vec<SampleTagRow>,
+    sync_items: Vec<SyncItemRow>,
+    /// Missing from bundles made before settings could be changed at runtime.
+    #[serde(default)]
+    runtime_settings: Vec<RuntimeSettingRow>,
+    /// Missing from bundles made before API keys were issued from the database.
+    #[serde(default)]
+    api_keys: Vec<ApiKeyRow>,
+    /// Missing from bundles made before the label taxonomy was kept in the database,
+    /// in which case the restored deployment keeps its own.
+    #[serde(default)]
+    labels: Vec<LabelRow>,
+    /// Missing from bundles made before image statistics were recorded for drift detection.
+    #[serde(default)]
+    training_image_stats: Vec<TrainingImageStatsRow>,
+    #[serde(default)]
+    prediction_image_stats: Vec<PredictionImageStatsRow>,
+}
+
+/// Reads every row of the metadata database.
+async fn dump_metadata(pool: &db::Pool) -> Result<Metadata, sqlx::Error> {
+    Ok(Metadata {
+        balls: sqlx::query_as("SELECT id, description, tag, created_at FROM balls ORDER BY id")
+            .fetch_all(pool)
+            .await?,
+        predictions: sqlx::query_as(
+            "SELECT id, request_id, created_at, prediction, confidence, image_size_bytes, profile, model_prediction, ball_id, model_version, second_opinion_model, second_opinion_prediction, image_hash FROM predictions ORDER BY id"
+        )
+        .fetch_all(pool)
+        .await?,
+        samples: sqlx::query_as(
+            "SELECT id, request_id, created_at, contributor, label, filename, file_path, content_hash, image_size_bytes, review_status, reviewed_label, reviewed_at, archive_id, prediction_id FROM samples ORDER BY id"
+        )
+        .fetch_all(pool)
+        .await?,
+        sample_capture: sqlx::query_as(
+            "SELECT sample_id, captured_at, camera_make, camera_model, focal_length_mm, focal_length_35mm, f_number, exposure_time, iso FROM sample_capture ORDER BY sample_id"
+        )
+        .fetch_all(pool)
+        .await?,
+        sample_tags: sqlx::query_as("SELECT sample_id, tag, created_at FROM sample_tags ORDER BY sample_id, tag")
+            .fetch_all(pool)
+            .await?,
+        sync_items: sqlx::query_as("SELECT client_id, kind, result_id, created_at FROM sync_items ORDER BY client_id")
+            .fetch_all(pool)
+            .await?,
+        runtime_settings: sqlx::query_as("SELECT name, value, updated_at FROM runtime_settings ORDER BY name")
+            .fetch_all(pool)
+            .await?,
+        api_keys: sqlx::query_as(
+            "SELECT id, name, key_hash, key_prefix, profile, scopes, created_at, rotated_at, last_used_at, revoked_at FROM api_keys ORDER BY id"
+        )
+        .fetch_all(pool)
+        .await?,
+        labels: sqlx::query_as("SELECT name, description, display_order FROM labels ORDER BY name")
+            .fetch_all(pool)
+            .await?,
+        training_image_stats: sqlx::query_as(
+            "SELECT content_hash, measured_at, brightness, contrast, red, green, blue, saturation, width, height FROM training_image_stats ORDER BY content_hash"
+        )
+        .fetch_all(pool)
+        .await?,
+        prediction_image_stats: sqlx::query_as(
+            "SELECT prediction_id, created_at, brightness, contrast, red, green, blue, saturation, width, height FROM prediction_image_stats ORDER BY prediction_id"
+        )
+        .fetch_all(pool)
+        .await?,
+    })
+}
+
+/// Whether the metadata database holds any rows that a restore would conflict with.
+async fn has_metadata(pool: &db::Pool) -> Result<bool, sqlx::Error> {
+    let count: i64 = sqlx::query_scalar(
+        "SELECT (SELECT COUNT(*) FROM balls) + (SELECT COUNT(*) FROM predictions) + (SELECT COUNT(*) FROM samples) + (SELECT COUNT(*) FROM sync_items)"
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(count > 0)
+}
+
+/// Replaces the metadata database's rows with those from a bundle, in one transaction.
+/// Row IDs are kept, so samples and sync items still refer to the right rows.
+async fn restore_metadata(pool: &db::Pool, metadata: &Metadata) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Delete in dependency order: predictions refer to balls, image statistics to predictions, and capture metadata
+    // and tags to samples
+    for table in [
+        "api_keys",
+        "runtime_settings",
+        "sync_items",
+        "sample_tags",
+        "sample_capture",
+        "samples",
+        "training_image_stats",
+        "prediction_image_stats",
+        "predictions",
+        "balls",
+    ] {
+        sqlx::query(&format!("DELETE FROM {}", table)).execute(&mut *tx).await?;
+    }
+    if !metadata.labels.is_empty() {
+        sqlx::query("DELETE FROM labels").execute(&mut *tx).await?;
+    }
+    for label in &metadata.labels {
+        sqlx::query("INSERT INTO labels (name, description, display_order) VALUES ($1, $2, $3)")
+            .bind(&label.name)
+            .bind(&label.description)
+            .bind(label.display_order)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    for ball in &metadata.balls {
+        sqlx::query("INSERT INTO balls (id, description, tag, created_at) VALUES ($1, $2, $3, $4)")
+            .bind(&ball.id)
+            .bind(&ball.description)
+            .bind(&ball.tag)
+            .bind(&ball.created_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for p in &metadata.predictions {
+        sqlx::query(
+            "INSERT INTO predictions (id, request_id, created_at, prediction, confidence, image_size_bytes, profile, model_prediction, ball_id, model_version, second_opinion_model, second_opinion_prediction, image_hash) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)"
+        )
+        .bind(p.id)
+        .bind(p.request_id)
+        .bind(&p.created_at)
+        .bind(&p.prediction)
+        .bind(p.confidence)
+        .bind(p.image_size_bytes)
+        .bind(&p.profile)
+        .bind(&p.model_prediction)
+        .bind(&p.ball_id)
+        .bind(&p.model_version)
+        .bind(&p.second_opinion_model)
+        .bind(&p.second_opinion_prediction)
+        .bind(&p.image_hash)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for s in &metadata.samples {
+        sqlx::query(
+            "INSERT INTO samples (id, request_id, created_at, contributor, label, filename, file_path, content_hash, image_size_bytes, review_status, reviewed_label, reviewed_at, archive_id, prediction_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"
+        )
+        .bind(s.id)
+        .bind(s.request_id)
+        .bind(&s.created_at)
+        .bind(&s.contributor)
+        .bind(&s.label)
+        .bind(&s.filename)
+        .bind(&s.file_path)
+        .bind(&s.content_hash)
+        .bind(s.image_size_bytes)
+        .bind(&s.review_status)
+        .bind(&s.reviewed_label)
+        .bind(&s.reviewed_at)
+        .bind(&s.archive_id)
+        .bind(s.prediction_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+    for row in &metadata.training_image_stats {
+        drift::record_training(&mut *tx, &row.content_hash, &row.measured_at, &row.stats).await?;
+    }
+    for row in &metadata.prediction_image_stats {
+        drift::record_prediction(&mut *tx, row.prediction_id, &row.created_at, &row.stats).await?;
+    }
+    for row in &metadata.sample_capture {
+        exif::record(&mut *tx, row.sample_id, &row.capture).await?;
+    }
+    for row in &metadata.sample_tags {
+        sqlx::query("INSERT INTO sample_tags (sample_id, tag, created_at) VALUES ($1, $2, $3)")
+            .bind(row.sample_id)
+            .bind(&row.tag)
+            .bind(&row.created_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for item in &metadata.sync_items {
+        sqlx::query("INSERT INTO sync_items (client_id, kind, result_id, created_at) VALUES ($1, $2, $3, $4)")
+            .bind(&item.client_id)
+            .bind(&item.kind)
+            .bind(item.result_id)
+            .bind(&item.created_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for setting in &metadata.runtime_settings {
+        sqlx::query("INSERT INTO runtime_settings (name, value, updated_at) VALUES ($1, $2, $3)")
+            .bind(&setting.name)
+            .bind(&setting.value)
+            .bind(&setting.updated_at)
+            .execute(&mut *tx)
+            .await?;
+    }
+    for key in &metadata.api_keys {
+        sqlx::query(
+            "INSERT INTO api_keys (id, name, key_hash, key_prefix, profile, scopes, created_at, rotated_at, last_used_at, revoked_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+        )
+        .bind(key.id)
+        .bind(&key.name)
+        .bind(&key.key_hash)
+        .bind(&key.key_prefix)
+        .bind(&key.profile)
+        .bind(&key.scopes)
+        .bind(&key.created_at)
+        .bind(&key.rotated_at)
+        .bind(&key.last_used_at)
+        .bind(&key.revoked_at)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    // PostgreSQL sequences don't advance for explicit IDs, so move them past the restored rows
+    if db::is_postgres(pool.connect_options().database_url.as_str()) {
+        for table in ["predictions", "samples", "api_keys"] {
+            sqlx::query(&format!(
+                "SELECT setval(pg_get_serial_sequence('{0}', 'id'), COALESCE(MAX(id), 0) + 1, false) FROM {0}",
+                table
+            ))
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    tx.commit().await
+}
+
+/// The settings a deployment runs with, minus secrets such as API keys and connection strings.
+fn config_snapshot() -> ConfigSnapshot {
+    let config = config::get();
+    ConfigSnapshot {
+        default_profile: config.default_profile.clone(),
+        quality_mode: config.quality_mode,
+        model_versions: config.model_versions.clone(),
+        active_model: config.active_model.clone(),
+        second_opinion_model: config.second_opinion_model.clone(),
+        storage_backend: match config.storage_backend {
+            StorageBackend::Local => "local",
+            StorageBackend::S3 { .. } => "s3",
+        }
+        .to_string(),
+        upload_rate_limit: config.upload_rate_limit,
+        prediction_cache_ttl: config.prediction_cache_ttl,
+        feature_flags: config.feature_flags.clone(),
+    }
+}
+
+/// Collects every file that belongs in a bundle, keyed by bundle path.
+fn collect_files() -> std::io::Result<BTreeMap<String, Vec<u8>>> {
+    let storage = storage::get();
+    let mut files = BTreeMap::new();
+
+    for key in storage.list(Area::TrainingData, "")? {
+        let data = storage.get(Area::TrainingData, &key)?;
+        files.insert(format!("{}{}", TRAINING_DATA_PREFIX, key), data);
+    }
+
+    for key in storage.list(Area::Archive, "")? {
+        let data = storage.get(Area::Archive, &key)?;
+        files.insert(format!("{}{}", ARCHIVE_PREFIX, key), data);
+    }
+
+    for model in models::versions() {
+        for key in storage.list(Area::Models, &format!("{}/", model.dir.trim_end_matches('/')))? {
+            let data = storage.get(Area::Models, &key)?;
+            files.insert(format!("{}{}", MODELS_PREFIX, key), data);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Packs files into a gzipped tar bundle, with a manifest of their checksums as the first entry.
+pub fn build_bundle(files: &BTreeMap<String, Vec<u8>>) -> std::io::Result<Vec<u8>> {
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        created_at: format_timestamp(Utc::now()),
+        files: files
+            .iter()
+            .map(|(path, data)| ManifestEntry { path: path.clone(), sha256: training::content_hash(data), size: data.len() })
+            .collect(),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest)?;
+
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (path, data) in std::iter::once((MANIFEST_PATH, &manifest)).chain(files.iter().map(|(p, d)| (p.as_str(), d))) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, path, data.as_slice())?;
+    }
+    archive.into_inner()?.finish()
+}
+
+/// Unpacks a bundle and checks every file against the manifest.
+/// Returns the files, excluding the manifest, only if the bundle is complete and intact.
+pub fn open_bundle(bundle: &[u8]) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let mut archive = tar::Archive::new(GzDecoder::new(bundle));
+    let mut files = BTreeMap::new();
+    for entry in archive.entries().map_err(|e| format!("Invalid bundle: {}", e))? {
+        let mut entry = entry.map_err(|e| format!("Invalid bundle: {}", e))?;
+        let path = entry.path().map_err(|e| format!("Invalid bundle: {}", e))?.to_string_lossy().into_owned();
+        if path.split('/').any(|part| part == ".." || part.is_empty()) {
+            return Err(format!("Invalid path in bundle: {}", path));
+        }
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).map_err(|e| format!("Invalid bundle: {}", e))?;
+        files.insert(path, data);
+    }
+
+    let manifest: Manifest = files
+        .remove(MANIFEST_PATH)
+        .ok_or("Bundle has no manifest")
+        .and_then(|data| serde_json::from_slice(&data).map_err(|_| "Bundle manifest is invalid"))?;
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(format!("Unsupported bundle format version {}", manifest.format_version));
+    }
+
+    let mut problems = Vec::new();
+    for entry in &manifest.files {
+        match files.get(&entry.path) {
+            None => problems.push(format!("{} is missing", entry.path)),
+            Some(data) if data.len() != entry.size || training::content_hash(data) != entry.sha256 => {
+                problems.push(format!("{} does not match its checksum", entry.path))
+            }
+            Some(_) => {}
+        }
+    }
+    if files.len() != manifest.files.len() {
+        problems.push("Bundle contains files not listed in its manifest".to_string());
+    }
+    if problems.is_empty() {
+        Ok(files)
+    } else {
+        Err(format!("Bundle failed integrity checks: {}", problems.join("; ")))
+    }
+}
+
+/// Backup route handler returning a bundle of the training dataset, metadata database, model artifacts and configuration.
+/// Requires the admin token. A copy is also kept in the exports area.
+pub async fn backup_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/backup");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized backup");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let metadata = match dump_metadata(pool).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            logger.error(format!("Failed to read metadata: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    let bundle = collect_files().and_then(|mut files| {
+        files.insert(CONFIG_PATH.to_string(), serde_json::to_vec_pretty(&config_snapshot())?);
+        files.insert(METADATA_PATH.to_string(), serde_json::to_vec(&metadata)?);
+        build_bundle(&files)
+    });
+    let bundle = match bundle {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            logger.error(format!("Failed to build backup: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Failed to build backup: {}", e));
+        }
+    };
+
+    let filename = format!("cricket_ready_backup_{}.tar.gz", Utc::now().format("%Y%m%d_%H%M%S"));
+    match storage::get().put(Area::Exports, &format!("backups/{}", filename), &bundle) {
+        Ok(location) => logger.info(format!("Backup saved: {} ({} bytes)", location, bundle.len())),
+        Err(e) => logger.error(format!("Failed to keep a copy of the backup: {}", e)),
+    }
+
+    rusty_api::HttpResponse::Ok()
+        .content_type("application/gzip")
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+        .body(bundle)
+}
+
+/// Query parameters accepted by `/admin/restore`.
+#[derive(Debug, Deserialize)]
+pub struct RestoreQuery {
+    /// Replace existing metadata rather than refusing to restore over it.
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// Restore route handler loading a bundle produced by `/admin/backup`.
+/// Requires the admin token. The whole bundle is verified before anything is written,
+/// and a deployment with existing metadata is only overwritten with `replace=true`.
+pub async fn restore_route(
+    req: rusty_api::HttpRequest,
+    query: rusty_api::web::Query<RestoreQuery>,
+    mut payload: rusty_api::web::Payload,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/restore");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized restore");
+        return resp;
+    }
+
+    let mut bundle = Vec::new();
+    while let Some(chunk) = payload.next().await {
+        match chunk {
+            Ok(chunk) => bundle.extend_from_slice(&chunk),
+            Err(e) => {
+                logger.error(format!("Failed to read bundle: {}", e));
+                return rusty_api::HttpResponse::BadRequest().body(format!("Failed to read bundle: {}", e));
+            }
+        }
+    }
+
+    let mut files = match open_bundle(&bundle) {
+        Ok(files) => files,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::BadRequest().body(message);
+        }
+    };
+    let config = match files.remove(CONFIG_PATH) {
+        Some(config) => config,
+        None => return rusty_api::HttpResponse::BadRequest().body("Bundle has no configuration"),
+    };
+    let metadata: Metadata = match files.remove(METADATA_PATH).map(|data| serde_json::from_slice(&data)) {
+        Some(Ok(metadata)) => metadata,
+        _ => return rusty_api::HttpResponse::BadRequest().body("Bundle metadata is missing or invalid"),
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match has_metadata(pool).await {
+        Ok(true) if !query.replace => {
+            return rusty_api::HttpResponse::Conflict()
+                .body("This deployment already has data; restore with replace=true to overwrite it");
+        }
+        Ok(_) => {}
+        Err(e) => {
+            logger.error(format!("Failed to check existing metadata: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    }
+
+    // Files go first: they are keyed by content or replace like for like, so a failure part way leaves nothing dangling
+    let storage = storage::get();
+    for (path, data) in &files {
+        let written = if let Some(key) = path.strip_prefix(TRAINING_DATA_PREFIX) {
+            storage.put(Area::TrainingData, key, data)
+        } else if let Some(key) = path.strip_prefix(ARCHIVE_PREFIX) {
+            storage.put(Area::Archive, key, data)
+        } else if let Some(key) = path.strip_prefix(MODELS_PREFIX) {
+            storage.put(Area::Models, key, data)
+        } else {
+            logger.error(format!("Skipping unexpected bundle entry: {}", path));
+            continue;
+        };
+        if let Err(e) = written {
+            logger.error(format!("Failed to restore {}: {}", path, e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Failed to restore {}: {}", path, e));
+        }
+    }
+
+    if let Err(e) = restore_metadata(pool, &metadata).await {
+        logger.error(format!("Failed to restore metadata: {}", e));
+        return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+    }
+
+    logger.info(format!("Restored {} file(s), {} sample(s), {} prediction(s)", files.len(), metadata.samples.len(), metadata.predictions.len()));
+
+    // Configuration comes from the environment, so it is handed back for the operator to apply
+    rusty_api::HttpResponse::Ok().json(RestoreResponse {
+        status: "success",
+        files_restored: files.len(),
+        balls: metadata.balls.len(),
+        predictions: metadata.predictions.len(),
+        samples: metadata.samples.len(),
+        config: serde_json::from_slice(&config).ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_files() -> BTreeMap<String, Vec<u8>> {
+        BTreeMap::from([
+            (CONFIG_PATH.to_string(), b"{}".to_vec()),
+            (METADATA_PATH.to_string(), serde_json::to_vec(&Metadata::default()).unwrap()),
+            ("training_data/training_log.jsonl".to_string(), b"{}\n".to_vec()),
+        ])
+    }
+
+    #[tokio::test]
+    async fn restores_keep_the_image_statistics_drift_is_measured_against() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let prediction_id = crate::predictions::record(&pool, &crate::predictions::NewPrediction::example()).await.unwrap();
+        let stats = ImageStats { brightness: 120.0, contrast: 40.0, red: 110.0, green: 90.0, blue: 80.0, saturation: 0.3, width: 4032, height: 3024 };
+        drift::record_training(&pool, &"a".repeat(64), "2025-03-14T09:26:53.589Z", &stats).await.unwrap();
+        drift::record_prediction(&pool, prediction_id, "2025-03-15T09:26:53.589Z", &stats).await.unwrap();
+
+        let metadata: Metadata = serde_json::from_slice(&serde_json::to_vec(&dump_metadata(&pool).await.unwrap()).unwrap()).unwrap();
+        restore_metadata(&pool, &metadata).await.unwrap();
+        let restored = dump_metadata(&pool).await.unwrap();
+        assert_eq!(restored.training_image_stats[0].measured_at, "2025-03-14T09:26:53.589Z");
+        assert_eq!(restored.training_image_stats[0].stats, stats);
+        assert_eq!((restored.prediction_image_stats[0].prediction_id, &restored.prediction_image_stats[0].stats), (prediction_id, &stats));
+    }
+
+    #[test]
+    fn bundle_round_trips_its_files() {
+        let files = sample_files();
+        let bundle = build_bundle(&files).unwrap();
+        assert_eq!(open_bundle(&bundle).unwrap(), files);
+    }
+
+    #[test]
+    fn tampered_bundle_fails_integrity_checks() {
+        let mut files = sample_files();
+        let bundle = build_bundle(&files).unwrap();
+
+        // Rebuild with the manifest from the original bundle but different file contents
+        let mut archive = tar::Archive::new(GzDecoder::new(bundle.as_slice()));
+        let mut manifest = Vec::new();
+        archive.entries().unwrap().next().unwrap().unwrap().read_to_end(&mut manifest).unwrap();
+        files.insert("training_data/training_log.jsonl".to_string(), b"tampered\n".to_vec());
+
+        let mut tampered = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, data) in std::iter::once((MANIFEST_PATH, &manifest)).chain(files.iter().map(|(p, d)| (p.as_str(), d))) {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            tampered.append_data(&mut header, path, data.as_slice()).unwrap();
+        }
+        let tampered = tampered.into_inner().unwrap().finish().unwrap();
+
+        let error = open_bundle(&tampered).unwrap_err();
+        assert!(error.contains("training_data/training_log.jsonl does not match its checksum"), "{}", error);
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::Utc;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::Deserialize;
+
+pub use crate::api_types::Ball;
+use crate::api_types::{BallHistoryResponse, RegisterBallResponse, TagResponse};
+use crate::auth;
+use crate::db;
+use crate::predictions::{self, format_timestamp};
+use crate::request_logger::RequestLogger;
+
+/// Length of the random token printed in a ball's QR code or written to its NFC tag.
+const TAG_LENGTH: usize = 16;
+
+/// Checks that a ball ID is short and only uses characters safe in URLs and file names.
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Generates a new random tag token.
+fn new_tag() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TAG_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Registers a ball with a fresh tag token.
+pub async fn register(pool: &db::Pool, id: &str, description: Option<&str>) -> Result<Ball, sqlx::Error> {
+    sqlx::query_as::<_, Ball>(
+        "INSERT INTO balls (id, description, tag, created_at) VALUES ($1, $2, $3, $4) RETURNING id, description, tag, created_at"
+    )
+    .bind(id)
+    .bind(description)
+    .bind(new_tag())
+    .bind(format_timestamp(Utc::now()))
+    .fetch_one(pool)
+    .await
+}
+
+/// Looks up a ball by its ID.
+pub async fn find(pool: &db::Pool, id: &str) -> Result<Option<Ball>, sqlx::Error> {
+    sqlx::query_as::<_, Ball>("SELECT id, description, tag, created_at FROM balls WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Looks up a ball by the token on its tag.
+pub async fn find_by_tag(pool: &db::Pool, tag: &str) -> Result<Option<Ball>, sqlx::Error> {
+    sqlx::query_as::<_, Ball>("SELECT id, description, tag, created_at FROM balls WHERE tag = $1")
+        .bind(tag)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The content encoded in a ball's QR code or NFC tag.
+/// When `PUBLIC_APP_URL` is set this is a link that opens the app with the tag pre-filled.
+pub fn tag_payload(tag: &str) -> String {
+    match std::env::var("PUBLIC_APP_URL") {
+        Ok(url) if !url.is_empty() => format!("{}?tag={}", url.trim_end_matches('/'), tag),
+        _ => format!("cricket-ready:tag:{}", tag),
+    }
+}
+
+/// Renders the QR code for a tag as an SVG document.
+pub fn qr_svg(tag: &str) -> Result<String, qrcode::types::QrError> {
+    let code = QrCode::new(tag_payload(tag).as_bytes())?;
+    Ok(code.render::<svg::Color>().min_dimensions(256, 256).build())
+}
+
+/// Body accepted by `/balls`.
+#[derive(Debug, Deserialize)]
+pub struct RegisterInput {
+    pub ball_id: String,
+    pub description: Option<String>,
+}
+
+/// Register route handler for adding a ball and issuing its tag.
+/// Requires the admin token.
+pub async fn register_route(
+    req: rusty_api::HttpRequest,
+    body: rusty_api::web::Json<RegisterInput>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /balls");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized ball registration");
+        return resp;
+    }
+
+    if !is_valid_id(&body.ball_id) {
+        logger.error(format!("Invalid ball ID: {}", body.ball_id));
+        return rusty_api::HttpResponse::BadRequest()
+            .body("Ball ID must be 1-64 letters, digits, '-' or '_'");
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match find(pool, &body.ball_id).await {
+        Ok(Some(_)) => {
+            return rusty_api::HttpResponse::Conflict()
+                .body(format!("Ball '{}' is already registered", body.ball_id));
+        }
+        Ok(None) => {}
+        Err(e) => {
+            logger.error(format!("Failed to look up ball: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    }
+
+    match register(pool, &body.ball_id, body.description.as_deref()).await {
+        Ok(ball) => {
+            logger.info(format!("Registered ball {}", ball.id));
+            rusty_api::HttpResponse::Ok().json(RegisterBallResponse { status: "success", tag_payload: tag_payload(&ball.tag), ball })
+        }
+        Err(e) => {
+            logger.error(format!("Failed to register ball: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// QR route handler returning the SVG QR code to print on a ball's box.
+pub async fn qr_route(path: rusty_api::web::Path<String>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let ball_id = path.into_inner();
+
+    logger.info(format!("Received request to /balls/{}/qr", ball_id));
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let ball = match find(pool, &ball_id).await {
+        Ok(Some(ball)) => ball,
+        Ok(None) => return rusty_api::HttpResponse::NotFound().body(format!("Ball '{}' not found", ball_id)),
+        Err(e) => {
+            logger.error(format!("Failed to look up ball: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    match qr_svg(&ball.tag) {
+        Ok(svg) => rusty_api::HttpResponse::Ok().content_type("image/svg+xml").body(svg),
+        Err(e) => {
+            logger.error(format!("Failed to generate QR code: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Failed to generate QR code: {}", e))
+        }
+    }
+}
+
+/// Tag route handler resolving a scanned QR/NFC tag to its ball.
+pub async fn resolve_route(path: rusty_api::web::Path<String>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let tag = path.into_inner();
+
+    logger.info("Received request to /tags/{tag}");
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match find_by_tag(pool, &tag).await {
+        Ok(Some(ball)) => rusty_api::HttpResponse::Ok().json(TagResponse {
+            ball_id: ball.id,
+            description: ball.description,
+        }),
+        Ok(None) => rusty_api::HttpResponse::NotFound().body("Unknown tag"),
+        Err(e) => {
+            logger.error(format!("Failed to resolve tag: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// History route handler listing the predictions made for a ball, newest first.
+pub async fn history_route(path: rusty_api::web::Path<String>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let ball_id = path.into_inner();
+
+    logger.info(format!("Received request to /balls/{}/predictions", ball_id));
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match predictions::for_ball(pool, &ball_id).await {
+        Ok(records) => rusty_api::HttpResponse::Ok().json(BallHistoryResponse {
+            ball_id,
+            predictions: records,
+        }),
+        Err(e) => {
+            logger.error(format!("Failed to load ball history: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn registered_ball_resolves_from_its_tag() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let ball = register(&pool, "club-ball-7", Some("Kookaburra Turf")).await.unwrap();
+        assert_eq!(ball.tag.len(), TAG_LENGTH);
+
+        let resolved = find_by_tag(&pool, &ball.tag).await.unwrap().unwrap();
+        assert_eq!(resolved.id, "club-ball-7");
+        assert!(qr_svg(&ball.tag).unwrap().starts_with("<?xml"));
+    }
+
+    #[test]
+    fn ball_ids_are_restricted_to_safe_characters() {
+        assert!(is_valid_id("nets_2024-01"));
+        assert!(!is_valid_id(""));
+        assert!(!is_valid_id("../etc"));
+    }
+}
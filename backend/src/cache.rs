@@ -0,0 +1,189 @@
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+use crate::config;
+use crate::request_logger::RequestLogger;
+
+/// Prefix for every key, so the backend can share a Redis instance with other services.
+const KEY_PREFIX: &str = "cricket_ready:";
+
+/// How often the in-process store drops expired entries. Expired entries are never read in the meantime.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Increments a counter and sets its expiry when it is created, in one step, so a failure between the two
+/// can't leave a counter that never expires. `ARGV[1]` is the expiry in seconds.
+const INCREMENT_SCRIPT: &str = r"
+local count = redis.call('INCR', KEYS[1])
+if count == 1 then
+    redis.call('EXPIRE', KEYS[1], ARGV[1])
+end
+return count
+";
+
+/// Entries of the in-process store, with their expiry times.
+pub struct MemoryEntries {
+    entries: HashMap<String, (String, Instant)>,
+    next_prune: Instant,
+}
+
+impl MemoryEntries {
+    /// Drops expired entries, at most once every `PRUNE_INTERVAL`.
+    fn prune(&mut self, now: Instant) {
+        if now >= self.next_prune {
+            self.entries.retain(|_, (_, expires)| *expires > now);
+            self.next_prune = now + PRUNE_INTERVAL;
+        }
+    }
+}
+
+/// Short-lived key-value state such as rate-limit counters and cached predictions.
+/// Kept in process memory by default, or in Redis when `REDIS_URL` is set so every replica shares it.
+pub enum Store {
+    Memory(Mutex<MemoryEntries>),
+    Redis(ConnectionManager),
+}
+
+static STORE: OnceCell<Store> = OnceCell::const_new();
+
+impl Store {
+    /// Creates an empty in-process store.
+    pub fn memory() -> Self {
+        Store::Memory(Mutex::new(MemoryEntries { entries: HashMap::new(), next_prune: Instant::now() + PRUNE_INTERVAL }))
+    }
+
+    /// Connects to Redis.
+    pub async fn redis(url: &str) -> Result<Self, String> {
+        let client = redis::Client::open(url).map_err(|e| format!("Invalid Redis URL: {}", e))?;
+        let manager = ConnectionManager::new(client)
+            .await
+            .map_err(|e| format!("Failed to connect to Redis: {}", e))?;
+        Ok(Store::Redis(manager))
+    }
+
+    /// Reads a value, or `None` if it is missing or expired.
+    pub async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        match self {
+            Store::Memory(entries) => {
+                let entries = entries.lock().unwrap();
+                Ok(entries.entries.get(key).filter(|(_, expires)| *expires > Instant::now()).map(|(value, _)| value.clone()))
+            }
+            Store::Redis(manager) => manager
+                .clone()
+                .get(format!("{}{}", KEY_PREFIX, key))
+                .await
+                .map_err(|e| format!("Redis error: {}", e)),
+        }
+    }
+
+    /// Stores a value that expires after `ttl`.
+    pub async fn set(&self, key: &str, value: &str, ttl: Duration) -> Result<(), String> {
+        match self {
+            Store::Memory(entries) => {
+                let mut entries = entries.lock().unwrap();
+                let now = Instant::now();
+                entries.prune(now);
+                entries.entries.insert(key.to_string(), (value.to_string(), now + ttl));
+                Ok(())
+            }
+            Store::Redis(manager) => manager
+                .clone()
+                .set_ex(format!("{}{}", KEY_PREFIX, key), value, ttl.as_secs().max(1))
+                .await
+                .map_err(|e| format!("Redis error: {}", e)),
+        }
+    }
+
+    /// Increments a counter and returns its new value.
+    /// The counter expires `ttl` after it was created, however often it is incremented.
+    pub async fn increment(&self, key: &str, ttl: Duration) -> Result<i64, String> {
+        match self {
+            Store::Memory(entries) => {
+                let mut entries = entries.lock().unwrap();
+                let now = Instant::now();
+                entries.prune(now);
+                let entry = entries.entries.entry(key.to_string()).or_insert_with(|| ("0".to_string(), now + ttl));
+                // A counter that has expired but not yet been pruned starts again
+                if entry.1 <= now {
+                    *entry = ("0".to_string(), now + ttl);
+                }
+                let count = entry.0.parse::<i64>().unwrap_or(0) + 1;
+                entry.0 = count.to_string();
+                Ok(count)
+            }
+            Store::Redis(manager) => redis::Script::new(INCREMENT_SCRIPT)
+                .key(format!("{}{}", KEY_PREFIX, key))
+                .arg(ttl.as_secs().max(1))
+                .invoke_async(&mut manager.clone())
+                .await
+                .map_err(|e| format!("Redis error: {}", e)),
+        }
+    }
+
+    /// Reads a JSON value, treating one that no longer parses as missing.
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        Ok(self.get(key).await?.and_then(|value| serde_json::from_str(&value).ok()))
+    }
+
+    /// Stores a value as JSON.
+    pub async fn set_json<T: Serialize>(&self, key: &str, value: &T, ttl: Duration) -> Result<(), String> {
+        let value = serde_json::to_string(value).map_err(|e| format!("Serialization error: {}", e))?;
+        self.set(key, &value, ttl).await
+    }
+}
+
+/// Returns the shared store, connecting to Redis on first use if `REDIS_URL` is set.
+pub async fn store() -> Result<&'static Store, String> {
+    STORE
+        .get_or_try_init(|| async {
+            match &config::get().redis_url {
+                Some(url) => Store::redis(url).await,
+                None => Ok(Store::memory()),
+            }
+        })
+        .await
+}
+
+/// Returns the shared store for a route handler, logging failures.
+/// Callers treat a missing store as a cache miss rather than failing the request.
+pub async fn store_for_request(logger: &RequestLogger) -> Option<&'static Store> {
+    match store().await {
+        Ok(store) => Some(store),
+        Err(message) => {
+            logger.error(format!("Shared cache unavailable: {}", message));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn memory_store_expires_values_and_counters() {
+        let store = Store::memory();
+
+        store.set("a", "1", Duration::from_secs(60)).await.unwrap();
+        store.set("b", "2", Duration::ZERO).await.unwrap();
+        assert_eq!(store.get("a").await.unwrap().as_deref(), Some("1"));
+        assert_eq!(store.get("b").await.unwrap(), None);
+
+        assert_eq!(store.increment("hits", Duration::from_secs(60)).await.unwrap(), 1);
+        assert_eq!(store.increment("hits", Duration::from_secs(60)).await.unwrap(), 2);
+        assert_eq!(store.increment("gone", Duration::ZERO).await.unwrap(), 1);
+        assert_eq!(store.increment("gone", Duration::ZERO).await.unwrap(), 1);
+
+        // Expired entries stay until the next prune, but are never read
+        let Store::Memory(entries) = &store else { unreachable!() };
+        assert_eq!(entries.lock().unwrap().entries.len(), 4);
+        let later = Instant::now() + PRUNE_INTERVAL;
+        entries.lock().unwrap().prune(later);
+        assert_eq!(entries.lock().unwrap().entries.len(), 0);
+    }
+}
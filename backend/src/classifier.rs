@@ -0,0 +1,382 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::cache;
+use crate::config;
+use crate::faults::Faults;
+use crate::models::{self, Device, ModelVersion, Precision};
+use crate::request_logger::RequestLogger;
+use crate::settings;
+use crate::storage::{self, Area};
+use crate::summary;
+
+/// Number of images this process is classifying right now.
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts an image as being classified until dropped.
+struct InFlightGuard;
+
+impl InFlightGuard {
+    fn new() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Returns how many images this process is classifying right now, i.e. the depth of its prediction queue.
+pub fn in_flight() -> usize {
+    IN_FLIGHT.load(Ordering::SeqCst)
+}
+
+/// Device the most recent prediction ran on.
+static ACTIVE_DEVICE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Returns the device the most recent prediction ran on, or `None` before the first one.
+pub fn active_device() -> Option<String> {
+    ACTIVE_DEVICE.lock().unwrap().clone()
+}
+
+/// The ensemble's verdict for a single image.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassifierOutput {
+    pub prediction: String,
+    pub confidence: f64,
+    /// Name of the model version that produced the verdict.
+    pub model_version: String,
+    /// Standard deviation of the match-ready probability over Monte Carlo dropout passes,
+    /// when they were asked for. High values mean the models can't make sense of the photo.
+    pub uncertainty: Option<f64>,
+    /// Precision of the weights that produced the verdict.
+    pub precision: Precision,
+    /// Device the models ran on, as PyTorch names it, e.g. `cuda:0`.
+    pub device: Option<String>,
+}
+
+/// Number of stochastic forward passes each model makes when estimating uncertainty.
+const MC_DROPOUT_PASSES: u32 = 20;
+
+/// Optional extra work done when classifying an image.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Options {
+    /// Average each verdict with the verdict on the mirrored image.
+    pub tta: bool,
+    /// Estimate uncertainty with Monte Carlo dropout.
+    pub uncertainty: bool,
+    /// Failures to simulate, when fault injection is enabled.
+    pub faults: Faults,
+}
+
+/// Runs the image through each model version in turn, returning their verdicts in the same order.
+/// `image_id` keeps temporary file names unique across concurrent requests.
+pub fn classify_with_models(
+    image_bytes: &[u8],
+    image_id: impl Display,
+    models: &[&ModelVersion],
+    options: Options,
+    logger: &RequestLogger,
+) -> Result<Vec<ClassifierOutput>, String> {
+    let _in_flight = InFlightGuard::new();
+    let storage = storage::get();
+
+    // Create temporary file for the image
+    let temp_key = format!("cricket_ball_{}.jpg", image_id);
+
+    // Write image to temporary file
+    let temp_path = match options.faults.check_write().and_then(|()| storage.put(Area::Temp, &temp_key, image_bytes)) {
+        Ok(path) => path,
+        Err(e) => {
+            logger.error(format!("Failed to write temporary file: {}", e));
+            return Err(format!("Failed to write temporary file: {}", e));
+        }
+    };
+
+    logger.info(format!("Temporary file created: {}", temp_path));
+
+    let outputs = models.iter().map(|model| run_script(&temp_path, model, options, logger)).collect();
+
+    // Clean up temporary file
+    if let Err(e) = storage.delete(Area::Temp, &temp_key) {
+        logger.error(format!("Failed to clean up temp file: {}", e));
+    }
+
+    outputs
+}
+
+/// Name, size and modification time of each file in a model version's directory that verdicts depend on:
+/// the weights at every precision, the calibration and the classes. Sorted by name.
+fn artifact_stamps(dir: &Path) -> Vec<(String, u64, u128)> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut stamps: Vec<(String, u64, u128)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !(name.starts_with("model_") || name == "calibration.json" || name == "classes.json") {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+            Some((name, metadata.len(), modified))
+        })
+        .collect();
+    stamps.sort();
+    stamps
+}
+
+/// Fingerprint of the artifacts a set of model versions runs from, so verdicts cached before a version was
+/// retrained or recalibrated in place aren't served afterwards. May download remote model artifacts, so
+/// call it on the blocking thread pool.
+fn artifacts_fingerprint(models: &[&ModelVersion]) -> String {
+    let mut hasher = Sha256::new();
+    for model in models {
+        let stamps = storage::get().local_path(Area::Models, &model.dir).map(|dir| artifact_stamps(&dir)).unwrap_or_default();
+        hasher.update(format!("{}:{:?}\n", model.name, stamps));
+    }
+    hasher.finalize().iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Key under which the verdicts of a set of model versions, with artifacts matching `fingerprint`, on an image are cached.
+fn cache_key(image_bytes: &[u8], models: &[&ModelVersion], fingerprint: &str, options: Options) -> String {
+    let digest = Sha256::digest(image_bytes);
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    let names: Vec<&str> = models.iter().map(|m| m.name.as_str()).collect();
+    let mut modes = String::new();
+    if options.tta {
+        modes.push_str(":tta");
+    }
+    if options.uncertainty {
+        modes.push_str(":mc");
+    }
+    let precision = config::get().model_precision;
+    if precision != Precision::Fp32 {
+        modes.push(':');
+        modes.push_str(precision.as_str());
+    }
+    format!("prediction:{}{}:{}:{}", names.join(","), modes, fingerprint, hex)
+}
+
+/// Like `classify_with_models`, but reuses verdicts cached in the shared store for an identical image,
+/// so a photo retried against another replica isn't classified twice. The models run on the blocking thread pool,
+/// so the worker stays free to serve other requests meanwhile.
+pub async fn classify_cached(
+    image_bytes: &[u8],
+    image_id: impl Display,
+    models: &[&'static ModelVersion],
+    options: Options,
+    logger: &RequestLogger,
+) -> Result<Vec<ClassifierOutput>, String> {
+    let ttl = settings::current(logger).await.prediction_cache_ttl;
+    // Injected faults must reach the classifier, so cached verdicts aren't used or kept
+    let store = match ttl {
+        0 => None,
+        _ if options.faults.any() => None,
+        _ => cache::store_for_request(logger).await,
+    };
+    let model_list = models.to_vec();
+    let fingerprint = rusty_api::web::block(move || artifacts_fingerprint(&model_list)).await.unwrap_or_default();
+    let key = cache_key(image_bytes, models, &fingerprint, options);
+
+    if let Some(store) = store {
+        match store.get_json::<Vec<ClassifierOutput>>(&key).await {
+            Ok(Some(outputs)) => {
+                logger.info("Using cached prediction");
+                return Ok(outputs);
+            }
+            Ok(None) => {}
+            Err(message) => logger.error(format!("Failed to read prediction cache: {}", message)),
+        }
+    }
+
+    // An injected slow inference waits here rather than holding a blocking thread
+    if options.faults.slow_inference_ms > 0 {
+        tokio::time::sleep(options.faults.inference_delay() * models.len() as u32).await;
+    }
+    let (image, image_id, model_list, handle) = (image_bytes.to_vec(), image_id.to_string(), models.to_vec(), logger.handle());
+    let classified = rusty_api::web::block(move || classify_with_models(&image, image_id, &model_list, options, &handle))
+        .await
+        .unwrap_or_else(|e| Err(format!("Prediction failed: {}", e)));
+    let outputs = match classified {
+        Ok(outputs) => outputs,
+        Err(message) => {
+            summary::count_prediction_error(logger).await;
+            return Err(message);
+        }
+    };
+
+    // Unknown verdicts usually mean the script failed on this image, so they aren't worth keeping
+    if let Some(store) = store {
+        if outputs.iter().all(|output| output.prediction != "unknown") {
+            if let Err(message) = store.set_json(&key, &outputs, Duration::from_secs(ttl)).await {
+                logger.error(format!("Failed to write prediction cache: {}", message));
+            }
+        }
+    }
+
+    Ok(outputs)
+}
+
+/// Calls the Python prediction script on an image already written to disk.
+fn run_script(image_path: &str, model: &ModelVersion, options: Options, logger: &RequestLogger) -> Result<ClassifierOutput, String> {
+    logger.info(format!("Running model version {}", model.name));
+
+    // Model weights may live in remote storage; make sure a local copy exists for the script
+    let models_dir = match storage::get().local_path(Area::Models, &model.dir) {
+        Ok(dir) => dir,
+        Err(e) => {
+            logger.error(format!("Failed to load model version {}: {}", model.name, e));
+            return Err(format!("Failed to load model version {}: {}", model.name, e));
+        }
+    };
+
+    // Run the configured quantized variant if it has been made; dropout sampling needs the weights as trained
+    let precision = if options.uncertainty { Precision::Fp32 } else { models::precision_for(&models_dir) };
+
+    // Call the Python prediction script
+    let mut command = Command::new("nn-classifier/venv/bin/python3");
+    command
+        .arg("nn-classifier/predict.py")
+        .arg(image_path)
+        .arg("--models-dir")
+        .arg(&models_dir)
+        .current_dir(".");  // Run from backend directory
+    if options.tta {
+        command.arg("--tta");
+    }
+    if precision != Precision::Fp32 {
+        command.arg("--precision").arg(precision.as_str());
+    }
+    if options.uncertainty {
+        command.arg("--mc-dropout").arg(MC_DROPOUT_PASSES.to_string());
+    }
+    let device = config::get().inference_device;
+    if device != Device::Auto {
+        command.arg("--device").arg(device.as_arg());
+    }
+    let output = if options.faults.classifier_crash {
+        // Kill the script as soon as it starts, so the crash is handled like a real one
+        logger.info("Killing predict.py: injected fault");
+        command.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().and_then(|mut child| {
+            child.kill().ok();
+            child.wait_with_output()
+        })
+    } else {
+        command.output()
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            logger.error(format!("Failed to execute predict.py: {}", e));
+            return Err(format!("Failed to execute prediction: {}", e));
+        }
+    };
+
+    // Check if the command executed successfully
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // A script that was killed leaves nothing on stderr, so report how it ended instead
+        let stderr = if stderr.trim().is_empty() { Cow::Owned(output.status.to_string()) } else { stderr };
+        logger.error(format!("Prediction script failed: {}", stderr));
+        return Err(format!("Prediction failed: {}", stderr));
+    }
+
+    // Parse the prediction output
+    let stdout = if options.faults.malformed_output {
+        Cow::Borrowed("Predicton: ??? (injected fault)\n")
+    } else {
+        String::from_utf8_lossy(&output.stdout)
+    };
+    logger.info("Prediction completed successfully");
+
+    let output = parse_output(&stdout, &model.name);
+    if let Some(device) = &output.device {
+        *ACTIVE_DEVICE.lock().unwrap() = Some(device.clone());
+    }
+
+    Ok(ClassifierOutput { precision, ..output })
+}
+
+/// Parse the output from predict.py script into a verdict.
+pub fn parse_output(output: &str, model_version: &str) -> ClassifierOutput {
+    let mut prediction = "unknown";
+    let mut confidence = 0.0;
+    let mut uncertainty = None;
+    // Expect output like: "Prediction: match_ready; Confidence: 0.9876", optionally followed by "; Uncertainty: 0.0123"
+    // The prediction is whichever label in the taxonomy the models were trained on scored highest
+    let re = Regex::new(r"Prediction:\s*([a-z][a-z0-9_]*);\s*Confidence:\s*([0-9.]+)(?:;\s*Uncertainty:\s*([0-9.]+))?").unwrap();
+    if let Some(caps) = re.captures(output) {
+        prediction = caps.get(1).map_or("unknown", |m| m.as_str());
+        confidence = caps.get(2).and_then(|m| m.as_str().parse::<f64>().ok()).unwrap_or(0.0);
+        uncertainty = caps.get(3).and_then(|m| m.as_str().parse::<f64>().ok());
+    }
+    // Followed by "; Device: cuda:0" since device selection was added
+    let device = Regex::new(r"Device:\s*(\S+)").unwrap().captures(output).map(|caps| caps[1].to_string());
+    ClassifierOutput {
+        prediction: prediction.to_string(),
+        confidence,
+        model_version: model_version.to_string(),
+        uncertainty,
+        precision: Precision::Fp32,
+        device,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_output_reads_verdict_or_falls_back_to_unknown() {
+        let output = parse_output("Prediction: not_match_ready; Confidence: 0.8123\n", "v2");
+        assert_eq!(output.prediction, "not_match_ready");
+        assert_eq!(output.confidence, 0.8123);
+        assert_eq!(output.model_version, "v2");
+        assert_eq!(output.uncertainty, None);
+        assert_eq!(parse_output("❌ Error loading image", "v2").prediction, "unknown");
+
+        let output = parse_output("Prediction: match_ready; Confidence: 0.6000; Uncertainty: 0.2100\n", "v2");
+        assert_eq!((output.confidence, output.uncertainty), (0.6, Some(0.21)));
+
+        let output = parse_output("Prediction: match_ready; Confidence: 0.6000; Device: cuda:1\n", "v2");
+        assert_eq!((output.confidence, output.device.as_deref()), (0.6, Some("cuda:1")));
+    }
+
+    #[test]
+    fn cache_key_depends_on_image_models_artifacts_and_options() {
+        let v1 = ModelVersion { name: "v1".to_string(), dir: "models/v1".to_string() };
+        let v2 = ModelVersion { name: "v2".to_string(), dir: "models/v2".to_string() };
+        assert_eq!(cache_key(b"ball", &[&v1], "a", Options::default()), cache_key(b"ball", &[&v1], "a", Options::default()));
+        assert_ne!(cache_key(b"ball", &[&v1], "a", Options::default()), cache_key(b"other", &[&v1], "a", Options::default()));
+        assert_ne!(cache_key(b"ball", &[&v1], "a", Options::default()), cache_key(b"ball", &[&v1, &v2], "a", Options::default()));
+        assert_ne!(cache_key(b"ball", &[&v1], "a", Options::default()), cache_key(b"ball", &[&v1], "b", Options::default()));
+        assert_ne!(cache_key(b"ball", &[&v1], "a", Options::default()), cache_key(b"ball", &[&v1], "a", Options { tta: true, ..Options::default() }));
+        assert_ne!(cache_key(b"ball", &[&v1], "a", Options::default()), cache_key(b"ball", &[&v1], "a", Options { uncertainty: true, ..Options::default() }));
+    }
+
+    #[test]
+    fn artifact_stamps_change_when_a_version_is_recalibrated() {
+        let dir = std::env::temp_dir().join(format!("cricket_ready_artifacts_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("model_1.pth"), b"weights").unwrap();
+        std::fs::write(dir.join("metrics.json"), b"{}").unwrap();
+        let before = artifact_stamps(&dir);
+        assert_eq!(before.iter().map(|(name, ..)| name.as_str()).collect::<Vec<_>>(), vec!["model_1.pth"]);
+
+        std::fs::write(dir.join("calibration.json"), b"{\"temperature\": 1.5}").unwrap();
+        assert_ne!(artifact_stamps(&dir), before);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(artifact_stamps(&dir).is_empty());
+    }
+}
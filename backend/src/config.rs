@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::OnceLock;
+
+use crate::models::{Device, ModelVersion, Precision};
+use crate::quality::QualityMode;
+use crate::storage::StorageBackend;
+
+/// Settings read from the environment once at first use.
+/// Those that can be changed at runtime (see `settings::RuntimeSettings`) are only their defaults.
+pub struct Config {
+    /// Strictness profile used when neither the request nor its API key selects one.
+    pub default_profile: String,
+    /// Strictness profile assigned to each API key, from `API_KEY_PROFILES`.
+    pub api_key_profiles: HashMap<String, String>,
+    /// Whether photo quality issues are ignored, reported as warnings, or rejected.
+    pub quality_mode: QualityMode,
+    /// Model versions available to the classifier, oldest first, from `MODEL_VERSIONS`.
+    pub model_versions: Vec<ModelVersion>,
+    /// Name of the model version serving predictions; defaults to the newest.
+    pub active_model: String,
+    /// Model version consulted for second opinions; defaults to the one before the active model.
+    pub second_opinion_model: Option<String>,
+    /// Weights to run each model version with, from `MODEL_PRECISION`; `auto` picks one for the machine.
+    pub model_precision: Precision,
+    /// Device the classifier runs on, from `INFERENCE_DEVICE`: `auto`, `cpu`, `cuda`, `cuda:<index>` or `mps`.
+    pub inference_device: Device,
+    /// Where images, logs, model artifacts and exports are kept, from `STORAGE_BACKEND` and the `S3_*` variables.
+    pub storage_backend: StorageBackend,
+    /// Redis instance holding rate-limit counters and cached predictions, shared by every replica.
+    /// When unset they are kept in process memory.
+    pub redis_url: Option<String>,
+    /// Name this replica records on the jobs it runs, from `INSTANCE_ID`; a random one for each run if unset.
+    pub instance_id: String,
+    /// Uploads each client may make per minute, from `UPLOAD_RATE_LIMIT`; `0` disables the limit.
+    pub upload_rate_limit: u32,
+    /// Uploads each client may have in progress at once, from `MAX_CONCURRENT_UPLOADS`; `0` disables the limit.
+    pub max_concurrent_uploads: usize,
+    /// How long classifier results are cached for identical images, in seconds; `0` disables the cache.
+    pub prediction_cache_ttl: u64,
+    /// Whether to identify clients by the address a load balancer forwards rather than the peer address.
+    pub behind_proxy: bool,
+    /// Base64-encoded AES-256 key for encrypting training images and exports at rest, from `STORAGE_ENCRYPTION_KEY`.
+    pub encryption_key: Option<String>,
+    /// Age in months after which `/admin/archive` moves training images to cold storage, from `ARCHIVE_AFTER_MONTHS`.
+    pub archive_after_months: u32,
+    /// Longest side, in pixels, training images are scaled down to before they are stored,
+    /// from `TRAINING_IMAGE_MAX_DIMENSION`; `0` keeps their resolution.
+    pub training_image_max_dimension: u32,
+    /// JPEG quality training images are recompressed at, from `TRAINING_IMAGE_QUALITY`; `0` stores them as uploaded.
+    pub training_image_quality: u8,
+    /// Whether the uploaded original of a recompressed training image is kept too, from `KEEP_ORIGINAL_TRAINING_IMAGES`.
+    pub keep_original_training_images: bool,
+    /// Size in bytes at which the training log moves on to a new segment, from `TRAINING_LOG_MAX_BYTES`;
+    /// `0` only starts a new segment each day.
+    pub training_log_max_bytes: u64,
+    /// Whether images sent for prediction are kept so the predictions can be replayed, from `STORE_PREDICTION_IMAGES`.
+    /// Off unless enabled, since the photos are kept indefinitely.
+    pub store_prediction_images: bool,
+    /// Hour (UTC) at which the previous day's summary report is produced, from `DAILY_REPORT_HOUR`; `off` disables it.
+    pub daily_report_hour: Option<u32>,
+    /// URL the daily report is posted to as JSON, from `DAILY_REPORT_WEBHOOK_URL`.
+    pub daily_report_webhook_url: Option<String>,
+    /// Address the daily report is emailed to through the local `sendmail` command, from `DAILY_REPORT_EMAIL`.
+    pub daily_report_email: Option<String>,
+    /// Feature flags switched on for every request, from the comma-separated `FEATURE_FLAGS`.
+    pub feature_flags: Vec<String>,
+    /// Addresses the API is served on over HTTPS, from the comma-separated `HTTPS_LISTEN`; `off` serves none.
+    pub https_listen: Vec<SocketAddr>,
+    /// Addresses the API is served on over plain HTTP, e.g. to a reverse proxy on the LAN, from `HTTP_LISTEN`.
+    pub http_listen: Vec<SocketAddr>,
+    /// Addresses that redirect plain HTTP requests to the HTTPS listener, from `HTTP_REDIRECT_LISTEN`.
+    pub http_redirect_listen: Vec<SocketAddr>,
+    /// Path of a Unix domain socket the API is also served on over plain HTTP, from `UNIX_SOCKET_PATH`.
+    pub unix_socket_path: Option<String>,
+    /// Permissions given to the Unix domain socket, from the octal `UNIX_SOCKET_MODE`.
+    pub unix_socket_mode: u32,
+    /// Whether failures can be injected on demand with `X-Inject-Faults` or `/admin/faults/active`, from `FAULT_INJECTION`.
+    /// For testing client apps and monitoring only.
+    pub fault_injection: bool,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+impl Config {
+    fn from_env() -> Self {
+        let mut model_versions: Vec<ModelVersion> = std::env::var("MODEL_VERSIONS")
+            .map(|v| parse_pairs(&v))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, dir)| ModelVersion { name, dir })
+            .collect();
+        if model_versions.is_empty() {
+            model_versions.push(ModelVersion { name: "v1".to_string(), dir: "nn-classifier/models".to_string() });
+        }
+
+        let active_model = std::env::var("ACTIVE_MODEL")
+            .unwrap_or_else(|_| model_versions[model_versions.len() - 1].name.clone());
+        let second_opinion_model = std::env::var("SECOND_OPINION_MODEL").ok().or_else(|| {
+            let active_index = model_versions.iter().position(|m| m.name == active_model)?;
+            active_index.checked_sub(1).map(|i| model_versions[i].name.clone())
+        });
+
+        let storage_backend = match std::env::var("STORAGE_BACKEND").as_deref() {
+            Ok("s3") => StorageBackend::S3 {
+                bucket: std::env::var("S3_BUCKET").unwrap_or_default(),
+                region: std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                endpoint: std::env::var("S3_ENDPOINT").ok().filter(|v| !v.is_empty()),
+                prefix: std::env::var("S3_PREFIX").unwrap_or_default(),
+            },
+            _ => StorageBackend::Local,
+        };
+
+        Self {
+            default_profile: std::env::var("DEFAULT_PROFILE").unwrap_or_else(|_| "social".to_string()),
+            api_key_profiles: std::env::var("API_KEY_PROFILES")
+                .map(|v| parse_pairs(&v).into_iter().collect())
+                .unwrap_or_default(),
+            quality_mode: std::env::var("QUALITY_CHECK")
+                .ok()
+                .and_then(|v| QualityMode::parse(&v))
+                .unwrap_or(QualityMode::Warn),
+            model_versions,
+            active_model,
+            second_opinion_model,
+            model_precision: match std::env::var("MODEL_PRECISION").as_deref() {
+                Ok("auto") => Precision::for_device(),
+                Ok(value) => Precision::parse(value).unwrap_or_default(),
+                Err(_) => Precision::Fp32,
+            },
+            inference_device: std::env::var("INFERENCE_DEVICE")
+                .ok()
+                .and_then(|v| Device::parse(v.trim()))
+                .unwrap_or_default(),
+            storage_backend,
+            redis_url: std::env::var("REDIS_URL").ok().filter(|v| !v.is_empty()),
+            instance_id: std::env::var("INSTANCE_ID")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>())),
+            upload_rate_limit: std::env::var("UPLOAD_RATE_LIMIT").ok().and_then(|v| v.parse().ok()).unwrap_or(30),
+            max_concurrent_uploads: std::env::var("MAX_CONCURRENT_UPLOADS").ok().and_then(|v| v.parse().ok()).unwrap_or(4),
+            prediction_cache_ttl: std::env::var("PREDICTION_CACHE_TTL").ok().and_then(|v| v.parse().ok()).unwrap_or(3600),
+            behind_proxy: std::env::var("BEHIND_PROXY").map(|v| v == "true" || v == "1").unwrap_or(false),
+            encryption_key: std::env::var("STORAGE_ENCRYPTION_KEY").ok().filter(|v| !v.is_empty()),
+            archive_after_months: std::env::var("ARCHIVE_AFTER_MONTHS").ok().and_then(|v| v.parse().ok()).unwrap_or(12),
+            training_image_max_dimension: std::env::var("TRAINING_IMAGE_MAX_DIMENSION").ok().and_then(|v| v.parse().ok()).unwrap_or(1024),
+            training_image_quality: std::env::var("TRAINING_IMAGE_QUALITY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&quality| quality <= 100)
+                .unwrap_or(85),
+            keep_original_training_images: std::env::var("KEEP_ORIGINAL_TRAINING_IMAGES").map(|v| v == "true" || v == "1").unwrap_or(false),
+            training_log_max_bytes: std::env::var("TRAINING_LOG_MAX_BYTES").ok().and_then(|v| v.parse().ok()).unwrap_or(10 * 1024 * 1024),
+            store_prediction_images: std::env::var("STORE_PREDICTION_IMAGES").map(|v| v == "true" || v == "1").unwrap_or(false),
+            daily_report_hour: match std::env::var("DAILY_REPORT_HOUR") {
+                Ok(v) => v.parse().ok().filter(|&hour| hour < 24),
+                Err(_) => Some(0),
+            },
+            daily_report_webhook_url: std::env::var("DAILY_REPORT_WEBHOOK_URL").ok().filter(|v| !v.is_empty()),
+            daily_report_email: std::env::var("DAILY_REPORT_EMAIL").ok().filter(|v| !v.is_empty()),
+            feature_flags: std::env::var("FEATURE_FLAGS")
+                .map(|v| v.split(',').map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect())
+                .unwrap_or_default(),
+            https_listen: std::env::var("HTTPS_LISTEN").map(|v| parse_addrs(&v)).unwrap_or_else(|_| parse_addrs("0.0.0.0:49161")),
+            http_listen: std::env::var("HTTP_LISTEN").map(|v| parse_addrs(&v)).unwrap_or_default(),
+            http_redirect_listen: std::env::var("HTTP_REDIRECT_LISTEN").map(|v| parse_addrs(&v)).unwrap_or_default(),
+            unix_socket_path: std::env::var("UNIX_SOCKET_PATH").ok().filter(|v| !v.is_empty()),
+            unix_socket_mode: std::env::var("UNIX_SOCKET_MODE")
+                .ok()
+                .and_then(|v| u32::from_str_radix(v.trim(), 8).ok())
+                .filter(|&mode| mode <= 0o777)
+                .unwrap_or(0o660),
+            fault_injection: std::env::var("FAULT_INJECTION").map(|v| v == "true" || v == "1").unwrap_or(false),
+        }
+    }
+}
+
+/// Returns the process-wide configuration.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::from_env)
+}
+
+/// Parses a comma-separated list of `key=value` pairs in order, skipping malformed entries.
+pub fn parse_pairs(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            let (key, value) = (key.trim(), value.trim());
+            (!key.is_empty() && !value.is_empty()).then(|| (key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// Parses a comma-separated list of `address:port` listen addresses, skipping malformed entries and `off`.
+pub fn parse_addrs(value: &str) -> Vec<SocketAddr> {
+    value.split(',').filter_map(|addr| addr.trim().parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pairs_skips_malformed_entries() {
+        let pairs = parse_pairs("abc=premier, def = club,broken,=social");
+        assert_eq!(pairs, vec![
+            ("abc".to_string(), "premier".to_string()),
+            ("def".to_string(), "club".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn parse_addrs_skips_malformed_entries() {
+        let addrs = parse_addrs("0.0.0.0:49161, [::1]:8080,localhost,off");
+        assert_eq!(addrs, vec!["0.0.0.0:49161".parse().unwrap(), "[::1]:8080".parse().unwrap()]);
+        assert!(parse_addrs("off").is_empty());
+    }
+}
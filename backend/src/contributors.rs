@@ -0,0 +1,84 @@
+use chrono::Utc;
+
+pub use crate::api_types::ContributorStats;
+use crate::api_types::ContributorsResponse;
+use crate::db;
+use crate::request_logger::RequestLogger;
+
+/// Computes per-contributor statistics, ordered as a leaderboard by approved samples.
+pub async fn stats(pool: &db::Pool) -> Result<Vec<ContributorStats>, sqlx::Error> {
+    let mut rows = sqlx::query_as::<_, ContributorStats>(
+        "SELECT contributor,
+                COUNT(*) AS samples_submitted,
+                SUM(CASE WHEN review_status = 'approved' THEN 1 ELSE 0 END) AS samples_approved,
+                SUM(CASE WHEN review_status = 'rejected' THEN 1 ELSE 0 END) AS samples_rejected,
+                SUM(CASE WHEN review_status = 'approved' AND reviewed_label = label THEN 1 ELSE 0 END) AS labels_confirmed
+         FROM samples
+         WHERE contributor IS NOT NULL
+         GROUP BY contributor
+         ORDER BY samples_approved DESC, samples_submitted DESC, contributor"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    for row in &mut rows {
+        if row.samples_approved > 0 {
+            row.label_accuracy = Some(row.labels_confirmed as f64 / row.samples_approved as f64);
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Stats route handler returning the contributor leaderboard as JSON.
+pub async fn stats_route() -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /contributors/stats");
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match stats(pool).await {
+        Ok(contributors) => rusty_api::HttpResponse::Ok().json(ContributorsResponse { contributors }),
+        Err(e) => {
+            logger.error(format!("Failed to compute contributor stats: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::{self, NewSample};
+
+    fn sample(contributor: &'static str, label: &'static str) -> NewSample<'static> {
+        NewSample { contributor: Some(contributor), label, ..NewSample::example() }
+    }
+
+    #[tokio::test]
+    async fn stats_reflect_reviewer_corrections() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+
+        let first = samples::record(&pool, &sample("alex", "match_ready")).await.unwrap();
+        let second = samples::record(&pool, &sample("alex", "match_ready")).await.unwrap();
+        let third = samples::record(&pool, &sample("sam", "not_match_ready")).await.unwrap();
+        samples::record(&pool, &sample("alex", "not_match_ready")).await.unwrap();
+
+        samples::review(&pool, first, true, None).await.unwrap();
+        samples::review(&pool, second, true, Some("not_match_ready")).await.unwrap();
+        samples::review(&pool, third, false, None).await.unwrap();
+
+        let stats = stats(&pool).await.unwrap();
+        assert_eq!(stats[0].contributor, "alex");
+        assert_eq!(stats[0].samples_submitted, 3);
+        assert_eq!(stats[0].samples_approved, 2);
+        assert_eq!(stats[0].label_accuracy, Some(0.5));
+        assert_eq!(stats[1].samples_rejected, 1);
+        assert_eq!(stats[1].label_accuracy, None);
+    }
+}
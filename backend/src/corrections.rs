@@ -0,0 +1,182 @@
+use chrono::Utc;
+
+use crate::api_keys;
+use crate::api_types::{CorrectionInput, CorrectionResponse, TrainingLogEntry};
+use crate::db;
+use crate::drift;
+use crate::exif;
+use crate::labels;
+use crate::predictions;
+use crate::replay;
+use crate::request_logger::RequestLogger;
+use crate::samples::{self, NewSample};
+use crate::training;
+
+/// The response when recording a correction fails. The unique index on `samples.prediction_id` catches a
+/// correction racing another for the same prediction past the check in `correct_route`.
+fn record_failed(prediction_id: i64, e: &sqlx::Error) -> rusty_api::HttpResponse {
+    match e.as_database_error() {
+        Some(db_error) if db_error.is_unique_violation() => {
+            rusty_api::HttpResponse::Conflict().body(format!("Prediction {} has already been corrected", prediction_id))
+        }
+        _ => rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+    }
+}
+
+/// Correction route handler turning a past prediction into a training sample with the label the user says
+/// is right. The kept photo is stored as a training image and queued for review like a `/training` upload,
+/// so feedback from the app reaches the next retrain without the photo being sent again.
+/// Each prediction can be corrected once. Prediction IDs are sequential, so this requires an issued API key
+/// with the `training` scope.
+pub async fn correct_route(
+    req: rusty_api::HttpRequest,
+    path: rusty_api::web::Path<i64>,
+    body: rusty_api::web::Json<CorrectionInput>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let prediction_id = path.into_inner();
+
+    logger.info(format!("Received request to /predictions/{}/correct", prediction_id));
+
+    let api_key = match api_keys::authenticate(&req, &logger).await {
+        Ok(api_key) => api_key,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = api_keys::require_key(api_key.as_ref(), "training", &logger) {
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let prediction = match predictions::find(pool, prediction_id).await {
+        Ok(Some(prediction)) => prediction,
+        Ok(None) => return rusty_api::HttpResponse::NotFound().body(format!("Prediction {} not found", prediction_id)),
+        Err(e) => {
+            logger.error(format!("Failed to look up prediction: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    match samples::for_prediction(pool, prediction_id).await {
+        Ok(None) => {}
+        Ok(Some(sample_id)) => {
+            logger.error(format!("Prediction {} was already corrected as sample {}", prediction_id, sample_id));
+            return rusty_api::HttpResponse::Conflict()
+                .body(format!("Prediction {} has already been corrected (sample {})", prediction_id, sample_id));
+        }
+        Err(e) => {
+            logger.error(format!("Failed to look up correction: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    }
+
+    match labels::names(pool).await {
+        Ok(names) if names.contains(&body.label) => {}
+        Ok(names) => {
+            logger.error(format!("Invalid label: {}", body.label));
+            return rusty_api::HttpResponse::BadRequest().body(labels::invalid_label_message("Label must be one of:", &names));
+        }
+        Err(e) => {
+            logger.error(format!("Failed to load labels: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    }
+
+    let Some(hash) = prediction.image_hash.as_deref() else {
+        return rusty_api::HttpResponse::Conflict()
+            .body(format!("The image for prediction {} wasn't kept, so it can't be used for training", prediction_id));
+    };
+    let image_bytes = match replay::read_image(hash) {
+        Ok(image_bytes) => image_bytes,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::InternalServerError().body(message);
+        }
+    };
+
+    let capture = exif::read(&image_bytes);
+    let image = match training::write_image(&image_bytes) {
+        Ok(image) => image,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::InternalServerError().body(message);
+        }
+    };
+
+    let sample = NewSample {
+        request_id,
+        contributor: body.contributor.as_deref(),
+        label: &body.label,
+        filename: &image.filename,
+        file_path: &image.file_path,
+        content_hash: &image.content_hash,
+        image_size_bytes: image_bytes.len(),
+        prediction_id: Some(prediction_id),
+    };
+    let sample_id = match samples::record(pool, &sample).await {
+        Ok(id) => id,
+        Err(e) => {
+            logger.error(format!("Failed to record training sample: {}", e));
+            // Don't leave behind an image no sample refers to. A concurrent correction of the same prediction
+            // stores the same image, so only remove it once no sample refers to it.
+            if image.created && !samples::hash_in_use(pool, &image.content_hash).await.unwrap_or(true) {
+                training::remove_image(&image.key).ok();
+                training::remove_original(&image.content_hash).ok();
+            }
+            return record_failed(prediction_id, &e);
+        }
+    };
+    drift::observe_training(pool, &image.content_hash, &image_bytes, &logger).await;
+    exif::observe(pool, sample_id, capture.as_ref(), &logger).await;
+
+    let log_entry = TrainingLogEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        request_id,
+        label: body.label.clone(),
+        contributor: body.contributor.clone(),
+        filename: image.filename.clone(),
+        file_path: image.file_path.clone(),
+        content_hash: Some(image.content_hash.clone()),
+        image_size_bytes: image_bytes.len() as u64,
+        stored_size_bytes: Some(image.stored_bytes as u64),
+        prediction_id: Some(prediction_id),
+        capture,
+        ..TrainingLogEntry::default()
+    };
+    if let Err(e) = training::append_log(&log_entry) {
+        logger.error(format!("Failed to write to training log: {}", e));
+    }
+
+    logger.info(format!("Prediction {} ({}) corrected to {} as sample {}", prediction_id, prediction.prediction, body.label, sample_id));
+    let body = body.into_inner();
+    rusty_api::HttpResponse::Ok().json(CorrectionResponse {
+        status: "success",
+        prediction_id,
+        sample_id,
+        label: body.label,
+        prediction: prediction.prediction,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn each_prediction_is_corrected_once() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let prediction = predictions::NewPrediction { image_hash: Some("abcd"), ..predictions::NewPrediction::example() };
+        let prediction_id = predictions::record(&pool, &prediction).await.unwrap();
+        assert_eq!(samples::for_prediction(&pool, prediction_id).await.unwrap(), None);
+
+        let sample = NewSample { label: "not_match_ready", prediction_id: Some(prediction_id), ..NewSample::example() };
+        let sample_id = samples::record(&pool, &sample).await.unwrap();
+        assert_eq!(samples::for_prediction(&pool, prediction_id).await.unwrap(), Some(sample_id));
+        let duplicate = samples::record(&pool, &sample).await.unwrap_err();
+        assert_eq!(record_failed(prediction_id, &duplicate).status(), rusty_api::StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,297 @@
+use chrono::Utc;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::auth;
+use crate::backup;
+use crate::config;
+use crate::db;
+use crate::exif;
+use crate::labels;
+use crate::predictions::csv_field;
+use crate::request_logger::RequestLogger;
+use crate::storage::{self, Area};
+use crate::training;
+
+/// JPEG quality for photos re-encoded to turn them upright.
+const JPEG_QUALITY: u8 = 95;
+const MANIFEST_PATH: &str = "labels.csv";
+const IMAGES_PREFIX: &str = "images/";
+/// Where `train.py` reads the dataset, one folder of images per label, relative to the backend directory.
+const TRAINING_DATASET_DIR: &str = "nn-classifier/dataset";
+
+/// A training sample as it appears in an anonymized dataset.
+#[derive(Debug, sqlx::FromRow)]
+struct DatasetSample {
+    content_hash: String,
+    label: String,
+    created_at: String,
+}
+
+/// Samples that weren't rejected and whose images are still in the training data, with their final labels.
+async fn samples(pool: &db::Pool) -> Result<Vec<DatasetSample>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT content_hash, COALESCE(reviewed_label, label) AS label, created_at FROM samples \
+         WHERE review_status <> 'rejected' AND archive_id IS NULL AND content_hash IS NOT NULL ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Approved samples whose images are still in the training data, with their final labels.
+async fn approved_samples(pool: &db::Pool) -> Result<Vec<DatasetSample>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT content_hash, COALESCE(reviewed_label, label) AS label, created_at FROM samples \
+         WHERE review_status = 'approved' AND archive_id IS NULL AND content_hash IS NOT NULL ORDER BY id",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Whether a dataset file was copied from the training data, which names images by content hash.
+fn is_copied_image(file_name: &str) -> bool {
+    file_name.strip_suffix(".jpg").is_some_and(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Brings the label folders under `dir` in line with the approved samples, copying each image in as
+/// `<label>/<hash>.jpg`. Copies whose samples have since been relabeled, rejected or archived are removed;
+/// other files, e.g. images put in the folders by hand, are left alone. Returns the number of copied images
+/// in the dataset, and the hashes of images that couldn't be read, which are left out.
+fn sync_folders(
+    dir: &Path,
+    labels: &[String],
+    samples: &[DatasetSample],
+    read_image: impl Fn(&str) -> Result<Vec<u8>, String>,
+) -> Result<(usize, Vec<String>), String> {
+    let wanted: HashSet<(&str, &str)> = samples
+        .iter()
+        .filter(|sample| labels.contains(&sample.label))
+        .map(|sample| (sample.label.as_str(), sample.content_hash.as_str()))
+        .collect();
+
+    for label in labels {
+        let folder = dir.join(label);
+        std::fs::create_dir_all(&folder).map_err(|e| format!("Failed to create {}: {}", folder.display(), e))?;
+        let entries = std::fs::read_dir(&folder).map_err(|e| format!("Failed to read {}: {}", folder.display(), e))?;
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if is_copied_image(&file_name) && !wanted.contains(&(label.as_str(), &file_name[..64])) {
+                std::fs::remove_file(entry.path()).map_err(|e| format!("Failed to remove {}: {}", entry.path().display(), e))?;
+            }
+        }
+    }
+
+    let mut skipped = Vec::new();
+    for (label, hash) in &wanted {
+        let path = dir.join(label).join(format!("{}.jpg", hash));
+        if path.exists() {
+            continue;
+        }
+        match read_image(hash) {
+            Ok(image_bytes) => std::fs::write(&path, image_bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?,
+            Err(_) => skipped.push(hash.to_string()),
+        }
+    }
+    Ok((wanted.len() - skipped.len(), skipped))
+}
+
+/// Copies the images put in the shared label folders by hand, i.e. those not copied from samples, into `dir`.
+fn copy_added_by_hand(shared: &Path, dir: &Path, labels: &[String]) -> Result<(), String> {
+    for label in labels {
+        std::fs::create_dir_all(dir.join(label)).map_err(|e| format!("Failed to create {}: {}", dir.join(label).display(), e))?;
+        let entries = std::fs::read_dir(shared.join(label)).map_err(|e| format!("Failed to read {}: {}", shared.join(label).display(), e))?;
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            if entry.path().is_file() && !is_copied_image(&file_name.to_string_lossy()) {
+                let path = dir.join(label).join(&file_name);
+                std::fs::copy(entry.path(), &path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Where a training job's dataset is written. Normally the shared label folders, so unchanged images aren't copied
+/// again for every job. With encryption at rest, a directory of the job's own, removed with
+/// `remove_training_dir` when the job ends, so decrypted training images don't stay on disk.
+pub fn training_dir(job_id: i64) -> PathBuf {
+    match config::get().encryption_key {
+        Some(_) => PathBuf::from(format!("{}.job-{}", TRAINING_DATASET_DIR, job_id)),
+        None => PathBuf::from(TRAINING_DATASET_DIR),
+    }
+}
+
+/// Removes the dataset a training job wrote to a directory of its own, if it did.
+pub fn remove_training_dir(job_id: i64) -> std::io::Result<()> {
+    let dir = training_dir(job_id);
+    if dir == Path::new(TRAINING_DATASET_DIR) || !dir.exists() {
+        return Ok(());
+    }
+    std::fs::remove_dir_all(dir)
+}
+
+/// Copies the images of approved samples into label folders under `dir` for `train.py` to read, so a retrain
+/// learns from what reviewers approved. A job-specific `dir` also gets the images added to the shared folders
+/// by hand, and any copies of samples in the shared folders, left from before encryption was turned on, are removed.
+/// Returns the number of images copied from samples into the dataset.
+pub async fn write_for_training(pool: &db::Pool, dir: PathBuf, logger: &RequestLogger) -> Result<usize, String> {
+    let labels = labels::names(pool).await.map_err(|e| format!("Failed to load labels: {}", e))?;
+    let samples = approved_samples(pool).await.map_err(|e| format!("Failed to load samples: {}", e))?;
+
+    let (images, skipped) = rusty_api::web::block(move || {
+        let shared = Path::new(TRAINING_DATASET_DIR);
+        if dir != shared {
+            sync_folders(shared, &labels, &[], training::read_image)?;
+            copy_added_by_hand(shared, &dir, &labels)?;
+        }
+        sync_folders(&dir, &labels, &samples, training::read_image)
+    })
+    .await
+    .map_err(|e| format!("Failed to write the dataset: {}", e))??;
+    for hash in &skipped {
+        logger.error(format!("Left training image {} out of the dataset: it couldn't be read", hash));
+    }
+    Ok(images)
+}
+
+/// The files of an anonymized dataset: each image with all its metadata removed, named by the hash of what is
+/// shared, and a `labels.csv` manifest of just image hashes, labels and the month each was submitted.
+/// Contributors, capture metadata and exact timestamps are left out, so the dataset can't be tied back to anyone.
+/// Returns the files and the hashes of images that couldn't be read or cleaned, which are left out too.
+fn build(samples: &[DatasetSample], read_image: impl Fn(&str) -> Result<Vec<u8>, String>) -> (BTreeMap<String, Vec<u8>>, Vec<String>) {
+    let mut files = BTreeMap::new();
+    let mut manifest = String::from("image_hash,label,month\n");
+    let mut skipped = Vec::new();
+    for sample in samples {
+        let Some(image_bytes) = read_image(&sample.content_hash).ok().and_then(|bytes| exif::strip_all(&bytes, JPEG_QUALITY)) else {
+            skipped.push(sample.content_hash.clone());
+            continue;
+        };
+        let extension = image::guess_format(&image_bytes).ok().and_then(|format| format.extensions_str().first().copied()).unwrap_or("bin");
+        let hash = training::content_hash(&image_bytes);
+        let path = format!("{}{}.{}", IMAGES_PREFIX, hash, extension);
+        // The same photo submitted twice is shared once
+        if files.contains_key(&path) {
+            continue;
+        }
+        let month = sample.created_at.get(..7).unwrap_or_default();
+        manifest.push_str(&format!("{},{},{}\n", hash, csv_field(&sample.label), month));
+        files.insert(path, image_bytes);
+    }
+    files.insert(MANIFEST_PATH.to_string(), manifest.into_bytes());
+    (files, skipped)
+}
+
+/// Anonymized dataset route handler returning a bundle of the training images and their labels that is safe to
+/// share with researchers outside the club. Rejected and archived samples are left out.
+/// Requires the admin token. A copy is also kept in the exports area.
+pub async fn export_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/dataset/anonymized");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized dataset export");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let samples = match samples(pool).await {
+        Ok(samples) => samples,
+        Err(e) => {
+            logger.error(format!("Failed to load samples: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    let (files, skipped) = build(&samples, training::read_image);
+    for hash in &skipped {
+        logger.error(format!("Left training image {} out of the dataset: it couldn't be read or cleaned", hash));
+    }
+    let bundle = match backup::build_bundle(&files) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            logger.error(format!("Failed to build dataset: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Failed to build dataset: {}", e));
+        }
+    };
+
+    let filename = format!("cricket_ready_dataset_{}.tar.gz", Utc::now().format("%Y%m%d_%H%M%S"));
+    match storage::get().put(Area::Exports, &format!("datasets/{}", filename), &bundle) {
+        Ok(location) => logger.info(format!("Dataset saved: {} ({} images, {} bytes)", location, files.len() - 1, bundle.len())),
+        Err(e) => logger.error(format!("Failed to keep a copy of the dataset: {}", e)),
+    }
+
+    rusty_api::HttpResponse::Ok()
+        .content_type("application/gzip")
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}\"", filename)))
+        .body(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::png::PngEncoder;
+    use image::{ImageEncoder, RgbImage};
+
+    #[test]
+    fn datasets_list_only_hashes_labels_and_months() {
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png).write_image(RgbImage::new(4, 4).as_raw(), 4, 4, image::ExtendedColorType::Rgb8).unwrap();
+        let sample = |content_hash: &str, label: &str| DatasetSample {
+            content_hash: content_hash.to_string(),
+            label: label.to_string(),
+            created_at: "2025-03-14T09:26:53.589Z".to_string(),
+        };
+        let samples = [sample("a", "match_ready"), sample("b", "match_ready"), sample("missing", "not_match_ready")];
+
+        let (files, skipped) = build(&samples, |hash| match hash {
+            "missing" => Err("Failed to read training image".to_string()),
+            _ => Ok(png.clone()),
+        });
+        let hash = training::content_hash(&png);
+        assert_eq!(skipped, vec!["missing"]);
+        assert_eq!(files.keys().cloned().collect::<Vec<_>>(), vec![format!("images/{}.png", hash), "labels.csv".to_string()]);
+        assert_eq!(String::from_utf8(files["labels.csv"].clone()).unwrap(), format!("image_hash,label,month\n{},match_ready,2025-03\n", hash));
+    }
+
+    #[test]
+    fn training_folders_follow_the_approved_samples() {
+        let dir = std::env::temp_dir().join(format!("cricket_ready_dataset_{}", std::process::id()));
+        let labels = vec!["match_ready".to_string(), "not_match_ready".to_string()];
+        let (a, b) = ("a".repeat(64), "b".repeat(64));
+        let sample = |content_hash: &str, label: &str| DatasetSample {
+            content_hash: content_hash.to_string(),
+            label: label.to_string(),
+            created_at: String::new(),
+        };
+        let read_image = |hash: &str| if hash == b.as_str() { Err("missing".to_string()) } else { Ok(hash.as_bytes().to_vec()) };
+
+        let samples = [sample(&a, "match_ready"), sample(&b, "match_ready")];
+        assert_eq!(sync_folders(&dir, &labels, &samples, read_image).unwrap(), (1, vec![b.clone()]));
+        std::fs::write(dir.join("not_match_ready/by_hand.jpg"), b"").unwrap();
+        assert!(dir.join(format!("match_ready/{}.jpg", a)).exists());
+
+        // Relabeled during review
+        let samples = [sample(&a, "not_match_ready")];
+        assert_eq!(sync_folders(&dir, &labels, &samples, read_image).unwrap(), (1, vec![]));
+        assert!(!dir.join(format!("match_ready/{}.jpg", a)).exists());
+        assert!(dir.join(format!("not_match_ready/{}.jpg", a)).exists());
+        assert!(dir.join("not_match_ready/by_hand.jpg").exists());
+
+        // With encryption at rest a job gets its own directory, and no copies are kept in the shared folders
+        let job_dir = dir.with_extension("job-1");
+        assert_eq!(sync_folders(&dir, &labels, &[], read_image).unwrap(), (0, vec![]));
+        copy_added_by_hand(&dir, &job_dir, &labels).unwrap();
+        assert!(!dir.join(format!("not_match_ready/{}.jpg", a)).exists());
+        assert!(job_dir.join("not_match_ready/by_hand.jpg").exists());
+        assert!(job_dir.join("match_ready").is_dir());
+        std::fs::remove_dir_all(&job_dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,66 @@
+use sqlx::any::AnyPoolOptions;
+use sqlx::migrate::MigrateDatabase;
+use sqlx::Any;
+use tokio::sync::OnceCell;
+
+use crate::request_logger::RequestLogger;
+
+/// Connection pool for the metadata database, which is either SQLite or PostgreSQL depending on its URL.
+/// Queries shared by both use `$1`-style placeholders, which SQLite also accepts.
+pub type Pool = sqlx::AnyPool;
+
+/// Lazily initialized connection pool for the metadata database.
+static POOL: OnceCell<Pool> = OnceCell::const_new();
+
+/// Default location of the metadata database, relative to the backend directory.
+const DEFAULT_DATABASE_URL: &str = "sqlite://cricket_ready.db";
+
+/// Whether a database URL points at PostgreSQL rather than SQLite.
+pub fn is_postgres(database_url: &str) -> bool {
+    database_url.starts_with("postgres://") || database_url.starts_with("postgresql://")
+}
+
+/// Opens a pool for the given SQLite or PostgreSQL URL, creating the database if needed,
+/// and applies any pending migrations.
+pub async fn connect(database_url: &str) -> Result<Pool, sqlx::Error> {
+    sqlx::any::install_default_drivers();
+
+    if !Any::database_exists(database_url).await? {
+        Any::create_database(database_url).await?;
+    }
+
+    // In-memory databases are per-connection, so keep a single connection for them.
+    let max_connections = if database_url.contains(":memory:") { 1 } else { 5 };
+
+    let pool = AnyPoolOptions::new()
+        .max_connections(max_connections)
+        .connect(database_url)
+        .await?;
+
+    if is_postgres(database_url) {
+        sqlx::migrate!("./migrations/postgres").run(&pool).await?;
+    } else {
+        sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+    }
+
+    Ok(pool)
+}
+
+/// Returns the shared metadata database pool, connecting on first use.
+/// The location can be overridden with the `METADATA_DATABASE_URL` environment variable.
+pub async fn pool() -> Result<&'static Pool, sqlx::Error> {
+    POOL.get_or_try_init(|| async {
+        let database_url = std::env::var("METADATA_DATABASE_URL")
+            .unwrap_or_else(|_| DEFAULT_DATABASE_URL.to_string());
+        connect(&database_url).await
+    })
+    .await
+}
+
+/// Returns the shared pool for a route handler, logging failures and converting them into a response.
+pub async fn pool_for_request(logger: &RequestLogger) -> Result<&'static Pool, rusty_api::HttpResponse> {
+    pool().await.map_err(|e| {
+        logger.error(format!("Failed to open metadata database: {}", e));
+        rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+    })
+}
@@ -0,0 +1,160 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat};
+pub use crate::api_types::BoundingBox;
+use crate::quality;
+
+/// Width the image is scaled to before looking for balls.
+const ANALYSIS_SIZE: u32 = 256;
+/// Smallest share of the frame a region must cover to count as a ball, so specks of dirt are ignored.
+const MIN_AREA: f64 = 0.002;
+/// Limits on a region's width divided by its height; balls are round.
+const MIN_ASPECT: f64 = 0.6;
+const MAX_ASPECT: f64 = 1.67;
+/// Smallest share of its bounding box a region must fill. A circle fills about 0.79.
+const MIN_FILL: f64 = 0.55;
+/// Margin added around each ball, as a share of its size, so the classifier sees its edges.
+const PADDING: f64 = 0.15;
+/// Most balls returned for one photo; the largest are kept.
+pub const MAX_BALLS: usize = 30;
+
+/// Finds the balls in a photo of several laid out on a plain background, e.g. the contents of a ball bag
+/// tipped onto the grass, ordered top to bottom and left to right. Balls touching each other are found as one.
+pub fn detect(image: &DynamicImage) -> Vec<BoundingBox> {
+    let scaled = image.resize(ANALYSIS_SIZE, ANALYSIS_SIZE, FilterType::Triangle).to_rgb8();
+    let (width, height) = scaled.dimensions();
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    let background = quality::border_colour(&scaled);
+    let mut foreground: Vec<bool> = scaled.pixels().map(|pixel| quality::is_foreground(pixel, background)).collect();
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+
+    // Flood fill each connected foreground region, tracking its area and extent
+    let mut regions = Vec::new();
+    for start in 0..foreground.len() {
+        if !foreground[start] {
+            continue;
+        }
+        foreground[start] = false;
+        let mut stack = vec![(start as u32 % width, start as u32 / width)];
+        let (mut min_x, mut min_y, mut max_x, mut max_y, mut area) = (u32::MAX, u32::MAX, 0, 0, 0u32);
+        while let Some((x, y)) = stack.pop() {
+            area += 1;
+            (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+            let neighbours = [
+                (x > 0).then(|| (x - 1, y)),
+                (x + 1 < width).then_some((x + 1, y)),
+                (y > 0).then(|| (x, y - 1)),
+                (y + 1 < height).then_some((x, y + 1)),
+            ];
+            for (nx, ny) in neighbours.into_iter().flatten() {
+                if foreground[index(nx, ny)] {
+                    foreground[index(nx, ny)] = false;
+                    stack.push((nx, ny));
+                }
+            }
+        }
+
+        let (region_width, region_height) = ((max_x - min_x + 1) as f64, (max_y - min_y + 1) as f64);
+        let aspect = region_width / region_height;
+        let fill = area as f64 / (region_width * region_height);
+        if area as f64 >= MIN_AREA * (width * height) as f64 && (MIN_ASPECT..=MAX_ASPECT).contains(&aspect) && fill >= MIN_FILL {
+            regions.push((area, min_x, min_y, region_width, region_height));
+        }
+    }
+
+    regions.sort_by_key(|region| std::cmp::Reverse(region.0));
+    regions.truncate(MAX_BALLS);
+
+    // Scale back to the original photo, with a margin
+    let scale_x = image.width() as f64 / width as f64;
+    let scale_y = image.height() as f64 / height as f64;
+    let mut boxes: Vec<BoundingBox> = regions
+        .into_iter()
+        .map(|(_, x, y, region_width, region_height)| {
+            let pad_x = region_width * PADDING;
+            let pad_y = region_height * PADDING;
+            let left = ((x as f64 - pad_x) * scale_x).max(0.0);
+            let top = ((y as f64 - pad_y) * scale_y).max(0.0);
+            let right = ((x as f64 + region_width + pad_x) * scale_x).min(image.width() as f64);
+            let bottom = ((y as f64 + region_height + pad_y) * scale_y).min(image.height() as f64);
+            BoundingBox {
+                x: left as u32,
+                y: top as u32,
+                width: (right - left).round().max(1.0) as u32,
+                height: (bottom - top).round().max(1.0) as u32,
+            }
+        })
+        .collect();
+
+    reading_order(&mut boxes);
+    boxes
+}
+
+/// Sorts boxes into rows, top to bottom, and each row left to right.
+/// A box is on the same row as the first box of the row if its centre is level with that box.
+fn reading_order(boxes: &mut [BoundingBox]) {
+    let centre_y = |b: &BoundingBox| b.y + b.height / 2;
+    boxes.sort_by_key(centre_y);
+
+    let mut row_start = 0;
+    while row_start < boxes.len() {
+        let first = boxes[row_start];
+        let row_end = boxes[row_start..]
+            .iter()
+            .position(|b| b.y > centre_y(&first))
+            .map_or(boxes.len(), |offset| row_start + offset);
+        boxes[row_start..row_end].sort_by_key(|b| b.x);
+        row_start = row_end;
+    }
+}
+
+/// Decodes a photo and returns each ball found in it with its own image, encoded as PNG for the classifier.
+pub fn crop_balls(image_bytes: &[u8]) -> Result<Vec<(BoundingBox, Vec<u8>)>, String> {
+    let image = image::load_from_memory(image_bytes).map_err(|e| format!("Failed to decode image: {}", e))?;
+
+    detect(&image)
+        .into_iter()
+        .map(|ball| {
+            let mut encoded = Vec::new();
+            image
+                .crop_imm(ball.x, ball.y, ball.width, ball.height)
+                .write_to(&mut std::io::Cursor::new(&mut encoded), ImageFormat::Png)
+                .map_err(|e| format!("Failed to encode ball image: {}", e))?;
+            Ok((ball, encoded))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn finds_each_ball_in_reading_order() {
+        // Three balls on grass, plus a thin stump and a speck of dirt that aren't balls
+        let balls = [(300.0, 80.0, 40.0), (80.0, 90.0, 50.0), (200.0, 220.0, 45.0)];
+        let photo = RgbImage::from_fn(400, 300, |x, y| {
+            let (x, y) = (x as f64, y as f64);
+            if balls.iter().any(|&(cx, cy, r)| (x - cx).powi(2) + (y - cy).powi(2) < r * r) {
+                Rgb([170, 25, 30])
+            } else if (360.0..368.0).contains(&x) && (150.0..290.0).contains(&y) {
+                Rgb([240, 240, 230])
+            } else if (20.0..22.0).contains(&x) && (270.0..272.0).contains(&y) {
+                Rgb([60, 40, 20])
+            } else {
+                Rgb([40, 130, 50])
+            }
+        });
+
+        let found = detect(&DynamicImage::ImageRgb8(photo));
+        assert_eq!(found.len(), 3, "{:?}", found);
+        let centres: Vec<(u32, u32)> = found.iter().map(|b| (b.x + b.width / 2, b.y + b.height / 2)).collect();
+        for ((cx, cy), expected) in centres.iter().zip([(80, 90), (300, 80), (200, 220)]) {
+            assert!(cx.abs_diff(expected.0) <= 4 && cy.abs_diff(expected.1) <= 4, "{:?}", centres);
+        }
+        assert!(found[0].width > 100, "balls should be padded: {:?}", found[0]);
+    }
+}
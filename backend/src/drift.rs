@@ -0,0 +1,391 @@
+use chrono::{Days, Utc};
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use sqlx::{Any, Executor};
+
+pub use crate::api_types::{DriftReport, FeatureDrift};
+use crate::api_types::{DriftBaselineResponse, ImageFailure};
+use crate::auth;
+use crate::db;
+use crate::predictions::format_timestamp;
+use crate::request_logger::RequestLogger;
+use crate::training;
+
+/// Width images are scaled to before measuring, so statistics don't depend on camera resolution.
+const ANALYSIS_SIZE: u32 = 128;
+
+/// Number of bins each feature is split into, by the training images' quantiles, to compare distributions.
+const BINS: usize = 10;
+
+/// Population stability index at which a feature has shifted noticeably, and significantly.
+const WARNING_PSI: f64 = 0.1;
+const DRIFT_PSI: f64 = 0.25;
+
+/// Fewest images on each side for a meaningful comparison.
+const MIN_IMAGES: usize = 30;
+
+/// How many days of predictions are compared unless `days` is given, and the most that can be asked for.
+const DEFAULT_DAYS: u64 = 7;
+const MAX_DAYS: u64 = 90;
+
+/// Statistics describing what an image looks like, compared between training and production images.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ImageStats {
+    /// Mean luminance, 0-255.
+    pub brightness: f64,
+    /// Standard deviation of luminance.
+    pub contrast: f64,
+    /// Mean of each colour channel, 0-255.
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
+    /// Mean HSV saturation, 0-1.
+    pub saturation: f64,
+    /// Size of the original image in pixels.
+    pub width: i64,
+    pub height: i64,
+}
+
+/// Names of the features compared for drift, in the order `features` returns them.
+pub const FEATURES: [&str; 8] = ["brightness", "contrast", "red", "green", "blue", "saturation", "megapixels", "aspect_ratio"];
+
+impl ImageStats {
+    /// Measures an encoded image, or returns `None` if it can't be decoded.
+    pub fn measure(image_bytes: &[u8]) -> Option<Self> {
+        let image = image::load_from_memory(image_bytes).ok()?;
+        let (width, height) = (image.width(), image.height());
+        let rgb = image.resize(ANALYSIS_SIZE, ANALYSIS_SIZE, FilterType::Triangle).to_rgb8();
+
+        let count = (rgb.width() as f64 * rgb.height() as f64).max(1.0);
+        let mut channels = [0.0f64; 3];
+        let mut luma = Vec::with_capacity(count as usize);
+        let mut saturation = 0.0;
+        for pixel in rgb.pixels() {
+            let [r, g, b] = pixel.0.map(|v| v as f64);
+            channels[0] += r;
+            channels[1] += g;
+            channels[2] += b;
+            luma.push(0.299 * r + 0.587 * g + 0.114 * b);
+            let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+            if max > 0.0 {
+                saturation += (max - min) / max;
+            }
+        }
+        let brightness = luma.iter().sum::<f64>() / count;
+        let contrast = (luma.iter().map(|l| (l - brightness).powi(2)).sum::<f64>() / count).sqrt();
+        let [red, green, blue] = channels.map(|total| total / count);
+
+        Some(Self {
+            brightness,
+            contrast,
+            red,
+            green,
+            blue,
+            saturation: saturation / count,
+            width: width as i64,
+            height: height as i64,
+        })
+    }
+
+    /// The compared features, named in `FEATURES`.
+    fn features(&self) -> [f64; 8] {
+        [
+            self.brightness,
+            self.contrast,
+            self.red,
+            self.green,
+            self.blue,
+            self.saturation,
+            (self.width * self.height) as f64 / 1_000_000.0,
+            self.width as f64 / self.height.max(1) as f64,
+        ]
+    }
+}
+
+const COLUMNS: &str = "brightness, contrast, red, green, blue, saturation, width, height";
+
+/// Stores the statistics of a training image, measured at `measured_at`, once per content hash.
+pub async fn record_training<'e>(
+    executor: impl Executor<'e, Database = Any>,
+    content_hash: &str,
+    measured_at: &str,
+    stats: &ImageStats,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "INSERT INTO training_image_stats (content_hash, measured_at, {}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         ON CONFLICT (content_hash) DO NOTHING",
+        COLUMNS
+    ))
+    .bind(content_hash)
+    .bind(measured_at)
+    .bind(stats.brightness)
+    .bind(stats.contrast)
+    .bind(stats.red)
+    .bind(stats.green)
+    .bind(stats.blue)
+    .bind(stats.saturation)
+    .bind(stats.width)
+    .bind(stats.height)
+    .execute(executor)
+    .await
+    .map(|_| ())
+}
+
+/// Stores the statistics of an image sent for prediction at `created_at`.
+pub async fn record_prediction<'e>(
+    executor: impl Executor<'e, Database = Any>,
+    prediction_id: i64,
+    created_at: &str,
+    stats: &ImageStats,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "INSERT INTO prediction_image_stats (prediction_id, created_at, {}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+        COLUMNS
+    ))
+    .bind(prediction_id)
+    .bind(created_at)
+    .bind(stats.brightness)
+    .bind(stats.contrast)
+    .bind(stats.red)
+    .bind(stats.green)
+    .bind(stats.blue)
+    .bind(stats.saturation)
+    .bind(stats.width)
+    .bind(stats.height)
+    .execute(executor)
+    .await
+    .map(|_| ())
+}
+
+/// Measures and stores a training image's statistics. Failures are only logged.
+pub async fn observe_training(pool: &db::Pool, content_hash: &str, image_bytes: &[u8], logger: &RequestLogger) {
+    let Some(stats) = ImageStats::measure(image_bytes) else { return };
+    if let Err(e) = record_training(pool, content_hash, &format_timestamp(Utc::now()), &stats).await {
+        logger.error(format!("Failed to record image statistics: {}", e));
+    }
+}
+
+/// Measures and stores the statistics of an image sent for prediction. Failures are only logged.
+pub async fn observe_prediction(pool: &db::Pool, prediction_id: i64, image_bytes: &[u8], logger: &RequestLogger) {
+    let Some(stats) = ImageStats::measure(image_bytes) else { return };
+    if let Err(e) = record_prediction(pool, prediction_id, &format_timestamp(Utc::now()), &stats).await {
+        logger.error(format!("Failed to record image statistics: {}", e));
+    }
+}
+
+/// Statistics of the images in the training dataset, excluding rejected samples.
+pub async fn training_stats(pool: &db::Pool) -> Result<Vec<ImageStats>, sqlx::Error> {
+    sqlx::query_as::<_, ImageStats>(&format!(
+        "SELECT {} FROM training_image_stats
+         WHERE content_hash IN (SELECT content_hash FROM samples WHERE review_status <> 'rejected')",
+        COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+}
+
+/// Statistics of the images sent for prediction at or after `since`.
+pub async fn prediction_stats(pool: &db::Pool, since: &str) -> Result<Vec<ImageStats>, sqlx::Error> {
+    sqlx::query_as::<_, ImageStats>(&format!("SELECT {} FROM prediction_image_stats WHERE created_at >= $1", COLUMNS))
+        .bind(since)
+        .fetch_all(pool)
+        .await
+}
+
+/// Population stability index of `production` against `baseline`: how far the share of values in each
+/// of the baseline's quantile bins has moved. 0 means identical distributions.
+pub fn population_stability(baseline: &[f64], production: &[f64]) -> f64 {
+    let mut sorted = baseline.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let edges: Vec<f64> = (1..BINS).map(|i| sorted[i * (sorted.len() - 1) / BINS]).collect();
+    let shares = |values: &[f64]| {
+        let mut counts = [0usize; BINS];
+        for value in values {
+            counts[edges.partition_point(|edge| edge < value)] += 1;
+        }
+        counts.map(|count| (count as f64 / values.len() as f64).max(1e-4))
+    };
+
+    let (expected, actual) = (shares(baseline), shares(production));
+    expected.iter().zip(&actual).map(|(e, a)| (a - e) * (a / e).ln()).sum()
+}
+
+/// Compares every feature of production images against the training images.
+pub fn compare(baseline: &[ImageStats], production: &[ImageStats]) -> Vec<FeatureDrift> {
+    let baseline: Vec<[f64; 8]> = baseline.iter().map(ImageStats::features).collect();
+    let production: Vec<[f64; 8]> = production.iter().map(ImageStats::features).collect();
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len().max(1) as f64;
+
+    FEATURES
+        .iter()
+        .enumerate()
+        .map(|(i, &name)| {
+            let expected: Vec<f64> = baseline.iter().map(|f| f[i]).collect();
+            let actual: Vec<f64> = production.iter().map(|f| f[i]).collect();
+            let psi = population_stability(&expected, &actual);
+            let level = if psi >= DRIFT_PSI { "drift" } else if psi >= WARNING_PSI { "warning" } else { "ok" };
+            FeatureDrift { name, psi, level, baseline_mean: mean(&expected), production_mean: mean(&actual) }
+        })
+        .collect()
+}
+
+/// Overall drift status of the images sent for prediction since `since`: `insufficient_data`, `ok`, `warning` or `drift`,
+/// with the comparison of each feature. Drift is logged as an error so log-based alerting picks it up.
+pub async fn status(pool: &db::Pool, since: &str, logger: &RequestLogger) -> Result<DriftReport, sqlx::Error> {
+    let baseline = training_stats(pool).await?;
+    let production = prediction_stats(pool, since).await?;
+
+    if baseline.len() < MIN_IMAGES || production.len() < MIN_IMAGES {
+        return Ok(DriftReport {
+            status: "insufficient_data",
+            since: since.to_string(),
+            baseline_images: baseline.len(),
+            production_images: production.len(),
+            features: Vec::new(),
+        });
+    }
+
+    let features = compare(&baseline, &production);
+    let status = ["drift", "warning"]
+        .into_iter()
+        .find(|level| features.iter().any(|f| f.level == *level))
+        .unwrap_or("ok");
+    if status == "drift" {
+        let drifted: Vec<&str> = features.iter().filter(|f| f.level == "drift").map(|f| f.name).collect();
+        logger.error(format!("Input drift detected in {} since {}", drifted.join(", "), since));
+    }
+
+    Ok(DriftReport {
+        status,
+        since: since.to_string(),
+        baseline_images: baseline.len(),
+        production_images: production.len(),
+        features,
+    })
+}
+
+/// Start of the comparison window ending now.
+pub fn window_start(days: u64) -> String {
+    format_timestamp((Utc::now().date_naive() - Days::new(days - 1)).and_hms_opt(0, 0, 0).unwrap().and_utc())
+}
+
+/// Query parameters accepted by `/admin/drift`.
+#[derive(Debug, Deserialize)]
+pub struct DriftQuery {
+    /// How many days of predictions to compare against the training images.
+    pub days: Option<u64>,
+}
+
+/// Drift route handler comparing recent production images against the training images. Requires the admin token.
+pub async fn drift_route(req: rusty_api::HttpRequest, query: rusty_api::web::Query<DriftQuery>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/drift");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized drift request");
+        return resp;
+    }
+
+    let days = query.days.unwrap_or(DEFAULT_DAYS);
+    if days == 0 || days > MAX_DAYS {
+        return rusty_api::HttpResponse::BadRequest().body(format!("days must be between 1 and {}", MAX_DAYS));
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match status(pool, &window_start(days), &logger).await {
+        Ok(report) => rusty_api::HttpResponse::Ok().json(report),
+        Err(e) => {
+            logger.error(format!("Failed to compute input drift: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Baseline route handler measuring every training image that has no statistics yet,
+/// e.g. those uploaded before drift was tracked. Archived images are skipped. Requires the admin token.
+pub async fn baseline_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/drift/baseline");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized drift baseline request");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let hashes: Vec<String> = match sqlx::query_scalar(
+        "SELECT DISTINCT content_hash FROM samples
+         WHERE content_hash IS NOT NULL AND archive_id IS NULL
+           AND content_hash NOT IN (SELECT content_hash FROM training_image_stats)
+         ORDER BY content_hash"
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(hashes) => hashes,
+        Err(e) => {
+            logger.error(format!("Failed to list unmeasured images: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    let mut measured = 0;
+    let mut failures = Vec::new();
+    for hash in &hashes {
+        let stats = training::read_image(hash).and_then(|image| {
+            ImageStats::measure(&image).ok_or_else(|| "Image could not be decoded".to_string())
+        });
+        let stored = match stats {
+            Ok(stats) => record_training(pool, hash, &format_timestamp(Utc::now()), &stats).await.map_err(|e| format!("Database error: {}", e)),
+            Err(message) => Err(message),
+        };
+        match stored {
+            Ok(()) => measured += 1,
+            Err(error) => failures.push(ImageFailure { content_hash: hash.clone(), error }),
+        }
+    }
+
+    logger.info(format!("Measured {} training image(s), {} failed", measured, failures.len()));
+    rusty_api::HttpResponse::Ok().json(DriftBaselineResponse { images_measured: measured, failures })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, Rgb, RgbImage};
+
+    #[test]
+    fn shifted_distributions_have_a_higher_stability_index() {
+        let baseline: Vec<f64> = (0..200).map(|i| (i % 100) as f64).collect();
+        let same: Vec<f64> = (0..100).map(|i| i as f64 + 0.5).collect();
+        let darker: Vec<f64> = (0..100).map(|i| (i / 3) as f64).collect();
+
+        assert!(population_stability(&baseline, &same) < WARNING_PSI);
+        assert!(population_stability(&baseline, &darker) >= DRIFT_PSI);
+    }
+
+    #[test]
+    fn measure_reads_colour_and_size() {
+        let image = RgbImage::from_pixel(40, 20, Rgb([200, 100, 0]));
+        let mut encoded = Vec::new();
+        DynamicImage::ImageRgb8(image).write_to(&mut std::io::Cursor::new(&mut encoded), image::ImageFormat::Png).unwrap();
+
+        let stats = ImageStats::measure(&encoded).unwrap();
+        assert_eq!((stats.width, stats.height), (40, 20));
+        assert!((stats.red - 200.0).abs() < 1.0 && stats.blue < 1.0);
+        assert!((stats.saturation - 1.0).abs() < 0.01 && stats.contrast < 1.0);
+        assert!(ImageStats::measure(b"not an image").is_none());
+    }
+}
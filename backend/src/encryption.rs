@@ -0,0 +1,173 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::storage::{Area, Storage};
+
+/// Marks an object as encrypted by this module, followed by a format version.
+const MAGIC: &[u8] = b"CRENC\x01";
+/// Length of the random AES-GCM nonce stored after the marker.
+const NONCE_LENGTH: usize = 12;
+
+/// Parses a base64-encoded 256-bit key, as given in `STORAGE_ENCRYPTION_KEY`.
+pub fn parse_key(value: &str) -> Result<[u8; 32], String> {
+    let bytes = STANDARD
+        .decode(value.trim())
+        .map_err(|e| format!("Encryption key is not valid base64: {}", e))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("Encryption key must be 32 bytes, not {}", bytes.len()))
+}
+
+/// Encrypts training images, archives and exports with AES-256-GCM before handing them to another storage backend,
+/// and decrypts them on the way back. Temp files and model artifacts are passed through unchanged.
+/// Objects written before encryption was enabled are still read as they are.
+pub struct EncryptedStorage {
+    inner: Box<dyn Storage>,
+    cipher: Aes256Gcm,
+    /// Serializes appends, which decrypt, extend and re-encrypt the whole object.
+    append_lock: Mutex<()>,
+}
+
+impl EncryptedStorage {
+    pub fn new(inner: Box<dyn Storage>, key: &[u8; 32]) -> Self {
+        Self {
+            inner,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+            append_lock: Mutex::new(()),
+        }
+    }
+
+    fn encrypts(area: Area) -> bool {
+        matches!(area, Area::TrainingData | Area::Exports | Area::Archive | Area::PredictionImages)
+    }
+
+    fn encrypt(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, data)
+            .map_err(|_| io::Error::other("Failed to encrypt object"))?;
+        Ok([MAGIC, nonce.as_slice(), &ciphertext].concat())
+    }
+
+    fn decrypt(&self, data: Vec<u8>, key: &str) -> io::Result<Vec<u8>> {
+        let Some(sealed) = data.strip_prefix(MAGIC) else {
+            return Ok(data);
+        };
+        if sealed.len() < NONCE_LENGTH {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("'{}' is truncated", key)));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LENGTH);
+        self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Failed to decrypt '{}'; the key may be wrong", key))
+        })
+    }
+}
+
+impl Storage for EncryptedStorage {
+    fn put(&self, area: Area, key: &str, data: &[u8]) -> io::Result<String> {
+        if !Self::encrypts(area) {
+            return self.inner.put(area, key, data);
+        }
+        self.inner.put(area, key, &self.encrypt(data)?)
+    }
+
+    fn location(&self, area: Area, key: &str) -> String {
+        self.inner.location(area, key)
+    }
+
+    fn get(&self, area: Area, key: &str) -> io::Result<Vec<u8>> {
+        let data = self.inner.get(area, key)?;
+        if !Self::encrypts(area) {
+            return Ok(data);
+        }
+        self.decrypt(data, key)
+    }
+
+    fn append(&self, area: Area, key: &str, data: &[u8]) -> io::Result<()> {
+        if !Self::encrypts(area) {
+            return self.inner.append(area, key, data);
+        }
+        let _guard = self.append_lock.lock().unwrap();
+        let mut contents = match self.get(area, key) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        contents.extend_from_slice(data);
+        self.put(area, key, &contents).map(|_| ())
+    }
+
+    fn delete(&self, area: Area, key: &str) -> io::Result<()> {
+        self.inner.delete(area, key)
+    }
+
+    fn exists(&self, area: Area, key: &str) -> io::Result<bool> {
+        self.inner.exists(area, key)
+    }
+
+    fn list(&self, area: Area, prefix: &str) -> io::Result<Vec<String>> {
+        self.inner.list(area, prefix)
+    }
+
+    /// Counts the stored size, which includes each encrypted object's marker, nonce and tag.
+    fn usage(&self, area: Area) -> io::Result<u64> {
+        self.inner.usage(area)
+    }
+
+    /// Encrypted objects would be unreadable to other processes, so they can't be handed out as paths.
+    fn local_path(&self, area: Area, key: &str) -> io::Result<PathBuf> {
+        if Self::encrypts(area) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("'{}' is encrypted", key)));
+        }
+        self.inner.local_path(area, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn encrypted_storage_round_trips_and_hides_contents() {
+        let root = std::env::temp_dir().join(format!("cricket_ready_encryption_{}", std::process::id()));
+        fs::remove_dir_all(&root).ok();
+        let roots = HashMap::from([(Area::TrainingData, root.clone())]);
+        let storage = EncryptedStorage::new(Box::new(LocalStorage::with_roots(roots)), &[7; 32]);
+
+        storage.put(Area::TrainingData, "images/ball.jpg", b"jpeg bytes").unwrap();
+        assert_eq!(storage.get(Area::TrainingData, "images/ball.jpg").unwrap(), b"jpeg bytes");
+        let on_disk = fs::read(root.join("images/ball.jpg")).unwrap();
+        assert!(on_disk.starts_with(MAGIC));
+        assert!(!on_disk.windows(10).any(|w| w == b"jpeg bytes"));
+
+        storage.append(Area::TrainingData, "log.jsonl", b"a\n").unwrap();
+        storage.append(Area::TrainingData, "log.jsonl", b"b\n").unwrap();
+        assert_eq!(storage.get(Area::TrainingData, "log.jsonl").unwrap(), b"a\nb\n");
+
+        // Files from before encryption was enabled are read unchanged
+        fs::write(root.join("old.jpg"), b"plain").unwrap();
+        assert_eq!(storage.get(Area::TrainingData, "old.jpg").unwrap(), b"plain");
+
+        // A different key can't read the data
+        let other = EncryptedStorage::new(Box::new(LocalStorage::with_roots(HashMap::from([(Area::TrainingData, root.clone())]))), &[8; 32]);
+        assert!(other.get(Area::TrainingData, "images/ball.jpg").is_err());
+
+        fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn parse_key_requires_32_bytes() {
+        assert!(parse_key(&STANDARD.encode([1u8; 32])).is_ok());
+        assert!(parse_key(&STANDARD.encode([1u8; 16])).is_err());
+        assert!(parse_key("not base64!").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, RgbImage};
+
+/// Mean luminance (0-255) exposure correction aims for.
+const TARGET_BRIGHTNESS: f64 = 128.0;
+/// Limits on the gamma used to correct exposure, so nearly black or white photos aren't blown out.
+const MIN_GAMMA: f64 = 0.4;
+const MAX_GAMMA: f64 = 2.5;
+/// Limits on each channel's white-balance gain, so photos of mostly one colour keep it.
+const MIN_GAIN: f64 = 0.5;
+const MAX_GAIN: f64 = 2.0;
+/// Unsharp mask radius and the difference below which pixels are left alone.
+const SHARPEN_SIGMA: f32 = 1.0;
+const SHARPEN_THRESHOLD: i32 = 3;
+/// JPEG quality of the enhanced image.
+const JPEG_QUALITY: u8 = 90;
+
+/// Corrects white balance and exposure and sharpens a photo, e.g. one taken in poor pavilion lighting,
+/// returning the result as a JPEG.
+pub fn enhance(image_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory(image_bytes).map_err(|e| format!("Failed to decode image for enhancement: {}", e))?;
+    let mut rgb = image.to_rgb8();
+    white_balance(&mut rgb);
+    correct_exposure(&mut rgb);
+    let sharpened = DynamicImage::ImageRgb8(rgb).unsharpen(SHARPEN_SIGMA, SHARPEN_THRESHOLD);
+
+    let mut encoded = Vec::new();
+    sharpened
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY))
+        .map_err(|e| format!("Failed to encode enhanced image: {}", e))?;
+    Ok(encoded)
+}
+
+/// Mean of each colour channel.
+fn channel_means(image: &RgbImage) -> [f64; 3] {
+    let mut totals = [0.0f64; 3];
+    for pixel in image.pixels() {
+        for (total, &value) in totals.iter_mut().zip(pixel.0.iter()) {
+            *total += value as f64;
+        }
+    }
+    let count = (image.width() as f64 * image.height() as f64).max(1.0);
+    totals.map(|total| total / count)
+}
+
+/// Applies a lookup table to every channel of every pixel.
+fn apply(image: &mut RgbImage, tables: &[[u8; 256]; 3]) {
+    for pixel in image.pixels_mut() {
+        for (value, table) in pixel.0.iter_mut().zip(tables) {
+            *value = table[*value as usize];
+        }
+    }
+}
+
+/// Removes colour casts from artificial light by scaling each channel so their means match (the grey-world assumption).
+fn white_balance(image: &mut RgbImage) {
+    let means = channel_means(image);
+    let grey = means.iter().sum::<f64>() / 3.0;
+    let tables = means.map(|mean| {
+        let gain = if mean > 0.0 { (grey / mean).clamp(MIN_GAIN, MAX_GAIN) } else { 1.0 };
+        std::array::from_fn(|value| (value as f64 * gain).round().min(255.0) as u8)
+    });
+    apply(image, &tables);
+}
+
+/// Brightens dark photos and darkens overexposed ones with a gamma curve that moves the mean luminance towards the middle.
+fn correct_exposure(image: &mut RgbImage) {
+    let [r, g, b] = channel_means(image);
+    let brightness = 0.299 * r + 0.587 * g + 0.114 * b;
+    if brightness <= 0.0 || brightness >= 255.0 {
+        return;
+    }
+    let gamma = ((TARGET_BRIGHTNESS / 255.0).ln() / (brightness / 255.0).ln()).clamp(MIN_GAMMA, MAX_GAMMA);
+    let table: [u8; 256] = std::array::from_fn(|value| (255.0 * (value as f64 / 255.0).powf(gamma)).round() as u8);
+    apply(image, &[table; 3]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn dark_yellowish_photos_are_brightened_and_neutralised() {
+        let photo = RgbImage::from_fn(64, 64, |x, _| if x < 32 { Rgb([60, 50, 20]) } else { Rgb([40, 30, 10]) });
+        let mut encoded = Vec::new();
+        DynamicImage::ImageRgb8(photo).write_to(&mut std::io::Cursor::new(&mut encoded), image::ImageFormat::Png).unwrap();
+
+        let enhanced = image::load_from_memory(&enhance(&encoded).unwrap()).unwrap().to_rgb8();
+        let [r, g, b] = channel_means(&enhanced);
+        assert!(g > 90.0, "still too dark: {}", g);
+        assert!((r - b).abs() < 30.0, "colour cast remains: {} vs {}", r, b);
+        assert!(enhance(b"not an image").is_err());
+    }
+}
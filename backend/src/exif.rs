@@ -0,0 +1,393 @@
+use chrono::NaiveDateTime;
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use sqlx::{Any, Executor};
+use std::io::Cursor;
+
+pub use crate::api_types::Capture;
+use crate::db;
+use crate::request_logger::RequestLogger;
+
+/// EXIF tags read from uploads, in the first IFD and the Exif IFD it points to.
+const TAG_MAKE: u16 = 0x010F;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_EXPOSURE_TIME: u16 = 0x829A;
+const TAG_F_NUMBER: u16 = 0x829D;
+const TAG_ISO: u16 = 0x8827;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const TAG_FOCAL_LENGTH: u16 = 0x920A;
+const TAG_FOCAL_LENGTH_35MM: u16 = 0xA405;
+
+/// Header of a JPEG APP1 segment holding EXIF, and of one holding XMP, which repeats much of it.
+const JPEG_EXIF_HEADER: &[u8] = b"Exif\0\0";
+const JPEG_XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// A TIFF structure, as EXIF metadata is laid out.
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+/// One entry of an IFD: its tag, value type, value count, and where the value starts.
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    offset: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..4)? {
+            b"MM\0\x2a" => true,
+            b"II\x2a\0" => false,
+            _ => return None,
+        };
+        Some(Self { data, big_endian })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    /// The entries of the IFD at `offset`, or of the first IFD if `None`.
+    fn entries(&self, offset: Option<usize>) -> Vec<Entry> {
+        let Some(start) = offset.or_else(|| self.u32_at(4).map(|o| o as usize)) else { return Vec::new() };
+        let count = self.u16_at(start).unwrap_or(0) as usize;
+        (0..count)
+            .filter_map(|i| {
+                let entry = start + 2 + i * 12;
+                let (tag, kind, count) = (self.u16_at(entry)?, self.u16_at(entry + 2)?, self.u32_at(entry + 4)?);
+                let size = match kind {
+                    2 => 1,
+                    3 => 2,
+                    4 => 4,
+                    5 => 8,
+                    _ => return None,
+                } * count as usize;
+                // Values of up to four bytes are stored in the entry itself
+                let offset = if size <= 4 { entry + 8 } else { self.u32_at(entry + 8)? as usize };
+                Some(Entry { tag, kind, count, offset })
+            })
+            .collect()
+    }
+
+    fn ascii(&self, entry: &Entry) -> Option<String> {
+        let bytes = self.data.get(entry.offset..entry.offset + entry.count as usize).filter(|_| entry.kind == 2)?;
+        let text = String::from_utf8_lossy(bytes).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+        (!text.is_empty()).then_some(text)
+    }
+
+    fn integer(&self, entry: &Entry) -> Option<i64> {
+        match entry.kind {
+            3 => self.u16_at(entry.offset).map(i64::from),
+            4 => self.u32_at(entry.offset).map(i64::from),
+            _ => None,
+        }
+    }
+
+    fn rational(&self, entry: &Entry) -> Option<f64> {
+        let (numerator, denominator) = (self.u32_at(entry.offset)?, self.u32_at(entry.offset + 4)?);
+        (entry.kind == 5 && denominator != 0).then(|| numerator as f64 / denominator as f64)
+    }
+}
+
+/// Reads the capture metadata from an EXIF block (a TIFF structure), or `None` if it records none.
+fn parse(exif: &[u8]) -> Option<Capture> {
+    let tiff = Tiff::new(exif.strip_prefix(JPEG_EXIF_HEADER).unwrap_or(exif))?;
+    let ifd0 = tiff.entries(None);
+    let exif_ifd = ifd0
+        .iter()
+        .find(|entry| entry.tag == TAG_EXIF_IFD)
+        .and_then(|entry| tiff.integer(entry))
+        .map(|offset| tiff.entries(Some(offset as usize)))
+        .unwrap_or_default();
+    let find = |tag| ifd0.iter().chain(&exif_ifd).find(|entry| entry.tag == tag);
+
+    let timestamp = find(TAG_DATE_TIME_ORIGINAL)
+        .or_else(|| find(TAG_DATE_TIME))
+        .and_then(|entry| tiff.ascii(entry))
+        .and_then(|text| NaiveDateTime::parse_from_str(&text, "%Y:%m:%d %H:%M:%S").ok());
+    let captured_at = timestamp.map(|timestamp| {
+        let offset = find(TAG_OFFSET_TIME_ORIGINAL).and_then(|entry| tiff.ascii(entry)).unwrap_or_default();
+        format!("{}{}", timestamp.format("%Y-%m-%dT%H:%M:%S"), offset)
+    });
+
+    let capture = Capture {
+        captured_at,
+        camera_make: find(TAG_MAKE).and_then(|entry| tiff.ascii(entry)),
+        camera_model: find(TAG_MODEL).and_then(|entry| tiff.ascii(entry)),
+        focal_length_mm: find(TAG_FOCAL_LENGTH).and_then(|entry| tiff.rational(entry)),
+        focal_length_35mm: find(TAG_FOCAL_LENGTH_35MM).and_then(|entry| tiff.integer(entry)).filter(|&mm| mm > 0),
+        f_number: find(TAG_F_NUMBER).and_then(|entry| tiff.rational(entry)),
+        exposure_time: find(TAG_EXPOSURE_TIME).and_then(|entry| tiff.rational(entry)),
+        iso: find(TAG_ISO).and_then(|entry| tiff.integer(entry)),
+    };
+    (capture != Capture::default()).then_some(capture)
+}
+
+/// The EXIF block of an encoded image, if it has one.
+fn exif_block(image_bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut decoder = ImageReader::new(Cursor::new(image_bytes)).with_guessed_format().ok()?.into_decoder().ok()?;
+    decoder.exif_metadata().ok()?
+}
+
+/// Reads how and when an uploaded photo was taken, or `None` if it has no EXIF metadata recording it.
+pub fn read(image_bytes: &[u8]) -> Option<Capture> {
+    parse(&exif_block(image_bytes)?)
+}
+
+/// An EXIF block recording nothing but the orientation, so photos stored without their metadata stay upright.
+fn orientation_only(orientation: u8) -> Vec<u8> {
+    let mut tiff = b"MM\0\x2a\0\0\0\x08\0\x01".to_vec();
+    tiff.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1, 0, orientation, 0, 0]);
+    tiff.extend_from_slice(&[0, 0, 0, 0]);
+    tiff
+}
+
+fn strip_jpeg(bytes: &[u8], exif: Option<&[u8]>) -> Option<Vec<u8>> {
+    let mut stripped = bytes[..2].to_vec();
+    if let Some(exif) = exif {
+        stripped.extend_from_slice(&[0xFF, 0xE1]);
+        stripped.extend_from_slice(&((2 + JPEG_EXIF_HEADER.len() + exif.len()) as u16).to_be_bytes());
+        stripped.extend_from_slice(JPEG_EXIF_HEADER);
+        stripped.extend_from_slice(exif);
+    }
+
+    let mut pos = 2;
+    loop {
+        let marker = *bytes.get(pos + 1).filter(|_| bytes[pos] == 0xFF)?;
+        // The image data follows the start of scan; nothing after it is metadata
+        if marker == 0xDA || marker == 0xD9 {
+            stripped.extend_from_slice(&bytes[pos..]);
+            return Some(stripped);
+        }
+        let len = u16::from_be_bytes(bytes.get(pos + 2..pos + 4)?.try_into().ok()?) as usize;
+        let segment = bytes.get(pos..pos + 2 + len)?;
+        let payload = &segment[4..];
+        if !(marker == 0xE1 && (payload.starts_with(JPEG_EXIF_HEADER) || payload.starts_with(JPEG_XMP_HEADER))) {
+            stripped.extend_from_slice(segment);
+        }
+        pos += 2 + len;
+    }
+}
+
+fn strip_png(bytes: &[u8], exif: Option<&[u8]>) -> Option<Vec<u8>> {
+    let mut stripped = bytes[..8].to_vec();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let len = u32::from_be_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?) as usize;
+        let chunk = bytes.get(pos..pos + 12 + len)?;
+        let (kind, data) = (&chunk[4..8], &chunk[8..8 + len]);
+        if kind != b"eXIf" && !(kind == b"iTXt" && data.starts_with(b"XML:com.adobe.xmp\0")) {
+            stripped.extend_from_slice(chunk);
+        }
+        // EXIF has to come before the image data, so it goes straight after the header
+        if let (b"IHDR", Some(exif)) = (kind, exif) {
+            let mut crc = flate2::Crc::new();
+            crc.update(b"eXIf");
+            crc.update(exif);
+            stripped.extend_from_slice(&(exif.len() as u32).to_be_bytes());
+            stripped.extend_from_slice(b"eXIf");
+            stripped.extend_from_slice(exif);
+            stripped.extend_from_slice(&crc.sum().to_be_bytes());
+        }
+        pos += 12 + len;
+    }
+    Some(stripped)
+}
+
+fn strip_webp(bytes: &[u8], exif: Option<&[u8]>) -> Option<Vec<u8>> {
+    let mut stripped = bytes[..12].to_vec();
+    let mut pos = 12;
+    while pos < bytes.len() {
+        let len = u32::from_le_bytes(bytes.get(pos + 4..pos + 8)?.try_into().ok()?) as usize;
+        let padded = len + len % 2;
+        let chunk = bytes.get(pos..(pos + 8 + padded).min(bytes.len()))?;
+        match &chunk[..4] {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                // The extended header flags which metadata chunks follow
+                let start = stripped.len();
+                stripped.extend_from_slice(chunk);
+                let flags = stripped.get_mut(start + 8)?;
+                *flags &= !0x0C;
+                if exif.is_some() {
+                    *flags |= 0x08;
+                }
+            }
+            _ => stripped.extend_from_slice(chunk),
+        }
+        pos += 8 + padded;
+    }
+    // Only the extended format can carry EXIF, so simple files never need it re-added
+    if let Some(exif) = exif.filter(|_| bytes.get(12..16) == Some(b"VP8X")) {
+        stripped.extend_from_slice(b"EXIF");
+        stripped.extend_from_slice(&(exif.len() as u32).to_le_bytes());
+        stripped.extend_from_slice(exif);
+        if exif.len() % 2 == 1 {
+            stripped.push(0);
+        }
+    }
+    let riff_size = (stripped.len() - 8) as u32;
+    stripped[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Some(stripped)
+}
+
+/// Replaces the EXIF and XMP metadata of a JPEG, PNG or WebP image with `exif`, or removes it if `None`, without
+/// re-encoding the image. Returns `None` for other images and any that can't be parsed.
+fn replace_metadata(image_bytes: &[u8], exif: Option<&[u8]>) -> Option<Vec<u8>> {
+    if image_bytes.starts_with(&[0xFF, 0xD8]) {
+        strip_jpeg(image_bytes, exif)
+    } else if image_bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        strip_png(image_bytes, exif)
+    } else if image_bytes.len() >= 12 && &image_bytes[..4] == b"RIFF" && &image_bytes[8..12] == b"WEBP" {
+        strip_webp(image_bytes, exif)
+    } else {
+        None
+    }
+}
+
+/// The EXIF orientation of an image, unless it is already upright.
+fn orientation(image_bytes: &[u8]) -> Option<u8> {
+    exif_block(image_bytes)
+        .and_then(|block| Orientation::from_exif_chunk(block.strip_prefix(JPEG_EXIF_HEADER).unwrap_or(&block)))
+        .map(Orientation::to_exif)
+        .filter(|&orientation| orientation != 1)
+}
+
+/// Removes EXIF and XMP metadata from a JPEG, PNG or WebP image without re-encoding it, so stored photos don't
+/// carry their location or the phone's serial number. The orientation is kept, since the photo would be shown
+/// sideways without it. Other images, and any that can't be parsed, are returned as they are.
+pub fn strip(image_bytes: &[u8]) -> Vec<u8> {
+    let exif = orientation(image_bytes).map(orientation_only);
+    replace_metadata(image_bytes, exif.as_deref()).unwrap_or_else(|| image_bytes.to_vec())
+}
+
+/// Removes all EXIF and XMP metadata, the orientation included, for images shared outside the club.
+/// A photo that isn't upright is turned upright and re-encoded as a JPEG at `quality`. Returns `None` if the
+/// image can't be parsed, so nothing is shared that might still carry metadata.
+pub fn strip_all(image_bytes: &[u8], quality: u8) -> Option<Vec<u8>> {
+    if orientation(image_bytes).is_none() {
+        return replace_metadata(image_bytes, None);
+    }
+
+    let mut decoder = ImageReader::new(Cursor::new(image_bytes)).with_guessed_format().ok()?.into_decoder().ok()?;
+    let orientation = decoder.orientation().ok()?;
+    let mut image = DynamicImage::from_decoder(decoder).ok()?;
+    image.apply_orientation(orientation);
+    let mut encoded = Vec::new();
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, quality))
+        .ok()?;
+    Some(encoded)
+}
+
+/// Stores the capture metadata of a training sample.
+pub async fn record<'e>(executor: impl Executor<'e, Database = Any>, sample_id: i64, capture: &Capture) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO sample_capture (sample_id, captured_at, camera_make, camera_model, focal_length_mm, focal_length_35mm, f_number, exposure_time, iso) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"
+    )
+    .bind(sample_id)
+    .bind(&capture.captured_at)
+    .bind(&capture.camera_make)
+    .bind(&capture.camera_model)
+    .bind(capture.focal_length_mm)
+    .bind(capture.focal_length_35mm)
+    .bind(capture.f_number)
+    .bind(capture.exposure_time)
+    .bind(capture.iso)
+    .execute(executor)
+    .await
+    .map(|_| ())
+}
+
+/// Stores a training sample's capture metadata, if its upload had any. Failures are only logged.
+pub async fn observe(pool: &db::Pool, sample_id: i64, capture: Option<&Capture>, logger: &RequestLogger) {
+    let Some(capture) = capture else { return };
+    if let Err(e) = record(pool, sample_id, capture).await {
+        logger.error(format!("Failed to record capture metadata: {}", e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageEncoder, RgbImage};
+
+    /// A little-endian EXIF block, as phones write, with a make, model, timestamp and focal length.
+    fn phone_exif() -> Vec<u8> {
+        let mut tiff = b"II\x2a\0\x08\0\0\0".to_vec();
+        let entry = |tag: u16, kind: u16, count: u32, value: u32| {
+            [tag.to_le_bytes().as_slice(), &kind.to_le_bytes(), &count.to_le_bytes(), &value.to_le_bytes()].concat()
+        };
+        // IFD0 at 8 with 4 entries ends at 62; its values follow, then the Exif IFD
+        tiff.extend_from_slice(&4u16.to_le_bytes());
+        tiff.extend(entry(TAG_MAKE, 2, 6, 62));
+        tiff.extend(entry(TAG_MODEL, 2, 10, 68));
+        tiff.extend(entry(0x0112, 3, 1, 6));
+        tiff.extend(entry(TAG_EXIF_IFD, 4, 1, 78));
+        tiff.extend_from_slice(&[0; 4]);
+        tiff.extend_from_slice(b"Apple\0iPhone 15\0");
+        // Exif IFD at 78 with 3 entries ends at 120
+        tiff.extend_from_slice(&3u16.to_le_bytes());
+        tiff.extend(entry(TAG_DATE_TIME_ORIGINAL, 2, 20, 120));
+        tiff.extend(entry(TAG_FOCAL_LENGTH, 5, 1, 140));
+        tiff.extend(entry(TAG_FOCAL_LENGTH_35MM, 3, 1, 26));
+        tiff.extend_from_slice(&[0; 4]);
+        tiff.extend_from_slice(b"2024:05:04 15:42:10\0");
+        tiff.extend_from_slice(&[&6800u32.to_le_bytes()[..], &1000u32.to_le_bytes()].concat());
+        tiff
+    }
+
+    #[test]
+    fn capture_metadata_is_read_from_exif() {
+        let capture = parse(&phone_exif()).unwrap();
+        assert_eq!(capture.captured_at.as_deref(), Some("2024-05-04T15:42:10"));
+        assert_eq!(capture.camera_make.as_deref(), Some("Apple"));
+        assert_eq!(capture.camera_model.as_deref(), Some("iPhone 15"));
+        assert_eq!(capture.focal_length_mm, Some(6.8));
+        assert_eq!(capture.focal_length_35mm, Some(26));
+        assert_eq!(capture.iso, None);
+
+        assert_eq!(parse(&orientation_only(6)), None);
+        assert_eq!(parse(b"not exif"), None);
+    }
+
+    #[test]
+    fn stripping_keeps_only_the_orientation() {
+        let mut jpeg = Vec::new();
+        JpegEncoder::new(&mut jpeg).write_image(&RgbImage::new(16, 8), 16, 8, image::ExtendedColorType::Rgb8).unwrap();
+        let exif = phone_exif();
+        let mut with_exif = jpeg[..2].to_vec();
+        with_exif.extend_from_slice(&[0xFF, 0xE1]);
+        with_exif.extend_from_slice(&((2 + JPEG_EXIF_HEADER.len() + exif.len()) as u16).to_be_bytes());
+        with_exif.extend_from_slice(JPEG_EXIF_HEADER);
+        with_exif.extend_from_slice(&exif);
+        with_exif.extend_from_slice(&jpeg[2..]);
+        assert!(read(&with_exif).is_some());
+
+        let stripped = strip(&with_exif);
+        assert_eq!(read(&stripped), None);
+        let block = exif_block(&stripped).unwrap();
+        assert_eq!(Orientation::from_exif_chunk(block.strip_prefix(JPEG_EXIF_HEADER).unwrap_or(&block)), Some(Orientation::Rotate90));
+        assert_eq!(image::load_from_memory(&stripped).unwrap().width(), 16);
+        assert_eq!(strip(&jpeg), jpeg);
+
+        // Stripping everything turns the photo upright instead
+        let shared = strip_all(&with_exif, 95).unwrap();
+        assert_eq!(exif_block(&shared), None);
+        assert_eq!(image::load_from_memory(&shared).unwrap().width(), 8);
+        assert_eq!(strip_all(b"not an image", 95), None);
+    }
+}
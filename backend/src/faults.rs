@@ -0,0 +1,158 @@
+use chrono::Utc;
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub use crate::api_types::Faults;
+use crate::auth;
+use crate::config;
+use crate::request_logger::RequestLogger;
+
+/// Header naming faults to inject into a single request, e.g. `X-Inject-Faults: slow_inference=3000, disk_full`.
+pub const HEADER: &str = "X-Inject-Faults";
+/// Delay added by `slow_inference` when no duration is given, and the longest allowed.
+const DEFAULT_SLOW_INFERENCE_MS: u64 = 5_000;
+const MAX_SLOW_INFERENCE_MS: u64 = 60_000;
+
+/// Faults injected into every request, as set with `/admin/faults/active`.
+static ACTIVE: Mutex<Faults> = Mutex::new(Faults { slow_inference_ms: 0, classifier_crash: false, malformed_output: false, disk_full: false });
+
+impl Faults {
+    /// Parses a comma-separated list of fault names, as sent in `X-Inject-Faults`.
+    /// `slow_inference` may give its delay in milliseconds, as `slow_inference=<ms>`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut faults = Faults::default();
+        for fault in value.split(',').map(str::trim).filter(|fault| !fault.is_empty()) {
+            match fault.split_once('=') {
+                None if fault == "slow_inference" => faults.slow_inference_ms = DEFAULT_SLOW_INFERENCE_MS,
+                Some(("slow_inference", ms)) => {
+                    faults.slow_inference_ms = ms.trim().parse().map_err(|_| format!("Invalid slow_inference delay '{}'", ms))?
+                }
+                None if fault == "classifier_crash" => faults.classifier_crash = true,
+                None if fault == "malformed_output" => faults.malformed_output = true,
+                None if fault == "disk_full" => faults.disk_full = true,
+                _ => return Err(format!("Unknown fault '{}'", fault)),
+            }
+        }
+        Ok(faults)
+    }
+
+    /// Every fault in either set, with the longer delay.
+    fn union(self, other: Faults) -> Self {
+        Faults {
+            slow_inference_ms: self.slow_inference_ms.max(other.slow_inference_ms),
+            classifier_crash: self.classifier_crash || other.classifier_crash,
+            malformed_output: self.malformed_output || other.malformed_output,
+            disk_full: self.disk_full || other.disk_full,
+        }
+    }
+
+    /// Whether any fault is injected.
+    pub fn any(&self) -> bool {
+        *self != Faults::default()
+    }
+
+    /// The delay `slow_inference` adds to each model run.
+    pub fn inference_delay(&self) -> Duration {
+        Duration::from_millis(self.slow_inference_ms.min(MAX_SLOW_INFERENCE_MS))
+    }
+
+    /// Fails a write of an image if `disk_full` is injected.
+    pub fn check_write(&self) -> io::Result<()> {
+        if self.disk_full {
+            return Err(io::Error::new(io::ErrorKind::StorageFull, "No space left on device (injected fault)"));
+        }
+        Ok(())
+    }
+}
+
+/// The faults to inject into a request: those set with `/admin/faults/active` and those named in its `X-Inject-Faults`
+/// header. None are injected unless `FAULT_INJECTION` is enabled. Invalid headers are logged and ignored.
+pub fn for_request(req: &rusty_api::HttpRequest, logger: &RequestLogger) -> Faults {
+    if !config::get().fault_injection {
+        return Faults::default();
+    }
+    let active = *ACTIVE.lock().unwrap();
+    let requested = match req.headers().get(HEADER).map(|value| value.to_str().map_err(|e| e.to_string()).and_then(Faults::parse)) {
+        None => Faults::default(),
+        Some(Ok(faults)) => faults,
+        Some(Err(message)) => {
+            logger.error(format!("Ignoring {} header: {}", HEADER, message));
+            Faults::default()
+        }
+    };
+    let faults = active.union(requested);
+    if faults.any() {
+        logger.info(format!("Injecting faults: {:?}", faults));
+    }
+    faults
+}
+
+/// Checks that fault injection is enabled and the request carries the admin token.
+fn require_enabled(req: &rusty_api::HttpRequest) -> Result<(), rusty_api::HttpResponse> {
+    auth::require_admin(req)?;
+    if !config::get().fault_injection {
+        return Err(rusty_api::HttpResponse::Forbidden().body("Fault injection is disabled; set FAULT_INJECTION=true to enable it"));
+    }
+    Ok(())
+}
+
+/// Fault route handler returning the faults injected into every request on this replica.
+/// Requires the admin token, and fault injection to be enabled.
+pub async fn get_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/faults");
+
+    if let Err(resp) = require_enabled(&req) {
+        logger.error("Rejected request for injected faults");
+        return resp;
+    }
+
+    rusty_api::HttpResponse::Ok().json(*ACTIVE.lock().unwrap())
+}
+
+/// Fault route handler replacing the faults injected into every request on this replica. Send `{}` to stop
+/// injecting them. Requires the admin token, and fault injection to be enabled.
+pub async fn put_route(req: rusty_api::HttpRequest, body: rusty_api::web::Json<Faults>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/faults/active");
+
+    if let Err(resp) = require_enabled(&req) {
+        logger.error("Rejected update of injected faults");
+        return resp;
+    }
+    if body.slow_inference_ms > MAX_SLOW_INFERENCE_MS {
+        return rusty_api::HttpResponse::BadRequest().body(format!("slow_inference_ms must be at most {}", MAX_SLOW_INFERENCE_MS));
+    }
+
+    let faults = body.into_inner();
+    *ACTIVE.lock().unwrap() = faults;
+    logger.info(format!("Injected faults set to {:?}", faults));
+    rusty_api::HttpResponse::Ok().json(faults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fault_headers_name_each_fault() {
+        assert_eq!(Faults::parse(""), Ok(Faults::default()));
+        assert_eq!(
+            Faults::parse("slow_inference=250, disk_full"),
+            Ok(Faults { slow_inference_ms: 250, disk_full: true, ..Faults::default() })
+        );
+        let faults = Faults::parse("slow_inference,classifier_crash,malformed_output").unwrap();
+        assert_eq!(faults.inference_delay(), Duration::from_secs(5));
+        assert!(faults.classifier_crash && faults.malformed_output && faults.check_write().is_ok());
+        assert!(Faults::parse("slow_inference=soon").is_err());
+        assert!(Faults::parse("power_cut").is_err());
+
+        let union = Faults { slow_inference_ms: 100, ..Faults::default() }.union(Faults { slow_inference_ms: 50, disk_full: true, ..Faults::default() });
+        assert_eq!(union, Faults { slow_inference_ms: 100, disk_full: true, ..Faults::default() });
+    }
+}
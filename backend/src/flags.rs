@@ -0,0 +1,74 @@
+use serde::Serialize;
+
+use crate::api_keys::ApiKey;
+pub use crate::api_types::Rollout;
+use crate::settings::RuntimeSettings;
+
+/// An experimental behaviour that can be switched on for everyone or only for some API keys.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Flag {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Test-time augmentation: also classify the mirrored photo and average the two verdicts.
+pub const TTA: &str = "tta";
+
+/// Serve verdicts from the second-opinion model, e.g. a newly trained version, instead of the active one.
+pub const CANDIDATE_MODEL: &str = "candidate_model";
+
+/// Honour `uncertainty=true`: sample each model with dropout left on and report how much the samples disagree.
+pub const UNCERTAINTY: &str = "uncertainty";
+
+/// Honour `enhance=true`: correct and sharpen photos taken in poor light before classifying them.
+pub const ENHANCE: &str = "enhance";
+
+/// Every flag the server knows about.
+pub const FLAGS: [Flag; 4] = [
+    Flag { name: TTA, description: "Average each verdict with the verdict on the mirrored photo" },
+    Flag { name: CANDIDATE_MODEL, description: "Serve verdicts from the second-opinion model instead of the active one" },
+    Flag { name: UNCERTAINTY, description: "Report the models' uncertainty when a prediction asks for it with uncertainty=true" },
+    Flag { name: ENHANCE, description: "Enhance photos taken in poor light when a prediction asks for it with enhance=true" },
+];
+
+/// Looks up a flag by name.
+pub fn find(name: &str) -> Option<&'static Flag> {
+    FLAGS.iter().find(|f| f.name == name)
+}
+
+/// Whether a flag is on for a request made with `api_key`. Unknown flags are off.
+pub fn is_enabled(settings: &RuntimeSettings, name: &str, api_key: Option<&ApiKey>) -> bool {
+    settings.feature_flags.get(name).is_some_and(|rollout| {
+        rollout.everyone || api_key.is_some_and(|key| rollout.api_keys.contains(&key.id))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_can_be_rolled_out_to_single_keys() {
+        let mut settings = RuntimeSettings::from_config();
+        settings.feature_flags.insert(TTA.to_string(), Rollout { everyone: false, api_keys: vec![2] });
+        let key = |id| ApiKey {
+            id,
+            name: "Hawks CC".to_string(),
+            key_prefix: "crk_abcd".to_string(),
+            profile: None,
+            scopes: "predict".to_string(),
+            created_at: String::new(),
+            rotated_at: None,
+            last_used_at: None,
+            revoked_at: None,
+        };
+
+        assert!(is_enabled(&settings, TTA, Some(&key(2))));
+        assert!(!is_enabled(&settings, TTA, Some(&key(3))));
+        assert!(!is_enabled(&settings, TTA, None));
+        assert!(!is_enabled(&settings, CANDIDATE_MODEL, Some(&key(2))));
+
+        settings.feature_flags.insert(CANDIDATE_MODEL.to_string(), Rollout { everyone: true, api_keys: vec![] });
+        assert!(is_enabled(&settings, CANDIDATE_MODEL, None));
+    }
+}
@@ -0,0 +1,131 @@
+use chrono::Utc;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use crate::api_types::{ReadinessCheck, ReadinessResponse};
+use crate::cache::{self, Store};
+use crate::config;
+use crate::db;
+use crate::request_logger::RequestLogger;
+use crate::server;
+use crate::storage::{self, Area};
+
+/// How long the `healthcheck` subcommand waits for the server, well within Docker's default 30 second timeout.
+const HEALTHCHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn check(name: &'static str, result: Result<(), String>) -> ReadinessCheck {
+    ReadinessCheck { name, ok: result.is_ok(), error: result.err() }
+}
+
+/// Checks every service the server needs to handle requests: the metadata database, the shared store and storage.
+async fn readiness(pool: Result<&db::Pool, String>, store: Result<&Store, String>) -> ReadinessResponse {
+    let database = match pool {
+        Ok(pool) => sqlx::query("SELECT 1").execute(pool).await.map(|_| ()).map_err(|e| e.to_string()),
+        Err(message) => Err(message),
+    };
+    let store = match store {
+        Ok(store) => store.get("ready").await.map(|_| ()),
+        Err(message) => Err(message),
+    };
+    let storage = storage::get().exists(Area::Temp, "ready").map(|_| ()).map_err(|e| e.to_string());
+
+    let checks = vec![check("database", database), check("store", store), check("storage", storage)];
+    let ready = checks.iter().all(|check| check.ok);
+    ReadinessResponse { status: if ready { "ready" } else { "unavailable" }, checks }
+}
+
+/// Readiness route handler for load balancers and container orchestrators.
+/// Responds `503 Service Unavailable` if any service the server needs is unreachable.
+pub async fn ready_route() -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /ready");
+
+    let response = readiness(db::pool().await.map_err(|e| e.to_string()), cache::store().await).await;
+    for failed in response.checks.iter().filter(|check| !check.ok) {
+        logger.error(format!("Readiness check {} failed: {}", failed.name, failed.error.as_deref().unwrap_or_default()));
+    }
+    if response.checks.iter().all(|check| check.ok) {
+        rusty_api::HttpResponse::Ok().json(response)
+    } else {
+        rusty_api::HttpResponse::ServiceUnavailable().json(response)
+    }
+}
+
+/// Asks the server for `/ready` at `url`, returning the response's status code and body.
+fn ready_over_tcp(url: &str) -> Result<(u16, String), String> {
+    // The certificate is self-signed and names the host, not the loopback address
+    let response = attohttpc::get(format!("{}/ready", url))
+        .timeout(HEALTHCHECK_TIMEOUT)
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .send()
+        .map_err(|e| e.to_string())?;
+    let status = response.status().as_u16();
+    Ok((status, response.text().unwrap_or_default()))
+}
+
+/// Asks the server for `/ready` over the Unix domain socket at `path`, returning the response's status code and body.
+fn ready_over_unix(path: &str) -> Result<(u16, String), String> {
+    let mut stream = UnixStream::connect(path).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(HEALTHCHECK_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.write_all(b"GET /ready HTTP/1.0\r\nHost: localhost\r\n\r\n").map_err(|e| e.to_string())?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head.split(' ').nth(1).and_then(|code| code.parse().ok()).ok_or("Malformed response")?;
+    Ok((status, body.to_string()))
+}
+
+/// The `healthcheck` subcommand: asks the server running on this machine whether it is ready, for Docker's
+/// `HEALTHCHECK` and Kubernetes exec probes. Exits with 1 if it isn't ready or doesn't answer.
+/// The server is reached on its first HTTPS or HTTP listener, or else its Unix domain socket.
+pub fn healthcheck() -> ExitCode {
+    let response = match (server::local_url(), &config::get().unix_socket_path) {
+        (Some(url), _) => ready_over_tcp(&url),
+        (None, Some(path)) => ready_over_unix(path),
+        (None, None) => {
+            println!("ERROR: No HTTPS, HTTP or Unix socket listener is configured");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match response {
+        Ok((200, _)) => {
+            println!("INFO: Server is ready");
+            ExitCode::SUCCESS
+        }
+        Ok((status, body)) => {
+            println!("ERROR: Server is not ready ({}): {}", status, body);
+            ExitCode::FAILURE
+        }
+        Err(message) => {
+            println!("ERROR: Server did not respond: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unreachable_services_make_the_server_unavailable() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let store = Store::memory();
+
+        let response = readiness(Ok(&pool), Ok(&store)).await;
+        assert_eq!(response.status, "ready");
+        assert_eq!(response.checks.iter().map(|check| check.name).collect::<Vec<_>>(), vec!["database", "store", "storage"]);
+        assert!(response.checks.iter().all(|check| check.ok && check.error.is_none()));
+
+        let response = readiness(Ok(&pool), Err("connection refused".to_string())).await;
+        assert_eq!(response.status, "unavailable");
+        assert_eq!(response.checks[1].error.as_deref(), Some("connection refused"));
+    }
+}
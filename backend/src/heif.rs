@@ -0,0 +1,65 @@
+use std::process::Command;
+
+use crate::storage::{self, Area};
+
+/// Major brands of HEIC/HEIF files, including the image sequences iPhones save for bursts.
+/// AVIF shares the container but has its own brand, and is left to the `image` crate.
+const BRANDS: [&[u8; 4]; 10] = [b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"hevm", b"hevs", b"mif1", b"msf1"];
+/// JPEG quality converted photos are encoded at; high, since they may be recompressed again when stored.
+const JPEG_QUALITY: u8 = 95;
+
+/// Why a HEIC/HEIF photo couldn't be converted.
+#[derive(Debug)]
+pub enum ConvertError {
+    /// The file isn't a photo the converter can read.
+    Unreadable(String),
+    /// The converter couldn't be run, e.g. because `pillow-heif` isn't installed.
+    Unavailable(String),
+}
+
+/// Whether an upload is a HEIC/HEIF file, from the brand in its `ftyp` box.
+pub fn is_heif(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && BRANDS.iter().any(|brand| &bytes[8..12] == *brand)
+}
+
+/// Converts a HEIC/HEIF photo to JPEG with `nn-classifier/convert_heif.py`, turned upright and with its colours
+/// converted to sRGB from the profile it was taken with (Display P3 on recent iPhones).
+/// `image_id` keeps temporary file names unique across concurrent requests.
+pub fn to_jpeg(bytes: &[u8], image_id: &str) -> Result<Vec<u8>, ConvertError> {
+    let storage = storage::get();
+    let temp_key = format!("heif_{}.heic", image_id);
+    let temp_path = storage
+        .put(Area::Temp, &temp_key, bytes)
+        .map_err(|e| ConvertError::Unavailable(format!("Failed to write temporary file: {}", e)))?;
+
+    let output = Command::new("nn-classifier/venv/bin/python3")
+        .arg("nn-classifier/convert_heif.py")
+        .arg(&temp_path)
+        .arg("--quality")
+        .arg(JPEG_QUALITY.to_string())
+        .output();
+    storage.delete(Area::Temp, &temp_key).ok();
+
+    let output = output.map_err(|e| ConvertError::Unavailable(format!("Failed to execute convert_heif.py: {}", e)))?;
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    match output.status.code() {
+        Some(0) => Ok(output.stdout),
+        // The script exits with 2 for photos it can't read, and 1 if its packages are missing
+        Some(2) => Err(ConvertError::Unreadable(stderr)),
+        _ => Err(ConvertError::Unavailable(format!("HEIC/HEIF conversion failed: {}", stderr))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heif_files_are_recognised_by_their_brand() {
+        assert!(is_heif(b"\0\0\0\x18ftypheic\0\0\0\0mif1heic"));
+        assert!(is_heif(b"\0\0\0\x1cftypmif1\0\0\0\0mif1heic"));
+        assert!(!is_heif(b"\0\0\0\x1cftypavif\0\0\0\0avifmif1"));
+        assert!(!is_heif(b"\xFF\xD8\xFF\xE0\0\x10JFIF\0\x01"));
+        assert!(!is_heif(b"ftyp"));
+    }
+}
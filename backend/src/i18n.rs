@@ -0,0 +1,178 @@
+use rusty_api::HttpRequest;
+
+/// Languages with translated response messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Es,
+    Fr,
+}
+
+/// Human-readable messages returned to clients.
+/// Machine-readable codes such as the `prediction` field are never translated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    VerdictMatchReady,
+    VerdictNotMatchReady,
+    VerdictUnknown,
+    RecommendMatchReady,
+    RecommendNotMatchReady,
+    RecommendUnknown,
+    /// Recommendation for labels other than the two the profiles decide between.
+    RecommendCheck,
+    TrainingSaved,
+    NoImageReceived,
+    NotAnImage,
+    LabelRequired,
+    InvalidLabel,
+    QualityTooBlurry,
+    QualityTooDark,
+    QualityTooBright,
+    QualityBallTooSmall,
+    QualityRejected,
+}
+
+impl Locale {
+    /// The language tag reported back in `Content-Language`.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Es => "es",
+            Locale::Fr => "fr",
+        }
+    }
+
+    /// Matches the primary subtag of a language tag, e.g. `es-AR` -> `Es`.
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next()?.trim().to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(Locale::En),
+            "es" => Some(Locale::Es),
+            "fr" => Some(Locale::Fr),
+            _ => None,
+        }
+    }
+
+    /// Picks the best supported locale from an `Accept-Language` header value,
+    /// honouring quality weights and falling back to English.
+    pub fn negotiate(accept_language: &str) -> Self {
+        let mut best: Option<(Locale, f32)> = None;
+
+        for entry in accept_language.split(',') {
+            let mut parts = entry.split(';');
+            let tag = parts.next().unwrap_or("").trim();
+            let quality = parts
+                .find_map(|p| p.trim().strip_prefix("q="))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if let Some(locale) = Locale::from_tag(tag) {
+                if quality > 0.0 && best.is_none_or(|(_, q)| quality > q) {
+                    best = Some((locale, quality));
+                }
+            }
+        }
+
+        best.map_or(Locale::En, |(locale, _)| locale)
+    }
+
+    /// Selects the locale for a request from its `Accept-Language` header.
+    pub fn from_request(req: &HttpRequest) -> Self {
+        req.headers()
+            .get("Accept-Language")
+            .and_then(|h| h.to_str().ok())
+            .map_or(Locale::En, Locale::negotiate)
+    }
+
+    /// Returns the translated text for a message.
+    pub fn text(self, message: Message) -> &'static str {
+        use Message::*;
+        match (self, message) {
+            (Locale::En, VerdictMatchReady) => "Match Ready",
+            (Locale::En, VerdictNotMatchReady) => "Not Match Ready",
+            (Locale::En, VerdictUnknown) => "Unknown",
+            (Locale::En, RecommendMatchReady) => "This ball is fit for use in a match.",
+            (Locale::En, RecommendNotMatchReady) => "Replace this ball before the next match.",
+            (Locale::En, RecommendUnknown) => "We couldn't assess this ball. Please retake the photo.",
+            (Locale::En, RecommendCheck) => "Have this ball checked before the next match.",
+            (Locale::En, TrainingSaved) => "Training data saved successfully",
+            (Locale::En, NoImageReceived) => "No image data received",
+            (Locale::En, NotAnImage) => "The uploaded file is not a supported image",
+            (Locale::En, LabelRequired) => "Label is required for training data",
+            (Locale::En, InvalidLabel) => "Label must be one of:",
+            (Locale::En, QualityTooBlurry) => "Image too blurry — hold the phone steady and tap to focus on the ball.",
+            (Locale::En, QualityTooDark) => "Image too dark — move into better light or turn on the flash.",
+            (Locale::En, QualityTooBright) => "Image too bright — move out of direct sunlight or turn off the flash.",
+            (Locale::En, QualityBallTooSmall) => "Ball too small in the frame — move closer so the ball fills more of the photo.",
+            (Locale::En, QualityRejected) => "Photo quality is too low to assess the ball. Please retake the photo.",
+
+            (Locale::Es, VerdictMatchReady) => "Apta para el partido",
+            (Locale::Es, VerdictNotMatchReady) => "No apta para el partido",
+            (Locale::Es, VerdictUnknown) => "Desconocido",
+            (Locale::Es, RecommendMatchReady) => "Esta pelota está en condiciones para un partido.",
+            (Locale::Es, RecommendNotMatchReady) => "Reemplace esta pelota antes del próximo partido.",
+            (Locale::Es, RecommendUnknown) => "No pudimos evaluar esta pelota. Vuelva a tomar la foto.",
+            (Locale::Es, RecommendCheck) => "Haga revisar esta pelota antes del próximo partido.",
+            (Locale::Es, TrainingSaved) => "Datos de entrenamiento guardados correctamente",
+            (Locale::Es, NoImageReceived) => "No se recibió ninguna imagen",
+            (Locale::Es, NotAnImage) => "El archivo enviado no es una imagen compatible",
+            (Locale::Es, LabelRequired) => "La etiqueta es obligatoria para los datos de entrenamiento",
+            (Locale::Es, InvalidLabel) => "La etiqueta debe ser una de:",
+            (Locale::Es, QualityTooBlurry) => "Imagen borrosa: sujete el teléfono con firmeza y toque para enfocar la pelota.",
+            (Locale::Es, QualityTooDark) => "Imagen demasiado oscura: busque mejor luz o active el flash.",
+            (Locale::Es, QualityTooBright) => "Imagen demasiado clara: evite la luz solar directa o desactive el flash.",
+            (Locale::Es, QualityBallTooSmall) => "La pelota se ve muy pequeña: acérquese para que ocupe más de la foto.",
+            (Locale::Es, QualityRejected) => "La calidad de la foto es insuficiente para evaluar la pelota. Vuelva a tomar la foto.",
+
+            (Locale::Fr, VerdictMatchReady) => "Prête pour le match",
+            (Locale::Fr, VerdictNotMatchReady) => "Pas prête pour le match",
+            (Locale::Fr, VerdictUnknown) => "Inconnu",
+            (Locale::Fr, RecommendMatchReady) => "Cette balle peut être utilisée en match.",
+            (Locale::Fr, RecommendNotMatchReady) => "Remplacez cette balle avant le prochain match.",
+            (Locale::Fr, RecommendUnknown) => "Impossible d'évaluer cette balle. Veuillez reprendre la photo.",
+            (Locale::Fr, RecommendCheck) => "Faites vérifier cette balle avant le prochain match.",
+            (Locale::Fr, TrainingSaved) => "Données d'entraînement enregistrées",
+            (Locale::Fr, NoImageReceived) => "Aucune image reçue",
+            (Locale::Fr, NotAnImage) => "Le fichier envoyé n'est pas une image prise en charge",
+            (Locale::Fr, LabelRequired) => "Une étiquette est requise pour les données d'entraînement",
+            (Locale::Fr, InvalidLabel) => "L'étiquette doit être l'une des suivantes :",
+            (Locale::Fr, QualityTooBlurry) => "Image floue — tenez le téléphone immobile et touchez la balle pour faire la mise au point.",
+            (Locale::Fr, QualityTooDark) => "Image trop sombre — placez-vous sous une meilleure lumière ou activez le flash.",
+            (Locale::Fr, QualityTooBright) => "Image trop claire — évitez le soleil direct ou désactivez le flash.",
+            (Locale::Fr, QualityBallTooSmall) => "Balle trop petite dans le cadre — rapprochez-vous pour qu'elle remplisse davantage la photo.",
+            (Locale::Fr, QualityRejected) => "La qualité de la photo est insuffisante pour évaluer la balle. Veuillez reprendre la photo.",
+        }
+    }
+
+    /// Returns the translated verdict name and recommendation for a prediction code. Other labels in the taxonomy
+    /// are named by the `description` given, which isn't translated; predictions without one are unknown.
+    pub fn verdict(self, prediction: &str, description: Option<&str>) -> (String, &'static str) {
+        let (verdict, recommendation) = match (prediction, description) {
+            ("match_ready", _) => (Message::VerdictMatchReady, Message::RecommendMatchReady),
+            ("not_match_ready", _) => (Message::VerdictNotMatchReady, Message::RecommendNotMatchReady),
+            (_, Some(description)) => return (description.to_string(), self.text(Message::RecommendCheck)),
+            _ => (Message::VerdictUnknown, Message::RecommendUnknown),
+        };
+        (self.text(verdict).to_string(), self.text(recommendation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiate_respects_quality_weights() {
+        assert_eq!(Locale::negotiate("fr-CA,fr;q=0.9,en;q=0.8"), Locale::Fr);
+        assert_eq!(Locale::negotiate("de-DE,en;q=0.5,es;q=0.7"), Locale::Es);
+        assert_eq!(Locale::negotiate("de-DE"), Locale::En);
+        assert_eq!(Locale::negotiate("es;q=0,fr;q=0.1"), Locale::Fr);
+    }
+
+    #[test]
+    fn other_labels_are_named_by_their_description() {
+        assert_eq!(Locale::Fr.verdict("not_match_ready", None).0, "Pas prête pour le match");
+        assert_eq!(Locale::Es.verdict("needs_repair", Some("Seam needs restitching.")).0, "Seam needs restitching.");
+        assert_eq!(Locale::En.verdict("unknown", None), ("Unknown".to_string(), Locale::En.text(Message::RecommendUnknown)));
+    }
+}
@@ -0,0 +1,524 @@
+use chrono::Utc;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::process::Command;
+
+pub use crate::api_types::Job;
+use crate::api_types::{JobCancelledResponse, JobStartedResponse, JobsResponse};
+use crate::auth;
+use crate::config;
+use crate::dataset;
+use crate::db;
+use crate::labels;
+use crate::predictions::format_timestamp;
+use crate::request_logger::RequestLogger;
+
+/// How long a cancelled job's process has to exit after `SIGTERM` before it is killed outright.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How often a replica renews its lease on the jobs it runs, and checks whether they are being cancelled.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+/// How long a job's lease lasts without being renewed, after which its replica is taken to have stopped.
+const LEASE_DURATION: Duration = Duration::from_secs(120);
+
+/// Number of jobs returned by `GET /jobs`.
+const RECENT_JOBS: i64 = 20;
+
+/// Where the classifier's scripts live, relative to the backend directory.
+const CLASSIFIER_DIR: &str = "nn-classifier";
+/// The directory within `CLASSIFIER_DIR` retrained models replace: model version v1's, by default.
+const TRAINED_MODELS_DIR: &str = "models";
+
+/// Process IDs of the jobs this process is running, by job ID.
+static RUNNING: Mutex<BTreeMap<i64, u32>> = Mutex::new(BTreeMap::new());
+
+/// Stores a new job for this replica to run and returns its ID, unless a job of the same kind is already active.
+/// Concurrent requests can't both start one: a start that races past the check hits the unique index on active jobs.
+/// Jobs whose replica has stopped are ended first, so they don't hold up new ones.
+pub async fn create(pool: &db::Pool, kind: &str) -> Result<Option<i64>, sqlx::Error> {
+    end_abandoned(pool, None).await?;
+    let created = sqlx::query_scalar(
+        "INSERT INTO jobs (kind, status, created_at, instance_id, heartbeat_at) SELECT $1, 'queued', $2, $3, $2
+         WHERE NOT EXISTS (SELECT 1 FROM jobs WHERE kind = $1 AND status IN ('queued', 'running', 'cancelling'))
+         RETURNING id"
+    )
+    .bind(kind)
+    .bind(format_timestamp(Utc::now()))
+    .bind(&config::get().instance_id)
+    .fetch_optional(pool)
+    .await;
+    match created {
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(None),
+        created => created,
+    }
+}
+
+/// Ends the active jobs of `instance`, and those whose replica stopped renewing their lease, since their processes
+/// ended with the replica. Jobs that were being cancelled are marked cancelled and the rest failed.
+/// Returns the IDs of the jobs ended.
+async fn end_abandoned(pool: &db::Pool, instance: Option<&str>) -> Result<Vec<i64>, sqlx::Error> {
+    let now = Utc::now();
+    let expired = now - chrono::Duration::from_std(LEASE_DURATION).unwrap_or_default();
+    sqlx::query_scalar(
+        "UPDATE jobs SET status = CASE WHEN status = 'cancelling' THEN 'cancelled' ELSE 'failed' END,
+                error = CASE WHEN status = 'cancelling' THEN NULL ELSE 'Interrupted: the server running it stopped' END,
+                finished_at = $1
+         WHERE status IN ('queued', 'running', 'cancelling')
+           AND (instance_id = $2 OR heartbeat_at IS NULL OR heartbeat_at < $3)
+         RETURNING id"
+    )
+    .bind(format_timestamp(now))
+    .bind(instance)
+    .bind(format_timestamp(expired))
+    .fetch_all(pool)
+    .await
+}
+
+/// Ends the jobs an earlier run of this replica left active, and any whose replica has stopped, and removes the
+/// models they were training and any decrypted datasets they trained on. Called at startup, before any new job
+/// can be started. Returns the number of jobs ended.
+pub async fn fail_interrupted(pool: &db::Pool) -> Result<usize, sqlx::Error> {
+    let ended = end_abandoned(pool, Some(&config::get().instance_id)).await?;
+    for id in &ended {
+        std::fs::remove_dir_all(Path::new(CLASSIFIER_DIR).join(staging_dir(*id))).ok();
+        dataset::remove_training_dir(*id).ok();
+    }
+    Ok(ended.len())
+}
+
+/// Looks up a job by ID.
+pub async fn find(pool: &db::Pool, id: i64) -> Result<Option<Job>, sqlx::Error> {
+    sqlx::query_as::<_, Job>("SELECT id, kind, status, created_at, started_at, finished_at, error FROM jobs WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Returns the most recent jobs, newest first.
+pub async fn recent(pool: &db::Pool, limit: i64) -> Result<Vec<Job>, sqlx::Error> {
+    sqlx::query_as::<_, Job>(
+        "SELECT id, kind, status, created_at, started_at, finished_at, error FROM jobs ORDER BY id DESC LIMIT $1"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Moves a job that hasn't finished yet to a new status, recording when it started or finished. Only a queued job
+/// can start running, and a job being cancelled ends up cancelled however its process ended.
+/// Returns the job's new status, or `None` if it had already finished, e.g. because it was cancelled while queued.
+async fn update_status(pool: &db::Pool, id: i64, status: &str, error: Option<&str>) -> Result<Option<String>, sqlx::Error> {
+    let now = format_timestamp(Utc::now());
+    sqlx::query_scalar(
+        "UPDATE jobs SET status = CASE WHEN status = 'cancelling' THEN 'cancelled' ELSE $1 END,
+                error = CASE WHEN status = 'cancelling' THEN NULL ELSE $2 END,
+                started_at = CASE WHEN $1 = 'running' THEN $3 ELSE started_at END,
+                finished_at = CASE WHEN $1 = 'running' THEN NULL ELSE $3 END,
+                heartbeat_at = $3
+         WHERE id = $4 AND status IN ('queued', 'running', 'cancelling') AND ($1 <> 'running' OR status = 'queued')
+         RETURNING status"
+    )
+    .bind(status)
+    .bind(error)
+    .bind(&now)
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Renews this replica's lease on a job it is running. Returns the job's status, or `None` once it has finished.
+async fn renew_lease(pool: &db::Pool, id: i64) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        "UPDATE jobs SET heartbeat_at = $1 WHERE id = $2 AND status IN ('queued', 'running', 'cancelling') RETURNING status"
+    )
+    .bind(format_timestamp(Utc::now()))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Keeps renewing this replica's lease on a job until it finishes, and stops the job's process once it is being
+/// cancelled, which may have been asked of another replica.
+async fn keep_lease(pool: &'static db::Pool, id: i64, logger: RequestLogger) {
+    let mut stopping = false;
+    loop {
+        tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+        match renew_lease(pool, id).await {
+            Ok(Some(status)) if status == "cancelling" && !stopping => {
+                let pid = RUNNING.lock().unwrap().get(&id).copied();
+                if let Some(pid) = pid {
+                    logger.info(format!("Stopping training job {}: it is being cancelled", id));
+                    terminate(id, pid, &logger);
+                    stopping = true;
+                }
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => return,
+            Err(e) => logger.error(format!("Failed to renew the lease on job {}: {}", id, e)),
+        }
+    }
+}
+
+/// Asks a job's process to stop, and kills it if it is still running after the grace period.
+fn terminate(id: i64, pid: u32, logger: &RequestLogger) {
+    let pid = Pid::from_raw(pid as i32);
+    if let Err(e) = kill(pid, Signal::SIGTERM) {
+        logger.error(format!("Failed to stop job {}: {}", id, e));
+    }
+
+    let logger = logger.handle();
+    tokio::spawn(async move {
+        tokio::time::sleep(CANCEL_GRACE_PERIOD).await;
+        let still_running = RUNNING.lock().unwrap().get(&id) == Some(&(pid.as_raw() as u32));
+        if still_running {
+            logger.error(format!("Job {} ignored SIGTERM; killing it", id));
+            kill(pid, Signal::SIGKILL).ok();
+        }
+    });
+}
+
+/// The directory within `CLASSIFIER_DIR` a training job saves its models in until they replace the live ones.
+fn staging_dir(id: i64) -> String {
+    format!("{}.job-{}", TRAINED_MODELS_DIR, id)
+}
+
+/// Swaps freshly trained models in for the live ones in a single step, so predictions never load a mix of the two.
+/// The previous models are left in `staging`.
+fn swap_in(staging: &Path, live: &Path) -> io::Result<()> {
+    if !live.exists() {
+        return std::fs::rename(staging, live);
+    }
+    exchange(staging, live)
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn exchange(staging: &Path, live: &Path) -> io::Result<()> {
+    use nix::fcntl::{renameat2, RenameFlags};
+    renameat2(None, staging, None, live, RenameFlags::RENAME_EXCHANGE).map_err(io::Error::from)
+}
+
+/// Without an atomic exchange there is a moment without live models, so predictions made then fail.
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn exchange(staging: &Path, live: &Path) -> io::Result<()> {
+    let previous = staging.with_extension("previous");
+    std::fs::rename(live, &previous)?;
+    std::fs::rename(staging, live)?;
+    std::fs::rename(&previous, staging)
+}
+
+/// Removes the decrypted dataset a training job wrote for itself, if encryption at rest gave it one.
+fn remove_dataset(id: i64, logger: &RequestLogger) {
+    if let Err(e) = dataset::remove_training_dir(id) {
+        logger.error(format!("Failed to remove the dataset of job {}: {}", id, e));
+    }
+}
+
+/// Runs the training script for a job and records how it ended, logging with a handle on the logger of the request
+/// that started it.
+async fn run_training(pool: &'static db::Pool, id: i64, logger: RequestLogger) {
+    tokio::spawn(keep_lease(pool, id, logger.handle()));
+
+    // Train on exactly the labels in the taxonomy, with the images reviewers approved
+    if let Err(message) = labels::write_for_training(pool).await {
+        logger.error(format!("Failed to start training job {}: {}", id, message));
+        update_status(pool, id, "failed", Some(&message)).await.ok();
+        return;
+    }
+    let dataset_dir = dataset::training_dir(id);
+    match dataset::write_for_training(pool, dataset_dir.clone(), &logger).await {
+        Ok(images) => logger.info(format!("Training job {} dataset has {} approved sample images", id, images)),
+        Err(message) => {
+            logger.error(format!("Failed to start training job {}: {}", id, message));
+            update_status(pool, id, "failed", Some(&message)).await.ok();
+            remove_dataset(id, &logger);
+            return;
+        }
+    }
+
+    // Train into a directory of its own, so predictions keep using the live models until the new ones are complete
+    let staging = Path::new(CLASSIFIER_DIR).join(staging_dir(id));
+    std::fs::remove_dir_all(&staging).ok();
+    let mut child = match std::path::absolute(&dataset_dir).and_then(|dataset_dir| {
+        Command::new("venv/bin/python3")
+            .args(["train.py", "--models-dir", &staging_dir(id)])
+            .arg("--dataset-dir")
+            .arg(dataset_dir)
+            .current_dir(CLASSIFIER_DIR)
+            .spawn()
+    }) {
+        Ok(child) => child,
+        Err(e) => {
+            logger.error(format!("Failed to start training job {}: {}", id, e));
+            update_status(pool, id, "failed", Some(&format!("Failed to start training: {}", e))).await.ok();
+            remove_dataset(id, &logger);
+            return;
+        }
+    };
+
+    let pid = child.id().unwrap_or_default();
+    RUNNING.lock().unwrap().insert(id, pid);
+
+    // The job may have been cancelled while it was starting
+    match update_status(pool, id, "running", None).await {
+        Ok(Some(_)) => logger.info(format!("Training job {} running as process {}", id, pid)),
+        Ok(None) => terminate(id, pid, &logger),
+        Err(e) => logger.error(format!("Failed to mark job {} running: {}", id, e)),
+    }
+
+    let result = child.wait().await;
+    RUNNING.lock().unwrap().remove(&id);
+
+    let (status, error) = match result {
+        Ok(exit) if exit.success() => ("succeeded", None),
+        Ok(exit) => ("failed", Some(format!("Training exited with {}", exit))),
+        Err(e) => ("failed", Some(format!("Failed to wait for training: {}", e))),
+    };
+    // Models from a job cancelled in the meantime are thrown away with those of failed ones
+    let still_wanted = matches!(find(pool, id).await, Ok(Some(job)) if job.status == "running");
+    let (status, error) = match status {
+        "succeeded" if still_wanted => match swap_in(&staging, &Path::new(CLASSIFIER_DIR).join(TRAINED_MODELS_DIR)) {
+            Ok(()) => (status, error),
+            Err(e) => ("failed", Some(format!("Failed to replace the live models: {}", e))),
+        },
+        _ => (status, error),
+    };
+    // After a swap this holds the previous models
+    if let Err(e) = std::fs::remove_dir_all(&staging) {
+        if e.kind() != io::ErrorKind::NotFound {
+            logger.error(format!("Failed to remove {}: {}", staging.display(), e));
+        }
+    }
+    remove_dataset(id, &logger);
+    match update_status(pool, id, status, error.as_deref()).await {
+        Ok(Some(status)) if status == "cancelled" => logger.info(format!("Training job {} stopped after being cancelled", id)),
+        Ok(Some(status)) => logger.info(format!("Training job {} {}", id, status)),
+        Ok(None) => logger.info(format!("Training job {} had already ended before its process did", id)),
+        Err(e) => logger.error(format!("Failed to record the end of job {}: {}", id, e)),
+    }
+}
+
+/// What happened when cancelling a job.
+#[derive(Debug, PartialEq)]
+pub enum CancelOutcome {
+    /// The job hadn't started, and was marked cancelled.
+    Cancelled,
+    /// The job is being cancelled, and is marked cancelled once its process has stopped. `terminated` says whether
+    /// this replica was running it and has started stopping it; otherwise the replica running it does so shortly.
+    Cancelling { terminated: bool },
+    /// The job had already finished with the given status.
+    Finished(String),
+    NotFound,
+}
+
+/// Cancels a queued or running job. A running job keeps its slot until its process has stopped: this replica
+/// stops it straight away if it is running it, and the replica that is checks for cancellations as it renews its lease.
+pub async fn cancel(pool: &db::Pool, id: i64, logger: &RequestLogger) -> Result<CancelOutcome, sqlx::Error> {
+    let status: Option<String> = sqlx::query_scalar(
+        "UPDATE jobs SET status = CASE WHEN status = 'queued' THEN 'cancelled' ELSE 'cancelling' END,
+                finished_at = CASE WHEN status = 'queued' THEN $1 ELSE finished_at END
+         WHERE id = $2 AND status IN ('queued', 'running')
+         RETURNING status"
+    )
+    .bind(format_timestamp(Utc::now()))
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    match status.as_deref() {
+        Some("cancelled") => Ok(CancelOutcome::Cancelled),
+        Some(_) => {
+            let pid = RUNNING.lock().unwrap().get(&id).copied();
+            if let Some(pid) = pid {
+                terminate(id, pid, logger);
+            }
+            Ok(CancelOutcome::Cancelling { terminated: pid.is_some() })
+        }
+        None => Ok(match find(pool, id).await? {
+            Some(job) if job.status == "cancelling" => CancelOutcome::Cancelling { terminated: false },
+            Some(job) => CancelOutcome::Finished(job.status),
+            None => CancelOutcome::NotFound,
+        }),
+    }
+}
+
+/// Training job route handler starting a full retrain of the models in the background.
+/// Only one training job can be queued or running at a time. Requires the admin token.
+pub async fn start_training_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /jobs/training");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized training job");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match create(pool, "training").await {
+        Ok(Some(id)) => {
+            logger.info(format!("Starting training job {}", id));
+            tokio::spawn(run_training(pool, id, logger.handle()));
+            rusty_api::HttpResponse::Accepted().json(JobStartedResponse { job_id: id, status: "queued" })
+        }
+        Ok(None) => rusty_api::HttpResponse::Conflict().body("A training job is already queued or running"),
+        Err(e) => {
+            logger.error(format!("Failed to create training job: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Jobs route handler listing the most recent jobs with their status. Requires the admin token.
+pub async fn list_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /jobs");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized job listing");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match recent(pool, RECENT_JOBS).await {
+        Ok(jobs) => rusty_api::HttpResponse::Ok().json(JobsResponse { jobs }),
+        Err(e) => {
+            logger.error(format!("Failed to list jobs: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Cancel route handler stopping a queued or running job. Requires the admin token.
+pub async fn cancel_route(req: rusty_api::HttpRequest, path: rusty_api::web::Path<i64>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let id = path.into_inner();
+
+    logger.info(format!("Received request to cancel job {}", id));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized job cancellation");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match cancel(pool, id, &logger).await {
+        Ok(CancelOutcome::Cancelled) => {
+            logger.info(format!("Cancelled job {}", id));
+            rusty_api::HttpResponse::Ok().json(JobCancelledResponse { job_id: id, status: "cancelled", terminated: false })
+        }
+        Ok(CancelOutcome::Cancelling { terminated }) => {
+            logger.info(format!("Cancelling job {}", id));
+            rusty_api::HttpResponse::Ok().json(JobCancelledResponse { job_id: id, status: "cancelling", terminated })
+        }
+        Ok(CancelOutcome::Finished(status)) => {
+            rusty_api::HttpResponse::Conflict().body(format!("Job {} has already finished ({})", id, status))
+        }
+        Ok(CancelOutcome::NotFound) => rusty_api::HttpResponse::NotFound().body(format!("Job {} not found", id)),
+        Err(e) => {
+            logger.error(format!("Failed to cancel job {}: {}", id, e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancelled_jobs_stay_cancelled() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let logger = RequestLogger::new(0);
+        let id = create(&pool, "training").await.unwrap().unwrap();
+        assert_eq!(create(&pool, "training").await.unwrap(), None);
+        assert_eq!(update_status(&pool, id, "running", None).await.unwrap().as_deref(), Some("running"));
+
+        // Another replica is running it, so the job keeps its slot until that replica has stopped it
+        assert_eq!(cancel(&pool, id, &logger).await.unwrap(), CancelOutcome::Cancelling { terminated: false });
+        assert_eq!(cancel(&pool, id, &logger).await.unwrap(), CancelOutcome::Cancelling { terminated: false });
+        assert_eq!(create(&pool, "training").await.unwrap(), None);
+        assert_eq!(renew_lease(&pool, id).await.unwrap().as_deref(), Some("cancelling"));
+        // The process exiting afterwards doesn't overwrite the cancellation
+        assert_eq!(update_status(&pool, id, "failed", Some("killed")).await.unwrap().as_deref(), Some("cancelled"));
+
+        let job = find(&pool, id).await.unwrap().unwrap();
+        assert_eq!((job.status.as_str(), job.error), ("cancelled", None));
+        assert!(job.started_at.is_some() && job.finished_at.is_some());
+        assert_eq!(renew_lease(&pool, id).await.unwrap(), None);
+        assert_eq!(cancel(&pool, id, &logger).await.unwrap(), CancelOutcome::Finished("cancelled".to_string()));
+        assert_eq!(cancel(&pool, id + 1, &logger).await.unwrap(), CancelOutcome::NotFound);
+
+        // A job cancelled before it started never runs
+        let queued = create(&pool, "training").await.unwrap().unwrap();
+        assert_eq!(cancel(&pool, queued, &logger).await.unwrap(), CancelOutcome::Cancelled);
+        assert_eq!(update_status(&pool, queued, "running", None).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn only_this_replicas_jobs_and_abandoned_ones_are_failed() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let id = create(&pool, "training").await.unwrap().unwrap();
+        assert!(update_status(&pool, id, "running", None).await.unwrap().is_some());
+        let other = |heartbeat_at: String| {
+            sqlx::query("INSERT INTO jobs (kind, status, created_at, instance_id, heartbeat_at) VALUES ('export', 'running', $1, 'other', $1)")
+                .bind(heartbeat_at)
+                .execute(&pool)
+        };
+        other(format_timestamp(Utc::now())).await.unwrap();
+
+        assert_eq!(fail_interrupted(&pool).await.unwrap(), 1);
+        let job = find(&pool, id).await.unwrap().unwrap();
+        assert_eq!((job.status.as_str(), job.error.as_deref()), ("failed", Some("Interrupted: the server running it stopped")));
+        assert_eq!(recent(&pool, 2).await.unwrap()[0].status, "running");
+        assert_eq!(create(&pool, "export").await.unwrap(), None);
+
+        // The other replica stopped renewing its lease
+        sqlx::query("UPDATE jobs SET heartbeat_at = $1 WHERE instance_id = 'other'")
+            .bind(format_timestamp(Utc::now() - chrono::Duration::minutes(5)))
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(create(&pool, "export").await.unwrap().is_some());
+        assert_eq!(recent(&pool, 3).await.unwrap()[1].status, "failed");
+    }
+
+    #[test]
+    fn trained_models_replace_the_live_ones() {
+        let dir = std::env::temp_dir().join(format!("cricket_ready_jobs_{}", std::process::id()));
+        let (staging, live) = (dir.join(staging_dir(1)), dir.join(TRAINED_MODELS_DIR));
+        std::fs::create_dir_all(&staging).unwrap();
+        std::fs::write(staging.join("model_1.pth"), b"new").unwrap();
+
+        swap_in(&staging, &live).unwrap();
+        assert_eq!(std::fs::read(live.join("model_1.pth")).unwrap(), b"new");
+        assert!(!staging.exists());
+
+        std::fs::create_dir_all(&staging).unwrap();
+        std::fs::write(staging.join("model_1.pth"), b"newer").unwrap();
+        swap_in(&staging, &live).unwrap();
+        assert_eq!(std::fs::read(live.join("model_1.pth")).unwrap(), b"newer");
+        assert_eq!(std::fs::read(staging.join("model_1.pth")).unwrap(), b"new");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::api_types::{LabelStudioResponse, TrainingLogEntry};
+use crate::auth;
+use crate::db;
+use crate::drift;
+use crate::exif;
+use crate::labels;
+use crate::replay;
+use crate::request_logger::RequestLogger;
+use crate::samples::{self, NewSample};
+use crate::storage::{self, Area};
+use crate::training;
+
+/// Contributor recorded for samples added from Label Studio annotations.
+const CONTRIBUTOR: &str = "label-studio";
+
+/// A webhook sent by Label Studio. Only the fields used here are read.
+#[derive(Debug, Deserialize)]
+pub struct Webhook {
+    pub action: String,
+    pub task: Option<Task>,
+    pub annotation: Option<Annotation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Task {
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub data: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Annotation {
+    pub id: Option<i64>,
+    #[serde(default)]
+    pub result: Vec<AnnotationResult>,
+    /// Set when the annotator skipped the task.
+    #[serde(default)]
+    pub was_cancelled: bool,
+}
+
+/// One region or choice in an annotation.
+#[derive(Debug, Deserialize)]
+pub struct AnnotationResult {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub value: Value,
+}
+
+/// The content hash of a task's image: its `content_hash` field, or else the name of its `image` file without
+/// the extension. Images exported from the training data or kept from predictions are named by their hash,
+/// including when served by Label Studio's local file storage as `/data/local-files/?d=<path>`.
+fn task_hash(data: &Value) -> Option<String> {
+    if let Some(hash) = data.get("content_hash").and_then(Value::as_str) {
+        return Some(hash.to_ascii_lowercase()).filter(|hash| is_hash(hash));
+    }
+    let url = data.get("image").and_then(Value::as_str)?;
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let path = query.split('&').find_map(|pair| pair.strip_prefix("d=")).unwrap_or(path);
+    let name = path.rsplit(['/', '\\']).next()?;
+    let stem = name.split_once('.').map_or(name, |(stem, _)| stem).to_ascii_lowercase();
+    Some(stem).filter(|hash| is_hash(hash))
+}
+
+fn is_hash(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The image hash and label an annotation gives, from its first choice. Returns `None` for webhooks that don't
+/// label anything, e.g. deletions and skipped tasks, and an error for annotations that can't be applied.
+fn labeled_image(webhook: &Webhook) -> Result<Option<(String, String)>, String> {
+    if !matches!(webhook.action.as_str(), "ANNOTATION_CREATED" | "ANNOTATION_UPDATED") {
+        return Ok(None);
+    }
+    let (Some(task), Some(annotation)) = (&webhook.task, &webhook.annotation) else {
+        return Err("Annotation webhooks need a task and an annotation".to_string());
+    };
+    if annotation.was_cancelled {
+        return Ok(None);
+    }
+
+    let label = annotation
+        .result
+        .iter()
+        .filter(|result| result.kind == "choices")
+        .find_map(|result| result.value.get("choices")?.get(0)?.as_str())
+        .ok_or("Annotation has no choice to use as a label")?;
+    let hash = task_hash(&task.data).ok_or("Task has no image named by its content hash")?;
+    Ok(Some((hash, label.to_string())))
+}
+
+/// Label Studio webhook route handler bringing annotations back into the training dataset.
+/// Point a Label Studio webhook at it with an `Authorization: Bearer <ADMIN_TOKEN>` header, and use a
+/// single-choice labeling config whose choices are the label names. An annotation counts as a reviewer's
+/// decision: samples with the annotated image are approved with its label, and a kept prediction image becomes
+/// a new approved sample. Other webhooks are acknowledged and ignored. Requires the admin token.
+pub async fn webhook_route(req: rusty_api::HttpRequest, body: rusty_api::web::Json<Webhook>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info(format!("Received request to /integrations/label-studio ({})", body.action));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized Label Studio webhook");
+        return resp;
+    }
+
+    let (hash, label) = match labeled_image(&body) {
+        Ok(Some(labeled)) => labeled,
+        Ok(None) => {
+            return rusty_api::HttpResponse::Ok().json(LabelStudioResponse {
+                status: "ignored",
+                action: body.action.clone(),
+                label: None,
+                sample_ids: None,
+                created: None,
+            });
+        }
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::BadRequest().body(message);
+        }
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match labels::names(pool).await {
+        Ok(names) if names.contains(&label) => {}
+        Ok(names) => {
+            logger.error(format!("Invalid label: {}", label));
+            return rusty_api::HttpResponse::BadRequest().body(labels::invalid_label_message("Label must be one of:", &names));
+        }
+        Err(e) => {
+            logger.error(format!("Failed to load labels: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    }
+
+    let mut sample_ids = match samples::with_hash(pool, &hash).await {
+        Ok(sample_ids) => sample_ids,
+        Err(e) => {
+            logger.error(format!("Failed to look up samples: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    // An image no sample refers to may be one kept from a prediction
+    let mut created = false;
+    if sample_ids.is_empty() {
+        match storage::get().exists(Area::PredictionImages, &training::image_key(&hash)) {
+            Ok(true) => {}
+            Ok(false) => {
+                logger.error(format!("No training sample or kept prediction image has hash {}", hash));
+                return rusty_api::HttpResponse::NotFound()
+                    .body(format!("No training sample or kept prediction image has hash {}", hash));
+            }
+            Err(e) => {
+                logger.error(format!("Failed to look up prediction image: {}", e));
+                return rusty_api::HttpResponse::InternalServerError().body(format!("Failed to look up prediction image: {}", e));
+            }
+        }
+        let image_bytes = match replay::read_image(&hash) {
+            Ok(image_bytes) => image_bytes,
+            Err(message) => {
+                logger.error(&message);
+                return rusty_api::HttpResponse::InternalServerError().body(message);
+            }
+        };
+
+        let capture = exif::read(&image_bytes);
+        let image = match training::write_image(&image_bytes) {
+            Ok(image) => image,
+            Err(message) => {
+                logger.error(&message);
+                return rusty_api::HttpResponse::InternalServerError().body(message);
+            }
+        };
+
+        // The same prediction image may have been annotated before, and stored under its stripped hash
+        sample_ids = match samples::with_hash(pool, &image.content_hash).await {
+            Ok(sample_ids) => sample_ids,
+            Err(e) => {
+                logger.error(format!("Failed to look up samples: {}", e));
+                return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+            }
+        };
+        if sample_ids.is_empty() {
+            let sample = NewSample {
+                request_id,
+                contributor: Some(CONTRIBUTOR),
+                label: &label,
+                filename: &image.filename,
+                file_path: &image.file_path,
+                content_hash: &image.content_hash,
+                image_size_bytes: image_bytes.len(),
+                prediction_id: None,
+            };
+            let sample_id = match samples::record(pool, &sample).await {
+                Ok(id) => id,
+                Err(e) => {
+                    logger.error(format!("Failed to record training sample: {}", e));
+                    // Don't leave behind an image no sample refers to
+                    if image.created {
+                        training::remove_image(&image.key).ok();
+                        training::remove_original(&image.content_hash).ok();
+                    }
+                    return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+                }
+            };
+            drift::observe_training(pool, &image.content_hash, &image_bytes, &logger).await;
+            exif::observe(pool, sample_id, capture.as_ref(), &logger).await;
+
+            let log_entry = TrainingLogEntry {
+                timestamp: Utc::now().to_rfc3339(),
+                request_id,
+                label: label.clone(),
+                contributor: Some(CONTRIBUTOR.to_string()),
+                filename: image.filename.clone(),
+                file_path: image.file_path.clone(),
+                content_hash: Some(image.content_hash.clone()),
+                image_size_bytes: image_bytes.len() as u64,
+                stored_size_bytes: Some(image.stored_bytes as u64),
+                label_studio_task: body.task.as_ref().and_then(|task| task.id),
+                capture,
+                ..TrainingLogEntry::default()
+            };
+            if let Err(e) = training::append_log(&log_entry) {
+                logger.error(format!("Failed to write to training log: {}", e));
+            }
+            sample_ids.push(sample_id);
+            created = true;
+        }
+    }
+
+    for &sample_id in &sample_ids {
+        if let Err(e) = samples::review(pool, sample_id, true, Some(&label)).await {
+            logger.error(format!("Failed to record review: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    }
+
+    logger.info(format!(
+        "Label Studio annotation {} labeled {} as {}: samples {:?}",
+        body.annotation.as_ref().and_then(|annotation| annotation.id).unwrap_or_default(),
+        hash,
+        label,
+        sample_ids
+    ));
+    rusty_api::HttpResponse::Ok().json(LabelStudioResponse {
+        status: "success",
+        action: body.action.clone(),
+        label: Some(label),
+        sample_ids: Some(sample_ids),
+        created: Some(created),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn annotations_give_the_image_hash_and_chosen_label() {
+        let hash = "b3f378033849bf19e8765ccc2d1923faa45357dcd3deba55100f350c05613e53";
+        let webhook = |action: &str, image: &str, was_cancelled: bool| -> Webhook {
+            serde_json::from_value(json!({
+                "action": action,
+                "task": { "id": 7, "data": { "image": image } },
+                "annotation": {
+                    "id": 3,
+                    "was_cancelled": was_cancelled,
+                    "result": [
+                        { "type": "rectanglelabels", "value": { "rectanglelabels": ["seam"] } },
+                        { "type": "choices", "value": { "choices": ["not_match_ready"] } }
+                    ]
+                },
+                "project": { "id": 1 }
+            }))
+            .unwrap()
+        };
+        let labeled = |webhook: Webhook| labeled_image(&webhook);
+        let expected = Ok(Some((hash.to_string(), "not_match_ready".to_string())));
+
+        assert_eq!(labeled(webhook("ANNOTATION_CREATED", &format!("https://club.local/images/{}.jpg", hash), false)), expected);
+        assert_eq!(labeled(webhook("ANNOTATION_UPDATED", &format!("/data/local-files/?d=export/images/{}.JPG", hash), false)), expected);
+        assert_eq!(labeled(webhook("ANNOTATION_CREATED", &format!("/images/{}.jpg", hash), true)), Ok(None));
+        assert_eq!(labeled(webhook("ANNOTATIONS_DELETED", "", false)), Ok(None));
+        assert!(labeled(webhook("ANNOTATION_CREATED", "/images/ball.jpg", false)).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use chrono::Utc;
+use serde::Deserialize;
+
+pub use crate::api_types::Label;
+use crate::api_types::{LabelRemovedResponse, LabelResponse, LabelsResponse};
+use crate::auth;
+use crate::db;
+use crate::i18n::Locale;
+use crate::request_logger::RequestLogger;
+
+/// Where the training job writes the label taxonomy for `train.py`, relative to the backend directory.
+const TRAINING_LABELS_PATH: &str = "nn-classifier/labels.json";
+
+/// Longest label name accepted.
+const MAX_NAME_LEN: usize = 64;
+
+/// Labels the strictness profiles decide between, which can't be removed. See `Profile::decide` and `Locale::verdict`.
+pub const PROFILE_LABELS: [&str; 2] = ["match_ready", "not_match_ready"];
+
+/// Every label in the taxonomy, in display order.
+pub async fn list(pool: &db::Pool) -> Result<Vec<Label>, sqlx::Error> {
+    sqlx::query_as::<_, Label>("SELECT name, description, display_order FROM labels ORDER BY display_order, name")
+        .fetch_all(pool)
+        .await
+}
+
+/// Names of every label in the taxonomy, in display order.
+pub async fn names(pool: &db::Pool) -> Result<Vec<String>, sqlx::Error> {
+    Ok(list(pool).await?.into_iter().map(|label| label.name).collect())
+}
+
+/// Looks up a label by name.
+pub async fn find(pool: &db::Pool, name: &str) -> Result<Option<Label>, sqlx::Error> {
+    sqlx::query_as::<_, Label>("SELECT name, description, display_order FROM labels WHERE name = $1")
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+}
+
+/// The verdict and recommendation shown for a prediction, in the client's language. Other labels than those in
+/// `PROFILE_LABELS` are named by their description, or their name if they have none. Failures are only logged.
+pub async fn verdict(locale: Locale, prediction: &str, logger: &RequestLogger) -> (String, &'static str) {
+    if PROFILE_LABELS.contains(&prediction) || prediction == "unknown" {
+        return locale.verdict(prediction, None);
+    }
+    let label = match db::pool().await {
+        Ok(pool) => find(pool, prediction).await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let description = match label {
+        Ok(label) => label.map(|label| if label.description.is_empty() { label.name } else { label.description }),
+        Err(message) => {
+            logger.error(format!("Failed to look up label {}: {}", prediction, message));
+            None
+        }
+    };
+    locale.verdict(prediction, description.as_deref())
+}
+
+/// Checks that a new label's name can be used as a dataset folder and a prediction code:
+/// lowercase letters, digits and underscores, starting with a letter.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!("name must be between 1 and {} characters", MAX_NAME_LEN));
+    }
+    if !name.starts_with(|c: char| c.is_ascii_lowercase())
+        || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err("name must be lowercase letters, digits and underscores, starting with a letter".to_string());
+    }
+    if name == "unknown" {
+        return Err("'unknown' is reserved for predictions the models couldn't make".to_string());
+    }
+    Ok(())
+}
+
+/// Message rejecting a label that isn't in the taxonomy, listing the ones that are.
+pub fn invalid_label_message(prefix: &str, names: &[String]) -> String {
+    format!("{} {}", prefix, names.join(", "))
+}
+
+/// Adds a label to the taxonomy. Returns `None` if one with the same name already exists.
+pub async fn create(pool: &db::Pool, name: &str, description: &str, display_order: i64) -> Result<Option<Label>, sqlx::Error> {
+    sqlx::query_as::<_, Label>(
+        "INSERT INTO labels (name, description, display_order) VALUES ($1, $2, $3)
+         ON CONFLICT (name) DO NOTHING
+         RETURNING name, description, display_order"
+    )
+    .bind(name)
+    .bind(description)
+    .bind(display_order)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Changes a label's description and position, keeping whichever isn't given.
+/// Returns `None` if there is no such label.
+pub async fn update(pool: &db::Pool, name: &str, description: Option<&str>, display_order: Option<i64>) -> Result<Option<Label>, sqlx::Error> {
+    sqlx::query_as::<_, Label>(
+        "UPDATE labels SET description = COALESCE($2, description), display_order = COALESCE($3, display_order)
+         WHERE name = $1
+         RETURNING name, description, display_order"
+    )
+    .bind(name)
+    .bind(description)
+    .bind(display_order)
+    .fetch_optional(pool)
+    .await
+}
+
+/// What happened when removing a label.
+#[derive(Debug, PartialEq)]
+pub enum RemoveOutcome {
+    Removed,
+    /// Samples still have the label, as submitted or after review.
+    InUse(i64),
+    /// The label is one of `PROFILE_LABELS`.
+    Required,
+    NotFound,
+}
+
+/// Removes a label from the taxonomy, unless the profiles need it or samples still have it.
+/// The profile labels always remain, so the models have at least two classes to tell apart.
+pub async fn remove(pool: &db::Pool, name: &str) -> Result<RemoveOutcome, sqlx::Error> {
+    if PROFILE_LABELS.contains(&name) {
+        return Ok(RemoveOutcome::Required);
+    }
+    let mut tx = pool.begin().await?;
+    let in_use: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM samples WHERE label = $1 OR reviewed_label = $1")
+        .bind(name)
+        .fetch_one(&mut *tx)
+        .await?;
+    if in_use > 0 {
+        return Ok(RemoveOutcome::InUse(in_use));
+    }
+
+    let removed = sqlx::query("DELETE FROM labels WHERE name = $1").bind(name).execute(&mut *tx).await?.rows_affected();
+    tx.commit().await?;
+    Ok(if removed > 0 { RemoveOutcome::Removed } else { RemoveOutcome::NotFound })
+}
+
+/// Writes the taxonomy where `train.py` reads it, so the models learn exactly its labels,
+/// each from the dataset folder of the same name.
+pub async fn write_for_training(pool: &db::Pool) -> Result<(), String> {
+    let names = names(pool).await.map_err(|e| format!("Failed to load labels: {}", e))?;
+    let json = serde_json::to_vec_pretty(&names).map_err(|e| format!("Failed to serialize labels: {}", e))?;
+    std::fs::write(TRAINING_LABELS_PATH, json).map_err(|e| format!("Failed to write {}: {}", TRAINING_LABELS_PATH, e))
+}
+
+/// List route handler returning the label taxonomy in display order, e.g. for the training tool to offer.
+pub async fn list_route() -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /labels");
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match list(pool).await {
+        Ok(labels) => rusty_api::HttpResponse::Ok().json(LabelsResponse { labels }),
+        Err(e) => {
+            logger.error(format!("Failed to list labels: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Body accepted by `/admin/labels/new`.
+#[derive(Debug, Deserialize)]
+pub struct CreateInput {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Defaults to after every existing label.
+    pub display_order: Option<i64>,
+}
+
+/// Body accepted by `PUT /admin/labels/{name}`.
+#[derive(Debug, Deserialize)]
+pub struct UpdateInput {
+    pub description: Option<String>,
+    pub display_order: Option<i64>,
+}
+
+/// Create route handler adding a label to the taxonomy. Requires the admin token.
+pub async fn create_route(req: rusty_api::HttpRequest, body: rusty_api::web::Json<CreateInput>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/labels/new");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized label creation");
+        return resp;
+    }
+
+    let name = body.name.trim();
+    if let Err(message) = validate_name(name) {
+        logger.error(format!("Rejected label '{}': {}", name, message));
+        return rusty_api::HttpResponse::BadRequest().body(message);
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let display_order = match body.display_order {
+        Some(display_order) => display_order,
+        None => match list(pool).await {
+            Ok(labels) => labels.iter().map(|label| label.display_order).max().unwrap_or(0) + 1,
+            Err(e) => {
+                logger.error(format!("Failed to list labels: {}", e));
+                return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+            }
+        },
+    };
+
+    match create(pool, name, body.description.trim(), display_order).await {
+        Ok(Some(label)) => {
+            logger.info(format!("Added label {}", label.name));
+            rusty_api::HttpResponse::Ok().json(LabelResponse { status: "success", label })
+        }
+        Ok(None) => rusty_api::HttpResponse::Conflict().body(format!("Label '{}' already exists", name)),
+        Err(e) => {
+            logger.error(format!("Failed to create label: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Update route handler changing a label's description or display order. Labels can't be renamed,
+/// since samples and predictions refer to them by name. Requires the admin token.
+pub async fn update_route(
+    req: rusty_api::HttpRequest,
+    path: rusty_api::web::Path<String>,
+    body: rusty_api::web::Json<UpdateInput>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let name = path.into_inner();
+
+    logger.info(format!("Received request to update label {}", name));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized label update");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match update(pool, &name, body.description.as_deref().map(str::trim), body.display_order).await {
+        Ok(Some(label)) => {
+            logger.info(format!("Updated label {}", name));
+            rusty_api::HttpResponse::Ok().json(LabelResponse { status: "success", label })
+        }
+        Ok(None) => rusty_api::HttpResponse::NotFound().body(format!("Label '{}' not found", name)),
+        Err(e) => {
+            logger.error(format!("Failed to update label {}: {}", name, e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Remove route handler taking a label out of the taxonomy. Labels that samples still have can't be removed;
+/// relabel those samples through review first. Requires the admin token.
+pub async fn remove_route(req: rusty_api::HttpRequest, path: rusty_api::web::Path<String>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let name = path.into_inner();
+
+    logger.info(format!("Received request to remove label {}", name));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized label removal");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match remove(pool, &name).await {
+        Ok(RemoveOutcome::Removed) => {
+            logger.info(format!("Removed label {}", name));
+            rusty_api::HttpResponse::Ok().json(LabelRemovedResponse { status: "success", name })
+        }
+        Ok(RemoveOutcome::InUse(samples)) => rusty_api::HttpResponse::Conflict()
+            .body(format!("Label '{}' is still used by {} sample(s)", name, samples)),
+        Ok(RemoveOutcome::Required) => rusty_api::HttpResponse::Conflict()
+            .body(format!("Label '{}' is needed by the strictness profiles and can't be removed", name)),
+        Ok(RemoveOutcome::NotFound) => rusty_api::HttpResponse::NotFound().body(format!("Label '{}' not found", name)),
+        Err(e) => {
+            logger.error(format!("Failed to remove label {}: {}", name, e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::{self, NewSample};
+
+    #[test]
+    fn label_names_must_work_as_folders_and_codes() {
+        assert!(validate_name("needs_repair").is_ok());
+        assert!(validate_name("Match Ready").is_err());
+        assert!(validate_name("../models").is_err());
+        assert!(validate_name("2nd_xi").is_err());
+        assert!(validate_name("unknown").is_err());
+        assert!(validate_name("").is_err());
+    }
+
+    #[tokio::test]
+    async fn labels_in_use_or_needed_are_kept() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        assert_eq!(names(&pool).await.unwrap(), vec!["match_ready", "not_match_ready"]);
+
+        create(&pool, "needs_repair", "Seam needs restitching.", 0).await.unwrap().unwrap();
+        assert!(create(&pool, "needs_repair", "", 5).await.unwrap().is_none());
+        assert_eq!(names(&pool).await.unwrap()[0], "needs_repair");
+
+        let sample = NewSample { label: "needs_repair", ..NewSample::example() };
+        samples::record(&pool, &sample).await.unwrap();
+        assert_eq!(remove(&pool, "needs_repair").await.unwrap(), RemoveOutcome::InUse(1));
+        assert_eq!(remove(&pool, "match_ready").await.unwrap(), RemoveOutcome::Required);
+        assert_eq!(remove(&pool, "not_match_ready").await.unwrap(), RemoveOutcome::Required);
+        create(&pool, "worn", "", 4).await.unwrap().unwrap();
+        assert_eq!(remove(&pool, "worn").await.unwrap(), RemoveOutcome::Removed);
+        assert_eq!(remove(&pool, "missing").await.unwrap(), RemoveOutcome::NotFound);
+    }
+}
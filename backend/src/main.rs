@@ -1,23 +1,139 @@
+mod abuse;
+mod analytics;
+mod api_types;
+mod api_keys;
+mod archive;
+mod auth;
+mod backup;
+mod balls;
+mod cache;
+mod classifier;
+mod config;
+mod contributors;
+mod corrections;
+mod dataset;
+mod db;
+mod detect;
+mod drift;
+mod encryption;
+mod enhance;
+mod exif;
+mod faults;
+mod flags;
+mod health;
+mod heif;
+mod i18n;
+mod jobs;
+mod label_studio;
+mod labels;
+mod metrics;
+mod model_card;
+mod models;
+mod predictions;
+mod profiles;
+mod quality;
+mod rate_limit;
+mod replay;
+mod report;
 mod request_logger;
+mod samples;
+mod server;
+mod settings;
+mod storage;
+mod summary;
+mod sync;
+mod systemd;
+mod training;
+mod uploads;
+mod version;
 
-use rusty_api;
 use actix_multipart::Multipart;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
 use futures_util::StreamExt as _;
 use bytes::BytesMut;
 use chrono::Utc;
-use std::process::Command;
-use std::fs;
-use std::path::Path;
-use serde_json::{json, Value};
-use actix_web::{http};
-use regex::Regex;
+use std::process::ExitCode;
 
+use api_types::{BallPrediction, CompareModelsResponse, ModelComparison, PredictMultiResponse, PredictResponse, SecondOpinion, TrainingLogEntry, TrainingResponse};
+
+use i18n::{Locale, Message};
 use request_logger::RequestLogger;
 
-/// Parses the multipart payload, extracting the image data and optional label.
-async fn parse_multipart(mut payload: Multipart) -> Result<(BytesMut, Option<String>), rusty_api::HttpResponse> {
-    let mut image_bytes = BytesMut::new();
-    let mut label = None;
+/// Returns the value of a query parameter, if it was given.
+fn query_param(req: &rusty_api::HttpRequest, name: &str) -> Option<String> {
+    rusty_api::web::Query::<std::collections::HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.get(name).cloned())
+}
+
+/// Returns whether a boolean query parameter such as `?second_opinion=true` is set.
+fn query_flag(req: &rusty_api::HttpRequest, name: &str) -> bool {
+    query_param(req, name).is_some_and(|v| v == "true" || v == "1")
+}
+
+/// Fields submitted with a training image.
+struct TrainingUpload {
+    image_bytes: BytesMut,
+    label: Option<String>,
+    contributor: Option<String>,
+}
+
+/// Largest multipart field accepted, in bytes. Phone photos are well under this.
+const MAX_FIELD_BYTES: usize = 25 * 1024 * 1024;
+
+/// Reads the full contents of a multipart field, rejecting fields over `MAX_FIELD_BYTES` with `413`.
+async fn read_field(field: &mut actix_multipart::Field) -> Result<BytesMut, rusty_api::HttpResponse> {
+    let mut data = BytesMut::new();
+    while let Some(chunk) = field.next().await {
+        let chunk = match chunk {
+            Ok(d) => d,
+            Err(e) => return Err(rusty_api::HttpResponse::InternalServerError().body(format!("Read error: {e}"))),
+        };
+        if data.len() + chunk.len() > MAX_FIELD_BYTES {
+            return Err(rusty_api::HttpResponse::PayloadTooLarge()
+                .body(format!("Field '{}' is larger than {} MB", field.name(), MAX_FIELD_BYTES / (1024 * 1024))));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// Checks that an upload looks like an image in a supported format, from its first bytes.
+fn check_image(image_bytes: &[u8], locale: Locale) -> Result<(), rusty_api::HttpResponse> {
+    match image::guess_format(image_bytes) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(rusty_api::HttpResponse::BadRequest().body(locale.text(Message::NotAnImage))),
+    }
+}
+
+/// Converts HEIC/HEIF photos, which iPhones take by default, to JPEG so every later step can read them,
+/// then checks that the upload is an image.
+fn prepare_image(image_bytes: BytesMut, locale: Locale) -> Result<BytesMut, rusty_api::HttpResponse> {
+    let image_bytes = if heif::is_heif(&image_bytes) {
+        match heif::to_jpeg(&image_bytes, &rand::random::<u64>().to_string()) {
+            Ok(jpeg) => BytesMut::from(&jpeg[..]),
+            Err(heif::ConvertError::Unreadable(_)) => {
+                return Err(rusty_api::HttpResponse::BadRequest().body(locale.text(Message::NotAnImage)));
+            }
+            Err(heif::ConvertError::Unavailable(message)) => {
+                return Err(rusty_api::HttpResponse::InternalServerError().body(message));
+            }
+        }
+    } else {
+        image_bytes
+    };
+    check_image(&image_bytes, locale)?;
+    Ok(image_bytes)
+}
+
+/// Parses the multipart payload, extracting the image data, optional label and optional contributor.
+async fn parse_multipart(mut payload: Multipart, locale: Locale) -> Result<TrainingUpload, rusty_api::HttpResponse> {
+    let mut upload = TrainingUpload {
+        image_bytes: BytesMut::new(),
+        label: None,
+        contributor: None,
+    };
 
     while let Some(item) = payload.next().await {
         let mut field = match item {
@@ -26,25 +142,17 @@ async fn parse_multipart(mut payload: Multipart) -> Result<(BytesMut, Option<Str
         };
 
         match field.name() {
-            "image" => {
-                while let Some(chunk) = field.next().await {
-                    let data = match chunk {
-                        Ok(d) => d,
-                        Err(e) => return Err(rusty_api::HttpResponse::InternalServerError().body(format!("Read error: {e}"))),
-                    };
-                    image_bytes.extend_from_slice(&data);
-                }
-            }
+            "image" => upload.image_bytes = read_field(&mut field).await?,
             "label" => {
-                let mut label_data = BytesMut::new();
-                while let Some(chunk) = field.next().await {
-                    let data = match chunk {
-                        Ok(d) => d,
-                        Err(e) => return Err(rusty_api::HttpResponse::InternalServerError().body(format!("Read error: {e}"))),
-                    };
-                    label_data.extend_from_slice(&data);
+                let label_data = read_field(&mut field).await?;
+                upload.label = Some(String::from_utf8_lossy(&label_data).to_string());
+            }
+            "contributor" => {
+                let contributor_data = read_field(&mut field).await?;
+                let contributor = String::from_utf8_lossy(&contributor_data).trim().to_string();
+                if !contributor.is_empty() {
+                    upload.contributor = Some(contributor);
                 }
-                label = Some(String::from_utf8_lossy(&label_data).to_string());
             }
             _ => {
                 return Err(rusty_api::HttpResponse::BadRequest()
@@ -53,16 +161,26 @@ async fn parse_multipart(mut payload: Multipart) -> Result<(BytesMut, Option<Str
         }
     }
 
-    if image_bytes.is_empty() {
-        return Err(rusty_api::HttpResponse::BadRequest().body("No image data received"));
+    if upload.image_bytes.is_empty() {
+        return Err(rusty_api::HttpResponse::BadRequest().body(locale.text(Message::NoImageReceived)));
     }
+    upload.image_bytes = prepare_image(upload.image_bytes, locale)?;
 
-    Ok((image_bytes, label))
+    Ok(upload)
 }
 
-/// Parses the multipart payload for prediction (image only).
-async fn parse_multipart_predict(mut payload: Multipart) -> Result<BytesMut, rusty_api::HttpResponse> {
-    let mut image_bytes = BytesMut::new();
+/// Fields submitted with an image for prediction.
+struct PredictUpload {
+    image_bytes: BytesMut,
+    ball_id: Option<String>,
+}
+
+/// Parses the multipart payload for prediction, extracting the image data and optional ball ID.
+async fn parse_multipart_predict(mut payload: Multipart, locale: Locale) -> Result<PredictUpload, rusty_api::HttpResponse> {
+    let mut upload = PredictUpload {
+        image_bytes: BytesMut::new(),
+        ball_id: None,
+    };
 
     while let Some(item) = payload.next().await {
         let mut field = match item {
@@ -71,13 +189,12 @@ async fn parse_multipart_predict(mut payload: Multipart) -> Result<BytesMut, rus
         };
 
         match field.name() {
-            "image" => {
-                while let Some(chunk) = field.next().await {
-                    let data = match chunk {
-                        Ok(d) => d,
-                        Err(e) => return Err(rusty_api::HttpResponse::InternalServerError().body(format!("Read error: {e}"))),
-                    };
-                    image_bytes.extend_from_slice(&data);
+            "image" => upload.image_bytes = read_field(&mut field).await?,
+            "ball_id" => {
+                let ball_id_data = read_field(&mut field).await?;
+                let ball_id = String::from_utf8_lossy(&ball_id_data).trim().to_string();
+                if !ball_id.is_empty() {
+                    upload.ball_id = Some(ball_id);
                 }
             }
             _ => {
@@ -87,26 +204,49 @@ async fn parse_multipart_predict(mut payload: Multipart) -> Result<BytesMut, rus
         }
     }
 
-    if image_bytes.is_empty() {
-        return Err(rusty_api::HttpResponse::BadRequest().body("No image data received"));
+    if upload.image_bytes.is_empty() {
+        return Err(rusty_api::HttpResponse::BadRequest().body(locale.text(Message::NoImageReceived)));
     }
+    upload.image_bytes = prepare_image(upload.image_bytes, locale)?;
 
-    Ok(image_bytes)
+    Ok(upload)
 }
 
 /// Training route handler for saving labeled cricket ball images.
-/// Accepts multipart form-data with "image" and "label" fields.
-async fn training_route(payload: Multipart) -> rusty_api::HttpResponse {
+/// Accepts multipart form-data with "image" and "label" fields, and an optional "contributor" field.
+async fn training_route(req: rusty_api::HttpRequest, payload: rusty_api::web::Payload) -> rusty_api::HttpResponse {
     let request_id = Utc::now().timestamp_millis();
     let logger = RequestLogger::new(request_id);
+    let locale = Locale::from_request(&req);
 
     logger.info("Received request to /training");
 
+    if let Err(resp) = abuse::check(&req, &logger).await {
+        return resp;
+    }
+    if let Err(resp) = rate_limit::check_upload(&req, &logger).await {
+        return resp;
+    }
+    let _upload = match rate_limit::acquire_upload(&req, &logger) {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    let api_key = match api_keys::authenticate(&req, &logger).await {
+        Ok(api_key) => api_key,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = api_keys::require_scope(api_key.as_ref(), "training", &logger) {
+        return resp;
+    }
+
     // Parse multipart payload
-    let (image_bytes, label) = match parse_multipart(payload).await {
-        Ok((bytes, lbl)) => (bytes, lbl),
+    let payload = uploads::multipart(&req, payload, &logger).await;
+    let TrainingUpload { image_bytes, label, contributor } = match parse_multipart(payload, locale).await {
+        Ok(upload) => upload,
         Err(resp) => {
             logger.error("Failed to parse multipart payload");
+            abuse::record_rejection(&req, &logger).await;
             return resp;
         },
     };
@@ -115,85 +255,129 @@ async fn training_route(payload: Multipart) -> rusty_api::HttpResponse {
         Some(l) => l,
         None => {
             logger.error("No label provided");
-            return rusty_api::HttpResponse::BadRequest().body("Label is required for training data");
+            abuse::record_rejection(&req, &logger).await;
+            return rusty_api::HttpResponse::BadRequest().body(locale.text(Message::LabelRequired));
         }
     };
 
-    // Validate label
-    if label != "match_ready" && label != "not_match_ready" {
-        logger.error(format!("Invalid label: {}", label));
-        return rusty_api::HttpResponse::BadRequest()
-            .body("Label must be either 'match_ready' or 'not_match_ready'");
+    // Validate label against the taxonomy
+    let taxonomy = match db::pool_for_request(&logger).await {
+        Ok(pool) => labels::names(pool).await,
+        Err(resp) => return resp,
+    };
+    match taxonomy {
+        Ok(names) if names.contains(&label) => {}
+        Ok(names) => {
+            logger.error(format!("Invalid label: {}", label));
+            abuse::record_rejection(&req, &logger).await;
+            return rusty_api::HttpResponse::BadRequest()
+                .body(labels::invalid_label_message(locale.text(Message::InvalidLabel), &names));
+        }
+        Err(e) => {
+            logger.error(format!("Failed to load labels: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
     }
+    abuse::record_success(&req, &logger).await;
 
     logger.info(format!("Training image received: {} bytes, label: {}", image_bytes.len(), label));
 
-    // Create training data directory structure
-    let training_dir = "training_data";
-    let label_dir = format!("{}/{}", training_dir, label);
-    
-    // Create directories if they don't exist
-    if let Err(e) = fs::create_dir_all(&label_dir) {
-        logger.error(format!("Failed to create training directory: {}", e));
-        return rusty_api::HttpResponse::InternalServerError()
-            .body(format!("Failed to create training directory: {}", e));
-    }
+    // Check photo quality before it enters the dataset
+    let quality_mode = settings::current(&logger).await.quality_mode;
+    let quality_warnings = match quality::precheck(&image_bytes, quality_mode, locale, &logger) {
+        Ok(issues) => issues,
+        Err(resp) => {
+            logger.error("Training image rejected by quality check");
+            return resp;
+        }
+    };
 
-    // Generate unique filename with timestamp
-    let timestamp = Utc::now().format("%Y%m%d_%H%M%S_%3f");
-    let filename = format!("cricket_ball_{}_{}.jpg", timestamp, request_id);
-    let file_path = format!("{}/{}", label_dir, filename);
+    // Read how the photo was taken before its metadata is stripped from the stored image
+    let capture = exif::read(&image_bytes);
 
     // Write image to training directory
-    if let Err(e) = fs::write(&file_path, &image_bytes) {
-        logger.error(format!("Failed to write training image: {}", e));
-        return rusty_api::HttpResponse::InternalServerError()
-            .body(format!("Failed to write training image: {}", e));
-    }
+    let written = faults::for_request(&req, &logger)
+        .check_write()
+        .map_err(|e| format!("Failed to write training image: {}", e))
+        .and_then(|()| training::write_image(&image_bytes));
+    let training::SavedImage { content_hash, filename, file_path, stored_bytes, .. } = match written {
+        Ok(saved) => saved,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::InternalServerError().body(message);
+        }
+    };
 
-    logger.info(format!("Training image saved: {}", file_path));
+    logger.info(format!("Training image saved: {} ({} of {} bytes)", file_path, stored_bytes, image_bytes.len()));
+
+    // Record the sample for review and contributor statistics
+    let sample = samples::NewSample {
+        request_id,
+        contributor: contributor.as_deref(),
+        label: &label,
+        filename: &filename,
+        file_path: &file_path,
+        content_hash: &content_hash,
+        image_size_bytes: image_bytes.len(),
+        prediction_id: None,
+    };
+    let sample_id = match db::pool().await {
+        Ok(pool) => {
+            drift::observe_training(pool, &content_hash, &image_bytes, &logger).await;
+            match samples::record(pool, &sample).await {
+                Ok(id) => {
+                    exif::observe(pool, id, capture.as_ref(), &logger).await;
+                    Some(id)
+                }
+                Err(e) => {
+                    logger.error(format!("Failed to record training sample: {}", e));
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            logger.error(format!("Failed to open metadata database: {}", e));
+            None
+        }
+    };
 
     // Log training data submission for audit trail
-    let log_entry = json!({
-        "timestamp": Utc::now().to_rfc3339(),
-        "request_id": request_id,
-        "label": label,
-        "filename": filename,
-        "file_path": file_path,
-        "image_size_bytes": image_bytes.len()
-    });
+    let log_entry = TrainingLogEntry {
+        timestamp: Utc::now().to_rfc3339(),
+        request_id,
+        label: label.clone(),
+        contributor,
+        filename: filename.clone(),
+        file_path,
+        content_hash: Some(content_hash),
+        image_size_bytes: image_bytes.len() as u64,
+        stored_size_bytes: Some(stored_bytes as u64),
+        capture,
+        ..TrainingLogEntry::default()
+    };
 
-    // Append to training log file
-    let log_file = format!("{}/training_log.jsonl", training_dir);
-    let log_line = format!("{}\n", log_entry.to_string());
-    
-    if let Err(e) = fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_file)
-        .and_then(|mut file| {
-            use std::io::Write;
-            file.write_all(log_line.as_bytes())
-        })
-    {
+    if let Err(e) = training::append_log(&log_entry) {
         logger.error(format!("Failed to write to training log: {}", e));
         // Don't fail the request if logging fails, just log the error
     }
 
     // Return success response
-    let response = json!({
-        "status": "success",
-        "message": "Training data saved successfully",
-        "filename": filename,
-        "label": label,
-        "request_id": request_id
-    });
+    let response = TrainingResponse {
+        status: "success",
+        message: locale.text(Message::TrainingSaved),
+        filename: filename.clone(),
+        label,
+        request_id,
+        sample_id,
+        quality_warnings,
+    };
 
     match serde_json::to_string(&response) {
         Ok(json) => {
             logger.info(format!("Training data saved successfully: {}", filename));
             rusty_api::HttpResponse::Ok()
                 .content_type("application/json")
+                .insert_header(("Content-Language", locale.tag()))
                 .body(json)
         }
         Err(e) => {
@@ -205,79 +389,201 @@ async fn training_route(payload: Multipart) -> rusty_api::HttpResponse {
 }
 
 /// Main route handler for cricket ball prediction.
-/// Accepts multipart form-data with "image" field.
-async fn predict_image_route(payload: Multipart) -> rusty_api::HttpResponse {
+/// Accepts multipart form-data with "image" field and an optional "ball_id" field.
+async fn predict_image_route(req: rusty_api::HttpRequest, payload: rusty_api::web::Payload) -> rusty_api::HttpResponse {
     let request_id = Utc::now().timestamp_millis();
     let logger = RequestLogger::new(request_id);
+    let locale = Locale::from_request(&req);
 
     logger.info("Received request to /predict");
 
+    if let Err(resp) = abuse::check(&req, &logger).await {
+        return resp;
+    }
+    if let Err(resp) = rate_limit::check_upload(&req, &logger).await {
+        return resp;
+    }
+    let _upload = match rate_limit::acquire_upload(&req, &logger) {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    let api_key = match api_keys::authenticate(&req, &logger).await {
+        Ok(api_key) => api_key,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = api_keys::require_scope(api_key.as_ref(), "predict", &logger) {
+        return resp;
+    }
+
+    let settings = settings::current(&logger).await;
+    let profile = match profiles::select(&req, &settings, api_key.as_ref()) {
+        Ok(profile) => profile,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::BadRequest().body(message);
+        }
+    };
+
     // Parse multipart payload
-    let image_bytes = match parse_multipart_predict(payload).await {
-        Ok(bytes) => bytes,
+    let payload = uploads::multipart(&req, payload, &logger).await;
+    let PredictUpload { image_bytes, ball_id } = match parse_multipart_predict(payload, locale).await {
+        Ok(upload) => upload,
         Err(resp) => {
             logger.error("Failed to parse multipart payload");
+            abuse::record_rejection(&req, &logger).await;
             return resp;
         },
     };
 
+    abuse::record_success(&req, &logger).await;
     logger.info(format!("Image received: {} bytes", image_bytes.len()));
 
-    // Create temporary file for the image
-    let temp_path = format!("/tmp/cricket_ball_{}.jpg", request_id);
-    
-    // Write image to temporary file
-    if let Err(e) = fs::write(&temp_path, &image_bytes) {
-        logger.error(format!("Failed to write temporary file: {}", e));
-        return rusty_api::HttpResponse::InternalServerError()
-            .body(format!("Failed to write temporary file: {}", e));
+    // Only registered balls can be linked to a prediction
+    if let Some(ball_id) = &ball_id {
+        let pool = match db::pool_for_request(&logger).await {
+            Ok(pool) => pool,
+            Err(resp) => return resp,
+        };
+        match balls::find(pool, ball_id).await {
+            Ok(Some(_)) => logger.info(format!("Prediction for ball {}", ball_id)),
+            Ok(None) => {
+                logger.error(format!("Unknown ball ID: {}", ball_id));
+                return rusty_api::HttpResponse::BadRequest().body(format!("Ball '{}' is not registered", ball_id));
+            }
+            Err(e) => {
+                logger.error(format!("Failed to look up ball: {}", e));
+                return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+            }
+        }
     }
 
-    logger.info(format!("Temporary file created: {}", temp_path));
-
-    // Call the Python prediction script
-    let output = match Command::new("nn-classifier/venv/bin/python3")
-        .arg("nn-classifier/predict.py")
-        .arg(&temp_path)
-        .current_dir(".")  // Run from backend directory
-        .output()
-    {
-        Ok(output) => output,
-        Err(e) => {
-            logger.error(format!("Failed to execute predict.py: {}", e));
-            // Clean up temp file
-            fs::remove_file(&temp_path).ok();
-            return rusty_api::HttpResponse::InternalServerError()
-                .body(format!("Failed to execute prediction: {}", e));
+    // Check photo quality before spending time on the classifier
+    let quality_warnings = match quality::precheck(&image_bytes, settings.quality_mode, locale, &logger) {
+        Ok(issues) => issues,
+        Err(resp) => {
+            logger.error("Image rejected by quality check");
+            return resp;
         }
     };
 
-    // Clean up temporary file
-    if let Err(e) = fs::remove_file(&temp_path) {
-        logger.error(format!("Failed to clean up temp file: {}", e));
+    // Optionally, and only where the `enhance` flag is on, correct white balance and exposure and sharpen the photo
+    // before the classifier sees it
+    // Images that can't be enhanced are classified as sent
+    let enhanced_image = if query_flag(&req, "enhance") && flags::is_enabled(&settings, flags::ENHANCE, api_key.as_ref()) {
+        enhance::enhance(&image_bytes).map_err(|message| logger.error(&message)).ok()
+    } else {
+        None
+    };
+    let model_input: &[u8] = enhanced_image.as_deref().unwrap_or(&image_bytes);
+
+    // Run the classifier, with the second-opinion model too if requested
+    let (serving_model, second_model) = models::for_request(flags::is_enabled(&settings, flags::CANDIDATE_MODEL, api_key.as_ref()));
+    let mut model_versions = vec![serving_model];
+    if query_flag(&req, "second_opinion") {
+        match second_model {
+            Some(model) => model_versions.push(model),
+            None => {
+                logger.error("Second opinion requested but no second model is configured");
+                return rusty_api::HttpResponse::BadRequest().body("No second-opinion model is configured");
+            }
+        }
     }
 
-    // Check if the command executed successfully
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        logger.error(format!("Prediction script failed: {}", stderr));
-        return rusty_api::HttpResponse::InternalServerError()
-            .body(format!("Prediction failed: {}", stderr));
-    }
+    let options = classifier::Options {
+        tta: flags::is_enabled(&settings, flags::TTA, api_key.as_ref()),
+        uncertainty: query_flag(&req, "uncertainty") && flags::is_enabled(&settings, flags::UNCERTAINTY, api_key.as_ref()),
+        faults: faults::for_request(&req, &logger),
+    };
+    let mut outputs = match classifier::classify_cached(model_input, request_id, &model_versions, options, &logger).await {
+        Ok(outputs) => outputs,
+        Err(message) => return rusty_api::HttpResponse::InternalServerError().body(message),
+    };
+    let output = outputs.remove(0);
+
+    // Apply the strictness profile to the model's verdict
+    let classifier::ClassifierOutput { prediction: model_prediction, confidence, model_version, uncertainty, precision, .. } = output;
+    let prediction = profile.decide(&model_prediction, confidence);
+    let enhanced_image_url = enhanced_image
+        .as_deref()
+        .filter(|_| query_flag(&req, "return_enhanced"))
+        .map(|enhanced| format!("data:image/jpeg;base64,{}", STANDARD.encode(enhanced)));
+
+    // Report the second model's verdict under the same profile, and whether the two agree
+    let second_opinion = outputs.pop().map(|second| {
+        let second_prediction = profile.decide(&second.prediction, second.confidence);
+        logger.info(format!("Second opinion from {}: {}", second.model_version, second_prediction));
+        SecondOpinion {
+            prediction: second_prediction.to_string(),
+            confidence: second.confidence,
+            model_prediction: second.prediction,
+            model_version: second.model_version,
+            uncertainty: second.uncertainty.filter(|_| options.uncertainty),
+        }
+    });
 
-    // Parse the prediction output
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    logger.info("Prediction completed successfully");
+    // Record the prediction for history and exports, keeping the image so it can be replayed
+    // Don't fail the request if recording fails, just log the error
+    let image_hash = replay::keep_image(&image_bytes, &logger);
+    let record = predictions::NewPrediction {
+        request_id,
+        prediction,
+        confidence,
+        image_size_bytes: image_bytes.len(),
+        profile: profile.name,
+        model_prediction: &model_prediction,
+        ball_id: ball_id.as_deref(),
+        model_version: &model_version,
+        second_opinion_model: second_opinion.as_ref().map(|second| second.model_version.as_str()),
+        second_opinion_prediction: second_opinion.as_ref().map(|second| second.prediction.as_str()),
+        image_hash: image_hash.as_deref(),
+    };
+    let prediction_id = match db::pool().await {
+        Ok(pool) => {
+            match predictions::record(pool, &record).await {
+                Ok(id) => {
+                    drift::observe_prediction(pool, id, &image_bytes, &logger).await;
+                    Some(id)
+                }
+                Err(e) => {
+                    logger.error(format!("Failed to record prediction: {}", e));
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            logger.error(format!("Failed to open metadata database: {}", e));
+            None
+        }
+    };
+
+    // Attach human-readable text in the client's language
+    let (verdict, recommendation) = labels::verdict(locale, prediction, &logger).await;
+    let prediction_result = PredictResponse {
+        prediction_id,
+        prediction: prediction.to_string(),
+        agreement: second_opinion.as_ref().map(|second| second.prediction == prediction),
+        confidence,
+        model_prediction,
+        model_version,
+        model_precision: precision,
+        profile: profile.name,
+        ball_id,
+        uncertainty: uncertainty.filter(|_| options.uncertainty),
+        enhanced: enhanced_image.is_some(),
+        enhanced_image: enhanced_image_url,
+        second_opinion,
+        verdict,
+        recommendation,
+        quality_warnings,
+    };
 
-    // Extract prediction results from the output
-    // The predict.py script outputs structured text, so we'll parse it
-    let prediction_result = parse_prediction_output(&stdout);
-    
     match serde_json::to_string(&prediction_result) {
         Ok(json) => {
             logger.info(format!("Returning prediction: {}", json));
             rusty_api::HttpResponse::Ok()
                 .content_type("application/json")
+                .insert_header(("Content-Language", locale.tag()))
                 .body(json)
         }
         Err(e) => {
@@ -288,35 +594,319 @@ async fn predict_image_route(payload: Multipart) -> rusty_api::HttpResponse {
     }
 }
 
-/// Parse the output from predict.py script into a structured JSON response
-fn parse_prediction_output(output: &str) -> Value {
-    let mut prediction = "unknown";
-    let mut confidence = 0.0;
-    // Expect output like: "Prediction: match_ready; Confidence: 0.9876"
-    let re = Regex::new(r"Prediction:\s*(match_ready|not_match_ready);\s*Confidence:\s*([0-9.]+)").unwrap();
-    if let Some(caps) = re.captures(output) {
-        prediction = caps.get(1).map_or("unknown", |m| m.as_str());
-        confidence = caps.get(2).and_then(|m| m.as_str().parse::<f64>().ok()).unwrap_or(0.0);
+/// Multi-ball prediction route handler for photos of several balls, e.g. a whole ball bag.
+/// Accepts the same multipart form-data as `/predict`, without "ball_id", and classifies each ball found separately.
+async fn predict_multi_route(req: rusty_api::HttpRequest, payload: rusty_api::web::Payload) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let locale = Locale::from_request(&req);
+
+    logger.info("Received request to /predict/multi");
+
+    if let Err(resp) = abuse::check(&req, &logger).await {
+        return resp;
+    }
+    if let Err(resp) = rate_limit::check_upload(&req, &logger).await {
+        return resp;
+    }
+    let _upload = match rate_limit::acquire_upload(&req, &logger) {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    let api_key = match api_keys::authenticate(&req, &logger).await {
+        Ok(api_key) => api_key,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = api_keys::require_scope(api_key.as_ref(), "predict", &logger) {
+        return resp;
     }
-    json!({ "prediction": prediction, "confidence": confidence })
+
+    let settings = settings::current(&logger).await;
+    let profile = match profiles::select(&req, &settings, api_key.as_ref()) {
+        Ok(profile) => profile,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::BadRequest().body(message);
+        }
+    };
+
+    let payload = uploads::multipart(&req, payload, &logger).await;
+
+    let PredictUpload { image_bytes, ball_id } = match parse_multipart_predict(payload, locale).await {
+        Ok(upload) => upload,
+        Err(resp) => {
+            logger.error("Failed to parse multipart payload");
+            abuse::record_rejection(&req, &logger).await;
+            return resp;
+        },
+    };
+    if ball_id.is_some() {
+        logger.error("Ball ID sent with a multi-ball photo");
+        abuse::record_rejection(&req, &logger).await;
+        return rusty_api::HttpResponse::BadRequest().body("ball_id can't be used with /predict/multi");
+    }
+    abuse::record_success(&req, &logger).await;
+
+    logger.info(format!("Image received: {} bytes", image_bytes.len()));
+
+    let quality_warnings = match quality::precheck(&image_bytes, settings.quality_mode, locale, &logger) {
+        Ok(issues) => issues,
+        Err(resp) => {
+            logger.error("Image rejected by quality check");
+            return resp;
+        }
+    };
+
+    // Find the balls and classify each one on its own
+    let balls = match detect::crop_balls(&image_bytes) {
+        Ok(balls) => balls,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::BadRequest().body(message);
+        }
+    };
+    logger.info(format!("Found {} ball(s)", balls.len()));
+
+    let (serving_model, _) = models::for_request(flags::is_enabled(&settings, flags::CANDIDATE_MODEL, api_key.as_ref()));
+    let options = classifier::Options {
+        tta: flags::is_enabled(&settings, flags::TTA, api_key.as_ref()),
+        uncertainty: false,
+        faults: faults::for_request(&req, &logger),
+    };
+    let pool = db::pool().await.map_err(|e| logger.error(format!("Failed to open metadata database: {}", e))).ok();
+
+    let mut results = Vec::new();
+    for (index, (bounding_box, ball_image)) in balls.iter().enumerate() {
+        let output = match classifier::classify_cached(ball_image, format!("{}-{}", request_id, index), &[serving_model], options, &logger).await {
+            Ok(mut outputs) => outputs.remove(0),
+            Err(message) => return rusty_api::HttpResponse::InternalServerError().body(message),
+        };
+        let prediction = profile.decide(&output.prediction, output.confidence);
+
+        // Record each ball as its own prediction; don't fail the request if recording fails
+        let mut prediction_id = None;
+        if let Some(pool) = pool {
+            let image_hash = replay::keep_image(ball_image, &logger);
+            let record = predictions::NewPrediction {
+                request_id,
+                prediction,
+                confidence: output.confidence,
+                image_size_bytes: ball_image.len(),
+                profile: profile.name,
+                model_prediction: &output.prediction,
+                ball_id: None,
+                model_version: &output.model_version,
+                second_opinion_model: None,
+                second_opinion_prediction: None,
+                image_hash: image_hash.as_deref(),
+            };
+            match predictions::record(pool, &record).await {
+                Ok(id) => {
+                    drift::observe_prediction(pool, id, ball_image, &logger).await;
+                    prediction_id = Some(id);
+                }
+                Err(e) => logger.error(format!("Failed to record prediction: {}", e)),
+            }
+        }
+
+        let (verdict, recommendation) = labels::verdict(locale, prediction, &logger).await;
+        results.push(BallPrediction {
+            bounding_box: *bounding_box,
+            prediction_id,
+            prediction: prediction.to_string(),
+            confidence: output.confidence,
+            model_prediction: output.prediction,
+            model_version: output.model_version,
+            model_precision: output.precision,
+            verdict,
+            recommendation,
+        });
+    }
+
+    let response = PredictMultiResponse {
+        count: results.len(),
+        balls: results,
+        profile: profile.name,
+        quality_warnings,
+    };
+    logger.info(format!("Returning {} prediction(s)", balls.len()));
+    rusty_api::HttpResponse::Ok()
+        .insert_header(("Content-Language", locale.tag()))
+        .json(response)
+}
+
+/// Model comparison route handler running one image through several model versions, given as `?models=v3,v4`
+/// (every configured version by default), and returning their verdicts side by side, e.g. to see why a new model
+/// disagrees with the old one on a particular ball. Accepts the same multipart form-data as `/predict`, without
+/// "ball_id". Nothing is recorded in the prediction history.
+async fn compare_models_route(req: rusty_api::HttpRequest, payload: rusty_api::web::Payload) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let locale = Locale::from_request(&req);
+
+    logger.info("Received request to /predict/compare-models");
+
+    if let Err(resp) = abuse::check(&req, &logger).await {
+        return resp;
+    }
+    if let Err(resp) = rate_limit::check_upload(&req, &logger).await {
+        return resp;
+    }
+    let _upload = match rate_limit::acquire_upload(&req, &logger) {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    let api_key = match api_keys::authenticate(&req, &logger).await {
+        Ok(api_key) => api_key,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = api_keys::require_scope(api_key.as_ref(), "predict", &logger) {
+        return resp;
+    }
+
+    let model_versions = match query_param(&req, "models") {
+        Some(names) => match models::find_all(&names) {
+            Ok(model_versions) => model_versions,
+            Err(message) => {
+                logger.error(&message);
+                return rusty_api::HttpResponse::BadRequest().body(message);
+            }
+        },
+        None => models::versions().iter().collect(),
+    };
+
+    let settings = settings::current(&logger).await;
+    let profile = match profiles::select(&req, &settings, api_key.as_ref()) {
+        Ok(profile) => profile,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::BadRequest().body(message);
+        }
+    };
+
+    let payload = uploads::multipart(&req, payload, &logger).await;
+
+    let PredictUpload { image_bytes, ball_id } = match parse_multipart_predict(payload, locale).await {
+        Ok(upload) => upload,
+        Err(resp) => {
+            logger.error("Failed to parse multipart payload");
+            abuse::record_rejection(&req, &logger).await;
+            return resp;
+        },
+    };
+    if ball_id.is_some() {
+        logger.error("Ball ID sent for a model comparison");
+        abuse::record_rejection(&req, &logger).await;
+        return rusty_api::HttpResponse::BadRequest().body("ball_id can't be used with /predict/compare-models");
+    }
+    abuse::record_success(&req, &logger).await;
+
+    logger.info(format!("Image received: {} bytes", image_bytes.len()));
+
+    let options = classifier::Options {
+        tta: flags::is_enabled(&settings, flags::TTA, api_key.as_ref()),
+        uncertainty: query_flag(&req, "uncertainty") && flags::is_enabled(&settings, flags::UNCERTAINTY, api_key.as_ref()),
+        faults: faults::for_request(&req, &logger),
+    };
+    let outputs = match classifier::classify_cached(&image_bytes, request_id, &model_versions, options, &logger).await {
+        Ok(outputs) => outputs,
+        Err(message) => return rusty_api::HttpResponse::InternalServerError().body(message),
+    };
+
+    // Decide each model's verdict under the same profile
+    let predictions: Vec<&str> = outputs.iter().map(|output| profile.decide(&output.prediction, output.confidence)).collect();
+    let results: Vec<ModelComparison> = outputs
+        .iter()
+        .zip(&predictions)
+        .map(|(output, prediction)| ModelComparison {
+            model_version: output.model_version.clone(),
+            prediction: prediction.to_string(),
+            confidence: output.confidence,
+            model_prediction: output.prediction.clone(),
+            model_precision: output.precision,
+            uncertainty: output.uncertainty.filter(|_| options.uncertainty),
+        })
+        .collect();
+
+    let agreement = predictions.windows(2).all(|pair| pair[0] == pair[1]);
+    let model_agreement = outputs.windows(2).all(|pair| pair[0].prediction == pair[1].prediction);
+    logger.info(format!("Compared {} model version(s), agreement: {}", outputs.len(), agreement));
+    rusty_api::HttpResponse::Ok().json(CompareModelsResponse {
+        profile: profile.name,
+        models: results,
+        agreement,
+        model_agreement,
+    })
 }
 
 /// Entrypoint: sets up API routes, TLS, CORS, and starts the server.
-fn main() {
+fn main() -> ExitCode {
+    // The crypto provider rusty_api uses, for the server and for outgoing HTTPS requests
+    let _ = rustls::crypto::CryptoProvider::install_default(rustls::crypto::ring::default_provider());
+
+    if std::env::args().nth(1).as_deref() == Some("healthcheck") {
+        return health::healthcheck();
+    }
+
+    // Open the storage backend up front so a misconfigured bucket stops the server from starting
+    storage::get();
+    report::start();
+
     let routes = rusty_api::Routes::new()
         .add_route(rusty_api::Method::POST, "/predict", predict_image_route)
-        .add_route(rusty_api::Method::POST, "/training", training_route);
-
-    rusty_api::Api::new()
-        .certs("cricket-ready.crt", "cricket-ready.key")
-        .rate_limit(3, 20)
-        .bind("0.0.0.0", 49161)
-        .configure_routes(routes)
-        .configure_cors(|| {
-            rusty_api::Cors::default()
-                .allow_any_method()
-                .allow_any_origin()
-                .allow_any_header()
-        })
-        .start();
+        .add_route(rusty_api::Method::POST, "/predict/multi", predict_multi_route)
+        .add_route(rusty_api::Method::POST, "/predict/compare-models", compare_models_route)
+        .add_route(rusty_api::Method::POST, "/training", training_route)
+        .add_route(rusty_api::Method::GET, "/training/log", training::log_route)
+        .add_route(rusty_api::Method::POST, "/uploads", uploads::create_route)
+        .add_route(rusty_api::Method::GET, "/uploads/{id}", uploads::progress_route)
+        .add_route(rusty_api::Method::GET, "/predictions/export", predictions::export_route)
+        .add_route(rusty_api::Method::GET, "/predictions/{id}", predictions::get_route)
+        .add_route(rusty_api::Method::POST, "/predictions/{id}/correct", corrections::correct_route)
+        .add_route(rusty_api::Method::POST, "/samples/{id}/review", samples::review_route)
+        .add_route(rusty_api::Method::GET, "/labels", labels::list_route)
+        .add_route(rusty_api::Method::POST, "/admin/labels/new", labels::create_route)
+        .add_route(rusty_api::Method::PUT, "/admin/labels/{name}", labels::update_route)
+        .add_route(rusty_api::Method::POST, "/admin/labels/{name}/remove", labels::remove_route)
+        .add_route(rusty_api::Method::GET, "/samples/integrity", samples::integrity_route)
+        .add_route(rusty_api::Method::POST, "/admin/samples/bulk", samples::bulk_route)
+        .add_route(rusty_api::Method::GET, "/contributors/stats", contributors::stats_route)
+        .add_route(rusty_api::Method::POST, "/balls", balls::register_route)
+        .add_route(rusty_api::Method::GET, "/balls/{id}/qr", balls::qr_route)
+        .add_route(rusty_api::Method::GET, "/balls/{id}/predictions", balls::history_route)
+        .add_route(rusty_api::Method::GET, "/tags/{tag}", balls::resolve_route)
+        .add_route(rusty_api::Method::POST, "/sync", sync::sync_route)
+        .add_route(rusty_api::Method::POST, "/integrations/label-studio", label_studio::webhook_route)
+        .add_route(rusty_api::Method::GET, "/admin/backup", backup::backup_route)
+        .add_route(rusty_api::Method::POST, "/admin/restore", backup::restore_route)
+        .add_route(rusty_api::Method::GET, "/admin/dataset/anonymized", dataset::export_route)
+        .add_route(rusty_api::Method::POST, "/admin/archive", archive::archive_route)
+        .add_route(rusty_api::Method::POST, "/admin/archives/{id}/rehydrate", archive::rehydrate_route)
+        .add_route(rusty_api::Method::GET, "/admin/summary", summary::summary_route)
+        .add_route(rusty_api::Method::GET, "/admin/config", settings::get_route)
+        .add_route(rusty_api::Method::PUT, "/admin/config", settings::update_route)
+        .add_route(rusty_api::Method::PUT, "/admin/config/{name}", settings::put_route)
+        .add_route(rusty_api::Method::GET, "/admin/faults", faults::get_route)
+        .add_route(rusty_api::Method::PUT, "/admin/faults/active", faults::put_route)
+        .add_route(rusty_api::Method::POST, "/jobs/training", jobs::start_training_route)
+        .add_route(rusty_api::Method::GET, "/jobs", jobs::list_route)
+        .add_route(rusty_api::Method::DELETE, "/jobs/{id}", jobs::cancel_route)
+        .add_route(rusty_api::Method::GET, "/version", version::version_route)
+        .add_route(rusty_api::Method::GET, "/ready", health::ready_route)
+        .add_route(rusty_api::Method::GET, "/models/metrics", metrics::metrics_route)
+        .add_route(rusty_api::Method::GET, "/models/{version}/report", model_card::report_route)
+        .add_route(rusty_api::Method::GET, "/analytics/confidence", analytics::confidence_route)
+        .add_route(rusty_api::Method::GET, "/admin/api-keys", api_keys::list_route)
+        .add_route(rusty_api::Method::POST, "/admin/api-keys/new", api_keys::create_route)
+        .add_route(rusty_api::Method::PUT, "/admin/api-keys/{id}", api_keys::update_route)
+        .add_route(rusty_api::Method::POST, "/admin/api-keys/{id}/rotate", api_keys::rotate_route)
+        .add_route(rusty_api::Method::POST, "/admin/api-keys/{id}/revoke", api_keys::revoke_route)
+        .add_route(rusty_api::Method::POST, "/admin/predictions/{id}/replay", replay::replay_route)
+        .add_route(rusty_api::Method::GET, "/admin/drift", drift::drift_route)
+        .add_route(rusty_api::Method::POST, "/admin/drift/baseline", drift::baseline_route);
+
+    server::run(routes);
+    ExitCode::SUCCESS
 }
\ No newline at end of file
@@ -0,0 +1,199 @@
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+pub use crate::api_types::{Bucket, PeriodStats};
+use crate::api_types::{MetricsResponse, ModelMetrics};
+use crate::classifier;
+use crate::db;
+use crate::models;
+use crate::predictions::format_timestamp;
+use crate::request_logger::RequestLogger;
+
+/// How far back production statistics go unless `days` is given, and the most that can be asked for.
+const DEFAULT_DAYS: u64 = 90;
+const MAX_DAYS: u64 = 730;
+
+impl PeriodStats {
+    /// Adds another period's counts to this one's.
+    fn add(&mut self, other: &PeriodStats) {
+        self.predictions += other.predictions;
+        self.unknown += other.unknown;
+        self.confidence_sum += other.confidence_sum;
+        self.second_opinions += other.second_opinions;
+        self.disagreements += other.disagreements;
+    }
+
+    /// Fills in the rates derived from the counts.
+    fn with_rates(mut self) -> Self {
+        if self.predictions > 0 {
+            self.mean_confidence = Some(self.confidence_sum / self.predictions as f64);
+        }
+        if self.second_opinions > 0 {
+            self.disagreement_rate = Some(self.disagreements as f64 / self.second_opinions as f64);
+        }
+        self
+    }
+}
+
+/// Counts each model version's predictions recorded at or after `since`, by day.
+pub async fn daily_stats(pool: &db::Pool, since: &str) -> Result<Vec<PeriodStats>, sqlx::Error> {
+    sqlx::query_as::<_, PeriodStats>(
+        "SELECT model_version,
+                SUBSTR(created_at, 1, 10) AS period,
+                COUNT(*) AS predictions,
+                SUM(CASE WHEN prediction = 'unknown' THEN 1 ELSE 0 END) AS unknown,
+                SUM(confidence) AS confidence_sum,
+                SUM(CASE WHEN second_opinion_prediction IS NOT NULL THEN 1 ELSE 0 END) AS second_opinions,
+                SUM(CASE WHEN second_opinion_prediction <> prediction THEN 1 ELSE 0 END) AS disagreements
+         FROM predictions
+         WHERE created_at >= $1 AND model_version IS NOT NULL
+         GROUP BY model_version, SUBSTR(created_at, 1, 10)
+         ORDER BY model_version, period"
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await
+}
+
+/// Groups daily statistics into periods, by model version, and fills in their rates.
+/// Weeks start on Monday.
+pub fn group(days: Vec<PeriodStats>, bucket: Bucket) -> BTreeMap<String, Vec<PeriodStats>> {
+    let mut grouped: BTreeMap<String, BTreeMap<String, PeriodStats>> = BTreeMap::new();
+    for mut day in days {
+        if bucket == Bucket::Week {
+            if let Ok(date) = NaiveDate::parse_from_str(&day.period, "%Y-%m-%d") {
+                let monday = date - Days::new(date.weekday().num_days_from_monday() as u64);
+                day.period = monday.to_string();
+            }
+        }
+        grouped
+            .entry(day.model_version.clone())
+            .or_default()
+            .entry(day.period.clone())
+            .and_modify(|period| period.add(&day))
+            .or_insert(day);
+    }
+
+    grouped
+        .into_iter()
+        .map(|(model_version, periods)| (model_version, periods.into_values().map(PeriodStats::with_rates).collect()))
+        .collect()
+}
+
+/// Query parameters accepted by `/models/metrics`.
+#[derive(Debug, Deserialize)]
+pub struct MetricsQuery {
+    /// `day` or `week` (the default).
+    pub bucket: Option<Bucket>,
+    /// How many days of production statistics to include.
+    pub days: Option<u64>,
+}
+
+/// Metrics route handler returning, for each model version, its evaluation results from training
+/// and how it has done in production over time, for charting whether the model is getting better.
+pub async fn metrics_route(query: rusty_api::web::Query<MetricsQuery>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /models/metrics");
+
+    let bucket = query.bucket.unwrap_or(Bucket::Week);
+    let days = query.days.unwrap_or(DEFAULT_DAYS);
+    if days == 0 || days > MAX_DAYS {
+        return rusty_api::HttpResponse::BadRequest().body(format!("days must be between 1 and {}", MAX_DAYS));
+    }
+    let since = Utc::now().date_naive() - Days::new(days - 1);
+    let since = format_timestamp(since.and_hms_opt(0, 0, 0).unwrap().and_utc());
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let mut production = match daily_stats(pool, &since).await {
+        Ok(stats) => group(stats, bucket),
+        Err(e) => {
+            logger.error(format!("Failed to load model metrics: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    // Configured versions first, oldest first, then any that served predictions but have since been removed
+    let active = models::active();
+    let mut versions: Vec<ModelMetrics> = models::versions()
+        .iter()
+        .map(|model| {
+            let eval_metrics = models::eval_metrics(model).unwrap_or_else(|message| {
+                logger.error(&message);
+                None
+            });
+            let calibration = models::calibration(model).unwrap_or_else(|message| {
+                logger.error(&message);
+                None
+            });
+            ModelMetrics {
+                name: model.name.clone(),
+                dir: Some(model.dir.clone()),
+                active: model.name == active.name,
+                eval_metrics,
+                calibration,
+                production: production.remove(&model.name).unwrap_or_default(),
+            }
+        })
+        .collect();
+    versions.extend(production.into_iter().map(|(name, periods)| ModelMetrics {
+        name,
+        dir: None,
+        active: false,
+        eval_metrics: None,
+        calibration: None,
+        production: periods,
+    }));
+
+    rusty_api::HttpResponse::Ok().json(MetricsResponse {
+        bucket,
+        since,
+        inference_device: classifier::active_device(),
+        models: versions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictions::{self, NewPrediction};
+
+    #[tokio::test]
+    async fn production_stats_are_grouped_by_week() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let rows = [
+            ("2024-05-06T10:00:00.000Z", "match_ready", Some("match_ready")),
+            ("2024-05-08T10:00:00.000Z", "match_ready", Some("not_match_ready")),
+            ("2024-05-13T10:00:00.000Z", "unknown", None),
+        ];
+        for (created_at, verdict, second_opinion) in rows {
+            let prediction = NewPrediction {
+                prediction: verdict,
+                confidence: 0.5,
+                model_prediction: verdict,
+                model_version: "v2",
+                second_opinion_model: second_opinion.map(|_| "v1"),
+                second_opinion_prediction: second_opinion,
+                ..NewPrediction::example()
+            };
+            let id = predictions::record(&pool, &prediction).await.unwrap();
+            sqlx::query("UPDATE predictions SET created_at = $1 WHERE id = $2").bind(created_at).bind(id).execute(&pool).await.unwrap();
+        }
+
+        let days = daily_stats(&pool, "2024-05-01T00:00:00.000Z").await.unwrap();
+        assert_eq!(days.len(), 3);
+
+        let weeks = &group(days, Bucket::Week)["v2"];
+        assert_eq!(weeks.iter().map(|w| w.period.as_str()).collect::<Vec<_>>(), vec!["2024-05-06", "2024-05-13"]);
+        assert_eq!((weeks[0].predictions, weeks[0].second_opinions, weeks[0].disagreements), (2, 2, 1));
+        assert_eq!(weeks[0].disagreement_rate, Some(0.5));
+        assert_eq!(weeks[0].mean_confidence, Some(0.5));
+        assert_eq!((weeks[1].unknown, weeks[1].disagreement_rate), (1, None));
+    }
+}
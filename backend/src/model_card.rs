@@ -0,0 +1,148 @@
+use chrono::Utc;
+use std::collections::BTreeMap;
+
+pub use crate::api_types::FailureMode;
+use crate::api_types::{ModelCard, ModelCardCalibration, ModelCardEvaluation, ModelCardTrainingData, ModelCardVersion};
+use crate::db;
+use crate::models;
+use crate::request_logger::RequestLogger;
+
+/// The failure modes of a model version, most frequent first, from predictions corrected through
+/// `/predictions/{id}/correct` whose corrections a reviewer approved.
+pub async fn failure_modes(pool: &db::Pool, model_version: &str) -> Result<Vec<FailureMode>, sqlx::Error> {
+    const CORRECTED: &str = "FROM samples s JOIN predictions p ON p.id = s.prediction_id \
+        WHERE p.model_version = $1 AND s.review_status = 'approved' \
+        AND COALESCE(p.model_prediction, p.prediction) <> COALESCE(s.reviewed_label, s.label)";
+    const PAIR: &str = "COALESCE(p.model_prediction, p.prediction), COALESCE(s.reviewed_label, s.label)";
+
+    let counts: Vec<(String, String, i64)> = sqlx::query_as(&format!("SELECT {0}, COUNT(*) {1} GROUP BY {0}", PAIR, CORRECTED))
+        .bind(model_version)
+        .fetch_all(pool)
+        .await?;
+    let tags: Vec<(String, String, String, i64)> = sqlx::query_as(&format!(
+        "SELECT {0}, t.tag, COUNT(*) {1} GROUP BY {0}, t.tag",
+        PAIR,
+        CORRECTED.replacen("WHERE", "JOIN sample_tags t ON t.sample_id = s.id WHERE", 1)
+    ))
+    .bind(model_version)
+    .fetch_all(pool)
+    .await?;
+
+    let mut modes: Vec<FailureMode> = counts
+        .into_iter()
+        .map(|(predicted, actual, count)| FailureMode { predicted, actual, count, tags: BTreeMap::new() })
+        .collect();
+    for (predicted, actual, tag, count) in tags {
+        if let Some(mode) = modes.iter_mut().find(|m| m.predicted == predicted && m.actual == actual) {
+            mode.tags.insert(tag, count);
+        }
+    }
+    modes.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| (&a.predicted, &a.actual).cmp(&(&b.predicted, &b.actual))));
+    Ok(modes)
+}
+
+/// Model report route handler returning a model card for a configured model version, for leagues auditing
+/// the classifier: the images it was trained on per class, its cross-validation results and per-class
+/// precision and recall, how well its confidences are calibrated, and the mistakes reviewers have confirmed.
+/// Sections recorded by training and calibration are `null` for versions trained before they were.
+pub async fn report_route(path: rusty_api::web::Path<String>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let version = path.into_inner();
+
+    logger.info(format!("Received request to /models/{}/report", version));
+
+    let Some(model) = models::find(&version) else {
+        return rusty_api::HttpResponse::NotFound().body(format!("Unknown model version '{}'", version));
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let failure_modes = match failure_modes(pool, &model.name).await {
+        Ok(modes) => modes,
+        Err(e) => {
+            logger.error(format!("Failed to load failure modes: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+    let eval_metrics = models::eval_metrics(model).unwrap_or_else(|message| {
+        logger.error(&message);
+        None
+    });
+    let calibration = models::calibration(model).unwrap_or_else(|message| {
+        logger.error(&message);
+        None
+    });
+    let per_class = calibration.as_ref().and_then(|calibration| calibration.per_class.clone());
+
+    rusty_api::HttpResponse::Ok().json(ModelCard {
+        model: ModelCardVersion { name: model.name.clone(), active: model.name == models::active().name },
+        training_data: eval_metrics.as_ref().and_then(|metrics| metrics.images_per_class.clone()).map(|images_per_class| {
+            ModelCardTrainingData { total_images: images_per_class.values().sum(), images_per_class }
+        }),
+        evaluation: eval_metrics.map(|metrics| ModelCardEvaluation {
+            k_folds: metrics.k_folds,
+            fold_accuracies: metrics.fold_accuracies,
+            average_accuracy: metrics.average_accuracy,
+            std_accuracy: metrics.std_accuracy,
+            per_class,
+        }),
+        calibration: calibration.map(|calibration| ModelCardCalibration {
+            method: calibration.method,
+            temperature: calibration.temperature,
+            validation_images: calibration.validation_images,
+            ece_before: calibration.ece_before,
+            ece_after: calibration.ece_after,
+            reliability: calibration.reliability,
+        }),
+        failure_modes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictions::{self, NewPrediction};
+    use crate::samples::{self, NewSample};
+
+    #[tokio::test]
+    async fn failure_modes_count_approved_corrections_with_their_tags() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let mut sample_ids = Vec::new();
+        for (i, (model_version, label, approved)) in [
+            ("v2", "not_match_ready", true),
+            ("v2", "not_match_ready", true),
+            ("v2", "not_match_ready", false),
+            ("v2", "match_ready", true),
+            ("v1", "not_match_ready", true),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let prediction = NewPrediction { model_version, ..NewPrediction::example() };
+            let prediction_id = predictions::record(&pool, &prediction).await.unwrap();
+            let hash = format!("hash{}", i);
+            let sample = NewSample { label, content_hash: &hash, prediction_id: Some(prediction_id), ..NewSample::example() };
+            let sample_id = samples::record(&pool, &sample).await.unwrap();
+            samples::review(&pool, sample_id, approved, None).await.unwrap();
+            sample_ids.push(sample_id);
+        }
+        sqlx::query("INSERT INTO sample_tags (sample_id, tag, created_at) VALUES ($1, 'glare', '2025-01-01T00:00:00Z')")
+            .bind(sample_ids[0])
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let modes = failure_modes(&pool, "v2").await.unwrap();
+        assert_eq!(modes, vec![FailureMode {
+            predicted: "match_ready".to_string(),
+            actual: "not_match_ready".to_string(),
+            count: 2,
+            tags: BTreeMap::from([("glare".to_string(), 1)]),
+        }]);
+        assert!(failure_modes(&pool, "v3").await.unwrap().is_empty());
+    }
+}
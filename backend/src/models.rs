@@ -0,0 +1,195 @@
+use serde::de::DeserializeOwned;
+use std::io;
+use std::path::Path;
+
+pub use crate::api_types::{Calibration, EvalMetrics, ModelVersion, Precision};
+use crate::config;
+use crate::storage::{self, Area};
+
+impl Precision {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fp32" => Some(Precision::Fp32),
+            "fp16" => Some(Precision::Fp16),
+            "int8" => Some(Precision::Int8),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Precision::Fp32 => "fp32",
+            Precision::Fp16 => "fp16",
+            Precision::Int8 => "int8",
+        }
+    }
+
+    /// The precision suited to the machine the server runs on: int8 on ARM boards such as the Raspberry Pi, fp32 elsewhere.
+    pub fn for_device() -> Self {
+        match std::env::consts::ARCH {
+            "aarch64" | "arm" => Precision::Int8,
+            _ => Precision::Fp32,
+        }
+    }
+
+    /// Names of the ensemble's weight files at this precision, within a model version's directory.
+    pub fn weight_files(self) -> [String; 3] {
+        std::array::from_fn(|i| match self {
+            Precision::Fp32 => format!("model_{}.pth", i + 1),
+            Precision::Fp16 => format!("model_{}.fp16.pth", i + 1),
+            Precision::Int8 => format!("model_{}.int8.pt", i + 1),
+        })
+    }
+}
+
+/// Device the classifier runs the models on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Device {
+    /// Apple's Metal GPU if available, then the first CUDA GPU, then the CPU.
+    #[default]
+    Auto,
+    Cpu,
+    /// An NVIDIA GPU, by index.
+    Cuda(u32),
+    /// Apple's Metal GPU.
+    Mps,
+}
+
+impl Device {
+    /// Parses `auto`, `cpu`, `cuda` (the first GPU), `cuda:<index>`, or `mps`/`metal`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Device::Auto),
+            "cpu" => Some(Device::Cpu),
+            "cuda" => Some(Device::Cuda(0)),
+            "mps" | "metal" => Some(Device::Mps),
+            _ => value.strip_prefix("cuda:")?.parse().ok().map(Device::Cuda),
+        }
+    }
+
+    /// The device as `predict.py --device` and PyTorch name it.
+    pub fn as_arg(self) -> String {
+        match self {
+            Device::Auto => "auto".to_string(),
+            Device::Cpu => "cpu".to_string(),
+            Device::Cuda(index) => format!("cuda:{}", index),
+            Device::Mps => "mps".to_string(),
+        }
+    }
+}
+
+/// Every configured model version, oldest first.
+pub fn versions() -> &'static [ModelVersion] {
+    &config::get().model_versions
+}
+
+/// Looks up a model version by name.
+pub fn find(name: &str) -> Option<&'static ModelVersion> {
+    versions().iter().find(|m| m.name == name)
+}
+
+/// Looks up a comma-separated list of model version names, e.g. `v3,v4`, in the order given and without repeats.
+pub fn find_all(names: &str) -> Result<Vec<&'static ModelVersion>, String> {
+    select(names, versions())
+}
+
+fn select<'a>(names: &str, versions: &'a [ModelVersion]) -> Result<Vec<&'a ModelVersion>, String> {
+    let mut selected: Vec<&ModelVersion> = Vec::new();
+    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let model = versions
+            .iter()
+            .find(|m| m.name == name)
+            .ok_or_else(|| format!("Unknown model version '{}'", name))?;
+        if !selected.iter().any(|m| m.name == model.name) {
+            selected.push(model);
+        }
+    }
+    if selected.is_empty() {
+        return Err("No model versions given".to_string());
+    }
+    Ok(selected)
+}
+
+/// The model version serving predictions.
+/// Falls back to the newest version if `ACTIVE_MODEL` names an unknown one.
+pub fn active() -> &'static ModelVersion {
+    find(&config::get().active_model).unwrap_or_else(|| &versions()[versions().len() - 1])
+}
+
+/// The model version consulted for a second opinion, if one is configured and differs from the active one.
+pub fn second_opinion() -> Option<&'static ModelVersion> {
+    let name = config::get().second_opinion_model.as_deref()?;
+    find(name).filter(|m| m.name != active().name)
+}
+
+/// The model version serving a request and the one consulted for its second opinion.
+/// When `candidate` is set, e.g. by the `candidate_model` flag, the second-opinion model serves instead
+/// and the active one gives the second opinion; without a second-opinion model nothing changes.
+pub fn for_request(candidate: bool) -> (&'static ModelVersion, Option<&'static ModelVersion>) {
+    match second_opinion() {
+        Some(second) if candidate => (second, Some(active())),
+        second => (active(), second),
+    }
+}
+
+/// The precision to run the model version in a local directory at: the configured one if `quantize.py`
+/// has made that variant, otherwise the weights as trained.
+pub fn precision_for(models_dir: &Path) -> Precision {
+    let preferred = config::get().model_precision;
+    if preferred.weight_files().iter().all(|file| models_dir.join(file).is_file()) {
+        preferred
+    } else {
+        Precision::Fp32
+    }
+}
+
+/// Reads the evaluation results `train.py` saved alongside a model version's weights,
+/// or `None` if it was trained before they were recorded.
+pub fn eval_metrics(model: &ModelVersion) -> Result<Option<EvalMetrics>, String> {
+    read_json(model, "metrics.json", "metrics")
+}
+
+/// Reads the temperature `calibrate.py` fitted for a model version, and how much it improved calibration,
+/// or `None` if the version's confidences are uncalibrated.
+pub fn calibration(model: &ModelVersion) -> Result<Option<Calibration>, String> {
+    read_json(model, "calibration.json", "calibration")
+}
+
+/// Reads a JSON file from a model version's directory, or `None` if there isn't one.
+fn read_json<T: DeserializeOwned>(model: &ModelVersion, file: &str, what: &str) -> Result<Option<T>, String> {
+    let key = format!("{}/{}", model.dir.trim_end_matches('/'), file);
+    match storage::get().get(Area::Models, &key) {
+        Ok(data) => serde_json::from_slice(&data)
+            .map(Some)
+            .map_err(|e| format!("Invalid {} for model version {}: {}", what, model.name, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {} for model version {}: {}", what, model.name, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_parse_to_pytorch_names() {
+        assert_eq!(Device::parse("cuda").map(Device::as_arg).as_deref(), Some("cuda:0"));
+        assert_eq!(Device::parse("cuda:2"), Some(Device::Cuda(2)));
+        assert_eq!(Device::parse("metal").map(Device::as_arg).as_deref(), Some("mps"));
+        assert_eq!(Device::parse("cuda:x"), None);
+        assert_eq!(Device::parse("tpu"), None);
+    }
+
+    #[test]
+    fn model_lists_keep_their_order_without_repeats() {
+        let versions: Vec<ModelVersion> = ["v1", "v2", "v3"]
+            .iter()
+            .map(|name| ModelVersion { name: name.to_string(), dir: format!("models_{}", name) })
+            .collect();
+        let names = |list: &str| select(list, &versions).map(|models| models.iter().map(|m| m.name.as_str()).collect::<Vec<_>>());
+
+        assert_eq!(names("v3, v1,v3"), Ok(vec!["v3", "v1"]));
+        assert_eq!(names("v1,v9"), Err("Unknown model version 'v9'".to_string()));
+        assert!(names(" , ").is_err());
+    }
+}
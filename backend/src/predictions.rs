@@ -0,0 +1,298 @@
+use bytes::Bytes;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use futures_util::{stream, StreamExt as _};
+use serde::Deserialize;
+
+pub use crate::api_types::PredictionRecord;
+use crate::api_keys;
+use crate::auth;
+use crate::db;
+use crate::request_logger::RequestLogger;
+
+/// A prediction about to be stored.
+pub struct NewPrediction<'a> {
+    pub request_id: i64,
+    /// The verdict after applying the strictness profile.
+    pub prediction: &'a str,
+    pub confidence: f64,
+    pub image_size_bytes: usize,
+    pub profile: &'a str,
+    /// The model's verdict before the profile threshold was applied.
+    pub model_prediction: &'a str,
+    /// The registered ball the photo was taken of, if known.
+    pub ball_id: Option<&'a str>,
+    pub model_version: &'a str,
+    /// The second-opinion model and its verdict after the profile threshold, if one was requested.
+    pub second_opinion_model: Option<&'a str>,
+    pub second_opinion_prediction: Option<&'a str>,
+    /// Content hash of the image kept for replaying the prediction, if it was kept.
+    pub image_hash: Option<&'a str>,
+}
+
+#[cfg(test)]
+impl NewPrediction<'_> {
+    /// A `match_ready` prediction by model version `v1` under the `club` profile, for tests to adjust.
+    pub fn example() -> Self {
+        NewPrediction {
+            request_id: 1,
+            prediction: "match_ready",
+            confidence: 0.9,
+            image_size_bytes: 10,
+            profile: "club",
+            model_prediction: "match_ready",
+            ball_id: None,
+            model_version: "v1",
+            second_opinion_model: None,
+            second_opinion_prediction: None,
+            image_hash: None,
+        }
+    }
+}
+
+/// Builds a `SELECT` of every `PredictionRecord` column, followed by the given clauses.
+macro_rules! select_predictions {
+    ($clauses:literal) => {
+        concat!(
+            "SELECT id, request_id, created_at, prediction, confidence, image_size_bytes, profile, model_prediction, ball_id, model_version, second_opinion_model, second_opinion_prediction, image_hash FROM predictions ",
+            $clauses
+        )
+    };
+}
+
+/// Formats a timestamp the way it is stored, so string comparison matches time order.
+pub fn format_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Stores a prediction and returns its row ID.
+pub async fn record(pool: &db::Pool, prediction: &NewPrediction<'_>) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "INSERT INTO predictions (request_id, created_at, prediction, confidence, image_size_bytes, profile, model_prediction, ball_id, model_version, second_opinion_model, second_opinion_prediction, image_hash) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING id"
+    )
+    .bind(prediction.request_id)
+    .bind(format_timestamp(Utc::now()))
+    .bind(prediction.prediction)
+    .bind(prediction.confidence)
+    .bind(prediction.image_size_bytes as i64)
+    .bind(prediction.profile)
+    .bind(prediction.model_prediction)
+    .bind(prediction.ball_id)
+    .bind(prediction.model_version)
+    .bind(prediction.second_opinion_model)
+    .bind(prediction.second_opinion_prediction)
+    .bind(prediction.image_hash)
+    .fetch_one(pool)
+    .await
+}
+
+/// Returns every prediction recorded for a ball, newest first.
+pub async fn for_ball(pool: &db::Pool, ball_id: &str) -> Result<Vec<PredictionRecord>, sqlx::Error> {
+    sqlx::query_as::<_, PredictionRecord>(select_predictions!("WHERE ball_id = $1 ORDER BY created_at DESC, id DESC"))
+        .bind(ball_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Looks up a prediction by ID.
+pub async fn find(pool: &db::Pool, id: i64) -> Result<Option<PredictionRecord>, sqlx::Error> {
+    sqlx::query_as::<_, PredictionRecord>(select_predictions!("WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Route handler returning a single stored prediction, by the `prediction_id` `/predict` responded with,
+/// e.g. to check what the classifier said about a ball last weekend. Prediction IDs are sequential, so this
+/// requires an issued API key with the `predict` scope.
+pub async fn get_route(req: rusty_api::HttpRequest, path: rusty_api::web::Path<i64>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let id = path.into_inner();
+
+    logger.info(format!("Received request to /predictions/{}", id));
+
+    let api_key = match api_keys::authenticate(&req, &logger).await {
+        Ok(api_key) => api_key,
+        Err(resp) => return resp,
+    };
+    if let Err(resp) = api_keys::require_key(api_key.as_ref(), "predict", &logger) {
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    match find(pool, id).await {
+        Ok(Some(record)) => rusty_api::HttpResponse::Ok().json(record),
+        Ok(None) => rusty_api::HttpResponse::NotFound().body(format!("Prediction {} not found", id)),
+        Err(e) => {
+            logger.error(format!("Failed to look up prediction: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Query parameters accepted by `/predictions/export`.
+#[derive(Debug, Deserialize)]
+pub struct ExportQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub format: Option<String>,
+}
+
+/// Parses a range bound given either as an RFC 3339 timestamp or a plain `YYYY-MM-DD` date.
+/// A plain date used as the upper bound covers the whole of that day.
+pub fn parse_range_bound(value: &str, is_upper: bool) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Some(time.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let date = if is_upper { date.succ_opt()? } else { date };
+    Some(date.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote, or line break.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders a prediction record as one CSV line.
+fn csv_row(record: &PredictionRecord) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+        record.id,
+        record.request_id,
+        csv_field(&record.created_at),
+        csv_field(&record.prediction),
+        record.confidence,
+        record.image_size_bytes,
+        csv_field(&record.profile),
+        csv_field(record.model_prediction.as_deref().unwrap_or("")),
+        csv_field(record.ball_id.as_deref().unwrap_or("")),
+        csv_field(record.model_version.as_deref().unwrap_or("")),
+        csv_field(record.second_opinion_model.as_deref().unwrap_or("")),
+        csv_field(record.second_opinion_prediction.as_deref().unwrap_or("")),
+        csv_field(record.image_hash.as_deref().unwrap_or(""))
+    )
+}
+
+const CSV_HEADER: &str = "id,request_id,created_at,prediction,confidence,image_size_bytes,profile,model_prediction,ball_id,model_version,second_opinion_model,second_opinion_prediction,image_hash\n";
+
+/// Export route handler streaming prediction history in a date range.
+/// Accepts `from`, `to` and `format` query parameters; only `csv` is currently supported. Requires the admin token.
+pub async fn export_route(req: rusty_api::HttpRequest, query: rusty_api::web::Query<ExportQuery>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /predictions/export");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized prediction export");
+        return resp;
+    }
+
+    let format = query.format.as_deref().unwrap_or("csv");
+    if format != "csv" {
+        logger.error(format!("Unsupported export format: {}", format));
+        return rusty_api::HttpResponse::BadRequest()
+            .body(format!("Unsupported export format: '{}'. Supported formats: csv", format));
+    }
+
+    // Missing bounds leave that side of the range open ("~" sorts after any timestamp)
+    let from = match query.from.as_deref().map(|v| parse_range_bound(v, false)) {
+        Some(Some(time)) => format_timestamp(time),
+        Some(None) => return rusty_api::HttpResponse::BadRequest().body("Invalid 'from' date"),
+        None => String::new(),
+    };
+    let to = match query.to.as_deref().map(|v| parse_range_bound(v, true)) {
+        Some(Some(time)) => format_timestamp(time),
+        Some(None) => return rusty_api::HttpResponse::BadRequest().body("Invalid 'to' date"),
+        None => "~".to_string(),
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    logger.info(format!("Exporting predictions from '{}' to '{}'", from, to));
+
+    let rows = sqlx::query_as::<_, PredictionRecord>(
+        select_predictions!("WHERE created_at >= $1 AND created_at < $2 ORDER BY created_at, id")
+    )
+    .bind(from)
+    .bind(to)
+    .fetch(pool)
+    .map(|row| match row {
+        Ok(record) => Ok(Bytes::from(csv_row(&record))),
+        Err(e) => Err(actix_web::error::ErrorInternalServerError(e)),
+    });
+
+    let body = stream::once(async { Ok(Bytes::from_static(CSV_HEADER.as_bytes())) }).chain(rows);
+
+    rusty_api::HttpResponse::Ok()
+        .content_type("text/csv")
+        .insert_header(("Content-Disposition", "attachment; filename=\"predictions.csv\""))
+        .streaming(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_field_quotes_special_characters() {
+        assert_eq!(csv_field("match_ready"), "match_ready");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn plain_date_upper_bound_covers_whole_day() {
+        let lower = parse_range_bound("2024-03-01", false).unwrap();
+        let upper = parse_range_bound("2024-03-01", true).unwrap();
+        assert_eq!(format_timestamp(lower), "2024-03-01T00:00:00.000Z");
+        assert_eq!(format_timestamp(upper), "2024-03-02T00:00:00.000Z");
+        assert!(parse_range_bound("yesterday", false).is_none());
+    }
+
+    #[tokio::test]
+    async fn recorded_predictions_can_be_read_back() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let prediction = NewPrediction { request_id: 42, prediction: "not_match_ready", profile: "premier", ..NewPrediction::example() };
+        let id = record(&pool, &prediction).await.unwrap();
+
+        let stored = find(&pool, id).await.unwrap().unwrap();
+
+        assert_eq!(stored.request_id, 42);
+        assert_eq!(stored.prediction, "not_match_ready");
+        assert_eq!(stored.model_prediction.as_deref(), Some("match_ready"));
+        assert_eq!(csv_row(&stored).split(',').count(), 13);
+        assert!(find(&pool, id + 1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn exports_need_the_admin_token() {
+        auth::enable_admin_for_tests();
+        let req = actix_web::test::TestRequest::get().uri("/predictions/export").to_http_request();
+        let query = rusty_api::web::Query(ExportQuery { from: None, to: None, format: None });
+
+        let resp = export_route(req, query).await;
+        assert_eq!(resp.status(), rusty_api::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn predictions_need_an_api_key() {
+        let req = actix_web::test::TestRequest::get().uri("/predictions/1").to_http_request();
+
+        let resp = get_route(req, rusty_api::web::Path::from(1)).await;
+        assert_eq!(resp.status(), rusty_api::StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use rusty_api::HttpRequest;
+
+use crate::api_keys::ApiKey;
+use crate::config;
+use crate::settings::RuntimeSettings;
+
+/// A named decision policy for a level of competition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Profile {
+    pub name: &'static str,
+    /// Minimum model confidence required to call a ball match ready.
+    pub min_match_ready_confidence: f64,
+}
+
+/// Available strictness profiles, from most to least lenient, with their default thresholds.
+pub const PROFILES: [Profile; 3] = [
+    Profile { name: "social", min_match_ready_confidence: 0.5 },
+    Profile { name: "club", min_match_ready_confidence: 0.75 },
+    Profile { name: "premier", min_match_ready_confidence: 0.9 },
+];
+
+/// Looks up a profile by name.
+pub fn find(name: &str) -> Option<&'static Profile> {
+    PROFILES.iter().find(|p| p.name == name)
+}
+
+/// Selects the profile for a request: the `profile` query parameter wins,
+/// then the profile of the request's issued API key, then the profile assigned to its `X-Api-Key` header
+/// in `API_KEY_PROFILES`, then the default in `settings`.
+/// The profile's threshold is taken from `settings` if it has been changed at runtime.
+pub fn select(req: &HttpRequest, settings: &RuntimeSettings, api_key: Option<&ApiKey>) -> Result<Profile, String> {
+    let requested = rusty_api::web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.get("profile").cloned());
+
+    let config = config::get();
+    let name = requested
+        .or_else(|| api_key.and_then(|key| key.profile.clone()))
+        .or_else(|| {
+            req.headers()
+                .get("X-Api-Key")
+                .and_then(|h| h.to_str().ok())
+                .and_then(|key| config.api_key_profiles.get(key).cloned())
+        })
+        .unwrap_or_else(|| settings.default_profile.clone());
+
+    with_settings(&name, settings).ok_or_else(|| {
+        let names: Vec<&str> = PROFILES.iter().map(|p| p.name).collect();
+        format!("Unknown profile '{}'. Available profiles: {}", name, names.join(", "))
+    })
+}
+
+/// Looks up a profile by name, with its threshold taken from `settings` if it has been changed at runtime.
+pub fn with_settings(name: &str, settings: &RuntimeSettings) -> Option<Profile> {
+    let profile = find(name)?;
+    Some(Profile {
+        min_match_ready_confidence: settings.profile_thresholds.get(profile.name).copied().unwrap_or(profile.min_match_ready_confidence),
+        ..*profile
+    })
+}
+
+impl Profile {
+    /// Applies this profile's threshold to the model's raw prediction.
+    /// A match-ready prediction below the threshold is downgraded to not match ready;
+    /// any other label in the taxonomy, or `unknown`, is kept as it is.
+    pub fn decide<'a>(&self, prediction: &'a str, confidence: f64) -> &'a str {
+        match prediction {
+            "match_ready" if confidence >= self.min_match_ready_confidence => "match_ready",
+            "match_ready" => "not_match_ready",
+            _ => prediction,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stricter_profiles_downgrade_borderline_balls() {
+        assert_eq!(find("social").unwrap().decide("match_ready", 0.8), "match_ready");
+        assert_eq!(find("premier").unwrap().decide("match_ready", 0.8), "not_match_ready");
+        assert_eq!(find("premier").unwrap().decide("not_match_ready", 0.99), "not_match_ready");
+        assert_eq!(find("club").unwrap().decide("unknown", 0.0), "unknown");
+        assert!(find("test").is_none());
+    }
+}
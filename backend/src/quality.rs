@@ -0,0 +1,224 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb, RgbImage};
+use serde::Serialize;
+
+pub use crate::api_types::{QualityIssue, QualityMode};
+use crate::api_types::QualityRejection;
+use crate::i18n::{Locale, Message};
+use crate::request_logger::RequestLogger;
+
+impl QualityMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(QualityMode::Off),
+            "warn" => Some(QualityMode::Warn),
+            "reject" => Some(QualityMode::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// Width the image is scaled to before measuring, so scores don't depend on camera resolution.
+const ANALYSIS_SIZE: u32 = 256;
+/// Variance of the Laplacian below which the photo is considered blurry.
+const MIN_SHARPNESS: f64 = 60.0;
+/// Mean luminance (0-255) bounds for a usable exposure.
+const MIN_BRIGHTNESS: f64 = 50.0;
+const MAX_BRIGHTNESS: f64 = 205.0;
+/// Minimum share of the frame the ball should fill.
+const MIN_BALL_COVERAGE: f64 = 0.05;
+/// Colour distance from the background at which a pixel counts as part of the ball.
+const FOREGROUND_DISTANCE: f64 = 60.0;
+
+/// Measurements taken from a photo and the issues they revealed.
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityReport {
+    pub sharpness: f64,
+    pub brightness: f64,
+    pub ball_coverage: f64,
+    pub issues: Vec<QualityIssue>,
+}
+
+/// Assesses blur, exposure and ball size for an encoded image.
+/// Returns `None` if the image can't be decoded.
+pub fn assess(image_bytes: &[u8], locale: Locale) -> Option<QualityReport> {
+    let image = image::load_from_memory(image_bytes).ok()?;
+    Some(assess_image(&image, locale))
+}
+
+/// Assesses an already decoded image.
+pub fn assess_image(image: &DynamicImage, locale: Locale) -> QualityReport {
+    let scaled = image.resize(ANALYSIS_SIZE, ANALYSIS_SIZE, FilterType::Triangle);
+    let sharpness = laplacian_variance(&scaled);
+    let brightness = mean_brightness(&scaled);
+    let ball_coverage = foreground_coverage(&scaled);
+
+    let mut issues = Vec::new();
+    if sharpness < MIN_SHARPNESS {
+        issues.push(QualityIssue { code: "too_blurry", message: locale.text(Message::QualityTooBlurry) });
+    }
+    if brightness < MIN_BRIGHTNESS {
+        issues.push(QualityIssue { code: "too_dark", message: locale.text(Message::QualityTooDark) });
+    } else if brightness > MAX_BRIGHTNESS {
+        issues.push(QualityIssue { code: "too_bright", message: locale.text(Message::QualityTooBright) });
+    }
+    if ball_coverage < MIN_BALL_COVERAGE {
+        issues.push(QualityIssue { code: "ball_too_small", message: locale.text(Message::QualityBallTooSmall) });
+    }
+
+    QualityReport { sharpness, brightness, ball_coverage, issues }
+}
+
+/// Finds quality issues with a photo unless the check is turned off.
+/// Images that can't be decoded are passed through for the classifier to handle.
+pub fn issues_for(image_bytes: &[u8], mode: QualityMode, locale: Locale, logger: &RequestLogger) -> Vec<QualityIssue> {
+    if mode == QualityMode::Off {
+        return Vec::new();
+    }
+
+    let report = match assess(image_bytes, locale) {
+        Some(report) => report,
+        None => {
+            logger.info("Quality check skipped: image could not be decoded");
+            return Vec::new();
+        }
+    };
+
+    logger.info(format!(
+        "Quality check: sharpness {:.1}, brightness {:.1}, ball coverage {:.3}, {} issue(s)",
+        report.sharpness, report.brightness, report.ball_coverage, report.issues.len()
+    ));
+
+    report.issues
+}
+
+/// Runs the pre-check according to the quality mode in force.
+/// Returns the issues to attach to the response as warnings, or a rejection response.
+pub fn precheck(image_bytes: &[u8], mode: QualityMode, locale: Locale, logger: &RequestLogger) -> Result<Vec<QualityIssue>, rusty_api::HttpResponse> {
+    let issues = issues_for(image_bytes, mode, locale, logger);
+
+    if mode == QualityMode::Reject && !issues.is_empty() {
+        return Err(rusty_api::HttpResponse::UnprocessableEntity()
+            .insert_header(("Content-Language", locale.tag()))
+            .json(QualityRejection {
+                status: "rejected",
+                code: "poor_quality",
+                message: locale.text(Message::QualityRejected),
+                issues,
+            }));
+    }
+
+    Ok(issues)
+}
+
+/// Variance of the 4-neighbour Laplacian of the luminance; low values mean few sharp edges.
+fn laplacian_variance(image: &DynamicImage) -> f64 {
+    let gray = image.to_luma8();
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let mut values = Vec::with_capacity(((width - 2) * (height - 2)) as usize);
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let centre = gray.get_pixel(x, y)[0] as f64;
+            let neighbours = gray.get_pixel(x - 1, y)[0] as f64
+                + gray.get_pixel(x + 1, y)[0] as f64
+                + gray.get_pixel(x, y - 1)[0] as f64
+                + gray.get_pixel(x, y + 1)[0] as f64;
+            values.push(neighbours - 4.0 * centre);
+        }
+    }
+
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64
+}
+
+/// Mean luminance of the image on a 0-255 scale.
+fn mean_brightness(image: &DynamicImage) -> f64 {
+    let gray = image.to_luma8();
+    let total: u64 = gray.pixels().map(|p| p[0] as u64).sum();
+    total as f64 / (gray.width() as u64 * gray.height() as u64).max(1) as f64
+}
+
+/// Estimates the share of the frame taken up by the ball as the fraction of pixels
+/// whose colour differs clearly from the average colour of the image border.
+fn foreground_coverage(image: &DynamicImage) -> f64 {
+    let rgb = image.to_rgb8();
+    let (width, height) = rgb.dimensions();
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+
+    let background = border_colour(&rgb);
+    let foreground = rgb.pixels().filter(|pixel| is_foreground(pixel, background)).count();
+
+    foreground as f64 / (width as u64 * height as u64) as f64
+}
+
+/// Average colour of the pixels along the edge of the image, taken to be the background.
+pub fn border_colour(rgb: &RgbImage) -> [f64; 3] {
+    let (width, height) = rgb.dimensions();
+    let mut border = [0.0f64; 3];
+    let mut border_count = 0.0;
+    for (x, y, pixel) in rgb.enumerate_pixels() {
+        if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+            for (sum, channel) in border.iter_mut().zip(pixel.0) {
+                *sum += channel as f64;
+            }
+            border_count += 1.0;
+        }
+    }
+    border.map(|sum| sum / f64::max(border_count, 1.0))
+}
+
+/// Whether a pixel's colour differs clearly from the background's.
+pub fn is_foreground(pixel: &Rgb<u8>, background: [f64; 3]) -> bool {
+    let distance: f64 = pixel.0.iter()
+        .zip(background)
+        .map(|(&c, b)| (c as f64 - b).powi(2))
+        .sum();
+    distance.sqrt() > FOREGROUND_DISTANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sharp red ball on a finely textured grey background.
+    fn ball_photo(radius: f64, background: u8) -> DynamicImage {
+        let image = RgbImage::from_fn(200, 200, |x, y| {
+            let (dx, dy) = (x as f64 - 100.0, y as f64 - 100.0);
+            if (dx * dx + dy * dy).sqrt() < radius {
+                Rgb([180, 20, 30])
+            } else if (x / 4 + y / 4) % 2 == 0 {
+                Rgb([background.saturating_add(15); 3])
+            } else {
+                Rgb([background.saturating_sub(15); 3])
+            }
+        });
+        DynamicImage::ImageRgb8(image)
+    }
+
+    #[test]
+    fn well_framed_photo_has_no_issues() {
+        let report = assess_image(&ball_photo(60.0, 120), Locale::En);
+        assert!(report.issues.is_empty(), "{:?}", report);
+    }
+
+    #[test]
+    fn flags_small_ball_and_dark_exposure() {
+        let report = assess_image(&ball_photo(8.0, 15), Locale::En);
+        let codes: Vec<&str> = report.issues.iter().map(|i| i.code).collect();
+        assert!(codes.contains(&"ball_too_small"));
+        assert!(codes.contains(&"too_dark"));
+    }
+
+    #[test]
+    fn flat_image_is_blurry() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(100, 100, Rgb([128, 128, 128])));
+        let report = assess_image(&image, Locale::En);
+        assert_eq!(report.issues[0].code, "too_blurry");
+    }
+}
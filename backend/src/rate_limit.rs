@@ -0,0 +1,142 @@
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::cache;
+use crate::config;
+use crate::request_logger::RequestLogger;
+use crate::settings;
+
+/// Length of each rate-limit window.
+const WINDOW_SECS: i64 = 60;
+
+/// Uploads in progress on this replica, by client.
+static IN_FLIGHT: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
+
+/// Identifies the client a request came from.
+/// Behind a load balancer (`BEHIND_PROXY=true`) this is the address it forwards, otherwise the peer address.
+pub fn client_id(req: &rusty_api::HttpRequest) -> String {
+    let info = req.connection_info();
+    let addr = if config::get().behind_proxy { info.realip_remote_addr() } else { info.peer_addr() };
+    addr.unwrap_or("unknown").to_string()
+}
+
+/// Key of the counter for a client's uploads in the window containing `now` (seconds since the epoch).
+fn window_key(client: &str, now: i64) -> String {
+    format!("rate:uploads:{}:{}", client, now / WINDOW_SECS)
+}
+
+/// Counts an upload against the client's per-minute allowance in the shared store,
+/// so every replica enforces the same limit. Responds with `429` once the allowance is used up.
+/// Requests are let through if the store is unavailable.
+pub async fn check_upload(req: &rusty_api::HttpRequest, logger: &RequestLogger) -> Result<(), rusty_api::HttpResponse> {
+    let limit = settings::current(logger).await.upload_rate_limit;
+    if limit == 0 {
+        return Ok(());
+    }
+
+    let store = match cache::store_for_request(logger).await {
+        Some(store) => store,
+        None => return Ok(()),
+    };
+
+    let client = client_id(req);
+    let now = Utc::now().timestamp();
+    let count = match store.increment(&window_key(&client, now), Duration::from_secs(WINDOW_SECS as u64)).await {
+        Ok(count) => count,
+        Err(message) => {
+            logger.error(format!("Failed to count upload: {}", message));
+            return Ok(());
+        }
+    };
+
+    if count > limit as i64 {
+        logger.error(format!("Upload rate limit exceeded for {}", client));
+        let retry_after = WINDOW_SECS - now % WINDOW_SECS;
+        return Err(rusty_api::HttpResponse::TooManyRequests()
+            .insert_header(("Retry-After", retry_after.to_string()))
+            .body(format!("Upload limit of {} per minute reached; try again in {} seconds", limit, retry_after)));
+    }
+
+    Ok(())
+}
+
+/// One of a client's uploads in progress, counted against its concurrent upload limit until dropped.
+#[derive(Debug)]
+pub struct UploadSlot {
+    client: String,
+}
+
+impl UploadSlot {
+    /// Takes one of the client's `limit` slots, or returns `None` if they are all taken.
+    fn acquire(client: String, limit: usize) -> Option<Self> {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        let count = in_flight.entry(client.clone()).or_default();
+        if *count >= limit {
+            return None;
+        }
+        *count += 1;
+        Some(Self { client })
+    }
+}
+
+impl Drop for UploadSlot {
+    fn drop(&mut self) {
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        if let Some(count) = in_flight.get_mut(&self.client) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(&self.client);
+            }
+        }
+    }
+}
+
+/// Reserves one of the client's concurrent uploads for the rest of the request, so a burst from one phone can't
+/// take up every worker parsing uploads. Responds with `429` while the client has `MAX_CONCURRENT_UPLOADS` in
+/// progress already. Unlike the per-minute allowance this is counted by each replica, since it guards the
+/// replica's own capacity.
+pub fn acquire_upload(req: &rusty_api::HttpRequest, logger: &RequestLogger) -> Result<Option<UploadSlot>, rusty_api::HttpResponse> {
+    let limit = config::get().max_concurrent_uploads;
+    if limit == 0 {
+        return Ok(None);
+    }
+
+    let client = client_id(req);
+    match UploadSlot::acquire(client.clone(), limit) {
+        Some(slot) => Ok(Some(slot)),
+        None => {
+            logger.error(format!("Concurrent upload limit exceeded for {}", client));
+            Err(rusty_api::HttpResponse::TooManyRequests()
+                .insert_header(("Retry-After", "1"))
+                .body(format!("Only {} uploads may be in progress at once; wait for one to finish", limit)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_key_changes_each_minute() {
+        assert_eq!(window_key("1.2.3.4", 120), window_key("1.2.3.4", 179));
+        assert_ne!(window_key("1.2.3.4", 179), window_key("1.2.3.4", 180));
+        assert_ne!(window_key("1.2.3.4", 120), window_key("5.6.7.8", 120));
+    }
+
+    #[test]
+    fn upload_slots_are_freed_when_dropped() {
+        let first = UploadSlot::acquire("10.0.0.1".to_string(), 2).unwrap();
+        let second = UploadSlot::acquire("10.0.0.1".to_string(), 2).unwrap();
+        assert!(UploadSlot::acquire("10.0.0.1".to_string(), 2).is_none());
+        assert!(UploadSlot::acquire("10.0.0.2".to_string(), 2).is_some());
+
+        drop(first);
+        let third = UploadSlot::acquire("10.0.0.1".to_string(), 2).unwrap();
+        drop(second);
+        drop(third);
+        assert!(!IN_FLIGHT.lock().unwrap().contains_key("10.0.0.1"));
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::api_types::{ReplayDiff, ReplayOriginal, ReplayResponse, ReplayVerdict};
+use crate::auth;
+use crate::classifier;
+use crate::config;
+use crate::db;
+use crate::models;
+use crate::profiles;
+use crate::request_logger::RequestLogger;
+use crate::settings;
+use crate::storage::{self, Area};
+use crate::training::{content_hash, image_key};
+
+/// Keeps an image sent for prediction under its content hash, so the prediction can be replayed later.
+/// Returns the hash, or `None` if images aren't kept or it couldn't be stored, which is only logged.
+pub fn keep_image(image_bytes: &[u8], logger: &RequestLogger) -> Option<String> {
+    if !config::get().store_prediction_images {
+        return None;
+    }
+
+    let storage = storage::get();
+    let hash = content_hash(image_bytes);
+    let key = image_key(&hash);
+    let stored = match storage.exists(Area::PredictionImages, &key) {
+        Ok(true) => Ok(()),
+        Ok(false) => storage.put(Area::PredictionImages, &key, image_bytes).map(|_| ()),
+        Err(e) => Err(e),
+    };
+    match stored {
+        Ok(()) => Some(hash),
+        Err(e) => {
+            logger.error(format!("Failed to keep prediction image: {}", e));
+            None
+        }
+    }
+}
+
+/// Reads a kept prediction image by content hash, checking it still matches its hash.
+pub fn read_image(hash: &str) -> Result<Vec<u8>, String> {
+    let image_bytes = storage::get()
+        .get(Area::PredictionImages, &image_key(hash))
+        .map_err(|e| format!("Failed to read prediction image: {}", e))?;
+    if content_hash(&image_bytes) != hash {
+        return Err(format!("Prediction image {} is corrupt", hash));
+    }
+    Ok(image_bytes)
+}
+
+/// What was served for a past prediction, and the image it was made on.
+#[derive(Debug, sqlx::FromRow)]
+struct PastPrediction {
+    created_at: String,
+    prediction: String,
+    confidence: f64,
+    profile: String,
+    model_prediction: Option<String>,
+    model_version: Option<String>,
+    image_hash: Option<String>,
+}
+
+/// Looks up a past prediction by ID.
+async fn find_past(pool: &db::Pool, id: i64) -> Result<Option<PastPrediction>, sqlx::Error> {
+    sqlx::query_as::<_, PastPrediction>(
+        "SELECT created_at, prediction, confidence, profile, model_prediction, model_version, image_hash FROM predictions WHERE id = $1"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Query parameters accepted by `/admin/predictions/{id}/replay`.
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    /// Model version to replay against; defaults to the active one.
+    pub model: Option<String>,
+}
+
+/// Replay route handler running the image of a past prediction through the current model, or the version named
+/// by `model`, under the same profile, and reporting what changed. Requires the admin token.
+pub async fn replay_route(
+    req: rusty_api::HttpRequest,
+    path: rusty_api::web::Path<i64>,
+    query: rusty_api::web::Query<ReplayQuery>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let id = path.into_inner();
+
+    logger.info(format!("Received request to /admin/predictions/{}/replay", id));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized replay request");
+        return resp;
+    }
+
+    let model = match query.model.as_deref() {
+        Some(name) => match models::find(name) {
+            Some(model) => model,
+            None => return rusty_api::HttpResponse::BadRequest().body(format!("Unknown model version '{}'", name)),
+        },
+        None => models::active(),
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let past = match find_past(pool, id).await {
+        Ok(Some(past)) => past,
+        Ok(None) => return rusty_api::HttpResponse::NotFound().body(format!("Prediction {} not found", id)),
+        Err(e) => {
+            logger.error(format!("Failed to look up prediction: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    let Some(hash) = past.image_hash.as_deref() else {
+        return rusty_api::HttpResponse::Conflict().body(format!("The image for prediction {} wasn't kept, so it can't be replayed", id));
+    };
+    let image_bytes = match read_image(hash) {
+        Ok(image_bytes) => image_bytes,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::InternalServerError().body(message);
+        }
+    };
+
+    // Decide under the profile the prediction was made with, at its current threshold
+    let settings = settings::current(&logger).await;
+    let Some(profile) = profiles::with_settings(&past.profile, &settings) else {
+        return rusty_api::HttpResponse::Conflict().body(format!("Profile '{}' no longer exists", past.profile));
+    };
+
+    let output = match classifier::classify_cached(&image_bytes, format!("replay_{}", request_id), &[model], classifier::Options::default(), &logger).await {
+        Ok(mut outputs) => outputs.remove(0),
+        Err(message) => return rusty_api::HttpResponse::InternalServerError().body(message),
+    };
+    let prediction = profile.decide(&output.prediction, output.confidence);
+
+    logger.info(format!("Replayed prediction {}: {} -> {}", id, past.prediction, prediction));
+    let diff = ReplayDiff {
+        prediction_changed: prediction != past.prediction,
+        model_prediction_changed: past.model_prediction.as_deref() != Some(output.prediction.as_str()),
+        model_version_changed: past.model_version.as_deref() != Some(output.model_version.as_str()),
+        confidence_change: output.confidence - past.confidence,
+    };
+    rusty_api::HttpResponse::Ok().json(ReplayResponse {
+        id,
+        image_hash: hash.to_string(),
+        profile: profile.name,
+        replay: ReplayVerdict {
+            prediction: prediction.to_string(),
+            confidence: output.confidence,
+            model_prediction: output.prediction,
+            model_version: output.model_version,
+            model_precision: output.precision,
+        },
+        original: ReplayOriginal {
+            created_at: past.created_at,
+            prediction: past.prediction,
+            confidence: past.confidence,
+            model_prediction: past.model_prediction,
+            model_version: past.model_version,
+        },
+        diff,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictions::{self, NewPrediction};
+
+    #[tokio::test]
+    async fn image_hash_is_stored_with_the_prediction() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let prediction = NewPrediction { image_hash: Some("abcd"), ..NewPrediction::example() };
+        let id = predictions::record(&pool, &prediction).await.unwrap();
+
+        let past = find_past(&pool, id).await.unwrap().unwrap();
+        assert_eq!((past.profile.as_str(), past.image_hash.as_deref()), ("club", Some("abcd")));
+        assert!(find_past(&pool, id + 1).await.unwrap().is_none());
+    }
+}
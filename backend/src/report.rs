@@ -0,0 +1,322 @@
+use chrono::{DateTime, Days, NaiveDate, Timelike, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::cache;
+use crate::config;
+use crate::db;
+use crate::predictions::format_timestamp;
+use crate::request_logger::RequestLogger;
+use crate::storage::{self, Area};
+use crate::summary::{self, DiskUsage};
+
+/// How long one replica's claim on producing a day's report lasts, so the others leave it alone.
+const CLAIM_TTL: Duration = Duration::from_secs(60 * 60);
+/// How long pushing a report to the webhook may take.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How often a second model disagreed with the active one on a day's predictions.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Disagreement {
+    /// Predictions that got a second opinion.
+    pub compared: i64,
+    pub disagreed: i64,
+    /// Share of compared predictions the models disagreed on, or 0 if there were none.
+    pub rate: f64,
+}
+
+/// A day's activity (UTC), as written to `reports/<date>.json` in the exports area.
+#[derive(Debug, Serialize)]
+pub struct DailyReport {
+    pub date: String,
+    pub generated_at: String,
+    pub predictions: i64,
+    pub predictions_by_verdict: BTreeMap<String, i64>,
+    /// Classification attempts that failed, from the shared store's counter.
+    pub failures: i64,
+    pub error_rate: f64,
+    /// Training samples contributed during the day, by the label they were given.
+    pub new_training_samples: i64,
+    pub new_training_samples_by_label: BTreeMap<String, i64>,
+    pub model_disagreement: Disagreement,
+    /// Bytes stored at the time the report was made.
+    pub disk_usage_bytes: DiskUsage,
+}
+
+/// Key of a day's report in the exports area.
+fn report_key(date: NaiveDate) -> String {
+    format!("reports/{}.json", date)
+}
+
+/// Start and end timestamps of a day, for comparing with `created_at` columns.
+fn day_bounds(date: NaiveDate) -> (String, String) {
+    let start = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    (format_timestamp(start), format_timestamp(start + Days::new(1)))
+}
+
+/// Counts the rows of `query`, grouped by its first column, created between the given bounds.
+async fn count_between(pool: &db::Pool, query: &str, from: &str, to: &str) -> Result<BTreeMap<String, i64>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, i64)>(query).bind(from).bind(to).fetch_all(pool).await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Compares the active model's verdicts with the second opinions recorded between the given bounds.
+async fn disagreement_between(pool: &db::Pool, from: &str, to: &str) -> Result<Disagreement, sqlx::Error> {
+    let (compared, disagreed): (i64, Option<i64>) = sqlx::query_as(
+        "SELECT COUNT(*), SUM(CASE WHEN second_opinion_prediction <> model_prediction THEN 1 ELSE 0 END)
+         FROM predictions
+         WHERE created_at >= $1 AND created_at < $2 AND second_opinion_prediction IS NOT NULL"
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_one(pool)
+    .await?;
+    let disagreed = disagreed.unwrap_or(0);
+    let rate = if compared == 0 { 0.0 } else { disagreed as f64 / compared as f64 };
+    Ok(Disagreement { compared, disagreed, rate })
+}
+
+/// Gathers the report for a day.
+pub async fn build(pool: &db::Pool, date: NaiveDate, logger: &RequestLogger) -> Result<DailyReport, sqlx::Error> {
+    let (from, to) = day_bounds(date);
+    let by_verdict = count_between(
+        pool,
+        "SELECT prediction, COUNT(*) FROM predictions WHERE created_at >= $1 AND created_at < $2 GROUP BY prediction",
+        &from,
+        &to,
+    )
+    .await?;
+    let by_label = count_between(
+        pool,
+        "SELECT label, COUNT(*) FROM samples WHERE created_at >= $1 AND created_at < $2 GROUP BY label",
+        &from,
+        &to,
+    )
+    .await?;
+    let model_disagreement = disagreement_between(pool, &from, &to).await?;
+
+    let predictions = by_verdict.values().sum();
+    let failures = summary::prediction_errors(date, logger).await;
+    Ok(DailyReport {
+        date: date.to_string(),
+        generated_at: format_timestamp(Utc::now()),
+        predictions,
+        predictions_by_verdict: by_verdict,
+        failures,
+        error_rate: summary::error_rate(failures, predictions),
+        new_training_samples: by_label.values().sum(),
+        new_training_samples_by_label: by_label,
+        model_disagreement,
+        disk_usage_bytes: summary::disk_usage(logger).await,
+    })
+}
+
+/// Plain-text version of a report for email.
+fn email_body(report: &DailyReport) -> String {
+    let mut body = format!("Cricket Ready summary for {}\n\n", report.date);
+    body += &format!("Predictions: {}\n", report.predictions);
+    for (verdict, count) in &report.predictions_by_verdict {
+        body += &format!("  {}: {}\n", verdict, count);
+    }
+    body += &format!("Failures: {} ({:.1}% of attempts)\n", report.failures, report.error_rate * 100.0);
+    body += &format!("New training samples: {}\n", report.new_training_samples);
+    for (label, count) in &report.new_training_samples_by_label {
+        body += &format!("  {}: {}\n", label, count);
+    }
+    let disagreement = &report.model_disagreement;
+    body += &format!(
+        "Model disagreement: {} of {} second opinions ({:.1}%)\n",
+        disagreement.disagreed, disagreement.compared, disagreement.rate * 100.0
+    );
+    body += &format!("Disk usage: {} bytes\n", report.disk_usage_bytes.total);
+    body
+}
+
+/// Posts a report as JSON to the configured webhook.
+fn push_to_webhook(url: &str, report: &DailyReport) -> Result<(), String> {
+    let response = attohttpc::post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(report)
+        .and_then(|request| request.send())
+        .map_err(|e| e.to_string())?;
+    if !response.is_success() {
+        return Err(format!("webhook responded with {}", response.status()));
+    }
+    Ok(())
+}
+
+/// Emails a report to the configured address through the local `sendmail` command.
+fn send_email(to: &str, report: &DailyReport) -> Result<(), String> {
+    let message = format!(
+        "To: {}\nSubject: Cricket Ready summary for {}\nContent-Type: text/plain; charset=utf-8\n\n{}",
+        to,
+        report.date,
+        email_body(report)
+    );
+    let mut child = Command::new("sendmail")
+        .arg("-t")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run sendmail: {}", e))?;
+    child.stdin.take().unwrap().write_all(message.as_bytes()).map_err(|e| e.to_string())?;
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("sendmail exited with {}", status));
+    }
+    Ok(())
+}
+
+/// Claims producing a day's report for this replica. Without a shared store every replica may claim it,
+/// which only matters when several share one storage backend.
+async fn claim(date: NaiveDate, logger: &RequestLogger) -> bool {
+    let Some(store) = cache::store_for_request(logger).await else {
+        return true;
+    };
+    match store.increment(&format!("report:claim:{}", date), CLAIM_TTL).await {
+        Ok(claims) => claims == 1,
+        Err(message) => {
+            logger.error(format!("Failed to claim daily report: {}", message));
+            true
+        }
+    }
+}
+
+/// Produces, stores and pushes the report for a day, unless it has already been made.
+/// Failures are only logged; pushing is skipped if the report couldn't be stored.
+async fn produce(date: NaiveDate, logger: &RequestLogger) {
+    let storage = storage::get();
+    let key = report_key(date);
+    match storage.exists(Area::Exports, &key) {
+        Ok(true) => return,
+        Ok(false) => {}
+        Err(e) => {
+            logger.error(format!("Failed to check for daily report: {}", e));
+            return;
+        }
+    }
+    if !claim(date, logger).await {
+        return;
+    }
+
+    let pool = match db::pool().await {
+        Ok(pool) => pool,
+        Err(e) => {
+            logger.error(format!("Failed to open database for daily report: {}", e));
+            return;
+        }
+    };
+    let report = match build(pool, date, logger).await {
+        Ok(report) => report,
+        Err(e) => {
+            logger.error(format!("Failed to build daily report: {}", e));
+            return;
+        }
+    };
+    let json = serde_json::to_vec_pretty(&report).unwrap();
+    if let Err(e) = storage.put(Area::Exports, &key, &json) {
+        logger.error(format!("Failed to store daily report: {}", e));
+        return;
+    }
+    logger.info(format!("Stored daily report for {}", date));
+
+    let config = config::get();
+    if let Some(url) = &config.daily_report_webhook_url {
+        match push_to_webhook(url, &report) {
+            Ok(()) => logger.info(format!("Pushed daily report for {} to webhook", date)),
+            Err(message) => logger.error(format!("Failed to push daily report: {}", message)),
+        }
+    }
+    if let Some(to) = &config.daily_report_email {
+        match send_email(to, &report) {
+            Ok(()) => logger.info(format!("Emailed daily report for {} to {}", date, to)),
+            Err(message) => logger.error(format!("Failed to email daily report: {}", message)),
+        }
+    }
+}
+
+/// The next time at or after `now` that falls on `hour` UTC.
+fn next_run(now: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    let today = now.date_naive().and_hms_opt(hour, 0, 0).unwrap().and_utc();
+    if now <= today { today } else { today + Days::new(1) }
+}
+
+/// Starts the scheduler producing the previous day's report at `DAILY_REPORT_HOUR` UTC each day,
+/// on its own thread. A report missed while the server was down is made when it next starts.
+pub fn start() {
+    let Some(hour) = config::get().daily_report_hour else {
+        return;
+    };
+
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+        runtime.block_on(async {
+            loop {
+                let now = Utc::now();
+                if now.hour() >= hour {
+                    let logger = RequestLogger::new(now.timestamp_millis());
+                    produce(now.date_naive() - Days::new(1), &logger).await;
+                }
+                let now = Utc::now();
+                let wait = (next_run(now, hour) - now).to_std().unwrap_or_default();
+                tokio::time::sleep(wait.max(Duration::from_secs(1))).await;
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictions::{self, NewPrediction};
+    use crate::samples::{self, NewSample};
+    use chrono::TimeZone;
+
+    #[test]
+    fn runs_are_scheduled_for_the_next_occurrence_of_the_hour() {
+        let at = |d, h, m| Utc.with_ymd_and_hms(2024, 5, d, h, m, 0).unwrap();
+        assert_eq!(next_run(at(2, 3, 30), 6), at(2, 6, 0));
+        assert_eq!(next_run(at(2, 6, 0), 6), at(2, 6, 0));
+        assert_eq!(next_run(at(2, 6, 1), 6), at(3, 6, 0));
+        assert_eq!(next_run(at(2, 23, 0), 0), at(3, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn report_counts_only_the_day_it_covers() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let predictions = [
+            ("match_ready", Some("match_ready"), "2024-05-01T23:59:59.000Z"),
+            ("match_ready", Some("not_match_ready"), "2024-05-02T10:00:00.000Z"),
+            ("not_match_ready", Some("not_match_ready"), "2024-05-02T11:00:00.000Z"),
+            ("not_match_ready", None, "2024-05-02T12:00:00.000Z"),
+            ("match_ready", None, "2024-05-03T00:00:00.000Z"),
+        ];
+        for (verdict, second_opinion, created_at) in predictions {
+            let prediction = NewPrediction {
+                prediction: verdict,
+                model_prediction: verdict,
+                model_version: "v2",
+                second_opinion_model: second_opinion.map(|_| "v1"),
+                second_opinion_prediction: second_opinion,
+                ..NewPrediction::example()
+            };
+            let id = predictions::record(&pool, &prediction).await.unwrap();
+            sqlx::query("UPDATE predictions SET created_at = $1 WHERE id = $2").bind(created_at).bind(id).execute(&pool).await.unwrap();
+        }
+        for (label, created_at) in [("match_ready", "2024-05-02T09:00:00.000Z"), ("match_ready", "2024-05-04T09:00:00.000Z")] {
+            let sample = NewSample { label, ..NewSample::example() };
+            let id = samples::record(&pool, &sample).await.unwrap();
+            sqlx::query("UPDATE samples SET created_at = $1 WHERE id = $2").bind(created_at).bind(id).execute(&pool).await.unwrap();
+        }
+
+        let logger = RequestLogger::new(1);
+        let report = build(&pool, NaiveDate::from_ymd_opt(2024, 5, 2).unwrap(), &logger).await.unwrap();
+        assert_eq!(report.predictions, 3);
+        assert_eq!(report.predictions_by_verdict, BTreeMap::from([("match_ready".to_string(), 1), ("not_match_ready".to_string(), 2)]));
+        assert_eq!(report.new_training_samples, 1);
+        assert_eq!(report.model_disagreement, Disagreement { compared: 2, disagreed: 1, rate: 0.5 });
+        assert!(email_body(&report).contains("Model disagreement: 1 of 2 second opinions (50.0%)"));
+    }
+}
@@ -9,6 +9,8 @@ static LOGGER_INIT: OnceLock<()> = OnceLock::new();
 /// A logger that tags each log entry with a unique request ID.
 pub struct RequestLogger {
     request_id: i64,
+    /// Whether dropping this logger marks the end of the request.
+    ends_request: bool,
 }
 
 impl RequestLogger {
@@ -41,7 +43,12 @@ impl RequestLogger {
     pub fn new(request_id: i64) -> Self {
         Self::init_logger();
         info!("------------------- [Request {}] Start -------------------", request_id);
-        Self { request_id }
+        Self { request_id, ends_request: true }
+    }
+
+    /// Another logger for the same request, e.g. to move into `web::block`. Dropping it doesn't end the request.
+    pub fn handle(&self) -> Self {
+        Self { request_id: self.request_id, ends_request: false }
     }
 
     /// Logs an informational message tagged with the request ID.
@@ -59,6 +66,9 @@ impl Drop for RequestLogger {
     /// Logs the end of the request when the logger is dropped.
     /// This is called automatically when the `RequestLogger` goes out of scope.
     fn drop(&mut self) {
+        if !self.ends_request {
+            return;
+        }
         info!("------------------- [Request {}] End ---------------------", self.request_id);
     }
 }
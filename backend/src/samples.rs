@@ -0,0 +1,481 @@
+use chrono::Utc;
+use serde::Deserialize;
+use sqlx::{Any, Executor};
+
+use crate::api_types::{BulkResponse, ImageFailure, IntegrityResponse, ReviewResponse};
+use crate::auth;
+use crate::db;
+use crate::labels;
+use crate::predictions::{self, format_timestamp};
+use crate::request_logger::RequestLogger;
+use crate::training;
+
+/// Most content hashes a bulk operation can list, and longest tag.
+const MAX_BULK_HASHES: usize = 10_000;
+const MAX_TAG_LEN: usize = 64;
+
+/// Metadata for a newly saved training image.
+pub struct NewSample<'a> {
+    pub request_id: i64,
+    pub contributor: Option<&'a str>,
+    pub label: &'a str,
+    pub filename: &'a str,
+    pub file_path: &'a str,
+    pub content_hash: &'a str,
+    pub image_size_bytes: usize,
+    /// The prediction the sample corrects, if it was made from one.
+    pub prediction_id: Option<i64>,
+}
+
+#[cfg(test)]
+impl NewSample<'_> {
+    /// A `match_ready` sample without a contributor, for tests to adjust.
+    pub fn example() -> Self {
+        NewSample {
+            request_id: 1,
+            contributor: None,
+            label: "match_ready",
+            filename: "ab.jpg",
+            file_path: "training_data/images/ab/ab.jpg",
+            content_hash: "ab",
+            image_size_bytes: 10,
+            prediction_id: None,
+        }
+    }
+}
+
+/// Stores a training sample awaiting review and returns its row ID.
+pub async fn record<'e>(executor: impl Executor<'e, Database = Any>, sample: &NewSample<'_>) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        "INSERT INTO samples (request_id, created_at, contributor, label, filename, file_path, content_hash, image_size_bytes, prediction_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING id"
+    )
+    .bind(sample.request_id)
+    .bind(format_timestamp(Utc::now()))
+    .bind(sample.contributor)
+    .bind(sample.label)
+    .bind(sample.filename)
+    .bind(sample.file_path)
+    .bind(sample.content_hash)
+    .bind(sample.image_size_bytes as i64)
+    .bind(sample.prediction_id)
+    .fetch_one(executor)
+    .await
+}
+
+/// Checks whether any sample refers to the image with the given content hash.
+pub async fn hash_in_use(pool: &db::Pool, content_hash: &str) -> Result<bool, sqlx::Error> {
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM samples WHERE content_hash = $1")
+        .bind(content_hash)
+        .fetch_one(pool)
+        .await?;
+    Ok(count > 0)
+}
+
+/// Returns the IDs of the samples that refer to the image with the given content hash.
+pub async fn with_hash(pool: &db::Pool, content_hash: &str) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM samples WHERE content_hash = $1 ORDER BY id")
+        .bind(content_hash)
+        .fetch_all(pool)
+        .await
+}
+
+/// Returns the ID of the sample made from a prediction, if it has been corrected.
+pub async fn for_prediction(pool: &db::Pool, prediction_id: i64) -> Result<Option<i64>, sqlx::Error> {
+    sqlx::query_scalar("SELECT id FROM samples WHERE prediction_id = $1")
+        .bind(prediction_id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Records a reviewer's decision on a sample.
+/// Approving with a different label counts as a correction of the contributor's label. Approving without one
+/// keeps any earlier correction, e.g. from `/admin/samples/bulk`.
+/// Returns `false` if no sample has the given ID.
+pub async fn review(
+    pool: &db::Pool,
+    sample_id: i64,
+    approved: bool,
+    reviewed_label: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let status = if approved { "approved" } else { "rejected" };
+    let result = sqlx::query(
+        "UPDATE samples SET review_status = $1, reviewed_label = COALESCE($2, reviewed_label, label), reviewed_at = $3 WHERE id = $4"
+    )
+    .bind(status)
+    .bind(reviewed_label)
+    .bind(format_timestamp(Utc::now()))
+    .bind(sample_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Body accepted by `/samples/{id}/review`.
+#[derive(Debug, Deserialize)]
+pub struct ReviewInput {
+    /// Either `approve` or `reject`.
+    pub decision: String,
+    /// The correct label, when it differs from the contributor's.
+    pub label: Option<String>,
+}
+
+/// Review route handler for approving, correcting or rejecting a training sample.
+/// Requires the admin token.
+pub async fn review_route(
+    req: rusty_api::HttpRequest,
+    path: rusty_api::web::Path<i64>,
+    body: rusty_api::web::Json<ReviewInput>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let sample_id = path.into_inner();
+
+    logger.info(format!("Received request to /samples/{}/review", sample_id));
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized review");
+        return resp;
+    }
+
+    let approved = match body.decision.as_str() {
+        "approve" => true,
+        "reject" => false,
+        other => {
+            logger.error(format!("Invalid review decision: {}", other));
+            return rusty_api::HttpResponse::BadRequest()
+                .body("Decision must be either 'approve' or 'reject'");
+        }
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    if let Some(label) = body.label.as_deref() {
+        match labels::names(pool).await {
+            Ok(names) if names.iter().any(|name| name == label) => {}
+            Ok(names) => {
+                logger.error(format!("Invalid label: {}", label));
+                return rusty_api::HttpResponse::BadRequest()
+                    .body(labels::invalid_label_message("Label must be one of:", &names));
+            }
+            Err(e) => {
+                logger.error(format!("Failed to load labels: {}", e));
+                return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+            }
+        }
+    }
+
+    match review(pool, sample_id, approved, body.label.as_deref()).await {
+        Ok(true) => {
+            logger.info(format!("Sample {} reviewed: {}", sample_id, body.decision));
+            rusty_api::HttpResponse::Ok().json(ReviewResponse { status: "success", sample_id, decision: body.decision.clone() })
+        }
+        Ok(false) => rusty_api::HttpResponse::NotFound().body(format!("Sample {} not found", sample_id)),
+        Err(e) => {
+            logger.error(format!("Failed to record review: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Which samples a bulk operation applies to. Every condition given must match.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SampleFilter {
+    /// Submitted at or after `from` and before `to` (RFC 3339 timestamps or `YYYY-MM-DD` dates).
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub contributor: Option<String>,
+    /// The sample's current label, after any correction.
+    pub label: Option<String>,
+    pub tag: Option<String>,
+    pub content_hashes: Option<Vec<String>>,
+}
+
+impl SampleFilter {
+    /// The filter as a `WHERE` clause and the values to bind to it, in order.
+    /// An empty filter is refused, so a mistake can't relabel the whole dataset.
+    fn where_clause(&self) -> Result<(String, Vec<String>), String> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        let mut condition = |sql: &str, value: String| {
+            values.push(value);
+            conditions.push(sql.replace('?', &format!("${}", values.len())));
+        };
+
+        if let Some(from) = &self.from {
+            let from = predictions::parse_range_bound(from, false).ok_or("Invalid 'from' date")?;
+            condition("created_at >= ?", format_timestamp(from));
+        }
+        if let Some(to) = &self.to {
+            let to = predictions::parse_range_bound(to, true).ok_or("Invalid 'to' date")?;
+            condition("created_at < ?", format_timestamp(to));
+        }
+        if let Some(contributor) = &self.contributor {
+            condition("contributor = ?", contributor.clone());
+        }
+        if let Some(label) = &self.label {
+            condition("COALESCE(reviewed_label, label) = ?", label.clone());
+        }
+        if let Some(tag) = &self.tag {
+            condition("id IN (SELECT sample_id FROM sample_tags WHERE tag = ?)", tag.clone());
+        }
+        if let Some(hashes) = &self.content_hashes {
+            if hashes.is_empty() || hashes.len() > MAX_BULK_HASHES {
+                return Err(format!("content_hashes must list between 1 and {} hashes", MAX_BULK_HASHES));
+            }
+            let first = values.len() + 1;
+            values.extend(hashes.iter().cloned());
+            let params: Vec<String> = (first..first + hashes.len()).map(|n| format!("${}", n)).collect();
+            conditions.push(format!("content_hash IN ({})", params.join(", ")));
+        }
+
+        if conditions.is_empty() {
+            return Err("Give at least one filter: from, to, contributor, label, tag or content_hashes".to_string());
+        }
+        Ok((conditions.join(" AND "), values))
+    }
+}
+
+/// Checks a tag: lowercase letters, digits, underscores and hyphens.
+fn validate_tag(tag: &str) -> Result<(), String> {
+    if tag.is_empty() || tag.len() > MAX_TAG_LEN {
+        return Err(format!("Tags must be between 1 and {} characters", MAX_TAG_LEN));
+    }
+    if !tag.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-') {
+        return Err(format!("Tag '{}' must be lowercase letters, digits, underscores and hyphens", tag));
+    }
+    Ok(())
+}
+
+/// Body accepted by `/admin/samples/bulk`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BulkInput {
+    pub filter: SampleFilter,
+    /// Label to correct every matching sample to.
+    pub label: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Reports what would change without changing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// What a bulk operation changed, or would change.
+#[derive(Debug, PartialEq)]
+struct BulkOutcome {
+    sample_ids: Vec<i64>,
+    relabeled: u64,
+    tagged: u64,
+}
+
+/// Applies a bulk operation in one transaction, which is rolled back for a dry run.
+/// Relabeling corrects a sample's label as a reviewer would, leaving its review status as it was.
+async fn apply_bulk(pool: &db::Pool, input: &BulkInput, filter: (&str, &[String])) -> Result<BulkOutcome, sqlx::Error> {
+    let (conditions, values) = filter;
+    let mut tx = pool.begin().await?;
+
+    let sql = format!("SELECT id FROM samples WHERE {} ORDER BY id", conditions);
+    let mut query = sqlx::query_scalar::<_, i64>(&sql);
+    for value in values {
+        query = query.bind(value);
+    }
+    let sample_ids = query.fetch_all(&mut *tx).await?;
+
+    let mut relabeled = 0;
+    let mut tagged = 0;
+    let now = format_timestamp(Utc::now());
+    for &id in &sample_ids {
+        if let Some(label) = &input.label {
+            relabeled += sqlx::query("UPDATE samples SET reviewed_label = $1 WHERE id = $2 AND COALESCE(reviewed_label, label) <> $1")
+                .bind(label)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+        for tag in &input.tags {
+            tagged += sqlx::query("INSERT INTO sample_tags (sample_id, tag, created_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+                .bind(id)
+                .bind(tag)
+                .bind(&now)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+    }
+
+    if input.dry_run {
+        tx.rollback().await?;
+    } else {
+        tx.commit().await?;
+    }
+    Ok(BulkOutcome { sample_ids, relabeled, tagged })
+}
+
+/// Bulk route handler correcting the label of, or tagging, every sample matching a filter at once, e.g. to clean
+/// up a batch of one contributor's mislabeled uploads. With `dry_run` it reports what would change instead.
+/// Requires the admin token.
+pub async fn bulk_route(req: rusty_api::HttpRequest, body: rusty_api::web::Json<BulkInput>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/samples/bulk");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized bulk operation");
+        return resp;
+    }
+
+    if body.label.is_none() && body.tags.is_empty() {
+        return rusty_api::HttpResponse::BadRequest().body("Give a label to apply, tags to add, or both");
+    }
+    if let Err(message) = body.tags.iter().try_for_each(|tag| validate_tag(tag)) {
+        logger.error(&message);
+        return rusty_api::HttpResponse::BadRequest().body(message);
+    }
+    let (conditions, values) = match body.filter.where_clause() {
+        Ok(filter) => filter,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::BadRequest().body(message);
+        }
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    if let Some(label) = body.label.as_deref() {
+        match labels::names(pool).await {
+            Ok(names) if names.iter().any(|name| name == label) => {}
+            Ok(names) => {
+                logger.error(format!("Invalid label: {}", label));
+                return rusty_api::HttpResponse::BadRequest()
+                    .body(labels::invalid_label_message("Label must be one of:", &names));
+            }
+            Err(e) => {
+                logger.error(format!("Failed to load labels: {}", e));
+                return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+            }
+        }
+    }
+
+    match apply_bulk(pool, &body, (&conditions, &values)).await {
+        Ok(outcome) => {
+            logger.info(format!(
+                "Bulk operation{} matched {} sample(s): {} relabeled, {} tag(s) added",
+                if body.dry_run { " (dry run)" } else { "" },
+                outcome.sample_ids.len(),
+                outcome.relabeled,
+                outcome.tagged
+            ));
+            rusty_api::HttpResponse::Ok().json(BulkResponse {
+                dry_run: body.dry_run,
+                matched: outcome.sample_ids.len(),
+                sample_ids: outcome.sample_ids,
+                relabeled: outcome.relabeled,
+                tags_added: outcome.tagged,
+            })
+        }
+        Err(e) => {
+            logger.error(format!("Failed to apply bulk operation: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Integrity route handler re-reading every content-addressed training image and checking it against its hash.
+/// Requires the admin token.
+pub async fn integrity_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /samples/integrity");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized integrity check");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    // Samples saved before images were content-addressed have no hash to check against,
+    // and archived images are checked when their archive is rehydrated
+    let hashes: Vec<String> = match sqlx::query_scalar(
+        "SELECT DISTINCT content_hash FROM samples WHERE content_hash IS NOT NULL AND archive_id IS NULL ORDER BY content_hash"
+    )
+    .fetch_all(pool)
+    .await
+    {
+        Ok(hashes) => hashes,
+        Err(e) => {
+            logger.error(format!("Failed to list sample images: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    let failures: Vec<ImageFailure> = hashes
+        .iter()
+        .filter_map(|hash| training::read_image(hash).err().map(|error| ImageFailure { content_hash: hash.clone(), error }))
+        .collect();
+
+    logger.info(format!("Checked {} image(s), {} failed", hashes.len(), failures.len()));
+    rusty_api::HttpResponse::Ok().json(IntegrityResponse { images_checked: hashes.len(), failures })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bulk_filters_must_narrow_the_samples_down() {
+        let filter = SampleFilter {
+            contributor: Some("sam".to_string()),
+            content_hashes: Some(vec!["ab".to_string(), "cd".to_string()]),
+            ..Default::default()
+        };
+        let (conditions, values) = filter.where_clause().unwrap();
+        assert_eq!(conditions, "contributor = $1 AND content_hash IN ($2, $3)");
+        assert_eq!(values, vec!["sam", "ab", "cd"]);
+
+        assert!(SampleFilter::default().where_clause().is_err());
+        assert!(SampleFilter { content_hashes: Some(Vec::new()), ..Default::default() }.where_clause().is_err());
+        assert!(validate_tag("blurry-batch_2").is_ok());
+        assert!(validate_tag("Blurry batch").is_err());
+    }
+
+    #[tokio::test]
+    async fn dry_runs_change_nothing() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        for (contributor, label) in [("sam", "match_ready"), ("sam", "not_match_ready"), ("alex", "match_ready")] {
+            let sample = NewSample { contributor: Some(contributor), label, ..NewSample::example() };
+            record(&pool, &sample).await.unwrap();
+        }
+
+        let filter = SampleFilter { contributor: Some("sam".to_string()), ..Default::default() };
+        let (conditions, values) = filter.where_clause().unwrap();
+        let mut input = BulkInput { filter, label: Some("not_match_ready".to_string()), tags: vec!["recheck".to_string()], dry_run: true };
+        let expected = BulkOutcome { sample_ids: vec![1, 2], relabeled: 1, tagged: 2 };
+
+        assert_eq!(apply_bulk(&pool, &input, (&conditions, &values)).await.unwrap(), expected);
+        let tags: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sample_tags").fetch_one(&pool).await.unwrap();
+        assert_eq!(tags, 0);
+
+        input.dry_run = false;
+        assert_eq!(apply_bulk(&pool, &input, (&conditions, &values)).await.unwrap(), expected);
+        let labels: Vec<String> = sqlx::query_scalar("SELECT COALESCE(reviewed_label, label) FROM samples ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(labels, vec!["not_match_ready", "not_match_ready", "match_ready"]);
+    }
+}
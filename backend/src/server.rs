@@ -0,0 +1,295 @@
+use actix_governor::{Governor, GovernorConfigBuilder, KeyExtractor};
+use actix_web::dev::ServiceRequest;
+use actix_web::{web, App, HttpServer};
+use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixListener;
+use std::sync::Arc;
+
+use crate::config;
+use crate::db;
+use crate::jobs;
+use crate::rate_limit;
+use crate::systemd;
+
+const CERT_PATH: &str = "cricket-ready.crt";
+const KEY_PATH: &str = "cricket-ready.key";
+/// Requests allowed per second from one client, and how many may arrive at once.
+const RATE_LIMIT_PER_SECOND: u64 = 3;
+const RATE_LIMIT_BURST: u32 = 20;
+
+/// What a listener is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// Serves the API over TLS.
+    Https,
+    /// Serves the API over plain HTTP, e.g. to a reverse proxy that terminates TLS.
+    Http,
+    /// Redirects plain HTTP requests to the HTTPS listener.
+    Redirect,
+}
+
+impl Role {
+    /// The role of a socket passed by systemd, from its `FileDescriptorName=`. Unnamed sockets serve HTTPS.
+    fn from_socket_name(name: &str) -> Self {
+        match name {
+            "http" => Role::Http,
+            "http-redirect" => Role::Redirect,
+            _ => Role::Https,
+        }
+    }
+}
+
+/// A listening socket, on a network port or a Unix domain socket path.
+enum Socket {
+    Tcp(TcpListener),
+    /// Always serves the API over plain HTTP, since whatever connects to it is on the same machine.
+    Unix(UnixListener),
+}
+
+impl Socket {
+    /// A socket passed by systemd, which may be either kind.
+    fn from_fd(fd: OwnedFd) -> io::Result<Self> {
+        let unix = UnixListener::from(fd);
+        if unix.local_addr().is_ok() {
+            return Ok(Socket::Unix(unix));
+        }
+        let tcp = TcpListener::from(OwnedFd::from(unix));
+        tcp.local_addr().map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("Socket from systemd isn't a TCP or Unix socket: {}", e))
+        })?;
+        Ok(Socket::Tcp(tcp))
+    }
+}
+
+/// Rate-limits clients as identified by `rate_limit::client_id`, rather than by the peer address alone as
+/// rusty_api does. Requests over a Unix socket have no peer address, and behind a proxy every request has the
+/// proxy's.
+#[derive(Debug, Clone, Copy)]
+struct ClientKeyExtractor;
+
+impl KeyExtractor for ClientKeyExtractor {
+    type Key = String;
+    type KeyExtractionError = &'static str;
+
+    fn extract(&self, req: &ServiceRequest) -> Result<Self::Key, Self::KeyExtractionError> {
+        Ok(rate_limit::client_id(req.request()))
+    }
+}
+
+/// Binds the Unix domain socket at `path`, replacing one left behind by a server that didn't shut down cleanly.
+fn bind_unix(path: &str, mode: u32) -> io::Result<UnixListener> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("{} exists and isn't a socket", path))),
+        Err(_) => {}
+    }
+    let listener = UnixListener::bind(path).map_err(|e| io::Error::new(e.kind(), format!("Failed to bind {}: {}", path, e)))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    Ok(listener)
+}
+
+fn cors() -> rusty_api::Cors {
+    rusty_api::Cors::default()
+        .allow_any_method()
+        .allow_any_origin()
+        .allow_any_header()
+}
+
+/// The sockets to listen on: those systemd passed under socket activation, or else those configured.
+/// Under socket activation systemd holds the ports, and passes the server its sockets when the first request arrives.
+fn listeners() -> io::Result<Vec<(Role, Socket)>> {
+    let passed = systemd::listeners()?;
+    if !passed.is_empty() {
+        return passed.into_iter().map(|(name, fd)| Ok((Role::from_socket_name(&name), Socket::from_fd(fd)?))).collect();
+    }
+
+    let config = config::get();
+    let configured = config.https_listen.iter().map(|addr| (Role::Https, addr))
+        .chain(config.http_listen.iter().map(|addr| (Role::Http, addr)))
+        .chain(config.http_redirect_listen.iter().map(|addr| (Role::Redirect, addr)));
+    let mut listeners = configured
+        .map(|(role, addr)| {
+            let listener = TcpListener::bind(addr).map_err(|e| io::Error::new(e.kind(), format!("Failed to bind {}: {}", addr, e)))?;
+            Ok((role, Socket::Tcp(listener)))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    if let Some(path) = &config.unix_socket_path {
+        listeners.push((Role::Http, Socket::Unix(bind_unix(path, config.unix_socket_mode)?)));
+    }
+    Ok(listeners)
+}
+
+/// Where to send a redirected request: the same host and path on the HTTPS port.
+fn redirect_location(host: &str, https_port: u16, path_and_query: &str) -> String {
+    // Drop the port the request was sent to, keeping IPv6 addresses such as `[::1]` whole
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && port.chars().all(|c| c.is_ascii_digit()) && !host.ends_with(']') => name,
+        _ => host,
+    };
+    match https_port {
+        443 => format!("https://{}{}", host, path_and_query),
+        port => format!("https://{}:{}{}", host, port, path_and_query),
+    }
+}
+
+/// Runs the API server until it is shut down, e.g. by `SIGTERM`.
+/// This is the server `rusty_api::Api::start` runs, with support for several listeners and for systemd
+/// socket activation and supervision.
+pub fn run(routes: rusty_api::Routes) {
+    let system = actix_web::rt::System::new();
+    if let Err(e) = system.block_on(serve(routes)) {
+        println!("ERROR: Failed to start API server: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn serve(routes: rusty_api::Routes) -> io::Result<()> {
+    println!("INFO: Starting API server...");
+    if config::get().fault_injection {
+        println!("INFO: Fault injection is enabled, so requests may fail on purpose. Never enable it in production");
+    }
+
+    // Jobs the server was running when it last stopped ended with it
+    match db::pool().await {
+        Ok(pool) => match jobs::fail_interrupted(pool).await {
+            Ok(0) => {}
+            Ok(failed) => println!("INFO: Marked {} job(s) interrupted by the last shutdown as failed", failed),
+            Err(e) => println!("ERROR: Failed to mark interrupted jobs as failed: {}", e),
+        },
+        Err(e) => println!("ERROR: Failed to open metadata database: {}", e),
+    }
+
+    let listeners = listeners()?;
+    if !listeners.iter().any(|(role, socket)| *role != Role::Redirect || matches!(socket, Socket::Unix(_))) {
+        return Err(io::Error::other("No HTTPS or HTTP listener is configured"));
+    }
+
+    let governor_config = GovernorConfigBuilder::default()
+        .per_second(RATE_LIMIT_PER_SECOND)
+        .burst_size(RATE_LIMIT_BURST)
+        .key_extractor(ClientKeyExtractor)
+        .finish()
+        .unwrap();
+    let routes = Arc::new(routes);
+    let mut server = HttpServer::new(move || {
+        let routes = routes.clone();
+        App::new()
+            .wrap(cors())
+            .wrap(Governor::new(&governor_config))
+            .configure(move |cfg| routes.configure(cfg))
+    });
+
+    let mut tls_config = None;
+    let mut https_port = None;
+    let mut redirect_listeners = Vec::new();
+    for (role, socket) in listeners {
+        let listener = match socket {
+            Socket::Tcp(listener) => listener,
+            Socket::Unix(listener) => {
+                let addr = listener.local_addr()?;
+                match addr.as_pathname() {
+                    Some(path) => println!("INFO: Server listening on {} (HTTP over Unix socket)", path.display()),
+                    None => println!("INFO: Server listening on {:?} (HTTP over Unix socket)", addr),
+                }
+                server = server.listen_uds(listener)?;
+                continue;
+            }
+        };
+        let addr = listener.local_addr()?;
+        match role {
+            Role::Https => {
+                let tls_config = match &tls_config {
+                    Some(tls_config) => tls_config,
+                    None => tls_config.insert(rusty_api::load_rustls_config(CERT_PATH, KEY_PATH).ok_or_else(|| {
+                        io::Error::other(format!("Failed to load TLS certificate {} and key {}", CERT_PATH, KEY_PATH))
+                    })?),
+                };
+                println!("INFO: Server listening on {} (HTTPS)", addr);
+                https_port.get_or_insert(addr.port());
+                server = server.listen_rustls_0_23(listener, tls_config.clone())?;
+            }
+            Role::Http => {
+                println!("INFO: Server listening on {} (HTTP)", addr);
+                server = server.listen(listener)?;
+            }
+            Role::Redirect => redirect_listeners.push(listener),
+        }
+    }
+
+    // Redirects are served separately, without the API's routes or rate limit
+    let redirects = match (redirect_listeners.is_empty(), https_port) {
+        (true, _) => None,
+        (false, None) => return Err(io::Error::other("HTTP redirects need an HTTPS listener to redirect to")),
+        (false, Some(https_port)) => {
+            let mut redirects = HttpServer::new(move || {
+                App::new().default_service(web::to(move |req: rusty_api::HttpRequest| async move {
+                    let path_and_query = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/");
+                    let location = redirect_location(req.connection_info().host(), https_port, path_and_query);
+                    rusty_api::HttpResponse::PermanentRedirect().insert_header(("Location", location)).finish()
+                }))
+            })
+            .workers(1);
+            for listener in redirect_listeners {
+                println!("INFO: Server redirecting {} to HTTPS", listener.local_addr()?);
+                redirects = redirects.listen(listener)?;
+            }
+            Some(redirects.run())
+        }
+    };
+
+    let server = server.run();
+    systemd::notify("READY=1");
+    if let Some(interval) = systemd::watchdog_interval() {
+        // Pinged from the server's own runtime, so systemd restarts the server if it stops making progress
+        actix_web::rt::spawn(async move {
+            loop {
+                actix_web::rt::time::sleep(interval).await;
+                systemd::notify("WATCHDOG=1");
+            }
+        });
+    }
+
+    // Both servers stop on the same signal
+    let result = match redirects {
+        Some(redirects) => futures_util::future::try_join(server, redirects).await.map(|_| ()),
+        None => server.await,
+    };
+    systemd::notify("STOPPING=1");
+    if let Some(path) = &config::get().unix_socket_path {
+        fs::remove_file(path).ok();
+    }
+    result
+}
+
+/// The URL the server can be reached at from this machine, for the `healthcheck` subcommand:
+/// the first configured HTTPS listener, or else the first plain HTTP one.
+pub fn local_url() -> Option<String> {
+    let config = config::get();
+    let (scheme, addr) = config.https_listen.first().map(|addr| ("https", addr))
+        .or_else(|| config.http_listen.first().map(|addr| ("http", addr)))?;
+    // Listeners on every interface are reached over loopback
+    let ip = match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    Some(format!("{}://{}", scheme, SocketAddr::new(ip, addr.port())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirects_keep_the_host_and_path_on_the_https_port() {
+        assert_eq!(redirect_location("club.local", 49161, "/predict?profile=junior"), "https://club.local:49161/predict?profile=junior");
+        assert_eq!(redirect_location("club.local:80", 443, "/version"), "https://club.local/version");
+        assert_eq!(redirect_location("192.168.1.20:8080", 49161, "/"), "https://192.168.1.20:49161/");
+        assert_eq!(redirect_location("[fe80::1]:80", 49161, "/"), "https://[fe80::1]:49161/");
+        assert_eq!(redirect_location("[fe80::1]", 443, "/"), "https://[fe80::1]/");
+    }
+}
@@ -0,0 +1,374 @@
+use chrono::Utc;
+use serde_json::{Map, Value};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+pub use crate::api_types::{AuditEntry, RuntimeSettings};
+use crate::api_types::{InvalidResponse, SettingsResponse, SettingsUpdateResponse};
+use crate::auth;
+use crate::config;
+use crate::db;
+use crate::flags::{self, Rollout};
+use crate::predictions::format_timestamp;
+use crate::profiles;
+use crate::rate_limit;
+use crate::request_logger::RequestLogger;
+
+/// How long a replica keeps using the settings it loaded before checking for changes made elsewhere.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Highest accepted values, to catch typos such as an extra zero.
+const MAX_UPLOAD_RATE_LIMIT: u32 = 10_000;
+const MAX_PREDICTION_CACHE_TTL: u64 = 7 * 24 * 60 * 60;
+const MAX_ARCHIVE_AFTER_MONTHS: u32 = 120;
+
+/// Number of audit entries returned by `GET /admin/config`.
+const HISTORY_LENGTH: i64 = 20;
+
+impl RuntimeSettings {
+    /// The settings as configured in the environment.
+    pub fn from_config() -> Self {
+        let config = config::get();
+        Self {
+            default_profile: config.default_profile.clone(),
+            profile_thresholds: profiles::PROFILES
+                .iter()
+                .map(|p| (p.name.to_string(), p.min_match_ready_confidence))
+                .collect(),
+            quality_mode: config.quality_mode,
+            upload_rate_limit: config.upload_rate_limit,
+            prediction_cache_ttl: config.prediction_cache_ttl,
+            archive_after_months: config.archive_after_months,
+            feature_flags: flags::FLAGS
+                .iter()
+                .map(|f| (f.name.to_string(), Rollout { everyone: config.feature_flags.iter().any(|n| n == f.name), api_keys: Vec::new() }))
+                .collect(),
+        }
+    }
+
+    /// Checks every setting, returning a message for each invalid one.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if profiles::find(&self.default_profile).is_none() {
+            errors.push(format!("default_profile: unknown profile '{}'", self.default_profile));
+        }
+        for (name, threshold) in &self.profile_thresholds {
+            if profiles::find(name).is_none() {
+                errors.push(format!("profile_thresholds: unknown profile '{}'", name));
+            }
+            if !(0.0..=1.0).contains(threshold) {
+                errors.push(format!("profile_thresholds.{}: must be between 0 and 1", name));
+            }
+        }
+        if self.upload_rate_limit > MAX_UPLOAD_RATE_LIMIT {
+            errors.push(format!("upload_rate_limit: must be at most {}", MAX_UPLOAD_RATE_LIMIT));
+        }
+        if self.prediction_cache_ttl > MAX_PREDICTION_CACHE_TTL {
+            errors.push(format!("prediction_cache_ttl: must be at most {} seconds", MAX_PREDICTION_CACHE_TTL));
+        }
+        if !(1..=MAX_ARCHIVE_AFTER_MONTHS).contains(&self.archive_after_months) {
+            errors.push(format!("archive_after_months: must be between 1 and {}", MAX_ARCHIVE_AFTER_MONTHS));
+        }
+        for name in self.feature_flags.keys() {
+            if flags::find(name).is_none() {
+                errors.push(format!("feature_flags: unknown flag '{}'", name));
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    /// Each setting by name, as JSON.
+    fn fields(&self) -> Map<String, Value> {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(fields)) => fields,
+            _ => unreachable!("settings serialize to an object"),
+        }
+    }
+
+    /// Applies stored overrides on top of these settings.
+    /// Overrides that no longer parse or validate, e.g. after an upgrade, are skipped.
+    fn with_overrides(&self, overrides: &[(String, String)]) -> Self {
+        let mut settings = self.clone();
+        for (name, value) in overrides {
+            let mut fields = settings.fields();
+            let Ok(value) = serde_json::from_str(value) else { continue };
+            if fields.insert(name.clone(), value).is_none() {
+                continue;
+            }
+            if let Ok(candidate) = serde_json::from_value::<Self>(Value::Object(fields)) {
+                if candidate.validate().is_ok() {
+                    settings = candidate;
+                }
+            }
+        }
+        settings
+    }
+
+    /// Applies new values for some settings.
+    /// Objects such as `profile_thresholds` and `feature_flags` are merged, so only the entries being changed need to be given.
+    pub fn merge(&self, changes: &Map<String, Value>) -> Result<Self, Vec<String>> {
+        let mut fields = self.fields();
+        let mut errors = Vec::new();
+        for (name, value) in changes {
+            match (fields.get_mut(name), value) {
+                (None, _) => errors.push(format!("{}: not a setting that can be changed at runtime", name)),
+                (Some(Value::Object(current)), Value::Object(entries)) => {
+                    current.extend(entries.iter().map(|(k, v)| (k.clone(), v.clone())))
+                }
+                (Some(current), _) => *current = value.clone(),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let settings: Self = serde_json::from_value(Value::Object(fields)).map_err(|e| vec![e.to_string()])?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// Names of the settings that differ from `other`, with their old and new values as JSON.
+    fn changes_from(&self, other: &Self) -> Vec<(String, Value, Value)> {
+        let old = other.fields();
+        self.fields()
+            .into_iter()
+            .filter(|(name, value)| old.get(name) != Some(value))
+            .map(|(name, value)| (name.clone(), old[&name].clone(), value))
+            .collect()
+    }
+}
+
+/// Loads the settings in force: the environment's, overridden by any changed at runtime.
+pub async fn load(pool: &db::Pool) -> Result<RuntimeSettings, sqlx::Error> {
+    let overrides: Vec<(String, String)> = sqlx::query_as("SELECT name, value FROM runtime_settings ORDER BY name")
+        .fetch_all(pool)
+        .await?;
+    Ok(RuntimeSettings::from_config().with_overrides(&overrides))
+}
+
+/// Stores every setting that differs between `old` and `new`, with an audit entry for each, in one transaction.
+/// Returns the names of the changed settings.
+pub async fn save(pool: &db::Pool, old: &RuntimeSettings, new: &RuntimeSettings, client: &str) -> Result<Vec<String>, sqlx::Error> {
+    let changes = new.changes_from(old);
+    let now = format_timestamp(Utc::now());
+
+    let mut tx = pool.begin().await?;
+    for (name, old_value, new_value) in &changes {
+        sqlx::query(
+            "INSERT INTO runtime_settings (name, value, updated_at) VALUES ($1, $2, $3)
+             ON CONFLICT (name) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at"
+        )
+        .bind(name)
+        .bind(new_value.to_string())
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("INSERT INTO settings_audit (changed_at, client, name, old_value, new_value) VALUES ($1, $2, $3, $4, $5)")
+            .bind(&now)
+            .bind(client)
+            .bind(name)
+            .bind(old_value.to_string())
+            .bind(new_value.to_string())
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    invalidate();
+    Ok(changes.into_iter().map(|(name, _, _)| name).collect())
+}
+
+/// Returns the most recent settings changes, newest first.
+pub async fn history(pool: &db::Pool, limit: i64) -> Result<Vec<AuditEntry>, sqlx::Error> {
+    sqlx::query_as::<_, AuditEntry>(
+        "SELECT changed_at, client, name, old_value, new_value FROM settings_audit ORDER BY id DESC LIMIT $1"
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Settings most recently loaded by this process, and when.
+static CURRENT: RwLock<Option<(Arc<RuntimeSettings>, Instant)>> = RwLock::new(None);
+
+/// Makes the next call to `current` reload the settings.
+fn invalidate() {
+    *CURRENT.write().unwrap() = None;
+}
+
+/// Returns the settings in force, reloading them if they are more than a few seconds old
+/// so that changes made through another replica take effect everywhere.
+/// If the metadata database is unavailable, the last settings loaded (or the environment's) are used.
+pub async fn current(logger: &RequestLogger) -> Arc<RuntimeSettings> {
+    let cached = CURRENT.read().unwrap().clone();
+    if let Some((settings, loaded)) = &cached {
+        if loaded.elapsed() < REFRESH_INTERVAL {
+            return settings.clone();
+        }
+    }
+
+    let settings = match db::pool().await {
+        Ok(pool) => load(pool).await,
+        Err(e) => Err(e),
+    };
+    let settings = match settings {
+        Ok(settings) => Arc::new(settings),
+        Err(e) => {
+            logger.error(format!("Failed to load runtime settings: {}", e));
+            cached.map(|(settings, _)| settings).unwrap_or_else(|| Arc::new(RuntimeSettings::from_config()))
+        }
+    };
+    *CURRENT.write().unwrap() = Some((settings.clone(), Instant::now()));
+    settings
+}
+
+/// Config route handler returning the runtime settings in force, the environment's defaults,
+/// and the most recent changes. Requires the admin token.
+pub async fn get_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/config");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized config request");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let loaded = async { Ok::<_, sqlx::Error>((load(pool).await?, history(pool, HISTORY_LENGTH).await?)) };
+    match loaded.await {
+        Ok((settings, history)) => rusty_api::HttpResponse::Ok().json(SettingsResponse {
+            settings,
+            defaults: RuntimeSettings::from_config(),
+            history,
+        }),
+        Err(e) => {
+            logger.error(format!("Failed to load runtime settings: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+/// Config route handler changing any number of runtime settings at once to the values in the JSON object sent
+/// as the body, e.g. `{"quality_mode": "reject", "profile_thresholds": {"club": 0.8}}`. Settings left out are kept.
+/// The changes are validated together and stored in one transaction, so either all of them apply or none do.
+/// Each is recorded in the audit log. Requires the admin token.
+pub async fn update_route(req: rusty_api::HttpRequest, body: rusty_api::web::Json<Map<String, Value>>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to PUT /admin/config");
+
+    apply(&req, &body.into_inner(), &logger).await
+}
+
+/// Config route handler changing one runtime setting to the JSON value sent as the body.
+/// The change is validated before it is stored, and recorded in the audit log. Requires the admin token.
+pub async fn put_route(
+    req: rusty_api::HttpRequest,
+    path: rusty_api::web::Path<String>,
+    body: rusty_api::web::Json<Value>,
+) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let name = path.into_inner();
+
+    logger.info(format!("Received request to PUT /admin/config/{}", name));
+
+    apply(&req, &Map::from_iter([(name, body.into_inner())]), &logger).await
+}
+
+/// Validates `changes` against the settings in force and stores them, for the config route handlers.
+async fn apply(req: &rusty_api::HttpRequest, changes: &Map<String, Value>, logger: &RequestLogger) -> rusty_api::HttpResponse {
+    if let Err(resp) = auth::require_admin(req) {
+        logger.error("Rejected unauthorized config change");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let old = match load(pool).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            logger.error(format!("Failed to load runtime settings: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    let new = match old.merge(changes) {
+        Ok(settings) => settings,
+        Err(errors) => {
+            logger.error(format!("Rejected config change: {}", errors.join("; ")));
+            return rusty_api::HttpResponse::BadRequest().json(InvalidResponse { status: "invalid", errors });
+        }
+    };
+
+    let client = rate_limit::client_id(req);
+    match save(pool, &old, &new, &client).await {
+        Ok(changed) => {
+            for (name, old_value, new_value) in new.changes_from(&old) {
+                logger.info(format!("Setting {} changed by {}: {} -> {}", name, client, old_value, new_value));
+            }
+            rusty_api::HttpResponse::Ok().json(SettingsUpdateResponse { status: "updated", changed, settings: new })
+        }
+        Err(e) => {
+            logger.error(format!("Failed to save runtime settings: {}", e));
+            rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quality::QualityMode;
+    use serde_json::json;
+
+    #[test]
+    fn merge_validates_and_keeps_unchanged_thresholds() {
+        let defaults = RuntimeSettings::from_config();
+
+        let changes = json!({ "profile_thresholds": { "club": 0.8 }, "quality_mode": "reject" });
+        let merged = defaults.merge(changes.as_object().unwrap()).unwrap();
+        assert_eq!(merged.profile_thresholds["club"], 0.8);
+        assert_eq!(merged.profile_thresholds["premier"], 0.9);
+        assert_eq!(merged.quality_mode, QualityMode::Reject);
+        let changed: Vec<String> = merged.changes_from(&defaults).into_iter().map(|(name, _, _)| name).collect();
+        assert_eq!(changed, vec!["profile_thresholds", "quality_mode"]);
+
+        for invalid in [json!({ "profile_thresholds": { "club": 1.5 } }), json!({ "default_profile": "test" }), json!({ "behind_proxy": true }), json!({ "upload_rate_limit": "lots" })] {
+            assert!(defaults.merge(invalid.as_object().unwrap()).is_err());
+        }
+
+        // A batch is rejected whole if any of its changes is invalid
+        let batch = json!({ "quality_mode": "reject", "profile_thresholds": { "club": 1.5 } });
+        assert!(defaults.merge(batch.as_object().unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn saved_changes_are_loaded_and_audited() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let old = load(&pool).await.unwrap();
+        let new = old.merge(json!({ "upload_rate_limit": 5 }).as_object().unwrap()).unwrap();
+
+        assert_eq!(save(&pool, &old, &new, "1.2.3.4").await.unwrap(), vec!["upload_rate_limit"]);
+        assert_eq!(load(&pool).await.unwrap().upload_rate_limit, 5);
+
+        let entries = history(&pool, 10).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].client, "1.2.3.4");
+        assert_eq!((entries[0].old_value.clone(), entries[0].new_value.as_str()), (old.upload_rate_limit.to_string(), "5"));
+
+        // A stored value that no longer validates falls back to the environment's
+        sqlx::query("UPDATE runtime_settings SET value = '-1' WHERE name = 'upload_rate_limit'").execute(&pool).await.unwrap();
+        assert_eq!(load(&pool).await.unwrap().upload_rate_limit, RuntimeSettings::from_config().upload_rate_limit);
+    }
+}
@@ -0,0 +1,466 @@
+use s3::creds::Credentials;
+use s3::{Bucket, Region};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::config;
+use crate::encryption::{self, EncryptedStorage};
+
+/// The kinds of files the backend keeps, each stored separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Area {
+    /// Submitted training images and the training audit log.
+    TrainingData,
+    /// Scratch files handed to the prediction script.
+    Temp,
+    /// Trained model weights, addressed by the directories named in `MODEL_VERSIONS`.
+    Models,
+    /// Generated exports and reports.
+    Exports,
+    /// Compressed bundles of old training images moved out of the training data area.
+    Archive,
+    /// Images sent for prediction, kept so past predictions can be replayed.
+    PredictionImages,
+}
+
+impl Area {
+    pub const ALL: [Area; 6] = [Area::TrainingData, Area::Temp, Area::Models, Area::Exports, Area::Archive, Area::PredictionImages];
+
+    /// Name used as the area's key prefix in object storage.
+    fn name(self) -> &'static str {
+        match self {
+            Area::TrainingData => "training_data",
+            Area::Temp => "tmp",
+            Area::Models => "models",
+            Area::Exports => "exports",
+            Area::Archive => "archive",
+            Area::PredictionImages => "prediction_images",
+        }
+    }
+
+    /// Where the area lives on the local filesystem, relative to the backend directory.
+    fn local_root(self) -> PathBuf {
+        match self {
+            Area::TrainingData => PathBuf::from("training_data"),
+            Area::Temp => std::env::temp_dir(),
+            Area::Models => PathBuf::from("."),
+            Area::Exports => PathBuf::from("exports"),
+            Area::Archive => PathBuf::from("archive"),
+            Area::PredictionImages => PathBuf::from("prediction_images"),
+        }
+    }
+}
+
+/// Which storage backend to use, from `STORAGE_BACKEND`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageBackend {
+    Local,
+    S3 {
+        bucket: String,
+        region: String,
+        /// Custom endpoint for S3-compatible services such as MinIO; uses path-style addressing.
+        endpoint: Option<String>,
+        /// Key prefix prepended to every object, so one bucket can hold several deployments.
+        prefix: String,
+    },
+}
+
+/// Reads and writes the backend's files by area and `/`-separated key, wherever they are kept.
+pub trait Storage: Send + Sync {
+    /// Writes an object, replacing any existing one, and returns its location.
+    /// Readers never see a partially written object.
+    fn put(&self, area: Area, key: &str, data: &[u8]) -> io::Result<String>;
+
+    /// Describes where an object is kept, for logs and records.
+    fn location(&self, area: Area, key: &str) -> String;
+
+    /// Reads an object.
+    fn get(&self, area: Area, key: &str) -> io::Result<Vec<u8>>;
+
+    /// Appends to an object, creating it if it doesn't exist.
+    fn append(&self, area: Area, key: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Removes an object. Removing an object that doesn't exist is not an error.
+    fn delete(&self, area: Area, key: &str) -> io::Result<()>;
+
+    /// Checks whether an object exists.
+    fn exists(&self, area: Area, key: &str) -> io::Result<bool>;
+
+    /// Lists the keys of every object under a prefix, sorted.
+    fn list(&self, area: Area, prefix: &str) -> io::Result<Vec<String>>;
+
+    /// Totals the size in bytes of every object in an area.
+    fn usage(&self, area: Area) -> io::Result<u64>;
+
+    /// Returns a local path holding the object, or every object under `key` if it names a directory,
+    /// downloading them first if the backend is remote. Used for files read by external processes.
+    fn local_path(&self, area: Area, key: &str) -> io::Result<PathBuf>;
+}
+
+/// Stores each area in a directory on the local filesystem.
+pub struct LocalStorage {
+    roots: HashMap<Area, PathBuf>,
+}
+
+impl LocalStorage {
+    /// Uses the default directory for every area.
+    pub fn new() -> Self {
+        Self::with_roots(Area::ALL.iter().map(|&area| (area, area.local_root())).collect())
+    }
+
+    /// Uses the given directory for each area; areas left out fall back to their default.
+    pub fn with_roots(roots: HashMap<Area, PathBuf>) -> Self {
+        let mut all: HashMap<Area, PathBuf> = Area::ALL.iter().map(|&area| (area, area.local_root())).collect();
+        all.extend(roots);
+        Self { roots: all }
+    }
+
+    fn path(&self, area: Area, key: &str) -> PathBuf {
+        self.roots[&area].join(key)
+    }
+
+    /// Creates the parent directories of a path.
+    fn create_parent(path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for LocalStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put(&self, area: Area, key: &str, data: &[u8]) -> io::Result<String> {
+        let path = self.path(area, key);
+        Self::create_parent(&path)?;
+
+        // Write to a uniquely named sibling, then rename it into place
+        let file_name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, rand::random::<u64>()));
+        if let Err(e) = fs::write(&temp_path, data).and_then(|_| fs::rename(&temp_path, &path)) {
+            fs::remove_file(&temp_path).ok();
+            return Err(e);
+        }
+
+        Ok(self.location(area, key))
+    }
+
+    fn location(&self, area: Area, key: &str) -> String {
+        self.path(area, key).display().to_string()
+    }
+
+    fn get(&self, area: Area, key: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(area, key))
+    }
+
+    fn append(&self, area: Area, key: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path(area, key);
+        Self::create_parent(&path)?;
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(data))
+    }
+
+    fn delete(&self, area: Area, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(area, key)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    fn exists(&self, area: Area, key: &str) -> io::Result<bool> {
+        Ok(self.path(area, key).is_file())
+    }
+
+    fn list(&self, area: Area, prefix: &str) -> io::Result<Vec<String>> {
+        fn walk(dir: &Path, key: &str, keys: &mut Vec<String>) -> io::Result<()> {
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let child = if key.is_empty() { name } else { format!("{}/{}", key, name) };
+                if entry.file_type()?.is_dir() {
+                    walk(&entry.path(), &child, keys)?;
+                } else {
+                    keys.push(child);
+                }
+            }
+            Ok(())
+        }
+
+        // Walk from the directory containing the prefix, then filter on the full prefix
+        let dir = match prefix.rfind('/') {
+            Some(end) => &prefix[..end],
+            None => "",
+        };
+        let mut keys = Vec::new();
+        match walk(&self.path(area, dir), dir, &mut keys) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            result => result?,
+        }
+        keys.retain(|key| key.starts_with(prefix));
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn usage(&self, area: Area) -> io::Result<u64> {
+        let mut total = 0;
+        for key in self.list(area, "")? {
+            total += fs::metadata(self.path(area, &key))?.len();
+        }
+        Ok(total)
+    }
+
+    fn local_path(&self, area: Area, key: &str) -> io::Result<PathBuf> {
+        Ok(self.path(area, key))
+    }
+}
+
+/// Stores areas as objects in an S3 bucket under `<prefix>/<area>/<key>`.
+/// Temp files stay on local disk, since they only exist to be read by the prediction script.
+pub struct S3Storage {
+    bucket: Box<Bucket>,
+    prefix: String,
+    /// Local directory holding temp files and downloaded copies of objects.
+    local: LocalStorage,
+}
+
+impl S3Storage {
+    /// Connects to a bucket using credentials from the standard AWS environment variables or profile.
+    pub fn new(bucket: &str, region: &str, endpoint: Option<&str>, prefix: &str) -> Result<Self, String> {
+        let region = match endpoint {
+            Some(endpoint) => Region::Custom { region: region.to_string(), endpoint: endpoint.to_string() },
+            None => region.parse().map_err(|e| format!("Invalid S3 region '{}': {}", region, e))?,
+        };
+        let credentials = Credentials::default().map_err(|e| format!("Failed to load S3 credentials: {}", e))?;
+        let mut bucket = Bucket::new(bucket, region, credentials).map_err(|e| format!("Failed to open S3 bucket: {}", e))?;
+        if endpoint.is_some() {
+            bucket = bucket.with_path_style();
+        }
+
+        let cache_dir = std::env::temp_dir().join("cricket_ready_cache");
+        let roots = Area::ALL
+            .iter()
+            .filter(|&&area| area != Area::Temp)
+            .map(|&area| (area, cache_dir.join(area.name())))
+            .collect();
+
+        Ok(Self {
+            bucket,
+            prefix: prefix.trim_matches('/').to_string(),
+            local: LocalStorage::with_roots(roots),
+        })
+    }
+
+    fn object_key(&self, area: Area, key: &str) -> String {
+        if self.prefix.is_empty() {
+            format!("{}/{}", area.name(), key)
+        } else {
+            format!("{}/{}/{}", self.prefix, area.name(), key)
+        }
+    }
+
+    /// Fetches an object, returning `None` if it doesn't exist.
+    fn fetch(&self, area: Area, key: &str) -> io::Result<Option<Vec<u8>>> {
+        let response = self.bucket.get_object(self.object_key(area, key)).map_err(io::Error::other)?;
+        match response.status_code() {
+            200 => Ok(Some(response.bytes().to_vec())),
+            404 => Ok(None),
+            status => Err(s3_status_error(status, "read", key)),
+        }
+    }
+}
+
+/// Converts an unexpected S3 response status into an I/O error.
+fn s3_status_error(status: u16, action: &str, key: &str) -> io::Error {
+    io::Error::other(format!("S3 returned {} trying to {} '{}'", status, action, key))
+}
+
+impl Storage for S3Storage {
+    fn put(&self, area: Area, key: &str, data: &[u8]) -> io::Result<String> {
+        if area == Area::Temp {
+            return self.local.put(area, key, data);
+        }
+        let response = self.bucket.put_object(self.object_key(area, key), data).map_err(io::Error::other)?;
+        match response.status_code() {
+            200..=299 => Ok(self.location(area, key)),
+            status => Err(s3_status_error(status, "write", key)),
+        }
+    }
+
+    fn location(&self, area: Area, key: &str) -> String {
+        if area == Area::Temp {
+            return self.local.location(area, key);
+        }
+        format!("s3://{}/{}", self.bucket.name(), self.object_key(area, key))
+    }
+
+    fn get(&self, area: Area, key: &str) -> io::Result<Vec<u8>> {
+        if area == Area::Temp {
+            return self.local.get(area, key);
+        }
+        self.fetch(area, key)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("'{}' not found", key)))
+    }
+
+    /// S3 objects can't be appended to, so the object is read and rewritten.
+    /// Concurrent appends to the same key may lose data.
+    fn append(&self, area: Area, key: &str, data: &[u8]) -> io::Result<()> {
+        if area == Area::Temp {
+            return self.local.append(area, key, data);
+        }
+        let mut contents = self.fetch(area, key)?.unwrap_or_default();
+        contents.extend_from_slice(data);
+        self.put(area, key, &contents).map(|_| ())
+    }
+
+    fn delete(&self, area: Area, key: &str) -> io::Result<()> {
+        if area == Area::Temp {
+            return self.local.delete(area, key);
+        }
+        let response = self.bucket.delete_object(self.object_key(area, key)).map_err(io::Error::other)?;
+        match response.status_code() {
+            200..=299 | 404 => Ok(()),
+            status => Err(s3_status_error(status, "delete", key)),
+        }
+    }
+
+    fn exists(&self, area: Area, key: &str) -> io::Result<bool> {
+        if area == Area::Temp {
+            return self.local.exists(area, key);
+        }
+        self.bucket.object_exists(self.object_key(area, key)).map_err(io::Error::other)
+    }
+
+    fn list(&self, area: Area, prefix: &str) -> io::Result<Vec<String>> {
+        if area == Area::Temp {
+            return self.local.list(area, prefix);
+        }
+        let area_prefix = self.object_key(area, "");
+        let pages = self.bucket.list(self.object_key(area, prefix), None).map_err(io::Error::other)?;
+        let mut keys: Vec<String> = pages
+            .into_iter()
+            .flat_map(|page| page.contents)
+            .filter_map(|object| object.key.strip_prefix(&area_prefix).map(str::to_string))
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn usage(&self, area: Area) -> io::Result<u64> {
+        if area == Area::Temp {
+            return self.local.usage(area);
+        }
+        let pages = self.bucket.list(self.object_key(area, ""), None).map_err(io::Error::other)?;
+        Ok(pages.iter().flat_map(|page| &page.contents).map(|object| object.size).sum())
+    }
+
+    /// Downloads into a local cache. Cached copies are reused, since model artifacts never change once published.
+    fn local_path(&self, area: Area, key: &str) -> io::Result<PathBuf> {
+        if area == Area::Temp {
+            return self.local.local_path(area, key);
+        }
+
+        let keys = match self.fetch(area, key)? {
+            Some(data) => {
+                self.local.put(area, key, &data)?;
+                Vec::new()
+            }
+            None => self.list(area, &format!("{}/", key.trim_end_matches('/')))?,
+        };
+        for object_key in keys {
+            if !self.local.exists(area, &object_key)? {
+                let data = self.get(area, &object_key)?;
+                self.local.put(area, &object_key, &data)?;
+            }
+        }
+
+        self.local.local_path(area, key)
+    }
+}
+
+static STORAGE: OnceLock<Box<dyn Storage>> = OnceLock::new();
+
+/// Opens the configured storage backend, encrypting it if a key is configured.
+fn open() -> Result<Box<dyn Storage>, String> {
+    let config = config::get();
+    let backend: Box<dyn Storage> = match &config.storage_backend {
+        StorageBackend::Local => Box::new(LocalStorage::new()),
+        StorageBackend::S3 { bucket, region, endpoint, prefix } => {
+            Box::new(S3Storage::new(bucket, region, endpoint.as_deref(), prefix)?)
+        }
+    };
+
+    match &config.encryption_key {
+        Some(key) => Ok(Box::new(EncryptedStorage::new(backend, &encryption::parse_key(key)?))),
+        None => Ok(backend),
+    }
+}
+
+/// Returns the process-wide storage backend.
+/// Panics if the configured backend can't be opened, so `main` calls this at startup to fail fast.
+pub fn get() -> &'static dyn Storage {
+    STORAGE
+        .get_or_init(|| open().unwrap_or_else(|e| panic!("Failed to open storage backend: {}", e)))
+        .as_ref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_storage(name: &str) -> (LocalStorage, PathBuf) {
+        let root = std::env::temp_dir().join(format!("cricket_ready_storage_{}_{}", name, std::process::id()));
+        fs::remove_dir_all(&root).ok();
+        let roots = Area::ALL.iter().map(|&area| (area, root.join(area.name()))).collect();
+        (LocalStorage::with_roots(roots), root)
+    }
+
+    #[test]
+    fn local_storage_round_trips_objects() {
+        let (storage, root) = temp_storage("round_trip");
+
+        let location = storage.put(Area::TrainingData, "match_ready/ball.jpg", b"jpeg").unwrap();
+        assert_eq!(location, root.join("training_data/match_ready/ball.jpg").display().to_string());
+        assert_eq!(storage.get(Area::TrainingData, "match_ready/ball.jpg").unwrap(), b"jpeg");
+        assert!(storage.exists(Area::TrainingData, "match_ready/ball.jpg").unwrap());
+
+        storage.append(Area::TrainingData, "log.jsonl", b"a\n").unwrap();
+        storage.append(Area::TrainingData, "log.jsonl", b"b\n").unwrap();
+        assert_eq!(storage.get(Area::TrainingData, "log.jsonl").unwrap(), b"a\nb\n");
+
+        storage.delete(Area::TrainingData, "match_ready/ball.jpg").unwrap();
+        storage.delete(Area::TrainingData, "match_ready/ball.jpg").unwrap();
+        assert!(!storage.exists(Area::TrainingData, "match_ready/ball.jpg").unwrap());
+
+        fs::remove_dir_all(root).ok();
+    }
+
+    #[test]
+    fn local_storage_lists_keys_under_a_prefix() {
+        let (storage, root) = temp_storage("list");
+
+        storage.put(Area::Exports, "2024/a.csv", b"").unwrap();
+        storage.put(Area::Exports, "2024/nested/b.csv", b"").unwrap();
+        storage.put(Area::Exports, "2025/c.csv", b"").unwrap();
+
+        assert_eq!(storage.list(Area::Exports, "2024/").unwrap(), vec!["2024/a.csv", "2024/nested/b.csv"]);
+        assert_eq!(storage.list(Area::Exports, "").unwrap().len(), 3);
+        assert!(storage.list(Area::Models, "missing/").unwrap().is_empty());
+        assert_eq!(storage.usage(Area::Exports).unwrap(), 0);
+        storage.put(Area::Exports, "2025/d.csv", b"a,b\n").unwrap();
+        assert_eq!(storage.usage(Area::Exports).unwrap(), 4);
+        assert_eq!(storage.usage(Area::Archive).unwrap(), 0);
+
+        fs::remove_dir_all(root).ok();
+    }
+}
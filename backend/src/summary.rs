@@ -0,0 +1,211 @@
+use chrono::{NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub use crate::api_types::{DiskUsage, LabelCount};
+use crate::api_types::{ActiveModel, PredictionCounts, SummaryResponse};
+use crate::auth;
+use crate::cache;
+use crate::classifier;
+use crate::db;
+use crate::models;
+use crate::predictions::format_timestamp;
+use crate::request_logger::RequestLogger;
+use crate::storage::{self, Area};
+
+/// How long a daily counter is kept after its first increment; long enough to outlive the day it counts.
+const COUNTER_TTL: Duration = Duration::from_secs(2 * 24 * 60 * 60);
+
+/// Key of the counter for failed classifications on a day (UTC).
+fn errors_key(date: NaiveDate) -> String {
+    format!("summary:prediction_errors:{}", date)
+}
+
+/// Counts a failed classification towards today's error rate.
+/// Kept in the shared store so every replica contributes; failures to count are only logged.
+pub async fn count_prediction_error(logger: &RequestLogger) {
+    let Some(store) = cache::store_for_request(logger).await else {
+        return;
+    };
+    if let Err(message) = store.increment(&errors_key(Utc::now().date_naive()), COUNTER_TTL).await {
+        logger.error(format!("Failed to count prediction error: {}", message));
+    }
+}
+
+/// Reads the number of failed classifications on a day, treating an unavailable store as none.
+pub async fn prediction_errors(date: NaiveDate, logger: &RequestLogger) -> i64 {
+    let Some(store) = cache::store_for_request(logger).await else {
+        return 0;
+    };
+    match store.get(&errors_key(date)).await {
+        Ok(value) => value.and_then(|v| v.parse().ok()).unwrap_or(0),
+        Err(message) => {
+            logger.error(format!("Failed to read prediction errors: {}", message));
+            0
+        }
+    }
+}
+
+/// Share of classification attempts that failed, or 0 if there were none.
+pub fn error_rate(errors: i64, served: i64) -> f64 {
+    match errors + served {
+        0 => 0.0,
+        attempts => errors as f64 / attempts as f64,
+    }
+}
+
+/// Counts predictions recorded at or after `since`, by verdict.
+pub async fn predictions_since(pool: &db::Pool, since: &str) -> Result<BTreeMap<String, i64>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT prediction, COUNT(*) FROM predictions WHERE created_at >= $1 GROUP BY prediction"
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Counts the training dataset by label.
+pub async fn dataset_by_label(pool: &db::Pool) -> Result<Vec<LabelCount>, sqlx::Error> {
+    sqlx::query_as::<_, LabelCount>(
+        "SELECT COALESCE(reviewed_label, label) AS label,
+                COUNT(*) AS samples,
+                SUM(CASE WHEN review_status = 'approved' THEN 1 ELSE 0 END) AS approved
+         FROM samples
+         WHERE review_status <> 'rejected'
+         GROUP BY COALESCE(reviewed_label, label)
+         ORDER BY label"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Counts samples waiting for review.
+pub async fn pending_reviews(pool: &db::Pool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM samples WHERE review_status = 'pending'")
+        .fetch_one(pool)
+        .await
+}
+
+/// Bytes stored in each area that grows with use, plus their total.
+/// An area whose size can't be read is reported as `null` and left out of the total.
+/// Measuring walks every stored file, so it runs on the blocking thread pool.
+pub async fn disk_usage(logger: &RequestLogger) -> DiskUsage {
+    let handle = logger.handle();
+    let measured = rusty_api::web::block(move || {
+        let storage = storage::get();
+        let measure = |name: &str, area: Area| match storage.usage(area) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                handle.error(format!("Failed to measure {} usage: {}", name, e));
+                None
+            }
+        };
+        [
+            measure("training_data", Area::TrainingData),
+            measure("exports", Area::Exports),
+            measure("archive", Area::Archive),
+            measure("prediction_images", Area::PredictionImages),
+        ]
+    })
+    .await;
+    let [training_data, exports, archive, prediction_images] = measured.unwrap_or_else(|e| {
+        logger.error(format!("Failed to measure disk usage: {}", e));
+        [None; 4]
+    });
+    DiskUsage {
+        training_data,
+        exports,
+        archive,
+        prediction_images,
+        total: [training_data, exports, archive, prediction_images].into_iter().flatten().sum(),
+    }
+}
+
+/// Summary route handler returning everything the admin dashboard shows in one document.
+/// Requires the admin token. Counts for "today" cover the current UTC day.
+pub async fn summary_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /admin/summary");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized summary request");
+        return resp;
+    }
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+
+    let today = Utc::now().date_naive();
+    let since = format_timestamp(today.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    let counts = async {
+        Ok::<_, sqlx::Error>((
+            predictions_since(pool, &since).await?,
+            dataset_by_label(pool).await?,
+            pending_reviews(pool).await?,
+        ))
+    };
+    let (by_prediction, dataset, pending) = match counts.await {
+        Ok(counts) => counts,
+        Err(e) => {
+            logger.error(format!("Failed to load summary: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    let served: i64 = by_prediction.values().sum();
+    let errors = prediction_errors(today, &logger).await;
+
+    let model = models::active();
+    let eval_metrics = models::eval_metrics(model).unwrap_or_else(|message| {
+        logger.error(&message);
+        None
+    });
+
+    rusty_api::HttpResponse::Ok().json(SummaryResponse {
+        date: today.to_string(),
+        predictions_today: PredictionCounts { total: served, by_prediction },
+        errors_today: errors,
+        error_rate: error_rate(errors, served),
+        queue_depth: classifier::in_flight(),
+        dataset,
+        pending_reviews: pending,
+        active_model: ActiveModel { name: model.name.clone(), dir: model.dir.clone(), eval_metrics },
+        disk_usage_bytes: disk_usage(&logger).await,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::predictions::{self, NewPrediction};
+    use crate::samples::{self, NewSample};
+
+    #[tokio::test]
+    async fn summary_counts_todays_predictions_and_dataset_labels() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        for (verdict, created_at) in [("match_ready", "2024-05-01T10:00:00.000Z"), ("match_ready", "2024-05-02T09:00:00.000Z"), ("unknown", "2024-05-02T11:00:00.000Z")] {
+            let prediction = NewPrediction { prediction: verdict, model_prediction: verdict, ..NewPrediction::example() };
+            let id = predictions::record(&pool, &prediction).await.unwrap();
+            sqlx::query("UPDATE predictions SET created_at = $1 WHERE id = $2").bind(created_at).bind(id).execute(&pool).await.unwrap();
+        }
+        let counts = predictions_since(&pool, "2024-05-02T00:00:00.000Z").await.unwrap();
+        assert_eq!(counts, BTreeMap::from([("match_ready".to_string(), 1), ("unknown".to_string(), 1)]));
+
+        for label in ["match_ready", "match_ready", "not_match_ready"] {
+            let sample = NewSample { label, ..NewSample::example() };
+            samples::record(&pool, &sample).await.unwrap();
+        }
+        samples::review(&pool, 1, true, Some("not_match_ready")).await.unwrap();
+        samples::review(&pool, 2, false, None).await.unwrap();
+
+        assert_eq!(dataset_by_label(&pool).await.unwrap(), vec![LabelCount { label: "not_match_ready".to_string(), samples: 2, approved: 1 }]);
+        assert_eq!(pending_reviews(&pool).await.unwrap(), 1);
+        assert_eq!(error_rate(1, 3), 0.25);
+        assert_eq!(error_rate(0, 0), 0.0);
+    }
+}
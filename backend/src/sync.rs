@@ -0,0 +1,499 @@
+use bytes::BytesMut;
+use chrono::Utc;
+use futures_util::StreamExt as _;
+use sqlx::{Any, Executor};
+use std::collections::{HashMap, HashSet};
+
+use crate::abuse;
+use crate::api_keys;
+pub use crate::api_types::{ItemKind, SyncItem, SyncManifest};
+use crate::api_types::{InvalidResponse, SyncResponse, SyncResult, SyncStatus, TrainingLogEntry};
+use crate::balls;
+use crate::classifier;
+use crate::db;
+use crate::drift;
+use crate::exif;
+use crate::faults::{self, Faults};
+use crate::flags;
+use crate::heif;
+use crate::i18n::Locale;
+use crate::labels;
+use crate::models;
+use crate::predictions::{self, format_timestamp};
+use crate::profiles;
+use crate::quality::{self, QualityMode};
+use crate::rate_limit;
+use crate::replay;
+use crate::request_logger::RequestLogger;
+use crate::samples;
+use crate::settings;
+use crate::training;
+use crate::uploads;
+
+/// Largest number of items accepted in one sync batch.
+const MAX_ITEMS: usize = 50;
+
+impl ItemKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ItemKind::Prediction => "prediction",
+            ItemKind::Training => "training",
+        }
+    }
+}
+
+impl SyncResult {
+    /// A result with only the item's identity and status; the rest is filled in as the item is processed.
+    fn new(item: &SyncItem, status: SyncStatus) -> Self {
+        SyncResult {
+            client_id: item.client_id.clone(),
+            kind: item.kind,
+            status,
+            id: None,
+            issues: None,
+            quality_warnings: None,
+            error: None,
+            prediction: None,
+            confidence: None,
+            model_prediction: None,
+            profile: None,
+            model_version: None,
+            ball_id: None,
+            verdict: None,
+            recommendation: None,
+        }
+    }
+}
+
+/// Checks a batch before anything is processed, so a malformed batch is rejected as a whole.
+/// Returns one message per problem found.
+/// Training items must use a label from `labels`, the taxonomy.
+pub fn validate(manifest: &SyncManifest, images: &HashMap<String, BytesMut>, labels: &[String]) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if manifest.items.is_empty() {
+        errors.push("Batch contains no items".to_string());
+    }
+    if manifest.items.len() > MAX_ITEMS {
+        errors.push(format!("Batch contains {} items; the limit is {}", manifest.items.len(), MAX_ITEMS));
+    }
+
+    let mut seen = HashSet::new();
+    for (index, item) in manifest.items.iter().enumerate() {
+        if item.client_id.trim().is_empty() {
+            errors.push(format!("Item {}: client_id is required", index));
+        } else if !seen.insert(item.client_id.as_str()) {
+            errors.push(format!("Item {}: duplicate client_id '{}'", index, item.client_id));
+        }
+
+        match images.get(&item.image) {
+            Some(bytes) if !bytes.is_empty() => {
+                if image::guess_format(bytes).is_err() {
+                    errors.push(format!("Item {}: field '{}' is not a supported image", index, item.image));
+                }
+            }
+            _ => errors.push(format!("Item {}: no image data in field '{}'", index, item.image)),
+        }
+
+        match (item.kind, item.label.as_deref()) {
+            (ItemKind::Training, None) => errors.push(format!("Item {}: label is required for training data", index)),
+            (ItemKind::Training, Some(label)) if !labels.iter().any(|l| l == label) => {
+                errors.push(format!("Item {}: label must be one of: {}", index, labels.join(", ")));
+            }
+            _ => {}
+        }
+    }
+
+    errors
+}
+
+/// Returns the client IDs from a batch that were already processed, with their kind and result ID.
+async fn processed_items(pool: &db::Pool, items: &[SyncItem]) -> Result<HashMap<String, (String, i64)>, sqlx::Error> {
+    let mut processed = HashMap::new();
+    for item in items {
+        let row: Option<(String, i64)> = sqlx::query_as("SELECT kind, result_id FROM sync_items WHERE client_id = $1")
+            .bind(&item.client_id)
+            .fetch_optional(pool)
+            .await?;
+        if let Some(row) = row {
+            processed.insert(item.client_id.clone(), row);
+        }
+    }
+    Ok(processed)
+}
+
+/// Remembers that a client item has been processed.
+async fn mark_processed<'e>(executor: impl Executor<'e, Database = Any>, client_id: &str, kind: ItemKind, result_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO sync_items (client_id, kind, result_id, created_at) VALUES ($1, $2, $3, $4)")
+        .bind(client_id)
+        .bind(kind.as_str())
+        .bind(result_id)
+        .bind(format_timestamp(Utc::now()))
+        .execute(executor)
+        .await?;
+    Ok(())
+}
+
+/// Saves every training item in one transaction: either all samples are stored or none are.
+/// Returns the new sample IDs and saved images, in the order given.
+async fn save_training_batch(
+    pool: &db::Pool,
+    items: &[(usize, &SyncItem, &[u8])],
+    request_id: i64,
+    faults: Faults,
+) -> Result<Vec<(i64, training::SavedImage)>, String> {
+    let mut written: Vec<(String, String)> = Vec::new();
+
+    let result: Result<Vec<(i64, training::SavedImage)>, String> = async {
+        let mut tx = pool.begin().await.map_err(|e| format!("Database error: {}", e))?;
+        let mut saved = Vec::new();
+
+        for (_, item, image_bytes) in items {
+            let label = item.label.as_deref().unwrap_or_default();
+            faults.check_write().map_err(|e| format!("Failed to write training image: {}", e))?;
+            let image = training::write_image(image_bytes)?;
+            if image.created {
+                written.push((image.content_hash.clone(), image.key.clone()));
+            }
+
+            let sample = samples::NewSample {
+                request_id,
+                contributor: item.contributor.as_deref(),
+                label,
+                filename: &image.filename,
+                file_path: &image.file_path,
+                content_hash: &image.content_hash,
+                image_size_bytes: image_bytes.len(),
+                prediction_id: None,
+            };
+            let sample_id = samples::record(&mut *tx, &sample).await.map_err(|e| format!("Database error: {}", e))?;
+            mark_processed(&mut *tx, &item.client_id, ItemKind::Training, sample_id)
+                .await
+                .map_err(|e| format!("Database error: {}", e))?;
+            saved.push((sample_id, image));
+        }
+
+        tx.commit().await.map_err(|e| format!("Database error: {}", e))?;
+        Ok(saved)
+    }
+    .await;
+
+    // The transaction rolls back when dropped; remove the images it stored,
+    // unless a sample saved concurrently has since referenced the same image
+    if result.is_err() {
+        for (content_hash, key) in &written {
+            if let Ok(false) = samples::hash_in_use(pool, content_hash).await {
+                training::remove_image(key).ok();
+                training::remove_original(content_hash).ok();
+            }
+        }
+    }
+
+    result
+}
+
+/// Sync route handler for batches queued on the phone while offline.
+/// Accepts multipart form-data with a JSON "manifest" field and one field per image named in it.
+pub async fn sync_route(req: rusty_api::HttpRequest, payload: rusty_api::web::Payload) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let locale = Locale::from_request(&req);
+
+    logger.info("Received request to /sync");
+
+    if let Err(resp) = abuse::check(&req, &logger).await {
+        return resp;
+    }
+    if let Err(resp) = rate_limit::check_upload(&req, &logger).await {
+        return resp;
+    }
+    let _upload = match rate_limit::acquire_upload(&req, &logger) {
+        Ok(slot) => slot,
+        Err(resp) => return resp,
+    };
+
+    let api_key = match api_keys::authenticate(&req, &logger).await {
+        Ok(api_key) => api_key,
+        Err(resp) => return resp,
+    };
+
+    let settings = settings::current(&logger).await;
+    let profile = match profiles::select(&req, &settings, api_key.as_ref()) {
+        Ok(profile) => profile,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::BadRequest().body(message);
+        }
+    };
+
+    let pool = match db::pool_for_request(&logger).await {
+        Ok(pool) => pool,
+        Err(resp) => return resp,
+    };
+    let labels = match labels::names(pool).await {
+        Ok(labels) => labels,
+        Err(e) => {
+            logger.error(format!("Failed to load labels: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    // Collect the manifest and every image field
+    let mut payload = uploads::multipart(&req, payload, &logger).await;
+    let upload: Result<(SyncManifest, HashMap<String, BytesMut>), rusty_api::HttpResponse> = async {
+        let mut manifest = None;
+        let mut images: HashMap<String, BytesMut> = HashMap::new();
+        while let Some(item) = payload.next().await {
+            let mut field = item.map_err(|e| rusty_api::HttpResponse::BadRequest().body(format!("Multipart error: {e}")))?;
+            let name = field.name().to_string();
+            let data = crate::read_field(&mut field).await?;
+
+            if name == "manifest" {
+                match serde_json::from_slice::<SyncManifest>(&data) {
+                    Ok(parsed) => manifest = Some(parsed),
+                    Err(e) => {
+                        logger.error(format!("Invalid manifest: {}", e));
+                        return Err(rusty_api::HttpResponse::BadRequest().body(format!("Invalid manifest: {}", e)));
+                    }
+                }
+            } else if heif::is_heif(&data) {
+                // Photos the converter can't read are left as they are, for validation to report
+                match heif::to_jpeg(&data, &format!("{}_{}", request_id, images.len())) {
+                    Ok(jpeg) => images.insert(name, BytesMut::from(&jpeg[..])),
+                    Err(heif::ConvertError::Unreadable(message)) => {
+                        logger.error(format!("Field '{}': {}", name, message));
+                        images.insert(name, data)
+                    }
+                    Err(heif::ConvertError::Unavailable(message)) => {
+                        logger.error(&message);
+                        return Err(rusty_api::HttpResponse::InternalServerError().body(message));
+                    }
+                };
+            } else {
+                images.insert(name, data);
+            }
+        }
+
+        let manifest = manifest.ok_or_else(|| rusty_api::HttpResponse::BadRequest().body("Manifest is required"))?;
+        let errors = validate(&manifest, &images, &labels);
+        if !errors.is_empty() {
+            logger.error(format!("Rejected sync batch: {}", errors.join("; ")));
+            return Err(rusty_api::HttpResponse::BadRequest().json(InvalidResponse { status: "invalid", errors }));
+        }
+        Ok((manifest, images))
+    }
+    .await;
+
+    let (manifest, images) = match upload {
+        Ok(upload) => upload,
+        Err(resp) => {
+            abuse::record_rejection(&req, &logger).await;
+            return resp;
+        }
+    };
+    abuse::record_success(&req, &logger).await;
+
+    // The key must allow every kind of item in the batch
+    for (kind, scope) in [(ItemKind::Prediction, "predict"), (ItemKind::Training, "training")] {
+        if manifest.items.iter().any(|item| item.kind == kind) {
+            if let Err(resp) = api_keys::require_scope(api_key.as_ref(), scope, &logger) {
+                return resp;
+            }
+        }
+    }
+
+    // Predictions may only reference registered balls
+    for item in &manifest.items {
+        if let Some(ball_id) = &item.ball_id {
+            match balls::find(pool, ball_id).await {
+                Ok(Some(_)) => {}
+                Ok(None) => return rusty_api::HttpResponse::BadRequest().body(format!("Ball '{}' is not registered", ball_id)),
+                Err(e) => return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e)),
+            }
+        }
+    }
+
+    let processed = match processed_items(pool, &manifest.items).await {
+        Ok(processed) => processed,
+        Err(e) => {
+            logger.error(format!("Failed to look up processed items: {}", e));
+            return rusty_api::HttpResponse::InternalServerError().body(format!("Database error: {}", e));
+        }
+    };
+
+    logger.info(format!("Sync batch: {} item(s), {} already processed", manifest.items.len(), processed.len()));
+
+    // Every item's status is settled below
+    let mut results: Vec<SyncResult> = manifest.items.iter().map(|item| SyncResult::new(item, SyncStatus::Error)).collect();
+
+    // Mark retried items and screen photo quality; only new, acceptable items are processed
+    let mut training_items = Vec::new();
+    let mut prediction_items = Vec::new();
+    for (index, item) in manifest.items.iter().enumerate() {
+        if let Some((_, result_id)) = processed.get(&item.client_id) {
+            results[index].status = SyncStatus::Duplicate;
+            results[index].id = Some(*result_id);
+            continue;
+        }
+
+        let image_bytes: &[u8] = &images[&item.image];
+        let issues = quality::issues_for(image_bytes, settings.quality_mode, locale, &logger);
+        if settings.quality_mode == QualityMode::Reject && !issues.is_empty() {
+            results[index].status = SyncStatus::Rejected;
+            results[index].issues = Some(issues);
+            continue;
+        }
+        results[index].quality_warnings = Some(issues);
+
+        match item.kind {
+            ItemKind::Training => training_items.push((index, item, image_bytes)),
+            ItemKind::Prediction => prediction_items.push((index, item, image_bytes)),
+        }
+    }
+
+    // Training submissions are stored all-or-nothing
+    let faults = faults::for_request(&req, &logger);
+    match save_training_batch(pool, &training_items, request_id, faults).await {
+        Ok(saved) => {
+            for ((index, item, image_bytes), (sample_id, image)) in training_items.iter().zip(saved) {
+                let capture = exif::read(image_bytes);
+                let log_entry = TrainingLogEntry {
+                    timestamp: Utc::now().to_rfc3339(),
+                    request_id,
+                    label: item.label.clone().unwrap_or_default(),
+                    contributor: item.contributor.clone(),
+                    filename: image.filename.clone(),
+                    file_path: image.file_path.clone(),
+                    content_hash: Some(image.content_hash.clone()),
+                    image_size_bytes: image_bytes.len() as u64,
+                    stored_size_bytes: Some(image.stored_bytes as u64),
+                    client_id: Some(item.client_id.clone()),
+                    capture: capture.clone(),
+                    ..TrainingLogEntry::default()
+                };
+                if let Err(e) = training::append_log(&log_entry) {
+                    logger.error(format!("Failed to write to training log: {}", e));
+                }
+                drift::observe_training(pool, &image.content_hash, image_bytes, &logger).await;
+                exif::observe(pool, sample_id, capture.as_ref(), &logger).await;
+                results[*index].status = SyncStatus::Saved;
+                results[*index].id = Some(sample_id);
+            }
+        }
+        Err(message) => {
+            logger.error(format!("Failed to save training batch: {}", message));
+            return rusty_api::HttpResponse::InternalServerError().body(message);
+        }
+    }
+
+    // Predictions are independent, so each gets its own result
+    let (serving_model, _) = models::for_request(flags::is_enabled(&settings, flags::CANDIDATE_MODEL, api_key.as_ref()));
+    let options = classifier::Options { tta: flags::is_enabled(&settings, flags::TTA, api_key.as_ref()), faults, ..Default::default() };
+    for (index, item, image_bytes) in prediction_items {
+        let output = match classifier::classify_cached(image_bytes, format!("{}_{}", request_id, index), &[serving_model], options, &logger).await {
+            Ok(mut outputs) => outputs.remove(0),
+            Err(message) => {
+                results[index].error = Some(message);
+                continue;
+            }
+        };
+
+        let prediction = profile.decide(&output.prediction, output.confidence);
+        let image_hash = replay::keep_image(image_bytes, &logger);
+        let record = predictions::NewPrediction {
+            request_id,
+            prediction,
+            confidence: output.confidence,
+            image_size_bytes: image_bytes.len(),
+            profile: profile.name,
+            model_prediction: &output.prediction,
+            ball_id: item.ball_id.as_deref(),
+            model_version: &output.model_version,
+            second_opinion_model: None,
+            second_opinion_prediction: None,
+            image_hash: image_hash.as_deref(),
+        };
+
+        let stored = match predictions::record(pool, &record).await {
+            Ok(id) => {
+                drift::observe_prediction(pool, id, image_bytes, &logger).await;
+                mark_processed(pool, &item.client_id, ItemKind::Prediction, id).await.map(|_| id)
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = &stored {
+            logger.error(format!("Failed to record synced prediction: {}", e));
+        }
+
+        let (verdict, recommendation) = labels::verdict(locale, prediction, &logger).await;
+        let result = &mut results[index];
+        result.status = SyncStatus::Predicted;
+        result.id = stored.ok();
+        result.prediction = Some(prediction.to_string());
+        result.confidence = Some(output.confidence);
+        result.model_prediction = Some(output.prediction);
+        result.profile = Some(profile.name);
+        result.model_version = Some(output.model_version);
+        result.ball_id = item.ball_id.clone();
+        result.verdict = Some(verdict);
+        result.recommendation = Some(recommendation);
+    }
+
+    logger.info("Sync batch processed");
+
+    rusty_api::HttpResponse::Ok()
+        .insert_header(("Content-Language", locale.tag()))
+        .json(SyncResponse {
+            status: "success",
+            request_id,
+            results,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(client_id: &str, kind: ItemKind, image: &str, label: Option<&str>) -> SyncItem {
+        SyncItem {
+            client_id: client_id.to_string(),
+            kind,
+            image: image.to_string(),
+            ball_id: None,
+            label: label.map(str::to_string),
+            contributor: None,
+        }
+    }
+
+    #[test]
+    fn validate_reports_every_problem_in_the_batch() {
+        let mut images = HashMap::new();
+        images.insert("a".to_string(), BytesMut::from(&b"\xFF\xD8\xFF\xE0jpeg"[..]));
+        images.insert("b".to_string(), BytesMut::from(&b"not an image"[..]));
+
+        let manifest = SyncManifest {
+            items: vec![
+                item("1", ItemKind::Prediction, "a", None),
+                item("1", ItemKind::Training, "a", Some("shiny")),
+                item("2", ItemKind::Training, "missing", None),
+                item("3", ItemKind::Prediction, "b", None),
+            ],
+        };
+
+        let errors = validate(&manifest, &images, &["match_ready".to_string(), "not_match_ready".to_string()]);
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert!(errors[0].contains("duplicate client_id"));
+    }
+
+    #[tokio::test]
+    async fn retried_items_are_recognised_as_processed() {
+        let pool = db::connect("sqlite::memory:").await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+        mark_processed(&mut *tx, "phone-1", ItemKind::Training, 7).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let items = vec![item("phone-1", ItemKind::Training, "a", Some("match_ready"))];
+        let processed = processed_items(&pool, &items).await.unwrap();
+        assert_eq!(processed["phone-1"], ("training".to_string(), 7));
+    }
+}
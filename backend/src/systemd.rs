@@ -0,0 +1,107 @@
+use nix::fcntl::{fcntl, FcntlArg, FdFlag};
+use std::io;
+use std::ops::Range;
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+/// First file descriptor systemd passes sockets in, after standard input, output and error.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// The file descriptors systemd passed to this process, given the `LISTEN_PID` and `LISTEN_FDS` it set.
+/// Sockets passed to another process, e.g. the shell that started this one, aren't ours to take.
+fn passed_fds(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> Range<RawFd> {
+    let ours = listen_pid.and_then(|v| v.parse::<u32>().ok()) == Some(pid);
+    let count = listen_fds.and_then(|v| v.parse::<RawFd>().ok()).filter(|_| ours).unwrap_or(0);
+    LISTEN_FDS_START..LISTEN_FDS_START + count.max(0)
+}
+
+/// Takes the listening sockets systemd passed to the server under socket activation, as `sd_listen_with_fds_names`
+/// does, each with the name given by the socket unit's `FileDescriptorName=` (`unknown` if it has none).
+/// Returns none if the server wasn't socket-activated. The variables naming them are removed, so the
+/// classifier and other child processes don't mistake the sockets for their own.
+pub fn listeners() -> io::Result<Vec<(String, OwnedFd)>> {
+    let fds = passed_fds(
+        std::env::var("LISTEN_PID").ok().as_deref(),
+        std::env::var("LISTEN_FDS").ok().as_deref(),
+        std::process::id(),
+    );
+    let names = std::env::var("LISTEN_FDNAMES").unwrap_or_default();
+    let mut names = names.split(':');
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(name);
+    }
+
+    fds.map(|fd| {
+        let name = names.next().filter(|name| !name.is_empty()).unwrap_or("unknown").to_string();
+        fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))?;
+        // Safety: systemd hands these descriptors to this process alone, and each is taken once
+        Ok((name, unsafe { OwnedFd::from_raw_fd(fd) }))
+    })
+    .collect()
+}
+
+/// Sends a state change, e.g. `READY=1`, to the socket at `path` (`@` for the abstract namespace).
+fn notify_to(path: &str, state: &str) -> io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+    match path.strip_prefix('@') {
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+        }
+        None => {
+            socket.send_to(state.as_bytes(), path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Tells systemd about a state change, as `sd_notify` does. Does nothing unless the service is run with
+/// `Type=notify`, which sets `NOTIFY_SOCKET`. Failures are only printed: they don't stop the server.
+pub fn notify(state: &str) {
+    let Ok(path) = std::env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(e) = notify_to(&path, state) {
+        println!("ERROR: Failed to notify systemd of {}: {}", state, e);
+    }
+}
+
+/// How often to tell systemd the server is alive, if the service has `WatchdogSec=` set: twice per timeout,
+/// as `sd_watchdog_enabled` recommends.
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let timeout = std::env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(timeout / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_sockets_passed_to_this_process_are_taken() {
+        assert_eq!(passed_fds(Some("42"), Some("2"), 42), 3..5);
+        assert_eq!(passed_fds(Some("41"), Some("2"), 42), 3..3);
+        assert_eq!(passed_fds(None, Some("2"), 42), 3..3);
+        assert_eq!(passed_fds(Some("42"), Some("-1"), 42), 3..3);
+    }
+
+    #[test]
+    fn notifications_are_sent_as_datagrams() {
+        let path = std::env::temp_dir().join(format!("cricket-ready-notify-{}.sock", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let receiver = UnixDatagram::bind(&path).unwrap();
+
+        notify_to(path.to_str().unwrap(), "READY=1").unwrap();
+        let mut buf = [0; 64];
+        let len = receiver.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,372 @@
+use chrono::{DateTime, Days, NaiveDate, TimeDelta, Utc};
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageReader};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use std::sync::Mutex;
+
+pub use crate::api_types::TrainingLogEntry;
+use crate::api_types::TrainingLogResponse;
+use crate::auth;
+use crate::config;
+use crate::exif;
+use crate::predictions::parse_range_bound;
+use crate::request_logger::RequestLogger;
+use crate::storage::{self, Area, Storage};
+
+/// Name of the training submission audit log before it was rotated, within the training data area.
+/// It is still read by `/training/log`, before the rotated segments.
+const LEGACY_LOG_KEY: &str = "training_log.jsonl";
+
+/// Prefix of the training log's segments. There is one per day, named after the date (UTC),
+/// and a day's log continues in `<date>.1.jsonl`, `<date>.2.jsonl` and so on once a segment reaches its size limit.
+const LOG_PREFIX: &str = "training_log/";
+
+/// How many entries `/training/log` returns unless `limit` is given, and the most that can be asked for.
+const DEFAULT_LOG_LIMIT: usize = 1000;
+const MAX_LOG_LIMIT: usize = 10_000;
+
+/// Where a training image was written.
+pub struct SavedImage {
+    /// SHA-256 of the image, which is also its name in storage.
+    pub content_hash: String,
+    pub filename: String,
+    /// Storage key of the image within the training data area.
+    pub key: String,
+    /// Location reported by the storage backend, recorded with the sample.
+    pub file_path: String,
+    /// Whether this call stored the image, rather than finding an identical one already there.
+    pub created: bool,
+    /// Size of the stored image, which is smaller than the upload if it was recompressed.
+    pub stored_bytes: usize,
+}
+
+/// Hex-encoded SHA-256 of an image's bytes.
+pub fn content_hash(image_bytes: &[u8]) -> String {
+    Sha256::digest(image_bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Storage key for an image with the given content hash.
+/// Images are fanned out by the first two hex digits to keep directories small.
+pub fn image_key(content_hash: &str) -> String {
+    format!("images/{}/{}.jpg", &content_hash[..2], content_hash)
+}
+
+/// Storage key for the uploaded original of a recompressed image, by the content hash of the stored image.
+pub fn original_key(content_hash: &str) -> String {
+    format!("originals/{}/{}", &content_hash[..2], content_hash)
+}
+
+/// Re-encodes an image as a JPEG at `quality`, scaled down so its longest side is at most `max_dimension`
+/// (`0` for no limit) and turned upright according to its EXIF orientation. Full-resolution phone photos are
+/// far larger than the 224x224 the models train on. Returns `None` if `quality` is `0`, the image can't be
+/// decoded, or the result isn't smaller than the original, in which case the original is stored as it is.
+fn recompress(image_bytes: &[u8], max_dimension: u32, quality: u8) -> Option<Vec<u8>> {
+    if quality == 0 {
+        return None;
+    }
+
+    let mut decoder = ImageReader::new(Cursor::new(image_bytes)).with_guessed_format().ok()?.into_decoder().ok()?;
+    let orientation = decoder.orientation().ok()?;
+    let mut image = DynamicImage::from_decoder(decoder).ok()?;
+    image.apply_orientation(orientation);
+    if max_dimension > 0 && image.width().max(image.height()) > max_dimension {
+        image = image.resize(max_dimension, max_dimension, FilterType::Lanczos3);
+    }
+
+    let mut encoded = Vec::new();
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, quality))
+        .ok()?;
+    (encoded.len() < image_bytes.len()).then_some(encoded)
+}
+
+/// Stores a training image under its content hash, recompressed according to the `TRAINING_IMAGE_*` settings
+/// and without its EXIF metadata, which is recorded with the sample instead.
+/// The hash is of the stored image, so re-uploads of the same photo are still only stored once.
+/// An identical image is only stored once, and concurrent writers of the same image write the same object.
+/// The label lives in the sample's metadata rather than the image's path.
+pub fn write_image(image_bytes: &[u8]) -> Result<SavedImage, String> {
+    let config = config::get();
+    let image_bytes = &exif::strip(image_bytes);
+    let recompressed = recompress(image_bytes, config.training_image_max_dimension, config.training_image_quality);
+    let stored_bytes: &[u8] = recompressed.as_deref().unwrap_or(image_bytes);
+
+    let storage = storage::get();
+    let content_hash = content_hash(stored_bytes);
+    let key = image_key(&content_hash);
+    let filename = format!("{}.jpg", content_hash);
+
+    let exists = storage
+        .exists(Area::TrainingData, &key)
+        .map_err(|e| format!("Failed to check training image: {}", e))?;
+    let file_path = if exists {
+        storage.location(Area::TrainingData, &key)
+    } else {
+        storage
+            .put(Area::TrainingData, &key, stored_bytes)
+            .map_err(|e| format!("Failed to write training image: {}", e))?
+    };
+
+    if recompressed.is_some() && config.keep_original_training_images && !exists {
+        storage
+            .put(Area::TrainingData, &original_key(&content_hash), image_bytes)
+            .map_err(|e| format!("Failed to write original training image: {}", e))?;
+    }
+
+    Ok(SavedImage { content_hash, filename, key, file_path, created: !exists, stored_bytes: stored_bytes.len() })
+}
+
+/// Reads a training image by content hash, checking it still matches its hash.
+pub fn read_image(content_hash: &str) -> Result<Vec<u8>, String> {
+    let image_bytes = storage::get()
+        .get(Area::TrainingData, &image_key(content_hash))
+        .map_err(|e| format!("Failed to read training image: {}", e))?;
+    if self::content_hash(&image_bytes) != content_hash {
+        return Err(format!("Training image {} is corrupt", content_hash));
+    }
+    Ok(image_bytes)
+}
+
+/// Reads the kept original of a training image, or `None` if there isn't one.
+pub fn read_original(content_hash: &str) -> Result<Option<Vec<u8>>, String> {
+    match storage::get().get(Area::TrainingData, &original_key(content_hash)) {
+        Ok(original) => Ok(Some(original)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read original training image: {}", e)),
+    }
+}
+
+/// Removes a training image written by `write_image`, given its storage key.
+pub fn remove_image(key: &str) -> std::io::Result<()> {
+    storage::get().delete(Area::TrainingData, key)
+}
+
+/// Removes the kept original of a training image, if there is one.
+pub fn remove_original(content_hash: &str) -> std::io::Result<()> {
+    let storage = storage::get();
+    let key = original_key(content_hash);
+    if storage.exists(Area::TrainingData, &key)? {
+        storage.delete(Area::TrainingData, &key)?;
+    }
+    Ok(())
+}
+
+/// The training log segment this process is appending to.
+struct Segment {
+    date: NaiveDate,
+    index: u32,
+    size: u64,
+}
+
+static ACTIVE_SEGMENT: Mutex<Option<Segment>> = Mutex::new(None);
+
+/// Storage key of a training log segment.
+fn segment_key(date: NaiveDate, index: u32) -> String {
+    match index {
+        0 => format!("{}{}.jsonl", LOG_PREFIX, date),
+        _ => format!("{}{}.{}.jsonl", LOG_PREFIX, date, index),
+    }
+}
+
+/// Date and index of a training log segment, from its storage key.
+fn parse_segment_key(key: &str) -> Option<(NaiveDate, u32)> {
+    let name = key.strip_prefix(LOG_PREFIX)?.strip_suffix(".jsonl")?;
+    let (date, index) = match name.split_once('.') {
+        Some((date, index)) => (date, index.parse().ok()?),
+        None => (name, 0),
+    };
+    Some((NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?, index))
+}
+
+/// Every training log segment, oldest first.
+fn segments(storage: &dyn Storage) -> std::io::Result<Vec<(NaiveDate, u32, String)>> {
+    let mut segments: Vec<(NaiveDate, u32, String)> = storage
+        .list(Area::TrainingData, LOG_PREFIX)?
+        .into_iter()
+        .filter_map(|key| parse_segment_key(&key).map(|(date, index)| (date, index, key)))
+        .collect();
+    segments.sort();
+    Ok(segments)
+}
+
+/// Appends an entry to the training submission audit log, starting a new segment each day
+/// and whenever the current one would grow past `TRAINING_LOG_MAX_BYTES`.
+pub fn append_log(entry: &TrainingLogEntry) -> std::io::Result<()> {
+    let log_line = format!("{}\n", serde_json::to_string(entry)?);
+    let storage = storage::get();
+    let today = Utc::now().date_naive();
+    let max_bytes = config::get().training_log_max_bytes;
+
+    let mut active = ACTIVE_SEGMENT.lock().unwrap();
+    let mut segment = match active.take() {
+        Some(segment) if segment.date == today => segment,
+        // After a restart or at midnight, carry on from the last of today's segments
+        _ => match segments(storage)?.into_iter().rev().find(|(date, _, _)| *date == today) {
+            Some((date, index, key)) => Segment { date, index, size: storage.get(Area::TrainingData, &key)?.len() as u64 },
+            None => Segment { date: today, index: 0, size: 0 },
+        },
+    };
+    if max_bytes > 0 && segment.size > 0 && segment.size + log_line.len() as u64 > max_bytes {
+        segment = Segment { date: today, index: segment.index + 1, size: 0 };
+    }
+
+    storage.append(Area::TrainingData, &segment_key(segment.date, segment.index), log_line.as_bytes())?;
+    segment.size += log_line.len() as u64;
+    *active = Some(segment);
+    Ok(())
+}
+
+/// Whether the segment for `date` can hold entries in `[from, to)`. An entry is written just after
+/// its timestamp is taken, so a segment may start with entries from the very end of the day before.
+fn segment_in_range(date: NaiveDate, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> bool {
+    let start = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    from.is_none_or(|from| from < start + Days::new(1)) && to.is_none_or(|to| to > start - TimeDelta::minutes(1))
+}
+
+/// Whether a log entry was written in `[from, to)` for the given label.
+fn entry_matches(entry: &TrainingLogEntry, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>, label: Option<&str>) -> bool {
+    let timestamp = DateTime::parse_from_rfc3339(&entry.timestamp).ok().map(|t| t.with_timezone(&Utc));
+    let in_range = match timestamp {
+        Some(timestamp) => from.is_none_or(|from| timestamp >= from) && to.is_none_or(|to| timestamp < to),
+        None => from.is_none() && to.is_none(),
+    };
+    in_range && label.is_none_or(|label| entry.label == label)
+}
+
+/// Reads up to `limit` training log entries written in `[from, to)` for the given label, oldest first,
+/// only opening the segments that can hold them. Also returns whether there were more.
+pub fn read_log(
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    label: Option<&str>,
+    limit: usize,
+) -> Result<(Vec<TrainingLogEntry>, bool), String> {
+    let storage = storage::get();
+    let mut keys = Vec::new();
+    if storage.exists(Area::TrainingData, LEGACY_LOG_KEY).map_err(|e| format!("Failed to read training log: {}", e))? {
+        keys.push(LEGACY_LOG_KEY.to_string());
+    }
+    let segments = segments(storage).map_err(|e| format!("Failed to list training log segments: {}", e))?;
+    keys.extend(segments.into_iter().filter(|(date, _, _)| segment_in_range(*date, from, to)).map(|(_, _, key)| key));
+
+    let mut entries = Vec::new();
+    for key in keys {
+        let data = storage.get(Area::TrainingData, &key).map_err(|e| format!("Failed to read training log {}: {}", key, e))?;
+        for line in String::from_utf8_lossy(&data).lines().filter(|line| !line.trim().is_empty()) {
+            let Ok(entry) = serde_json::from_str::<TrainingLogEntry>(line) else { continue };
+            if !entry_matches(&entry, from, to, label) {
+                continue;
+            }
+            if entries.len() == limit {
+                return Ok((entries, true));
+            }
+            entries.push(entry);
+        }
+    }
+    Ok((entries, false))
+}
+
+/// Query parameters accepted by `/training/log`.
+#[derive(Debug, Deserialize)]
+pub struct LogQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub label: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Training log route handler returning the audit trail of training submissions, oldest first.
+/// Accepts `from` and `to` (RFC 3339 timestamps or `YYYY-MM-DD` dates), `label` and `limit`. Requires the admin token.
+pub async fn log_route(req: rusty_api::HttpRequest, query: rusty_api::web::Query<LogQuery>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /training/log");
+
+    if let Err(resp) = auth::require_admin(&req) {
+        logger.error("Rejected unauthorized training log request");
+        return resp;
+    }
+
+    let from = match query.from.as_deref().map(|v| parse_range_bound(v, false)) {
+        Some(None) => return rusty_api::HttpResponse::BadRequest().body("Invalid 'from' date"),
+        from => from.flatten(),
+    };
+    let to = match query.to.as_deref().map(|v| parse_range_bound(v, true)) {
+        Some(None) => return rusty_api::HttpResponse::BadRequest().body("Invalid 'to' date"),
+        to => to.flatten(),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LOG_LIMIT);
+    if limit == 0 || limit > MAX_LOG_LIMIT {
+        return rusty_api::HttpResponse::BadRequest().body(format!("limit must be between 1 and {}", MAX_LOG_LIMIT));
+    }
+
+    let (entries, truncated) = match read_log(from, to, query.label.as_deref(), limit) {
+        Ok(result) => result,
+        Err(message) => {
+            logger.error(&message);
+            return rusty_api::HttpResponse::InternalServerError().body(message);
+        }
+    };
+
+    logger.info(format!("Returning {} training log entries", entries.len()));
+    rusty_api::HttpResponse::Ok().json(TrainingLogResponse { count: entries.len(), truncated, entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn image_key_is_derived_from_content() {
+        let hash = content_hash(b"ball");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, content_hash(b"ball"));
+        assert_eq!(image_key(&hash), format!("images/{}/{}.jpg", &hash[..2], hash));
+    }
+
+    #[test]
+    fn large_photos_are_scaled_down_and_small_ones_kept() {
+        // Noisy enough that the PNG doesn't compress well, like a photo
+        let photo = image::RgbImage::from_fn(1600, 1200, |x, y| {
+            let noise = (x.wrapping_mul(7919) ^ y.wrapping_mul(104729)).wrapping_mul(2654435761) >> 24;
+            image::Rgb([noise as u8, (y % 256) as u8, 90])
+        });
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(photo).write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).unwrap();
+
+        let recompressed = recompress(&png, 800, 85).expect("large PNG should shrink");
+        let stored = image::load_from_memory(&recompressed).unwrap();
+        assert_eq!((stored.width(), stored.height()), (800, 600));
+        assert_eq!(image::guess_format(&recompressed).unwrap(), image::ImageFormat::Jpeg);
+
+        assert_eq!(recompress(&png, 800, 0), None);
+        assert_eq!(recompress(&recompressed, 0, 100), None, "re-encoding at a higher quality isn't smaller");
+    }
+
+    #[test]
+    fn log_segments_are_named_by_day_and_filtered_by_range() {
+        let day = NaiveDate::from_ymd_opt(2025, 3, 14).unwrap();
+        assert_eq!(segment_key(day, 0), "training_log/2025-03-14.jsonl");
+        assert_eq!(parse_segment_key(&segment_key(day, 2)), Some((day, 2)));
+        assert_eq!(parse_segment_key("training_log/notes.txt"), None);
+
+        let from = parse_range_bound("2025-03-14", false);
+        let to = parse_range_bound("2025-03-14", true);
+        assert!(!segment_in_range(day.pred_opt().unwrap(), from, to));
+        assert!(segment_in_range(day.succ_opt().unwrap(), from, to), "may hold entries from just before midnight");
+        assert!(!segment_in_range(day + Days::new(2), from, to));
+
+        let entry = TrainingLogEntry {
+            timestamp: "2025-03-14T23:59:59.999+00:00".to_string(),
+            label: "match_ready".to_string(),
+            ..TrainingLogEntry::default()
+        };
+        assert!(entry_matches(&entry, from, to, Some("match_ready")));
+        assert!(!entry_matches(&entry, from, to, Some("not_match_ready")));
+        assert!(!entry_matches(&entry, to, None, None));
+    }
+}
@@ -0,0 +1,229 @@
+use actix_multipart::Multipart;
+use chrono::Utc;
+use futures_util::StreamExt;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::api_types::{UploadProgress, UploadSession, UploadStatus};
+use crate::cache::{self, Store};
+use crate::request_logger::RequestLogger;
+
+/// Header naming the upload session an upload reports its progress to.
+pub const SESSION_HEADER: &str = "X-Upload-Session";
+/// How long an upload session lasts after it was created or last made progress.
+const SESSION_TTL: Duration = Duration::from_secs(60 * 60);
+/// Bytes received between progress updates, so large uploads don't write to the store for every chunk.
+const UPDATE_EVERY_BYTES: u64 = 256 * 1024;
+const SESSION_ID_LENGTH: usize = 32;
+
+/// Progress of an upload session, as kept in the shared store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Progress {
+    pub status: UploadStatus,
+    pub received_bytes: u64,
+    /// Size of the whole request body, from the session or the upload's `Content-Length`, if known.
+    pub total_bytes: Option<u64>,
+}
+
+impl Progress {
+    /// Share of the upload received so far, if its size is known.
+    fn fraction(&self) -> Option<f64> {
+        match (self.status, self.total_bytes) {
+            (UploadStatus::Received, _) => Some(1.0),
+            (_, Some(0)) | (_, None) => None,
+            (_, Some(total)) => Some((self.received_bytes as f64 / total as f64).min(1.0)),
+        }
+    }
+}
+
+fn session_key(id: &str) -> String {
+    format!("upload:{}", id)
+}
+
+/// Generates a new random session ID.
+fn new_session_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SESSION_ID_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// Counts the bytes of an upload as they arrive, saving its progress to the store.
+struct Tracker {
+    store: &'static Store,
+    key: String,
+    progress: Progress,
+    saved_bytes: u64,
+    /// Logs under the ID of the request the upload belongs to.
+    logger: RequestLogger,
+}
+
+impl Tracker {
+    /// Saves the progress if the upload has moved on far enough, or always if `force` is set.
+    /// Failures are only logged: the upload itself carries on.
+    async fn save(&mut self, force: bool) {
+        if !force && self.progress.received_bytes - self.saved_bytes < UPDATE_EVERY_BYTES {
+            return;
+        }
+        self.saved_bytes = self.progress.received_bytes;
+        if let Err(message) = self.store.set_json(&self.key, &self.progress, SESSION_TTL).await {
+            self.logger.error(format!("Failed to save upload progress: {}", message));
+        }
+    }
+}
+
+/// Reads a request's multipart body, reporting its progress to the upload session named by the
+/// `X-Upload-Session` header, if any. Uploads naming an unknown session are read without tracking.
+/// Failures to save progress while the body is read are logged with `logger`.
+pub async fn multipart(req: &rusty_api::HttpRequest, payload: rusty_api::web::Payload, logger: &RequestLogger) -> Multipart {
+    let Some(id) = req.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+        return Multipart::new(req.headers(), payload);
+    };
+
+    let Some(store) = cache::store_for_request(logger).await else {
+        return Multipart::new(req.headers(), payload);
+    };
+    let key = session_key(id);
+    let progress = match store.get_json::<Progress>(&key).await {
+        Ok(Some(progress)) => progress,
+        Ok(None) => {
+            logger.error(format!("Upload session {} not found; progress won't be tracked", id));
+            return Multipart::new(req.headers(), payload);
+        }
+        Err(message) => {
+            logger.error(format!("Failed to read upload session: {}", message));
+            return Multipart::new(req.headers(), payload);
+        }
+    };
+
+    let content_length = req.headers().get("Content-Length").and_then(|v| v.to_str().ok()).and_then(|v| v.parse().ok());
+    let tracker = Tracker {
+        store,
+        key,
+        progress: Progress {
+            status: UploadStatus::Receiving,
+            received_bytes: 0,
+            total_bytes: content_length.or(progress.total_bytes),
+        },
+        saved_bytes: 0,
+        logger: logger.handle(),
+    };
+
+    let stream = futures_util::stream::unfold(Some((payload, tracker)), |state| async move {
+        let (mut payload, mut tracker) = state?;
+        match payload.next().await {
+            Some(Ok(chunk)) => {
+                let first = tracker.progress.received_bytes == 0;
+                tracker.progress.received_bytes += chunk.len() as u64;
+                tracker.save(first).await;
+                Some((Ok(chunk), Some((payload, tracker))))
+            }
+            Some(Err(e)) => {
+                tracker.progress.status = UploadStatus::Interrupted;
+                tracker.save(true).await;
+                Some((Err(e), None))
+            }
+            None => {
+                tracker.progress.status = UploadStatus::Received;
+                tracker.save(true).await;
+                None
+            }
+        }
+    });
+    // Fused, since the multipart reader polls the body again after it has ended
+    Multipart::new(req.headers(), stream.fuse())
+}
+
+/// Upload session route handler starting a session that an upload can report its progress to.
+/// The size of the upload can be given up front as `?total_bytes=`, e.g. for uploads sent without a `Content-Length`.
+pub async fn create_route(req: rusty_api::HttpRequest) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /uploads");
+
+    let total_bytes = match crate::query_param(&req, "total_bytes").map(|v| v.parse::<u64>()) {
+        Some(Ok(total_bytes)) => Some(total_bytes),
+        Some(Err(_)) => return rusty_api::HttpResponse::BadRequest().body("total_bytes must be a whole number of bytes"),
+        None => None,
+    };
+    let Some(store) = cache::store_for_request(&logger).await else {
+        return rusty_api::HttpResponse::ServiceUnavailable().body("Upload progress is unavailable");
+    };
+
+    let id = new_session_id();
+    let progress = Progress { status: UploadStatus::Waiting, received_bytes: 0, total_bytes };
+    if let Err(message) = store.set_json(&session_key(&id), &progress, SESSION_TTL).await {
+        logger.error(format!("Failed to create upload session: {}", message));
+        return rusty_api::HttpResponse::ServiceUnavailable().body("Upload progress is unavailable");
+    }
+
+    logger.info(format!("Created upload session {}", id));
+    rusty_api::HttpResponse::Ok().json(UploadSession {
+        status: "success",
+        id,
+        header: SESSION_HEADER,
+        expires_in_seconds: SESSION_TTL.as_secs(),
+    })
+}
+
+/// Upload progress route handler returning how much of a session's upload has arrived.
+pub async fn progress_route(path: rusty_api::web::Path<String>) -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+    let id = path.into_inner();
+
+    logger.info(format!("Received request to /uploads/{}", id));
+
+    let Some(store) = cache::store_for_request(&logger).await else {
+        return rusty_api::HttpResponse::ServiceUnavailable().body("Upload progress is unavailable");
+    };
+    match store.get_json::<Progress>(&session_key(&id)).await {
+        Ok(Some(progress)) => rusty_api::HttpResponse::Ok().json(UploadProgress {
+            fraction: progress.fraction(),
+            id,
+            status: progress.status,
+            received_bytes: progress.received_bytes,
+            total_bytes: progress.total_bytes,
+        }),
+        Ok(None) => rusty_api::HttpResponse::NotFound().body(format!("Upload session {} not found", id)),
+        Err(message) => {
+            logger.error(format!("Failed to read upload session: {}", message));
+            rusty_api::HttpResponse::ServiceUnavailable().body("Upload progress is unavailable")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn progress_is_saved_in_steps_and_when_the_upload_ends() {
+        let store: &'static Store = Box::leak(Box::new(Store::memory()));
+        let mut tracker = Tracker {
+            store,
+            key: session_key("abc"),
+            progress: Progress { status: UploadStatus::Receiving, received_bytes: 0, total_bytes: Some(UPDATE_EVERY_BYTES * 4) },
+            saved_bytes: 0,
+            logger: RequestLogger::new(1),
+        };
+        let saved = || async { store.get_json::<Progress>(&session_key("abc")).await.unwrap() };
+
+        tracker.progress.received_bytes = UPDATE_EVERY_BYTES - 1;
+        tracker.save(false).await;
+        assert_eq!(saved().await, None);
+
+        tracker.progress.received_bytes = UPDATE_EVERY_BYTES * 2;
+        tracker.save(false).await;
+        assert_eq!(saved().await.unwrap().fraction(), Some(0.5));
+
+        tracker.progress.status = UploadStatus::Received;
+        tracker.save(true).await;
+        let progress = saved().await.unwrap();
+        assert_eq!((progress.status, progress.fraction()), (UploadStatus::Received, Some(1.0)));
+    }
+}
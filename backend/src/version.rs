@@ -0,0 +1,27 @@
+use chrono::Utc;
+
+use crate::api_types::{InferenceDevice, VersionResponse};
+use crate::classifier;
+use crate::config;
+use crate::models;
+use crate::request_logger::RequestLogger;
+
+/// Version route handler returning the server version, the model version serving predictions,
+/// and the device the classifier is configured for and last ran on.
+pub async fn version_route() -> rusty_api::HttpResponse {
+    let request_id = Utc::now().timestamp_millis();
+    let logger = RequestLogger::new(request_id);
+
+    logger.info("Received request to /version");
+
+    let config = config::get();
+    rusty_api::HttpResponse::Ok().json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        active_model: models::active().name.clone(),
+        model_precision: config.model_precision,
+        inference_device: InferenceDevice {
+            configured: config.inference_device.as_arg().to_string(),
+            active: classifier::active_device(),
+        },
+    })
+}
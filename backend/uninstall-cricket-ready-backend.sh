@@ -14,6 +14,19 @@ echo -e "${GREEN}Starting Cricket-Ready Ball Classifier Backend uninstallation..
 INSTALL_DIR="$HOME/Cricket-Ready-Backend"
 SERVICE_NAME="cricket-ready-backend.service"
 SERVICE_FILE="/etc/systemd/system/$SERVICE_NAME"
+SOCKET_NAME="cricket-ready-backend.socket"
+SOCKET_FILE="/etc/systemd/system/$SOCKET_NAME"
+
+# Stop and disable the socket, so it doesn't start the service again
+if systemctl is-active "$SOCKET_NAME" >/dev/null 2>&1; then
+    echo "Stopping $SOCKET_NAME..."
+    sudo systemctl stop "$SOCKET_NAME"
+fi
+
+if systemctl is-enabled "$SOCKET_NAME" >/dev/null 2>&1; then
+    echo "Disabling $SOCKET_NAME..."
+    sudo systemctl disable "$SOCKET_NAME"
+fi
 
 # Stop and disable the service
 if systemctl is-active "$SERVICE_NAME" >/dev/null 2>&1; then
@@ -32,6 +45,11 @@ if [ -f "$SERVICE_FILE" ]; then
     sudo rm "$SERVICE_FILE"
 fi
 
+if [ -f "$SOCKET_FILE" ]; then
+    echo "Removing socket file $SOCKET_FILE..."
+    sudo rm "$SOCKET_FILE"
+fi
+
 # Reload systemd
 echo "Reloading systemd daemon..."
 sudo systemctl daemon-reload
@@ -0,0 +1,1580 @@
+// Generated from backend/src/api_types.rs. Do not edit by hand: run
+// `UPDATE_CLIENT_TYPES=1 cargo test` in backend/ after changing the types there.
+
+import Foundation
+
+/// Number type a model version's weights are stored in. Lower precisions trade a little accuracy
+/// for lower latency and memory, e.g. on a Raspberry Pi.
+public enum Precision: String, Codable {
+    /// The weights as trained, in `model_{1,2,3}.pth`.
+    case fp32
+    /// Half-precision copies made by `quantize.py`, in `model_{1,2,3}.fp16.pth`. Best suited to GPUs.
+    case fp16
+    /// Quantized TorchScript models made by `quantize.py`, in `model_{1,2,3}.int8.pt`. Run on the CPU.
+    case int8
+}
+
+/// How the photo quality pre-check affects a request.
+public enum QualityMode: String, Codable {
+    /// Skip the check entirely.
+    case off
+    /// Attach any issues to the response as warnings.
+    case warn
+    /// Reject photos with any issue before running the classifier.
+    case reject
+}
+
+/// A problem found with a photo, with guidance on how to fix it.
+public struct QualityIssue: Codable {
+    /// Stable code: `too_blurry`, `too_dark`, `too_bright` or `ball_too_small`.
+    public let code: String
+    /// How to retake the photo, in the client's language.
+    public let message: String
+
+    enum CodingKeys: String, CodingKey {
+        case code
+        case message
+    }
+}
+
+/// Body of the `422` response to a photo rejected by the quality pre-check.
+public struct QualityRejection: Codable {
+    public let status: String
+    public let code: String
+    public let message: String
+    public let issues: [QualityIssue]
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case code
+        case message
+        case issues
+    }
+}
+
+/// Body of the `400` response to a request that failed validation, with one message per problem.
+public struct InvalidResponse: Codable {
+    public let status: String
+    public let errors: [String]
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case errors
+    }
+}
+
+/// The second-opinion model's verdict on a `/predict` photo, under the same profile.
+public struct SecondOpinion: Codable {
+    public let prediction: String
+    public let confidence: Double
+    public let modelPrediction: String
+    public let modelVersion: String
+    public let uncertainty: Double?
+
+    enum CodingKeys: String, CodingKey {
+        case prediction
+        case confidence
+        case modelPrediction = "model_prediction"
+        case modelVersion = "model_version"
+        case uncertainty
+    }
+}
+
+/// Response from `/predict`.
+public struct PredictResponse: Codable {
+    /// ID of the stored prediction, for `/predictions/{id}`; `null` if it couldn't be recorded.
+    public let predictionId: Int?
+    /// The verdict under the profile: a label from the taxonomy, or `unknown`.
+    public let prediction: String
+    public let confidence: Double
+    /// The model's verdict before the profile's threshold was applied.
+    public let modelPrediction: String
+    public let modelVersion: String
+    public let modelPrecision: Precision
+    public let profile: String
+    public let ballId: String?
+    /// Spread of the match-ready probability across dropout passes, when asked for with `uncertainty=true` and the `uncertainty` flag is on.
+    public let uncertainty: Double?
+    /// Whether the photo was enhanced, when asked for with `enhance=true` and the `enhance` flag is on.
+    public let enhanced: Bool
+    /// The enhanced photo as a JPEG data URL, when asked for with `return_enhanced=true`.
+    public let enhancedImage: String?
+    public let secondOpinion: SecondOpinion?
+    /// Whether the second opinion reached the same verdict.
+    public let agreement: Bool?
+    public let verdict: String
+    public let recommendation: String
+    public let qualityWarnings: [QualityIssue]
+
+    enum CodingKeys: String, CodingKey {
+        case predictionId = "prediction_id"
+        case prediction
+        case confidence
+        case modelPrediction = "model_prediction"
+        case modelVersion = "model_version"
+        case modelPrecision = "model_precision"
+        case profile
+        case ballId = "ball_id"
+        case uncertainty
+        case enhanced
+        case enhancedImage = "enhanced_image"
+        case secondOpinion = "second_opinion"
+        case agreement
+        case verdict
+        case recommendation
+        case qualityWarnings = "quality_warnings"
+    }
+}
+
+/// Where a ball is in the original photo, in pixels.
+public struct BoundingBox: Codable {
+    public let x: Int
+    public let y: Int
+    public let width: Int
+    public let height: Int
+
+    enum CodingKeys: String, CodingKey {
+        case x
+        case y
+        case width
+        case height
+    }
+}
+
+/// The verdict on one ball found by `/predict/multi`.
+public struct BallPrediction: Codable {
+    public let box: BoundingBox
+    /// ID of the stored prediction, for `/predictions/{id}`; `null` if it couldn't be recorded.
+    public let predictionId: Int?
+    public let prediction: String
+    public let confidence: Double
+    public let modelPrediction: String
+    public let modelVersion: String
+    public let modelPrecision: Precision
+    public let verdict: String
+    public let recommendation: String
+
+    enum CodingKeys: String, CodingKey {
+        case box
+        case predictionId = "prediction_id"
+        case prediction
+        case confidence
+        case modelPrediction = "model_prediction"
+        case modelVersion = "model_version"
+        case modelPrecision = "model_precision"
+        case verdict
+        case recommendation
+    }
+}
+
+/// Response from `/predict/multi`.
+public struct PredictMultiResponse: Codable {
+    public let count: Int
+    public let balls: [BallPrediction]
+    public let profile: String
+    public let qualityWarnings: [QualityIssue]
+
+    enum CodingKeys: String, CodingKey {
+        case count
+        case balls
+        case profile
+        case qualityWarnings = "quality_warnings"
+    }
+}
+
+/// One model version's verdict in `/predict/compare-models`.
+public struct ModelComparison: Codable {
+    public let modelVersion: String
+    public let prediction: String
+    public let confidence: Double
+    public let modelPrediction: String
+    public let modelPrecision: Precision
+    public let uncertainty: Double?
+
+    enum CodingKeys: String, CodingKey {
+        case modelVersion = "model_version"
+        case prediction
+        case confidence
+        case modelPrediction = "model_prediction"
+        case modelPrecision = "model_precision"
+        case uncertainty
+    }
+}
+
+/// Response from `/predict/compare-models`.
+public struct CompareModelsResponse: Codable {
+    public let profile: String
+    public let models: [ModelComparison]
+    /// Whether every model reached the same verdict under the profile.
+    public let agreement: Bool
+    /// Whether every model's own verdict was the same.
+    public let modelAgreement: Bool
+
+    enum CodingKeys: String, CodingKey {
+        case profile
+        case models
+        case agreement
+        case modelAgreement = "model_agreement"
+    }
+}
+
+/// Response from `/training`.
+public struct TrainingResponse: Codable {
+    public let status: String
+    public let message: String
+    public let filename: String
+    public let label: String
+    public let requestId: Int
+    /// ID of the sample recorded for review; `null` if it couldn't be recorded.
+    public let sampleId: Int?
+    public let qualityWarnings: [QualityIssue]
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case message
+        case filename
+        case label
+        case requestId = "request_id"
+        case sampleId = "sample_id"
+        case qualityWarnings = "quality_warnings"
+    }
+}
+
+/// Body accepted by `/predictions/{id}/correct`.
+public struct CorrectionInput: Codable {
+    /// What the ball really was: a label from the taxonomy.
+    public let label: String
+    public let contributor: String?
+
+    enum CodingKeys: String, CodingKey {
+        case label
+        case contributor
+    }
+}
+
+/// Response from `/predictions/{id}/correct`.
+public struct CorrectionResponse: Codable {
+    public let status: String
+    public let predictionId: Int
+    /// ID of the sample queued for review with the corrected label.
+    public let sampleId: Int
+    /// The label the prediction was corrected to, and the prediction it corrects.
+    public let label: String
+    public let prediction: String
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case predictionId = "prediction_id"
+        case sampleId = "sample_id"
+        case label
+        case prediction
+    }
+}
+
+/// The kind of work queued on the phone.
+public enum ItemKind: String, Codable {
+    case prediction
+    case training
+}
+
+/// One queued request, referring to an image sent in the same multipart payload.
+public struct SyncItem: Codable {
+    /// ID generated on the phone, used to make retries idempotent.
+    public let clientId: String
+    public let type: ItemKind
+    /// Name of the multipart field holding this item's image.
+    public let image: String
+    public let ballId: String?
+    public let label: String?
+    public let contributor: String?
+
+    enum CodingKeys: String, CodingKey {
+        case clientId = "client_id"
+        case type
+        case image
+        case ballId = "ball_id"
+        case label
+        case contributor
+    }
+}
+
+/// The `manifest` field of a `/sync` upload, describing every item in the batch.
+public struct SyncManifest: Codable {
+    public let items: [SyncItem]
+
+    enum CodingKeys: String, CodingKey {
+        case items
+    }
+}
+
+/// What became of a synced item.
+public enum SyncStatus: String, Codable {
+    /// A training image was stored.
+    case saved
+    case predicted
+    /// The item was synced before; `id` is what it produced then.
+    case duplicate
+    /// The photo failed the quality pre-check; see `issues`.
+    case rejected
+    /// The item couldn't be processed; see `error`.
+    case error
+}
+
+/// The result for one item of a `/sync` batch. Prediction fields are only given for predicted items.
+public struct SyncResult: Codable {
+    public let clientId: String
+    public let type: ItemKind
+    public let status: SyncStatus
+    /// ID of the new sample or prediction.
+    public let id: Int?
+    public let issues: [QualityIssue]?
+    public let qualityWarnings: [QualityIssue]?
+    public let error: String?
+    public let prediction: String?
+    public let confidence: Double?
+    public let modelPrediction: String?
+    public let profile: String?
+    public let modelVersion: String?
+    public let ballId: String?
+    public let verdict: String?
+    public let recommendation: String?
+
+    enum CodingKeys: String, CodingKey {
+        case clientId = "client_id"
+        case type
+        case status
+        case id
+        case issues
+        case qualityWarnings = "quality_warnings"
+        case error
+        case prediction
+        case confidence
+        case modelPrediction = "model_prediction"
+        case profile
+        case modelVersion = "model_version"
+        case ballId = "ball_id"
+        case verdict
+        case recommendation
+    }
+}
+
+/// Response from `/sync`, with one result per item in manifest order.
+public struct SyncResponse: Codable {
+    public let status: String
+    public let requestId: Int
+    public let results: [SyncResult]
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case requestId = "request_id"
+        case results
+    }
+}
+
+/// A label training images can be given, which is also a class folder in the dataset and a class the models predict.
+public struct Label: Codable {
+    public let name: String
+    public let description: String
+    /// Position of the label when shown to contributors, lowest first.
+    public let displayOrder: Int
+
+    enum CodingKeys: String, CodingKey {
+        case name
+        case description
+        case displayOrder = "display_order"
+    }
+}
+
+/// Response from `/labels`.
+public struct LabelsResponse: Codable {
+    public let labels: [Label]
+
+    enum CodingKeys: String, CodingKey {
+        case labels
+    }
+}
+
+/// Where an upload session's upload has got to.
+public enum UploadStatus: String, Codable {
+    /// No bytes have arrived yet.
+    case waiting
+    case receiving
+    /// Every byte has arrived; the upload is being processed or has been.
+    case received
+    /// The connection broke before the upload finished.
+    case interrupted
+}
+
+/// Response from `/uploads`.
+public struct UploadSession: Codable {
+    public let status: String
+    public let id: String
+    /// Header to send the session ID in with the upload.
+    public let header: String
+    public let expiresInSeconds: Int
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case id
+        case header
+        case expiresInSeconds = "expires_in_seconds"
+    }
+}
+
+/// Response from `/uploads/{id}`.
+public struct UploadProgress: Codable {
+    public let id: String
+    public let status: UploadStatus
+    public let receivedBytes: Int
+    public let totalBytes: Int?
+    /// Share of the upload received so far, if its size is known.
+    public let fraction: Double?
+
+    enum CodingKeys: String, CodingKey {
+        case id
+        case status
+        case receivedBytes = "received_bytes"
+        case totalBytes = "total_bytes"
+        case fraction
+    }
+}
+
+/// Response from `/tags/{tag}`.
+public struct TagResponse: Codable {
+    public let ballId: String
+    public let description: String?
+
+    enum CodingKeys: String, CodingKey {
+        case ballId = "ball_id"
+        case description
+    }
+}
+
+/// A single prediction served by `/predict`, as stored in the metadata database.
+public struct PredictionRecord: Codable {
+    public let id: Int
+    public let requestId: Int
+    public let createdAt: String
+    public let prediction: String
+    public let confidence: Double
+    public let imageSizeBytes: Int
+    public let profile: String
+    public let modelPrediction: String?
+    public let ballId: String?
+    public let modelVersion: String?
+    /// The second-opinion model and its verdict under the profile, if one was asked for.
+    public let secondOpinionModel: String?
+    public let secondOpinionPrediction: String?
+    /// Content hash of the photo, if it was kept for replaying the prediction.
+    public let imageHash: String?
+
+    enum CodingKeys: String, CodingKey {
+        case id
+        case requestId = "request_id"
+        case createdAt = "created_at"
+        case prediction
+        case confidence
+        case imageSizeBytes = "image_size_bytes"
+        case profile
+        case modelPrediction = "model_prediction"
+        case ballId = "ball_id"
+        case modelVersion = "model_version"
+        case secondOpinionModel = "second_opinion_model"
+        case secondOpinionPrediction = "second_opinion_prediction"
+        case imageHash = "image_hash"
+    }
+}
+
+/// Response from `/balls/{id}/predictions`, newest first.
+public struct BallHistoryResponse: Codable {
+    public let ballId: String
+    public let predictions: [PredictionRecord]
+
+    enum CodingKeys: String, CodingKey {
+        case ballId = "ball_id"
+        case predictions
+    }
+}
+
+/// What was served for a prediction when it was made, in `/admin/predictions/{id}/replay`.
+public struct ReplayOriginal: Codable {
+    public let createdAt: String
+    public let prediction: String
+    public let confidence: Double
+    public let modelPrediction: String?
+    public let modelVersion: String?
+
+    enum CodingKeys: String, CodingKey {
+        case createdAt = "created_at"
+        case prediction
+        case confidence
+        case modelPrediction = "model_prediction"
+        case modelVersion = "model_version"
+    }
+}
+
+/// The verdict on a replayed prediction's photo now, in `/admin/predictions/{id}/replay`.
+public struct ReplayVerdict: Codable {
+    public let prediction: String
+    public let confidence: Double
+    public let modelPrediction: String
+    public let modelVersion: String
+    public let modelPrecision: Precision
+
+    enum CodingKeys: String, CodingKey {
+        case prediction
+        case confidence
+        case modelPrediction = "model_prediction"
+        case modelVersion = "model_version"
+        case modelPrecision = "model_precision"
+    }
+}
+
+/// How a replayed prediction differs from the original.
+public struct ReplayDiff: Codable {
+    public let predictionChanged: Bool
+    public let modelPredictionChanged: Bool
+    public let modelVersionChanged: Bool
+    /// The replay's confidence less the original's.
+    public let confidenceChange: Double
+
+    enum CodingKeys: String, CodingKey {
+        case predictionChanged = "prediction_changed"
+        case modelPredictionChanged = "model_prediction_changed"
+        case modelVersionChanged = "model_version_changed"
+        case confidenceChange = "confidence_change"
+    }
+}
+
+/// Response from `/admin/predictions/{id}/replay`.
+public struct ReplayResponse: Codable {
+    public let id: Int
+    public let imageHash: String
+    /// The profile the prediction was made with, applied at its current threshold.
+    public let profile: String
+    public let original: ReplayOriginal
+    public let replay: ReplayVerdict
+    public let diff: ReplayDiff
+
+    enum CodingKeys: String, CodingKey {
+        case id
+        case imageHash = "image_hash"
+        case profile
+        case original
+        case replay
+        case diff
+    }
+}
+
+/// The result of one of the checks made by `/ready`.
+public struct ReadinessCheck: Codable {
+    /// The service checked: `database`, `store` or `storage`.
+    public let name: String
+    public let ok: Bool
+    public let error: String?
+
+    enum CodingKeys: String, CodingKey {
+        case name
+        case ok
+        case error
+    }
+}
+
+/// Response from `/ready`: `ready` with `200`, or `unavailable` with `503`.
+public struct ReadinessResponse: Codable {
+    public let status: String
+    public let checks: [ReadinessCheck]
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case checks
+    }
+}
+
+/// The device the classifier is configured for, and the one the last prediction ran on.
+public struct InferenceDevice: Codable {
+    public let configured: String
+    public let active: String?
+
+    enum CodingKeys: String, CodingKey {
+        case configured
+        case active
+    }
+}
+
+/// Response from `/version`.
+public struct VersionResponse: Codable {
+    public let version: String
+    public let activeModel: String
+    public let modelPrecision: Precision
+    public let inferenceDevice: InferenceDevice
+
+    enum CodingKeys: String, CodingKey {
+        case version
+        case activeModel = "active_model"
+        case modelPrecision = "model_precision"
+        case inferenceDevice = "inference_device"
+    }
+}
+
+/// How and when a photo was taken, from its EXIF metadata, for analysing the dataset by phone and conditions.
+public struct Capture: Codable {
+    /// When the photo was taken, in the phone's local time (`2024-05-04T15:42:10`), with its UTC offset if recorded.
+    public let capturedAt: String?
+    public let cameraMake: String?
+    public let cameraModel: String?
+    public let focalLengthMm: Double?
+    /// Focal length as it would be on a full-frame camera, comparable between phones.
+    public let focalLength35mm: Int?
+    public let fNumber: Double?
+    /// Exposure time in seconds.
+    public let exposureTime: Double?
+    public let iso: Int?
+
+    enum CodingKeys: String, CodingKey {
+        case capturedAt = "captured_at"
+        case cameraMake = "camera_make"
+        case cameraModel = "camera_model"
+        case focalLengthMm = "focal_length_mm"
+        case focalLength35mm = "focal_length_35mm"
+        case fNumber = "f_number"
+        case exposureTime = "exposure_time"
+        case iso
+    }
+}
+
+/// A training submission, as recorded in the training log. Fields added to the log later are left out of older entries.
+public struct TrainingLogEntry: Codable {
+    /// When the image was received, as an RFC 3339 timestamp.
+    public let timestamp: String
+    public let requestId: Int
+    public let label: String
+    public let contributor: String?
+    public let filename: String
+    public let filePath: String
+    public let contentHash: String?
+    public let imageSizeBytes: Int
+    /// Size of the image as stored, after any recompression.
+    public let storedSizeBytes: Int?
+    /// The `/sync` item the image came in, for images queued on a phone.
+    public let clientId: String?
+    /// The prediction the image corrects, for images sent to `/predictions/{id}/correct`.
+    public let predictionId: Int?
+    /// The annotated task, for images added from Label Studio.
+    public let labelStudioTask: Int?
+    public let capture: Capture?
+
+    enum CodingKeys: String, CodingKey {
+        case timestamp
+        case requestId = "request_id"
+        case label
+        case contributor
+        case filename
+        case filePath = "file_path"
+        case contentHash = "content_hash"
+        case imageSizeBytes = "image_size_bytes"
+        case storedSizeBytes = "stored_size_bytes"
+        case clientId = "client_id"
+        case predictionId = "prediction_id"
+        case labelStudioTask = "label_studio_task"
+        case capture
+    }
+}
+
+/// Response from `/training/log`, oldest first.
+public struct TrainingLogResponse: Codable {
+    public let count: Int
+    /// Whether more entries matched than `limit` allowed.
+    public let truncated: Bool
+    public let entries: [TrainingLogEntry]
+
+    enum CodingKeys: String, CodingKey {
+        case count
+        case truncated
+        case entries
+    }
+}
+
+/// Response from `/samples/{id}/review`.
+public struct ReviewResponse: Codable {
+    public let status: String
+    public let sampleId: Int
+    /// `approve` or `reject`, as sent.
+    public let decision: String
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case sampleId = "sample_id"
+        case decision
+    }
+}
+
+/// Response from `/admin/samples/bulk`.
+public struct BulkResponse: Codable {
+    /// Whether nothing was changed, and the counts are what would have been.
+    public let dryRun: Bool
+    public let matched: Int
+    public let sampleIds: [Int]
+    public let relabeled: Int
+    public let tagsAdded: Int
+
+    enum CodingKeys: String, CodingKey {
+        case dryRun = "dry_run"
+        case matched
+        case sampleIds = "sample_ids"
+        case relabeled
+        case tagsAdded = "tags_added"
+    }
+}
+
+/// A training image that couldn't be read or checked.
+public struct ImageFailure: Codable {
+    public let contentHash: String
+    public let error: String
+
+    enum CodingKeys: String, CodingKey {
+        case contentHash = "content_hash"
+        case error
+    }
+}
+
+/// Response from `/samples/integrity`.
+public struct IntegrityResponse: Codable {
+    public let imagesChecked: Int
+    public let failures: [ImageFailure]
+
+    enum CodingKeys: String, CodingKey {
+        case imagesChecked = "images_checked"
+        case failures
+    }
+}
+
+/// Response from adding or updating a label through `/admin/labels`.
+public struct LabelResponse: Codable {
+    public let status: String
+    public let label: Label
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case label
+    }
+}
+
+/// Response from `DELETE /admin/labels/{name}`.
+public struct LabelRemovedResponse: Codable {
+    public let status: String
+    public let name: String
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case name
+    }
+}
+
+/// Response from `/integrations/label-studio`: `ignored` for webhooks that don't label anything, otherwise
+/// `success` with the samples the annotation was applied to.
+public struct LabelStudioResponse: Codable {
+    public let status: String
+    /// The webhook's action, e.g. `ANNOTATION_CREATED`.
+    public let action: String
+    public let label: String?
+    public let sampleIds: [Int]?
+    /// Whether a kept prediction image became a new sample.
+    public let created: Bool?
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case action
+        case label
+        case sampleIds = "sample_ids"
+        case created
+    }
+}
+
+/// A registered ball.
+public struct Ball: Codable {
+    public let id: String
+    public let description: String?
+    public let tag: String
+    public let createdAt: String
+
+    enum CodingKeys: String, CodingKey {
+        case id
+        case description
+        case tag
+        case createdAt = "created_at"
+    }
+}
+
+/// Response from `/balls`.
+public struct RegisterBallResponse: Codable {
+    public let status: String
+    /// What to write to the ball's NFC tag or QR code.
+    public let tagPayload: String
+    public let ball: Ball
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case tagPayload = "tag_payload"
+        case ball
+    }
+}
+
+/// Aggregated submission and review statistics for one contributor.
+public struct ContributorStats: Codable {
+    public let contributor: String
+    public let samplesSubmitted: Int
+    public let samplesApproved: Int
+    public let samplesRejected: Int
+    /// Share of approved samples the reviewer didn't have to relabel.
+    public let labelAccuracy: Double?
+
+    enum CodingKeys: String, CodingKey {
+        case contributor
+        case samplesSubmitted = "samples_submitted"
+        case samplesApproved = "samples_approved"
+        case samplesRejected = "samples_rejected"
+        case labelAccuracy = "label_accuracy"
+    }
+}
+
+/// Response from `/contributors/stats`, as a leaderboard by approved samples.
+public struct ContributorsResponse: Codable {
+    public let contributors: [ContributorStats]
+
+    enum CodingKeys: String, CodingKey {
+        case contributors
+    }
+}
+
+/// Number of predictions whose confidence fell in one bin.
+public struct Bin: Codable {
+    /// Lower bound of the bin, inclusive.
+    public let from: Double
+    /// Upper bound of the bin, exclusive except for the last bin.
+    public let to: Double
+    public let count: Int
+
+    enum CodingKeys: String, CodingKey {
+        case from
+        case to
+        case count
+    }
+}
+
+/// How confident the model was in one verdict's predictions.
+public struct ConfidenceDistribution: Codable {
+    public let predictions: Int
+    /// `null` without any predictions.
+    public let meanConfidence: Double?
+    public let histogram: [Bin]
+
+    enum CodingKeys: String, CodingKey {
+        case predictions
+        case meanConfidence = "mean_confidence"
+        case histogram
+    }
+}
+
+/// Response from `/analytics/confidence`.
+public struct ConfidenceResponse: Codable {
+    public let since: String
+    public let modelVersion: String?
+    public let all: ConfidenceDistribution
+    /// The distribution for each verdict.
+    public let verdicts: [String: ConfidenceDistribution]
+
+    enum CodingKeys: String, CodingKey {
+        case since
+        case modelVersion = "model_version"
+        case all
+        case verdicts
+    }
+}
+
+/// A trained ensemble the classifier can run, stored as `model_{1,2,3}.pth` in `dir`.
+public struct ModelVersion: Codable {
+    public let name: String
+    public let dir: String
+
+    enum CodingKeys: String, CodingKey {
+        case name
+        case dir
+    }
+}
+
+/// How often the models were right among validation predictions in one confidence bin.
+public struct ReliabilityBin: Codable {
+    public let confidence: Double
+    public let accuracy: Double
+    public let count: Int
+
+    enum CodingKeys: String, CodingKey {
+        case confidence
+        case accuracy
+        case count
+    }
+}
+
+/// Precision and recall of one class on the validation images; `null` when nothing was predicted as,
+/// or labeled as, the class.
+public struct ClassMetrics: Codable {
+    public let precision: Double?
+    public let recall: Double?
+    /// Validation images of the class.
+    public let support: Int
+
+    enum CodingKeys: String, CodingKey {
+        case precision
+        case recall
+        case support
+    }
+}
+
+/// Cross-validation results `train.py` saves with a model version, in `metrics.json`.
+public struct EvalMetrics: Codable {
+    public let foldAccuracies: [Double]
+    public let averageAccuracy: Double
+    public let stdAccuracy: Double
+    public let numEpochs: Int
+    public let kFolds: Int
+    /// Training images of each class; left out for versions trained before it was recorded.
+    public let imagesPerClass: [String: Int]?
+
+    enum CodingKeys: String, CodingKey {
+        case foldAccuracies = "fold_accuracies"
+        case averageAccuracy = "average_accuracy"
+        case stdAccuracy = "std_accuracy"
+        case numEpochs = "num_epochs"
+        case kFolds = "k_folds"
+        case imagesPerClass = "images_per_class"
+    }
+}
+
+/// The temperature `calibrate.py` fitted for a model version and how much it improved calibration, in `calibration.json`.
+public struct Calibration: Codable {
+    /// Always `temperature`.
+    public let method: String
+    public let temperature: Double
+    public let validationImages: Int
+    /// Negative log-likelihood and expected calibration error on the validation images, before and after scaling.
+    public let nllBefore: Double
+    public let nllAfter: Double
+    public let eceBefore: Double
+    public let eceAfter: Double
+    /// Left out for versions calibrated before they were recorded.
+    public let reliability: [ReliabilityBin]?
+    public let perClass: [String: ClassMetrics]?
+
+    enum CodingKeys: String, CodingKey {
+        case method
+        case temperature
+        case validationImages = "validation_images"
+        case nllBefore = "nll_before"
+        case nllAfter = "nll_after"
+        case eceBefore = "ece_before"
+        case eceAfter = "ece_after"
+        case reliability
+        case perClass = "per_class"
+    }
+}
+
+/// Length of the periods production statistics are grouped into.
+public enum Bucket: String, Codable {
+    case day
+    /// Weeks start on Monday.
+    case week
+}
+
+/// How a model version performed in production over one period.
+public struct PeriodStats: Codable {
+    /// First day of the period, as `YYYY-MM-DD`.
+    public let period: String
+    public let predictions: Int
+    /// Predictions the model couldn't make, usually because the script failed on the image.
+    public let unknown: Int
+    /// Predictions that also asked the second-opinion model.
+    public let secondOpinions: Int
+    /// Second opinions that reached a different verdict.
+    public let disagreements: Int
+    public let meanConfidence: Double?
+    /// Share of second opinions that disagreed; `null` without any second opinions.
+    public let disagreementRate: Double?
+
+    enum CodingKeys: String, CodingKey {
+        case period
+        case predictions
+        case unknown
+        case secondOpinions = "second_opinions"
+        case disagreements
+        case meanConfidence = "mean_confidence"
+        case disagreementRate = "disagreement_rate"
+    }
+}
+
+/// A model version's evaluation results from training and its production statistics, in `/models/metrics`.
+/// Versions that served predictions but are no longer configured have no directory or evaluation results.
+public struct ModelMetrics: Codable {
+    public let name: String
+    public let dir: String?
+    public let active: Bool
+    public let evalMetrics: EvalMetrics?
+    public let calibration: Calibration?
+    public let production: [PeriodStats]
+
+    enum CodingKeys: String, CodingKey {
+        case name
+        case dir
+        case active
+        case evalMetrics = "eval_metrics"
+        case calibration
+        case production
+    }
+}
+
+/// Response from `/models/metrics`.
+public struct MetricsResponse: Codable {
+    public let bucket: Bucket
+    public let since: String
+    public let inferenceDevice: String?
+    /// Configured versions first, oldest first, then any that have since been removed.
+    public let models: [ModelMetrics]
+
+    enum CodingKeys: String, CodingKey {
+        case bucket
+        case since
+        case inferenceDevice = "inference_device"
+        case models
+    }
+}
+
+/// A way a model version has been wrong: what it called the balls reviewers confirmed were something else,
+/// how often, and the tags reviewers gave those samples, e.g. `glare`.
+public struct FailureMode: Codable {
+    public let predicted: String
+    public let actual: String
+    public let count: Int
+    public let tags: [String: Int]
+
+    enum CodingKeys: String, CodingKey {
+        case predicted
+        case actual
+        case count
+        case tags
+    }
+}
+
+/// The model version a model card describes.
+public struct ModelCardVersion: Codable {
+    public let name: String
+    public let active: Bool
+
+    enum CodingKeys: String, CodingKey {
+        case name
+        case active
+    }
+}
+
+/// The images a model version was trained on.
+public struct ModelCardTrainingData: Codable {
+    public let totalImages: Int
+    public let imagesPerClass: [String: Int]
+
+    enum CodingKeys: String, CodingKey {
+        case totalImages = "total_images"
+        case imagesPerClass = "images_per_class"
+    }
+}
+
+/// A model version's cross-validation results, and its per-class precision and recall from calibration.
+public struct ModelCardEvaluation: Codable {
+    public let kFolds: Int
+    public let foldAccuracies: [Double]
+    public let averageAccuracy: Double
+    public let stdAccuracy: Double
+    public let perClass: [String: ClassMetrics]?
+
+    enum CodingKeys: String, CodingKey {
+        case kFolds = "k_folds"
+        case foldAccuracies = "fold_accuracies"
+        case averageAccuracy = "average_accuracy"
+        case stdAccuracy = "std_accuracy"
+        case perClass = "per_class"
+    }
+}
+
+/// How well a model version's confidences are calibrated.
+public struct ModelCardCalibration: Codable {
+    public let method: String
+    public let temperature: Double
+    public let validationImages: Int
+    public let eceBefore: Double
+    public let eceAfter: Double
+    public let reliability: [ReliabilityBin]?
+
+    enum CodingKeys: String, CodingKey {
+        case method
+        case temperature
+        case validationImages = "validation_images"
+        case eceBefore = "ece_before"
+        case eceAfter = "ece_after"
+        case reliability
+    }
+}
+
+/// Response from `/models/{version}/report`. Sections recorded by training and calibration are `null` for
+/// versions trained before they were.
+public struct ModelCard: Codable {
+    public let model: ModelCardVersion
+    public let trainingData: ModelCardTrainingData?
+    public let evaluation: ModelCardEvaluation?
+    public let calibration: ModelCardCalibration?
+    /// Most frequent first.
+    public let failureModes: [FailureMode]
+
+    enum CodingKeys: String, CodingKey {
+        case model
+        case trainingData = "training_data"
+        case evaluation
+        case calibration
+        case failureModes = "failure_modes"
+    }
+}
+
+/// An issued API key, without the key itself.
+public struct ApiKeyRecord: Codable {
+    public let id: Int
+    /// Who the key was issued to, e.g. the club's name.
+    public let name: String
+    /// The key's first characters.
+    public let keyPrefix: String
+    /// Strictness profile applied to the key's requests unless they ask for another.
+    public let profile: String?
+    public let scopes: [String]
+    public let createdAt: String
+    public let rotatedAt: String?
+    public let lastUsedAt: String?
+    public let revokedAt: String?
+
+    enum CodingKeys: String, CodingKey {
+        case id
+        case name
+        case keyPrefix = "key_prefix"
+        case profile
+        case scopes
+        case createdAt = "created_at"
+        case rotatedAt = "rotated_at"
+        case lastUsedAt = "last_used_at"
+        case revokedAt = "revoked_at"
+    }
+}
+
+/// Response from issuing or rotating an API key. The key is only ever shown here.
+public struct IssuedApiKeyResponse: Codable {
+    public let status: String
+    public let key: String
+    public let apiKey: ApiKeyRecord
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case key
+        case apiKey = "api_key"
+    }
+}
+
+/// Response from updating or revoking an API key.
+public struct ApiKeyResponse: Codable {
+    public let status: String
+    public let apiKey: ApiKeyRecord
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case apiKey = "api_key"
+    }
+}
+
+/// Response from `/admin/api-keys`.
+public struct ApiKeysResponse: Codable {
+    public let apiKeys: [ApiKeyRecord]
+
+    enum CodingKeys: String, CodingKey {
+        case apiKeys = "api_keys"
+    }
+}
+
+/// Who a feature flag is switched on for.
+public struct Rollout: Codable {
+    /// On for every request.
+    public let everyone: Bool
+    /// On for requests made with these issued API keys, by ID.
+    public let apiKeys: [Int]
+
+    enum CodingKeys: String, CodingKey {
+        case everyone
+        case apiKeys = "api_keys"
+    }
+}
+
+/// Settings that are safe to change while the server is running.
+/// They start from the environment, and changes made through `/admin/config` are stored in the metadata database.
+public struct RuntimeSettings: Codable {
+    /// Strictness profile used when neither the request nor its API key selects one.
+    public let defaultProfile: String
+    /// Minimum confidence for a match-ready verdict, by profile. Profiles left out use their built-in threshold.
+    public let profileThresholds: [String: Double]
+    /// Whether photo quality issues are ignored, reported as warnings, or rejected.
+    public let qualityMode: QualityMode
+    /// Uploads each client may make per minute; `0` disables the limit.
+    public let uploadRateLimit: Int
+    /// How long classifier results are cached for identical images, in seconds; `0` disables the cache.
+    public let predictionCacheTtl: Int
+    /// Age in months after which `/admin/archive` moves training images to cold storage.
+    public let archiveAfterMonths: Int
+    /// Who each experimental behaviour is switched on for, by flag name.
+    public let featureFlags: [String: Rollout]
+
+    enum CodingKeys: String, CodingKey {
+        case defaultProfile = "default_profile"
+        case profileThresholds = "profile_thresholds"
+        case qualityMode = "quality_mode"
+        case uploadRateLimit = "upload_rate_limit"
+        case predictionCacheTtl = "prediction_cache_ttl"
+        case archiveAfterMonths = "archive_after_months"
+        case featureFlags = "feature_flags"
+    }
+}
+
+/// A change recorded in the settings audit log. Values are JSON.
+public struct AuditEntry: Codable {
+    public let changedAt: String
+    public let client: String
+    public let name: String
+    public let oldValue: String
+    public let newValue: String
+
+    enum CodingKeys: String, CodingKey {
+        case changedAt = "changed_at"
+        case client
+        case name
+        case oldValue = "old_value"
+        case newValue = "new_value"
+    }
+}
+
+/// Response from `/admin/config`.
+public struct SettingsResponse: Codable {
+    /// The settings in force.
+    public let settings: RuntimeSettings
+    /// The settings as configured in the environment.
+    public let defaults: RuntimeSettings
+    /// The most recent changes, newest first.
+    public let history: [AuditEntry]
+
+    enum CodingKeys: String, CodingKey {
+        case settings
+        case defaults
+        case history
+    }
+}
+
+/// Response from `PUT /admin/config` and `PUT /admin/config/{name}`.
+public struct SettingsUpdateResponse: Codable {
+    public let status: String
+    /// Names of the settings whose values changed.
+    public let changed: [String]
+    public let settings: RuntimeSettings
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case changed
+        case settings
+    }
+}
+
+/// A background job, as stored in the metadata database.
+public struct Job: Codable {
+    public let id: Int
+    /// What the job does; currently always `training`, since predictions are answered while the client waits.
+    public let kind: String
+    /// One of `queued`, `running`, `cancelling`, `succeeded`, `failed` or `cancelled`.
+    public let status: String
+    public let createdAt: String
+    public let startedAt: String?
+    public let finishedAt: String?
+    public let error: String?
+
+    enum CodingKeys: String, CodingKey {
+        case id
+        case kind
+        case status
+        case createdAt = "created_at"
+        case startedAt = "started_at"
+        case finishedAt = "finished_at"
+        case error
+    }
+}
+
+/// Response from `/jobs/training`.
+public struct JobStartedResponse: Codable {
+    public let jobId: Int
+    public let status: String
+
+    enum CodingKeys: String, CodingKey {
+        case jobId = "job_id"
+        case status
+    }
+}
+
+/// Response from `/jobs`, newest first.
+public struct JobsResponse: Codable {
+    public let jobs: [Job]
+
+    enum CodingKeys: String, CodingKey {
+        case jobs
+    }
+}
+
+/// Response from `/jobs/{id}/cancel`.
+public struct JobCancelledResponse: Codable {
+    public let jobId: Int
+    /// `cancelled` for a job that hadn't started, or `cancelling` until a running job's process has stopped.
+    public let status: String
+    /// Whether this server was running the job's process and has started stopping it.
+    public let terminated: Bool
+
+    enum CodingKeys: String, CodingKey {
+        case jobId = "job_id"
+        case status
+        case terminated
+    }
+}
+
+/// How far one image feature has drifted.
+public struct FeatureDrift: Codable {
+    public let name: String
+    /// Population stability index of production images against training images.
+    public let psi: Double
+    /// `ok`, `warning` or `drift`.
+    public let level: String
+    public let baselineMean: Double
+    public let productionMean: Double
+
+    enum CodingKeys: String, CodingKey {
+        case name
+        case psi
+        case level
+        case baselineMean = "baseline_mean"
+        case productionMean = "production_mean"
+    }
+}
+
+/// Response from `/admin/drift`.
+public struct DriftReport: Codable {
+    /// `insufficient_data`, `ok`, `warning` or `drift`.
+    public let status: String
+    public let since: String
+    public let baselineImages: Int
+    public let productionImages: Int
+    /// Empty with `insufficient_data`.
+    public let features: [FeatureDrift]
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case since
+        case baselineImages = "baseline_images"
+        case productionImages = "production_images"
+        case features
+    }
+}
+
+/// Response from `/admin/drift/baseline`.
+public struct DriftBaselineResponse: Codable {
+    public let imagesMeasured: Int
+    public let failures: [ImageFailure]
+
+    enum CodingKeys: String, CodingKey {
+        case imagesMeasured = "images_measured"
+        case failures
+    }
+}
+
+/// Response from `/admin/archive`: `archived`, or `nothing_to_archive` without the archive's details.
+public struct ArchiveResponse: Codable {
+    public let status: String
+    public let archiveId: String?
+    public let images: Int?
+    public let samples: Int?
+    /// Images older than this were archived.
+    public let cutoff: String
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case archiveId = "archive_id"
+        case images
+        case samples
+        case cutoff
+    }
+}
+
+/// Response from `/admin/archives/{id}/rehydrate`.
+public struct RehydrateResponse: Codable {
+    public let status: String
+    public let archiveId: String
+    public let images: Int
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case archiveId = "archive_id"
+        case images
+    }
+}
+
+/// The settings a deployment runs with, minus secrets such as API keys and connection strings.
+public struct ConfigSnapshot: Codable {
+    public let defaultProfile: String
+    public let qualityMode: QualityMode
+    public let modelVersions: [ModelVersion]
+    public let activeModel: String
+    public let secondOpinionModel: String?
+    /// `local` or `s3`.
+    public let storageBackend: String
+    public let uploadRateLimit: Int
+    public let predictionCacheTtl: Int
+    /// Flags switched on for everyone; left out of backups made before feature flags.
+    public let featureFlags: [String]
+
+    enum CodingKeys: String, CodingKey {
+        case defaultProfile = "default_profile"
+        case qualityMode = "quality_mode"
+        case modelVersions = "model_versions"
+        case activeModel = "active_model"
+        case secondOpinionModel = "second_opinion_model"
+        case storageBackend = "storage_backend"
+        case uploadRateLimit = "upload_rate_limit"
+        case predictionCacheTtl = "prediction_cache_ttl"
+        case featureFlags = "feature_flags"
+    }
+}
+
+/// Response from `/admin/restore`. Configuration comes from the environment, so the backup's is handed back
+/// for the operator to apply; `null` if the bundle's couldn't be read.
+public struct RestoreResponse: Codable {
+    public let status: String
+    public let filesRestored: Int
+    public let balls: Int
+    public let predictions: Int
+    public let samples: Int
+    public let config: ConfigSnapshot?
+
+    enum CodingKeys: String, CodingKey {
+        case status
+        case filesRestored = "files_restored"
+        case balls
+        case predictions
+        case samples
+        case config
+    }
+}
+
+/// Number of training samples carrying a label, excluding rejected ones.
+public struct LabelCount: Codable {
+    /// The reviewer's label if the sample has been reviewed, otherwise the contributor's.
+    public let label: String
+    public let samples: Int
+    public let approved: Int
+
+    enum CodingKeys: String, CodingKey {
+        case label
+        case samples
+        case approved
+    }
+}
+
+/// Predictions served today, by verdict.
+public struct PredictionCounts: Codable {
+    public let total: Int
+    public let byPrediction: [String: Int]
+
+    enum CodingKeys: String, CodingKey {
+        case total
+        case byPrediction = "by_prediction"
+    }
+}
+
+/// The model version serving predictions, with its evaluation results from training.
+public struct ActiveModel: Codable {
+    public let name: String
+    public let dir: String
+    public let evalMetrics: EvalMetrics?
+
+    enum CodingKeys: String, CodingKey {
+        case name
+        case dir
+        case evalMetrics = "eval_metrics"
+    }
+}
+
+/// Bytes stored in each area that grows with use, plus their total.
+/// An area whose size can't be read is `null` and left out of the total.
+public struct DiskUsage: Codable {
+    public let trainingData: Int?
+    public let exports: Int?
+    public let archive: Int?
+    public let predictionImages: Int?
+    public let total: Int
+
+    enum CodingKeys: String, CodingKey {
+        case trainingData = "training_data"
+        case exports
+        case archive
+        case predictionImages = "prediction_images"
+        case total
+    }
+}
+
+/// Response from `/admin/summary`. Counts for "today" cover the current UTC day.
+public struct SummaryResponse: Codable {
+    public let date: String
+    public let predictionsToday: PredictionCounts
+    /// Classification attempts that failed today.
+    public let errorsToday: Int
+    public let errorRate: Double
+    /// Classifications running right now.
+    public let queueDepth: Int
+    public let dataset: [LabelCount]
+    public let pendingReviews: Int
+    public let activeModel: ActiveModel
+    public let diskUsageBytes: DiskUsage
+
+    enum CodingKeys: String, CodingKey {
+        case date
+        case predictionsToday = "predictions_today"
+        case errorsToday = "errors_today"
+        case errorRate = "error_rate"
+        case queueDepth = "queue_depth"
+        case dataset
+        case pendingReviews = "pending_reviews"
+        case activeModel = "active_model"
+        case diskUsageBytes = "disk_usage_bytes"
+    }
+}
+
+/// Artificial failures to inject, for checking how client apps and monitoring cope with them.
+/// Only injected when the server runs with `FAULT_INJECTION=true`, which is never meant for production.
+public struct Faults: Codable {
+    /// Extra time each model takes to classify an image, in milliseconds.
+    public let slowInferenceMs: Int
+    /// The prediction script dies without giving a verdict.
+    public let classifierCrash: Bool
+    /// The prediction script prints something that isn't a verdict.
+    public let malformedOutput: Bool
+    /// Writing images fails as if the disk were full.
+    public let diskFull: Bool
+
+    enum CodingKeys: String, CodingKey {
+        case slowInferenceMs = "slow_inference_ms"
+        case classifierCrash = "classifier_crash"
+        case malformedOutput = "malformed_output"
+        case diskFull = "disk_full"
+    }
+}
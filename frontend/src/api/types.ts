@@ -0,0 +1,895 @@
+// Generated from backend/src/api_types.rs. Do not edit by hand: run
+// `UPDATE_CLIENT_TYPES=1 cargo test` in backend/ after changing the types there.
+
+/**
+ * Number type a model version's weights are stored in. Lower precisions trade a little accuracy
+ * for lower latency and memory, e.g. on a Raspberry Pi.
+ */
+export type Precision = 'fp32' | 'fp16' | 'int8';
+
+/** How the photo quality pre-check affects a request. */
+export type QualityMode = 'off' | 'warn' | 'reject';
+
+/** A problem found with a photo, with guidance on how to fix it. */
+export interface QualityIssue {
+	/** Stable code: `too_blurry`, `too_dark`, `too_bright` or `ball_too_small`. */
+	code: string;
+	/** How to retake the photo, in the client's language. */
+	message: string;
+}
+
+/** Body of the `422` response to a photo rejected by the quality pre-check. */
+export interface QualityRejection {
+	status: string;
+	code: string;
+	message: string;
+	issues: QualityIssue[];
+}
+
+/** Body of the `400` response to a request that failed validation, with one message per problem. */
+export interface InvalidResponse {
+	status: string;
+	errors: string[];
+}
+
+/** The second-opinion model's verdict on a `/predict` photo, under the same profile. */
+export interface SecondOpinion {
+	prediction: string;
+	confidence: number;
+	model_prediction: string;
+	model_version: string;
+	uncertainty?: number;
+}
+
+/** Response from `/predict`. */
+export interface PredictResponse {
+	/** ID of the stored prediction, for `/predictions/{id}`; `null` if it couldn't be recorded. */
+	prediction_id: number | null;
+	/** The verdict under the profile: a label from the taxonomy, or `unknown`. */
+	prediction: string;
+	confidence: number;
+	/** The model's verdict before the profile's threshold was applied. */
+	model_prediction: string;
+	model_version: string;
+	model_precision: Precision;
+	profile: string;
+	ball_id: string | null;
+	/** Spread of the match-ready probability across dropout passes, when asked for with `uncertainty=true` and the `uncertainty` flag is on. */
+	uncertainty?: number;
+	/** Whether the photo was enhanced, when asked for with `enhance=true` and the `enhance` flag is on. */
+	enhanced: boolean;
+	/** The enhanced photo as a JPEG data URL, when asked for with `return_enhanced=true`. */
+	enhanced_image?: string;
+	second_opinion?: SecondOpinion;
+	/** Whether the second opinion reached the same verdict. */
+	agreement?: boolean;
+	verdict: string;
+	recommendation: string;
+	quality_warnings: QualityIssue[];
+}
+
+/** Where a ball is in the original photo, in pixels. */
+export interface BoundingBox {
+	x: number;
+	y: number;
+	width: number;
+	height: number;
+}
+
+/** The verdict on one ball found by `/predict/multi`. */
+export interface BallPrediction {
+	box: BoundingBox;
+	/** ID of the stored prediction, for `/predictions/{id}`; `null` if it couldn't be recorded. */
+	prediction_id: number | null;
+	prediction: string;
+	confidence: number;
+	model_prediction: string;
+	model_version: string;
+	model_precision: Precision;
+	verdict: string;
+	recommendation: string;
+}
+
+/** Response from `/predict/multi`. */
+export interface PredictMultiResponse {
+	count: number;
+	balls: BallPrediction[];
+	profile: string;
+	quality_warnings: QualityIssue[];
+}
+
+/** One model version's verdict in `/predict/compare-models`. */
+export interface ModelComparison {
+	model_version: string;
+	prediction: string;
+	confidence: number;
+	model_prediction: string;
+	model_precision: Precision;
+	uncertainty?: number;
+}
+
+/** Response from `/predict/compare-models`. */
+export interface CompareModelsResponse {
+	profile: string;
+	models: ModelComparison[];
+	/** Whether every model reached the same verdict under the profile. */
+	agreement: boolean;
+	/** Whether every model's own verdict was the same. */
+	model_agreement: boolean;
+}
+
+/** Response from `/training`. */
+export interface TrainingResponse {
+	status: string;
+	message: string;
+	filename: string;
+	label: string;
+	request_id: number;
+	/** ID of the sample recorded for review; `null` if it couldn't be recorded. */
+	sample_id: number | null;
+	quality_warnings: QualityIssue[];
+}
+
+/** Body accepted by `/predictions/{id}/correct`. */
+export interface CorrectionInput {
+	/** What the ball really was: a label from the taxonomy. */
+	label: string;
+	contributor: string | null;
+}
+
+/** Response from `/predictions/{id}/correct`. */
+export interface CorrectionResponse {
+	status: string;
+	prediction_id: number;
+	/** ID of the sample queued for review with the corrected label. */
+	sample_id: number;
+	/** The label the prediction was corrected to, and the prediction it corrects. */
+	label: string;
+	prediction: string;
+}
+
+/** The kind of work queued on the phone. */
+export type ItemKind = 'prediction' | 'training';
+
+/** One queued request, referring to an image sent in the same multipart payload. */
+export interface SyncItem {
+	/** ID generated on the phone, used to make retries idempotent. */
+	client_id: string;
+	type: ItemKind;
+	/** Name of the multipart field holding this item's image. */
+	image: string;
+	ball_id?: string;
+	label?: string;
+	contributor?: string;
+}
+
+/** The `manifest` field of a `/sync` upload, describing every item in the batch. */
+export interface SyncManifest {
+	items: SyncItem[];
+}
+
+/** What became of a synced item. */
+export type SyncStatus = 'saved' | 'predicted' | 'duplicate' | 'rejected' | 'error';
+
+/** The result for one item of a `/sync` batch. Prediction fields are only given for predicted items. */
+export interface SyncResult {
+	client_id: string;
+	type: ItemKind;
+	status: SyncStatus;
+	/** ID of the new sample or prediction. */
+	id?: number;
+	issues?: QualityIssue[];
+	quality_warnings?: QualityIssue[];
+	error?: string;
+	prediction?: string;
+	confidence?: number;
+	model_prediction?: string;
+	profile?: string;
+	model_version?: string;
+	ball_id?: string;
+	verdict?: string;
+	recommendation?: string;
+}
+
+/** Response from `/sync`, with one result per item in manifest order. */
+export interface SyncResponse {
+	status: string;
+	request_id: number;
+	results: SyncResult[];
+}
+
+/** A label training images can be given, which is also a class folder in the dataset and a class the models predict. */
+export interface Label {
+	name: string;
+	description: string;
+	/** Position of the label when shown to contributors, lowest first. */
+	display_order: number;
+}
+
+/** Response from `/labels`. */
+export interface LabelsResponse {
+	labels: Label[];
+}
+
+/** Where an upload session's upload has got to. */
+export type UploadStatus = 'waiting' | 'receiving' | 'received' | 'interrupted';
+
+/** Response from `/uploads`. */
+export interface UploadSession {
+	status: string;
+	id: string;
+	/** Header to send the session ID in with the upload. */
+	header: string;
+	expires_in_seconds: number;
+}
+
+/** Response from `/uploads/{id}`. */
+export interface UploadProgress {
+	id: string;
+	status: UploadStatus;
+	received_bytes: number;
+	total_bytes: number | null;
+	/** Share of the upload received so far, if its size is known. */
+	fraction: number | null;
+}
+
+/** Response from `/tags/{tag}`. */
+export interface TagResponse {
+	ball_id: string;
+	description: string | null;
+}
+
+/** A single prediction served by `/predict`, as stored in the metadata database. */
+export interface PredictionRecord {
+	id: number;
+	request_id: number;
+	created_at: string;
+	prediction: string;
+	confidence: number;
+	image_size_bytes: number;
+	profile: string;
+	model_prediction: string | null;
+	ball_id: string | null;
+	model_version: string | null;
+	/** The second-opinion model and its verdict under the profile, if one was asked for. */
+	second_opinion_model: string | null;
+	second_opinion_prediction: string | null;
+	/** Content hash of the photo, if it was kept for replaying the prediction. */
+	image_hash: string | null;
+}
+
+/** Response from `/balls/{id}/predictions`, newest first. */
+export interface BallHistoryResponse {
+	ball_id: string;
+	predictions: PredictionRecord[];
+}
+
+/** What was served for a prediction when it was made, in `/admin/predictions/{id}/replay`. */
+export interface ReplayOriginal {
+	created_at: string;
+	prediction: string;
+	confidence: number;
+	model_prediction: string | null;
+	model_version: string | null;
+}
+
+/** The verdict on a replayed prediction's photo now, in `/admin/predictions/{id}/replay`. */
+export interface ReplayVerdict {
+	prediction: string;
+	confidence: number;
+	model_prediction: string;
+	model_version: string;
+	model_precision: Precision;
+}
+
+/** How a replayed prediction differs from the original. */
+export interface ReplayDiff {
+	prediction_changed: boolean;
+	model_prediction_changed: boolean;
+	model_version_changed: boolean;
+	/** The replay's confidence less the original's. */
+	confidence_change: number;
+}
+
+/** Response from `/admin/predictions/{id}/replay`. */
+export interface ReplayResponse {
+	id: number;
+	image_hash: string;
+	/** The profile the prediction was made with, applied at its current threshold. */
+	profile: string;
+	original: ReplayOriginal;
+	replay: ReplayVerdict;
+	diff: ReplayDiff;
+}
+
+/** The result of one of the checks made by `/ready`. */
+export interface ReadinessCheck {
+	/** The service checked: `database`, `store` or `storage`. */
+	name: string;
+	ok: boolean;
+	error?: string;
+}
+
+/** Response from `/ready`: `ready` with `200`, or `unavailable` with `503`. */
+export interface ReadinessResponse {
+	status: string;
+	checks: ReadinessCheck[];
+}
+
+/** The device the classifier is configured for, and the one the last prediction ran on. */
+export interface InferenceDevice {
+	configured: string;
+	active: string | null;
+}
+
+/** Response from `/version`. */
+export interface VersionResponse {
+	version: string;
+	active_model: string;
+	model_precision: Precision;
+	inference_device: InferenceDevice;
+}
+
+/** How and when a photo was taken, from its EXIF metadata, for analysing the dataset by phone and conditions. */
+export interface Capture {
+	/** When the photo was taken, in the phone's local time (`2024-05-04T15:42:10`), with its UTC offset if recorded. */
+	captured_at: string | null;
+	camera_make: string | null;
+	camera_model: string | null;
+	focal_length_mm: number | null;
+	/** Focal length as it would be on a full-frame camera, comparable between phones. */
+	focal_length_35mm: number | null;
+	f_number: number | null;
+	/** Exposure time in seconds. */
+	exposure_time: number | null;
+	iso: number | null;
+}
+
+/** A training submission, as recorded in the training log. Fields added to the log later are left out of older entries. */
+export interface TrainingLogEntry {
+	/** When the image was received, as an RFC 3339 timestamp. */
+	timestamp: string;
+	request_id: number;
+	label: string;
+	contributor?: string;
+	filename: string;
+	file_path: string;
+	content_hash?: string;
+	image_size_bytes: number;
+	/** Size of the image as stored, after any recompression. */
+	stored_size_bytes?: number;
+	/** The `/sync` item the image came in, for images queued on a phone. */
+	client_id?: string;
+	/** The prediction the image corrects, for images sent to `/predictions/{id}/correct`. */
+	prediction_id?: number;
+	/** The annotated task, for images added from Label Studio. */
+	label_studio_task?: number;
+	capture?: Capture;
+}
+
+/** Response from `/training/log`, oldest first. */
+export interface TrainingLogResponse {
+	count: number;
+	/** Whether more entries matched than `limit` allowed. */
+	truncated: boolean;
+	entries: TrainingLogEntry[];
+}
+
+/** Response from `/samples/{id}/review`. */
+export interface ReviewResponse {
+	status: string;
+	sample_id: number;
+	/** `approve` or `reject`, as sent. */
+	decision: string;
+}
+
+/** Response from `/admin/samples/bulk`. */
+export interface BulkResponse {
+	/** Whether nothing was changed, and the counts are what would have been. */
+	dry_run: boolean;
+	matched: number;
+	sample_ids: number[];
+	relabeled: number;
+	tags_added: number;
+}
+
+/** A training image that couldn't be read or checked. */
+export interface ImageFailure {
+	content_hash: string;
+	error: string;
+}
+
+/** Response from `/samples/integrity`. */
+export interface IntegrityResponse {
+	images_checked: number;
+	failures: ImageFailure[];
+}
+
+/** Response from adding or updating a label through `/admin/labels`. */
+export interface LabelResponse {
+	status: string;
+	label: Label;
+}
+
+/** Response from `DELETE /admin/labels/{name}`. */
+export interface LabelRemovedResponse {
+	status: string;
+	name: string;
+}
+
+/**
+ * Response from `/integrations/label-studio`: `ignored` for webhooks that don't label anything, otherwise
+ * `success` with the samples the annotation was applied to.
+ */
+export interface LabelStudioResponse {
+	status: string;
+	/** The webhook's action, e.g. `ANNOTATION_CREATED`. */
+	action: string;
+	label?: string;
+	sample_ids?: number[];
+	/** Whether a kept prediction image became a new sample. */
+	created?: boolean;
+}
+
+/** A registered ball. */
+export interface Ball {
+	id: string;
+	description: string | null;
+	tag: string;
+	created_at: string;
+}
+
+/** Response from `/balls`. */
+export interface RegisterBallResponse {
+	status: string;
+	/** What to write to the ball's NFC tag or QR code. */
+	tag_payload: string;
+	ball: Ball;
+}
+
+/** Aggregated submission and review statistics for one contributor. */
+export interface ContributorStats {
+	contributor: string;
+	samples_submitted: number;
+	samples_approved: number;
+	samples_rejected: number;
+	/** Share of approved samples the reviewer didn't have to relabel. */
+	label_accuracy: number | null;
+}
+
+/** Response from `/contributors/stats`, as a leaderboard by approved samples. */
+export interface ContributorsResponse {
+	contributors: ContributorStats[];
+}
+
+/** Number of predictions whose confidence fell in one bin. */
+export interface Bin {
+	/** Lower bound of the bin, inclusive. */
+	from: number;
+	/** Upper bound of the bin, exclusive except for the last bin. */
+	to: number;
+	count: number;
+}
+
+/** How confident the model was in one verdict's predictions. */
+export interface ConfidenceDistribution {
+	predictions: number;
+	/** `null` without any predictions. */
+	mean_confidence: number | null;
+	histogram: Bin[];
+}
+
+/** Response from `/analytics/confidence`. */
+export interface ConfidenceResponse {
+	since: string;
+	model_version: string | null;
+	all: ConfidenceDistribution;
+	/** The distribution for each verdict. */
+	verdicts: Record<string, ConfidenceDistribution>;
+}
+
+/** A trained ensemble the classifier can run, stored as `model_{1,2,3}.pth` in `dir`. */
+export interface ModelVersion {
+	name: string;
+	dir: string;
+}
+
+/** How often the models were right among validation predictions in one confidence bin. */
+export interface ReliabilityBin {
+	confidence: number;
+	accuracy: number;
+	count: number;
+}
+
+/**
+ * Precision and recall of one class on the validation images; `null` when nothing was predicted as,
+ * or labeled as, the class.
+ */
+export interface ClassMetrics {
+	precision: number | null;
+	recall: number | null;
+	/** Validation images of the class. */
+	support: number;
+}
+
+/** Cross-validation results `train.py` saves with a model version, in `metrics.json`. */
+export interface EvalMetrics {
+	fold_accuracies: number[];
+	average_accuracy: number;
+	std_accuracy: number;
+	num_epochs: number;
+	k_folds: number;
+	/** Training images of each class; left out for versions trained before it was recorded. */
+	images_per_class?: Record<string, number>;
+}
+
+/** The temperature `calibrate.py` fitted for a model version and how much it improved calibration, in `calibration.json`. */
+export interface Calibration {
+	/** Always `temperature`. */
+	method: string;
+	temperature: number;
+	validation_images: number;
+	/** Negative log-likelihood and expected calibration error on the validation images, before and after scaling. */
+	nll_before: number;
+	nll_after: number;
+	ece_before: number;
+	ece_after: number;
+	/** Left out for versions calibrated before they were recorded. */
+	reliability?: ReliabilityBin[];
+	per_class?: Record<string, ClassMetrics>;
+}
+
+/** Length of the periods production statistics are grouped into. */
+export type Bucket = 'day' | 'week';
+
+/** How a model version performed in production over one period. */
+export interface PeriodStats {
+	/** First day of the period, as `YYYY-MM-DD`. */
+	period: string;
+	predictions: number;
+	/** Predictions the model couldn't make, usually because the script failed on the image. */
+	unknown: number;
+	/** Predictions that also asked the second-opinion model. */
+	second_opinions: number;
+	/** Second opinions that reached a different verdict. */
+	disagreements: number;
+	mean_confidence: number | null;
+	/** Share of second opinions that disagreed; `null` without any second opinions. */
+	disagreement_rate: number | null;
+}
+
+/**
+ * A model version's evaluation results from training and its production statistics, in `/models/metrics`.
+ * Versions that served predictions but are no longer configured have no directory or evaluation results.
+ */
+export interface ModelMetrics {
+	name: string;
+	dir: string | null;
+	active: boolean;
+	eval_metrics: EvalMetrics | null;
+	calibration: Calibration | null;
+	production: PeriodStats[];
+}
+
+/** Response from `/models/metrics`. */
+export interface MetricsResponse {
+	bucket: Bucket;
+	since: string;
+	inference_device: string | null;
+	/** Configured versions first, oldest first, then any that have since been removed. */
+	models: ModelMetrics[];
+}
+
+/**
+ * A way a model version has been wrong: what it called the balls reviewers confirmed were something else,
+ * how often, and the tags reviewers gave those samples, e.g. `glare`.
+ */
+export interface FailureMode {
+	predicted: string;
+	actual: string;
+	count: number;
+	tags: Record<string, number>;
+}
+
+/** The model version a model card describes. */
+export interface ModelCardVersion {
+	name: string;
+	active: boolean;
+}
+
+/** The images a model version was trained on. */
+export interface ModelCardTrainingData {
+	total_images: number;
+	images_per_class: Record<string, number>;
+}
+
+/** A model version's cross-validation results, and its per-class precision and recall from calibration. */
+export interface ModelCardEvaluation {
+	k_folds: number;
+	fold_accuracies: number[];
+	average_accuracy: number;
+	std_accuracy: number;
+	per_class: Record<string, ClassMetrics> | null;
+}
+
+/** How well a model version's confidences are calibrated. */
+export interface ModelCardCalibration {
+	method: string;
+	temperature: number;
+	validation_images: number;
+	ece_before: number;
+	ece_after: number;
+	reliability: ReliabilityBin[] | null;
+}
+
+/**
+ * Response from `/models/{version}/report`. Sections recorded by training and calibration are `null` for
+ * versions trained before they were.
+ */
+export interface ModelCard {
+	model: ModelCardVersion;
+	training_data: ModelCardTrainingData | null;
+	evaluation: ModelCardEvaluation | null;
+	calibration: ModelCardCalibration | null;
+	/** Most frequent first. */
+	failure_modes: FailureMode[];
+}
+
+/** An issued API key, without the key itself. */
+export interface ApiKeyRecord {
+	id: number;
+	/** Who the key was issued to, e.g. the club's name. */
+	name: string;
+	/** The key's first characters. */
+	key_prefix: string;
+	/** Strictness profile applied to the key's requests unless they ask for another. */
+	profile: string | null;
+	scopes: string[];
+	created_at: string;
+	rotated_at: string | null;
+	last_used_at: string | null;
+	revoked_at: string | null;
+}
+
+/** Response from issuing or rotating an API key. The key is only ever shown here. */
+export interface IssuedApiKeyResponse {
+	status: string;
+	key: string;
+	api_key: ApiKeyRecord;
+}
+
+/** Response from updating or revoking an API key. */
+export interface ApiKeyResponse {
+	status: string;
+	api_key: ApiKeyRecord;
+}
+
+/** Response from `/admin/api-keys`. */
+export interface ApiKeysResponse {
+	api_keys: ApiKeyRecord[];
+}
+
+/** Who a feature flag is switched on for. */
+export interface Rollout {
+	/** On for every request. */
+	everyone: boolean;
+	/** On for requests made with these issued API keys, by ID. */
+	api_keys: number[];
+}
+
+/**
+ * Settings that are safe to change while the server is running.
+ * They start from the environment, and changes made through `/admin/config` are stored in the metadata database.
+ */
+export interface RuntimeSettings {
+	/** Strictness profile used when neither the request nor its API key selects one. */
+	default_profile: string;
+	/** Minimum confidence for a match-ready verdict, by profile. Profiles left out use their built-in threshold. */
+	profile_thresholds: Record<string, number>;
+	/** Whether photo quality issues are ignored, reported as warnings, or rejected. */
+	quality_mode: QualityMode;
+	/** Uploads each client may make per minute; `0` disables the limit. */
+	upload_rate_limit: number;
+	/** How long classifier results are cached for identical images, in seconds; `0` disables the cache. */
+	prediction_cache_ttl: number;
+	/** Age in months after which `/admin/archive` moves training images to cold storage. */
+	archive_after_months: number;
+	/** Who each experimental behaviour is switched on for, by flag name. */
+	feature_flags: Record<string, Rollout>;
+}
+
+/** A change recorded in the settings audit log. Values are JSON. */
+export interface AuditEntry {
+	changed_at: string;
+	client: string;
+	name: string;
+	old_value: string;
+	new_value: string;
+}
+
+/** Response from `/admin/config`. */
+export interface SettingsResponse {
+	/** The settings in force. */
+	settings: RuntimeSettings;
+	/** The settings as configured in the environment. */
+	defaults: RuntimeSettings;
+	/** The most recent changes, newest first. */
+	history: AuditEntry[];
+}
+
+/** Response from `PUT /admin/config` and `PUT /admin/config/{name}`. */
+export interface SettingsUpdateResponse {
+	status: string;
+	/** Names of the settings whose values changed. */
+	changed: string[];
+	settings: RuntimeSettings;
+}
+
+/** A background job, as stored in the metadata database. */
+export interface Job {
+	id: number;
+	/** What the job does; currently always `training`, since predictions are answered while the client waits. */
+	kind: string;
+	/** One of `queued`, `running`, `cancelling`, `succeeded`, `failed` or `cancelled`. */
+	status: string;
+	created_at: string;
+	started_at: string | null;
+	finished_at: string | null;
+	error: string | null;
+}
+
+/** Response from `/jobs/training`. */
+export interface JobStartedResponse {
+	job_id: number;
+	status: string;
+}
+
+/** Response from `/jobs`, newest first. */
+export interface JobsResponse {
+	jobs: Job[];
+}
+
+/** Response from `/jobs/{id}/cancel`. */
+export interface JobCancelledResponse {
+	job_id: number;
+	/** `cancelled` for a job that hadn't started, or `cancelling` until a running job's process has stopped. */
+	status: string;
+	/** Whether this server was running the job's process and has started stopping it. */
+	terminated: boolean;
+}
+
+/** How far one image feature has drifted. */
+export interface FeatureDrift {
+	name: string;
+	/** Population stability index of production images against training images. */
+	psi: number;
+	/** `ok`, `warning` or `drift`. */
+	level: string;
+	baseline_mean: number;
+	production_mean: number;
+}
+
+/** Response from `/admin/drift`. */
+export interface DriftReport {
+	/** `insufficient_data`, `ok`, `warning` or `drift`. */
+	status: string;
+	since: string;
+	baseline_images: number;
+	production_images: number;
+	/** Empty with `insufficient_data`. */
+	features: FeatureDrift[];
+}
+
+/** Response from `/admin/drift/baseline`. */
+export interface DriftBaselineResponse {
+	images_measured: number;
+	failures: ImageFailure[];
+}
+
+/** Response from `/admin/archive`: `archived`, or `nothing_to_archive` without the archive's details. */
+export interface ArchiveResponse {
+	status: string;
+	archive_id?: string;
+	images?: number;
+	samples?: number;
+	/** Images older than this were archived. */
+	cutoff: string;
+}
+
+/** Response from `/admin/archives/{id}/rehydrate`. */
+export interface RehydrateResponse {
+	status: string;
+	archive_id: string;
+	images: number;
+}
+
+/** The settings a deployment runs with, minus secrets such as API keys and connection strings. */
+export interface ConfigSnapshot {
+	default_profile: string;
+	quality_mode: QualityMode;
+	model_versions: ModelVersion[];
+	active_model: string;
+	second_opinion_model: string | null;
+	/** `local` or `s3`. */
+	storage_backend: string;
+	upload_rate_limit: number;
+	prediction_cache_ttl: number;
+	/** Flags switched on for everyone; left out of backups made before feature flags. */
+	feature_flags: string[];
+}
+
+/**
+ * Response from `/admin/restore`. Configuration comes from the environment, so the backup's is handed back
+ * for the operator to apply; `null` if the bundle's couldn't be read.
+ */
+export interface RestoreResponse {
+	status: string;
+	files_restored: number;
+	balls: number;
+	predictions: number;
+	samples: number;
+	config: ConfigSnapshot | null;
+}
+
+/** Number of training samples carrying a label, excluding rejected ones. */
+export interface LabelCount {
+	/** The reviewer's label if the sample has been reviewed, otherwise the contributor's. */
+	label: string;
+	samples: number;
+	approved: number;
+}
+
+/** Predictions served today, by verdict. */
+export interface PredictionCounts {
+	total: number;
+	by_prediction: Record<string, number>;
+}
+
+/** The model version serving predictions, with its evaluation results from training. */
+export interface ActiveModel {
+	name: string;
+	dir: string;
+	eval_metrics: EvalMetrics | null;
+}
+
+/**
+ * Bytes stored in each area that grows with use, plus their total.
+ * An area whose size can't be read is `null` and left out of the total.
+ */
+export interface DiskUsage {
+	training_data: number | null;
+	exports: number | null;
+	archive: number | null;
+	prediction_images: number | null;
+	total: number;
+}
+
+/** Response from `/admin/summary`. Counts for "today" cover the current UTC day. */
+export interface SummaryResponse {
+	date: string;
+	predictions_today: PredictionCounts;
+	/** Classification attempts that failed today. */
+	errors_today: number;
+	error_rate: number;
+	/** Classifications running right now. */
+	queue_depth: number;
+	dataset: LabelCount[];
+	pending_reviews: number;
+	active_model: ActiveModel;
+	disk_usage_bytes: DiskUsage;
+}
+
+/**
+ * Artificial failures to inject, for checking how client apps and monitoring cope with them.
+ * Only injected when the server runs with `FAULT_INJECTION=true`, which is never meant for production.
+ */
+export interface Faults {
+	/** Extra time each model takes to classify an image, in milliseconds. */
+	slow_inference_ms: number;
+	/** The prediction script dies without giving a verdict. */
+	classifier_crash: boolean;
+	/** The prediction script prints something that isn't a verdict. */
+	malformed_output: boolean;
+	/** Writing images fails as if the disk were full. */
+	disk_full: boolean;
+}